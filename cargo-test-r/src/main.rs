@@ -4,8 +4,10 @@ use clap::{Args, Parser};
 use glob_match::glob_match;
 use humansize::{BINARY, format_size};
 use nextest_metadata::{BinaryListSummary, RustTestBinaryKind, RustTestBinarySummary};
+use std::collections::{BTreeSet, HashMap};
 use std::fs::File;
 use std::process::ExitStatus;
+use std::time::{Duration, SystemTime};
 use tar::Archive;
 
 #[derive(Parser, Debug, Clone)]
@@ -17,6 +19,9 @@ enum Command {
 
     #[command()]
     Run(RunSubcommand),
+
+    #[command()]
+    Watch(WatchSubcommand),
 }
 
 /// Unpacks a binary archive created by `cargo-nextest` to be used by follow-up `cargo-test-r run` commands.
@@ -30,6 +35,20 @@ struct ReuseNextestArchiveSubcommand {
     target_dir: Option<String>,
 }
 
+/// Watches the sources of the workspace, and rebuilds and reruns the selected tests of the packages
+/// affected by the changes.
+#[derive(Args, Debug, Clone)]
+struct WatchSubcommand {
+    /// Run the tests that failed in the previous run first (requires the test-r harness)
+    #[clap(long)]
+    failed_first: bool,
+    /// Milliseconds to wait for further changes before rebuilding
+    #[clap(long, default_value_t = 500)]
+    delay: u64,
+    #[clap(flatten)]
+    run: RunSubcommand,
+}
+
 #[derive(Args, Debug, Clone)]
 struct RunSubcommand {
     /// Compile, but don't run tests
@@ -182,6 +201,7 @@ fn try_main() -> anyhow::Result<ExitStatus> {
     match command {
         Command::ReuseNextestArchive(cmd) => reuse_nextest_archive(workspace_root, cmd),
         Command::Run(cmd) => run(workspace_root, cmd),
+        Command::Watch(cmd) => watch(workspace_root, cmd),
     }
 }
 
@@ -268,6 +288,434 @@ fn run(workspace_root: &Utf8Path, cmd: RunSubcommand) -> anyhow::Result<ExitStat
     }
 }
 
+fn watch(workspace_root: &Utf8Path, cmd: WatchSubcommand) -> anyhow::Result<ExitStatus> {
+    let metadata = workspace_metadata(&cmd.run)?;
+    let selected = select_packages(&metadata.packages, &metadata.default_members, &cmd.run);
+    if selected.is_empty() {
+        return Err(anyhow!("No workspace packages are selected"));
+    }
+    let watched = watched_paths(workspace_root, &metadata.packages);
+
+    let mut sources = source_files(&watched, &metadata.target_directory);
+    let mut affected = selected.clone();
+    loop {
+        let status = watch_run(&cmd, &affected)?;
+        println!(
+            "Tests finished ({status}), watching {workspace_root} for changes. Press Ctrl+C to stop."
+        );
+
+        affected = loop {
+            let changed = wait_for_changes(
+                &watched,
+                &metadata.target_directory,
+                &mut sources,
+                Duration::from_millis(cmd.delay),
+            );
+            let affected: BTreeSet<String> = affected_packages(&metadata.packages, &changed)
+                .intersection(&selected)
+                .cloned()
+                .collect();
+            if !affected.is_empty() {
+                break affected;
+            }
+            println!("No selected packages are affected by the changes");
+        };
+    }
+}
+
+/// Rebuilds and runs the tests of the given packages with `cargo test`
+fn watch_run(cmd: &WatchSubcommand, packages: &BTreeSet<String>) -> anyhow::Result<ExitStatus> {
+    let cargo_path: Utf8PathBuf = std::env::var("CARGO").map_or("cargo".into(), Utf8PathBuf::from);
+    let mut args = vec!["test".to_string()];
+    for package in packages {
+        args.push("--package".to_string());
+        args.push(package.clone());
+    }
+    args.extend(cargo_test_args(&cmd.run));
+    args.extend(cmd.run.testname.iter().cloned());
+    args.push("--".to_string());
+    args.extend(cmd.run.test_args.iter().cloned());
+
+    let mut command = std::process::Command::new(&cargo_path);
+    command.args(&args);
+    if cmd.failed_first {
+        // Only recognized by the test-r harness, other harnesses would reject `--failed-first`
+        command.env(FAILED_FIRST_ENV_VAR, "1");
+        println!(
+            "Executing {FAILED_FIRST_ENV_VAR}=1 {} {}",
+            cargo_path,
+            args.join(" ")
+        );
+    } else {
+        println!("Executing {} {}", cargo_path, args.join(" "));
+    }
+    Ok(command.status()?)
+}
+
+/// Renders the options of `cmd` to be passed to `cargo test`, except the package selection and
+/// the test name filters and arguments
+fn cargo_test_args(cmd: &RunSubcommand) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut flag = |enabled: bool, name: &str| {
+        if enabled {
+            args.push(name.to_string());
+        }
+    };
+    flag(cmd.no_run, "--no-run");
+    flag(cmd.no_fail_fast, "--no-fail-fast");
+    flag(cmd.lib, "--lib");
+    flag(cmd.bins, "--bins");
+    flag(cmd.examples, "--examples");
+    flag(cmd.tests, "--tests");
+    flag(cmd.benches, "--benches");
+    flag(cmd.all_targets, "--all-targets");
+    flag(cmd.doc, "--doc");
+    flag(cmd.no_default_features, "--no-default-features");
+    flag(cmd.release, "--release");
+    flag(cmd.ignore_rust_version, "--ignore-rust-version");
+    flag(cmd.quiet, "--quiet");
+    flag(cmd.frozen, "--frozen");
+    flag(cmd.locked, "--locked");
+    flag(cmd.offline, "--offline");
+    flag(cmd.keep_going, "--keep-going");
+    flag(cmd.future_incompat_report, "--future-incompat-report");
+
+    let options = [
+        ("--bin", &cmd.bin),
+        ("--example", &cmd.example),
+        ("--bench", &cmd.bench),
+        ("--features", &cmd.features),
+        ("--target", &cmd.target),
+        ("--profile", &cmd.profile),
+        ("--target-dir", &cmd.target_dir),
+        ("--color", &cmd.color),
+        ("--message-format", &cmd.message_format),
+        ("--manifest-path", &cmd.manifest_path),
+    ];
+    for (name, value) in options {
+        if let Some(value) = value {
+            args.push(name.to_string());
+            args.push(value.clone());
+        }
+    }
+    if let Some(timings) = &cmd.timings {
+        args.push(format!("--timings={timings}"));
+    }
+    for test in &cmd.test {
+        args.push("--test".to_string());
+        args.push(test.clone());
+    }
+    if let Some(jobs) = cmd.jobs {
+        args.push("--jobs".to_string());
+        args.push(jobs.to_string());
+    }
+    for _ in 0..cmd.verbose.unwrap_or(0) {
+        args.push("--verbose".to_string());
+    }
+    args
+}
+
+/// Environment variable enabling `--failed-first` in the test-r harness
+const FAILED_FIRST_ENV_VAR: &str = "TEST_R_FAILED_FIRST";
+
+/// The workspace, as reported by `cargo metadata`
+#[derive(Debug, Clone)]
+struct WorkspaceMetadata {
+    packages: Vec<WorkspacePackage>,
+    /// Names of the packages tested when no package is selected: the package in the current
+    /// directory, or the default members of the workspace
+    default_members: BTreeSet<String>,
+    /// The target directory, including the ones set with `--target-dir`, `CARGO_TARGET_DIR` or
+    /// `build.target-dir`
+    target_directory: Utf8PathBuf,
+}
+
+/// A package of the workspace, as reported by `cargo metadata`
+#[derive(Debug, Clone)]
+struct WorkspacePackage {
+    name: String,
+    directory: Utf8PathBuf,
+    /// Names of the workspace packages it depends on, including dev- and build-dependencies
+    dependencies: Vec<String>,
+    /// The manifest, and the source directories of the package's targets. Sources placed
+    /// directly in the package directory, like `build.rs`, are listed as files.
+    sources: Vec<Utf8PathBuf>,
+}
+
+fn workspace_metadata(cmd: &RunSubcommand) -> anyhow::Result<WorkspaceMetadata> {
+    let cargo_path: Utf8PathBuf = std::env::var("CARGO").map_or("cargo".into(), Utf8PathBuf::from);
+    let mut command = std::process::Command::new(cargo_path);
+    command.args(["metadata", "--no-deps", "--format-version", "1"]);
+    if let Some(manifest_path) = &cmd.manifest_path {
+        command.args(["--manifest-path", manifest_path]);
+    }
+    if let Some(target_dir) = &cmd.target_dir {
+        // `cargo metadata` has no `--target-dir` option
+        command.env("CARGO_TARGET_DIR", target_dir);
+    }
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let packages = metadata["packages"]
+        .as_array()
+        .ok_or_else(|| anyhow!("Unexpected cargo metadata output"))?;
+    let target_directory = metadata["target_directory"]
+        .as_str()
+        .ok_or_else(|| anyhow!("No target directory in cargo metadata output"))?;
+
+    let names: BTreeSet<&str> = packages
+        .iter()
+        .filter_map(|package| package["name"].as_str())
+        .collect();
+    let mut result = Vec::new();
+    let mut ids = HashMap::new();
+    for package in packages {
+        let name = package["name"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Package without name in cargo metadata output"))?;
+        let manifest_path = package["manifest_path"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Package {name} without manifest path"))?;
+        let directory = Utf8Path::new(manifest_path)
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid manifest path of package {name}: {manifest_path}"))?
+            .to_path_buf();
+        let dependencies = package["dependencies"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|dependency| dependency["name"].as_str())
+            .filter(|dependency| names.contains(dependency))
+            .map(|dependency| dependency.to_string())
+            .collect();
+        let mut sources = BTreeSet::from([Utf8PathBuf::from(manifest_path)]);
+        for src_path in package["targets"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|target| target["src_path"].as_str())
+        {
+            let src_path = Utf8Path::new(src_path);
+            match src_path.parent() {
+                Some(parent) if parent != directory => sources.insert(parent.to_path_buf()),
+                _ => sources.insert(src_path.to_path_buf()),
+            };
+        }
+        if let Some(id) = package["id"].as_str() {
+            ids.insert(id, name);
+        }
+        result.push(WorkspacePackage {
+            name: name.to_string(),
+            directory,
+            dependencies,
+            sources: sources.into_iter().collect(),
+        });
+    }
+
+    let mut current_directory = Utf8PathBuf::try_from(std::env::current_dir()?)?;
+    if let Some(manifest_path) = &cmd.manifest_path
+        && let Some(directory) = current_directory.join(manifest_path).parent()
+    {
+        current_directory = directory.to_path_buf();
+    }
+    let default_members = match innermost_package(&result, &current_directory) {
+        Some(package) => BTreeSet::from([package.name.clone()]),
+        None => metadata["workspace_default_members"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|id| id.as_str().and_then(|id| ids.get(id)))
+            .map(|name| name.to_string())
+            .collect(),
+    };
+
+    Ok(WorkspaceMetadata {
+        packages: result,
+        default_members,
+        target_directory: target_directory.into(),
+    })
+}
+
+/// The names of the workspace packages selected by `--package`, `--workspace` and `--exclude`, or
+/// the default members if no package is selected, like `cargo test` does
+fn select_packages(
+    packages: &[WorkspacePackage],
+    default_members: &BTreeSet<String>,
+    cmd: &RunSubcommand,
+) -> BTreeSet<String> {
+    packages
+        .iter()
+        .map(|package| &package.name)
+        .filter(|name| {
+            if !cmd.package.is_empty() {
+                cmd.package
+                    .iter()
+                    .any(|condition| glob_match(condition, name))
+            } else {
+                cmd.workspace || cmd.all || default_members.contains(*name)
+            }
+        })
+        .filter(|name| {
+            cmd.exclude
+                .iter()
+                .all(|condition| !glob_match(condition, name))
+        })
+        .cloned()
+        .collect()
+}
+
+/// The innermost package directory containing `path`, as packages may be nested
+fn innermost_package<'a>(
+    packages: &'a [WorkspacePackage],
+    path: &Utf8Path,
+) -> Option<&'a WorkspacePackage> {
+    packages
+        .iter()
+        .filter(|package| path.starts_with(&package.directory))
+        .max_by_key(|package| package.directory.as_str().len())
+}
+
+/// Files outside of the packages which affect the build of every package
+const WORKSPACE_FILES: [&str; 4] = [
+    "Cargo.toml",
+    "Cargo.lock",
+    "rust-toolchain",
+    "rust-toolchain.toml",
+];
+
+/// The names of the packages containing the changed files, and of the packages depending on
+/// them. Changing the workspace's `Cargo.toml` or `Cargo.lock` affects every package, while
+/// other files outside of the packages are ignored.
+fn affected_packages(packages: &[WorkspacePackage], changed: &[Utf8PathBuf]) -> BTreeSet<String> {
+    let mut affected = BTreeSet::new();
+    for path in changed {
+        match innermost_package(packages, path) {
+            Some(package) => {
+                affected.insert(package.name.clone());
+            }
+            None if path
+                .file_name()
+                .is_some_and(|name| WORKSPACE_FILES.contains(&name)) =>
+            {
+                return packages
+                    .iter()
+                    .map(|package| package.name.clone())
+                    .collect();
+            }
+            None => {}
+        }
+    }
+
+    loop {
+        let dependents: Vec<String> = packages
+            .iter()
+            .filter(|package| !affected.contains(&package.name))
+            .filter(|package| {
+                package
+                    .dependencies
+                    .iter()
+                    .any(|dependency| affected.contains(dependency))
+            })
+            .map(|package| package.name.clone())
+            .collect();
+        if dependents.is_empty() {
+            return affected;
+        }
+        affected.extend(dependents);
+    }
+}
+
+/// The files and directories watched for changes: the sources and manifests of the packages, and
+/// the files of the workspace affecting every package
+fn watched_paths(workspace_root: &Utf8Path, packages: &[WorkspacePackage]) -> Vec<Utf8PathBuf> {
+    let paths: BTreeSet<Utf8PathBuf> = WORKSPACE_FILES
+        .iter()
+        .map(|file| workspace_root.join(file))
+        .chain(
+            packages
+                .iter()
+                .flat_map(|package| package.sources.iter().cloned()),
+        )
+        .collect();
+    paths.into_iter().collect()
+}
+
+/// Modification times of the files in the watched paths, except the target directory and hidden
+/// files and directories
+fn source_files(
+    watched: &[Utf8PathBuf],
+    target_dir: &Utf8Path,
+) -> HashMap<Utf8PathBuf, SystemTime> {
+    let mut result = HashMap::new();
+    let mut directories = Vec::new();
+    for path in watched {
+        let Ok(metadata) = path.metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            directories.push(path.clone());
+        } else if let Ok(modified) = metadata.modified() {
+            result.insert(path.clone(), modified);
+        }
+    }
+    while let Some(directory) = directories.pop() {
+        let Ok(entries) = directory.read_dir_utf8() else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_name().starts_with('.') || path == target_dir {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                directories.push(path.to_path_buf());
+            } else if let Ok(modified) = metadata.modified() {
+                result.insert(path.to_path_buf(), modified);
+            }
+        }
+    }
+    result
+}
+
+/// Polls the watched paths until some files are added, modified or removed, and no more changes
+/// happen for `delay`. Returns the changed files, and updates `sources` to the current state.
+fn wait_for_changes(
+    watched: &[Utf8PathBuf],
+    target_dir: &Utf8Path,
+    sources: &mut HashMap<Utf8PathBuf, SystemTime>,
+    delay: Duration,
+) -> Vec<Utf8PathBuf> {
+    let mut changed = BTreeSet::new();
+    loop {
+        std::thread::sleep(delay);
+        let current = source_files(watched, target_dir);
+        let changes: Vec<Utf8PathBuf> = current
+            .iter()
+            .filter(|(path, modified)| sources.get(*path) != Some(modified))
+            .map(|(path, _)| path.clone())
+            .chain(
+                sources
+                    .keys()
+                    .filter(|path| !current.contains_key(*path))
+                    .cloned(),
+            )
+            .collect();
+        *sources = current;
+        if changes.is_empty() && !changed.is_empty() {
+            return changed.into_iter().collect();
+        }
+        changed.extend(changes);
+    }
+}
+
 fn validate_supported_test_args(cmd: &RunSubcommand) -> anyhow::Result<()> {
     // NOTE: this feature is experimental and only a small subset of the features are
     // supported. We fail if the user is trying to use something unsupported rather than
@@ -483,3 +931,119 @@ fn nextest_archive_target(workspace_root: &Utf8Path, target_dir: &Option<String>
         workspace_root.join("target/nextest-archive")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, directory: &str, dependencies: &[&str]) -> WorkspacePackage {
+        WorkspacePackage {
+            name: name.to_string(),
+            directory: directory.into(),
+            dependencies: dependencies.iter().map(|name| name.to_string()).collect(),
+            sources: vec![format!("{directory}/Cargo.toml").into()],
+        }
+    }
+
+    fn workspace() -> Vec<WorkspacePackage> {
+        vec![
+            package("core", "/ws/core", &[]),
+            package("macro", "/ws/macro", &[]),
+            package("lib", "/ws/lib", &["core", "macro"]),
+            package("app", "/ws/app", &["lib"]),
+            package("app-plugin", "/ws/app/plugin", &[]),
+        ]
+    }
+
+    fn run_args(args: &[&str]) -> RunSubcommand {
+        let args = ["cargo-test-r", "run"].iter().chain(args);
+        match Command::try_parse_from(args).unwrap() {
+            Command::Run(cmd) => cmd,
+            other => panic!("Unexpected command: {other:?}"),
+        }
+    }
+
+    fn names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn default_members_are_selected_without_package_selection() {
+        let default_members = names(&["app"]);
+        assert_eq!(
+            select_packages(&workspace(), &default_members, &run_args(&[])),
+            names(&["app"])
+        );
+    }
+
+    #[test]
+    fn workspace_selects_every_package() {
+        let default_members = names(&["app"]);
+        for flag in ["--workspace", "--all"] {
+            assert_eq!(
+                select_packages(&workspace(), &default_members, &run_args(&[flag])),
+                names(&["app", "app-plugin", "core", "lib", "macro"])
+            );
+        }
+        assert_eq!(
+            select_packages(
+                &workspace(),
+                &default_members,
+                &run_args(&["--workspace", "--exclude", "app*"])
+            ),
+            names(&["core", "lib", "macro"])
+        );
+    }
+
+    #[test]
+    fn packages_are_selected_by_glob() {
+        assert_eq!(
+            select_packages(
+                &workspace(),
+                &names(&["app"]),
+                &run_args(&["-p", "app*", "-p", "core"])
+            ),
+            names(&["app", "app-plugin", "core"])
+        );
+    }
+
+    #[test]
+    fn dependents_of_changed_packages_are_affected() {
+        assert_eq!(
+            affected_packages(&workspace(), &["/ws/core/src/lib.rs".into()]),
+            names(&["app", "core", "lib"])
+        );
+        assert_eq!(
+            affected_packages(&workspace(), &["/ws/app/src/main.rs".into()]),
+            names(&["app"])
+        );
+    }
+
+    #[test]
+    fn changes_belong_to_the_innermost_package() {
+        assert_eq!(
+            affected_packages(&workspace(), &["/ws/app/plugin/src/lib.rs".into()]),
+            names(&["app-plugin"])
+        );
+    }
+
+    #[test]
+    fn workspace_files_affect_every_package() {
+        assert_eq!(
+            affected_packages(&workspace(), &["/ws/Cargo.lock".into()]),
+            names(&["app", "app-plugin", "core", "lib", "macro"])
+        );
+        assert!(affected_packages(&workspace(), &["/ws/book/src/intro.md".into()]).is_empty());
+    }
+
+    #[test]
+    fn watched_paths_include_package_sources_and_workspace_files() {
+        let mut packages = workspace();
+        packages[0].sources.push("/ws/core/src".into());
+        let watched = watched_paths(Utf8Path::new("/ws"), &packages);
+        assert!(watched.contains(&"/ws/Cargo.lock".into()));
+        assert!(watched.contains(&"/ws/core/src".into()));
+        assert!(watched.contains(&"/ws/app/Cargo.toml".into()));
+        assert!(!watched.iter().any(|path| path.starts_with("/ws/book")));
+    }
+}
@@ -223,6 +223,152 @@ mod suite_timeout_tests {
     }
 }
 
+#[cfg(test)]
+mod sync_timeout_tests {
+    use test_r::{test, timeout};
+
+    #[test]
+    #[timeout("3s")]
+    fn sync_timeout_exceeds() {
+        println!("Start sleeping in sync_timeout_exceeds");
+        std::thread::sleep(std::time::Duration::from_secs(30));
+        let result = 2 + 2;
+        assert_eq!(result, 4);
+    }
+
+    #[test]
+    #[timeout("3s")]
+    fn sync_timeout_short_test() {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let result = 2 + 2;
+        assert_eq!(result, 4);
+    }
+}
+
+#[cfg(test)]
+mod worker_crash_tests {
+    use test_r::test;
+
+    #[test]
+    fn worker_crash_aborts() {
+        eprintln!("About to crash in worker_crash_aborts");
+        std::process::abort();
+    }
+
+    #[test]
+    fn worker_crash_once() {
+        // Crashes only on the first run, when pointed to a marker file
+        if let Ok(marker) = std::env::var("TEST_R_CRASH_ONCE_MARKER") {
+            if !std::path::Path::new(&marker).exists() {
+                std::fs::write(&marker, "crashed").unwrap();
+                std::process::abort();
+            }
+        }
+    }
+
+    #[test]
+    fn worker_crash_survivor() {
+        let result = 2 + 2;
+        assert_eq!(result, 4);
+    }
+}
+
+#[cfg(test)]
+mod leftover_process_tests {
+    use test_r::{isolated, test};
+
+    #[test]
+    #[isolated]
+    #[allow(clippy::zombie_processes)]
+    fn leaves_a_process_behind() {
+        // Starts a process inheriting the worker's stdout and stderr, which
+        // outlives the test, when given how long it should run
+        if let Ok(seconds) = std::env::var("TEST_R_LEFTOVER_PROCESS_SECONDS") {
+            std::process::Command::new("sleep")
+                .arg(seconds)
+                .spawn()
+                .unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod isolation_tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use test_r::{isolated, test};
+
+    static TESTS_RUN_IN_PROCESS: AtomicUsize = AtomicUsize::new(0);
+
+    fn report_process(test_name: &str) -> usize {
+        println!("Process of {test_name}: {}", std::process::id());
+        TESTS_RUN_IN_PROCESS.fetch_add(1, Ordering::SeqCst)
+    }
+
+    #[test]
+    #[isolated]
+    fn isolated_test_1() {
+        assert_eq!(report_process("isolated_test_1"), 0);
+    }
+
+    #[test]
+    #[isolated]
+    fn isolated_test_2() {
+        assert_eq!(report_process("isolated_test_2"), 0);
+    }
+
+    #[test]
+    fn non_isolated_test_1() {
+        report_process("non_isolated_test_1");
+    }
+
+    #[test]
+    fn non_isolated_test_2() {
+        report_process("non_isolated_test_2");
+    }
+}
+
+#[cfg(test)]
+mod rerun_tests {
+    use test_r::test;
+
+    #[test]
+    fn rerun_fails_once() {
+        // Fails only on the first run, when pointed to a marker file
+        if let Ok(marker) = std::env::var("TEST_R_FAIL_ONCE_MARKER") {
+            if !std::path::Path::new(&marker).exists() {
+                std::fs::write(&marker, "failed").unwrap();
+                panic!("rerun_fails_once failed on the first run");
+            }
+        }
+    }
+
+    #[test]
+    fn rerun_passes() {
+        let result = 2 + 2;
+        assert_eq!(result, 4);
+    }
+}
+
+#[cfg(test)]
+mod fail_fast_tests {
+    use test_r::test;
+
+    #[test]
+    fn fail_fast_1() {
+        panic!("fail_fast_1 failed");
+    }
+
+    #[test]
+    fn fail_fast_2() {
+        panic!("fail_fast_2 failed");
+    }
+
+    #[test]
+    fn fail_fast_3() {
+        panic!("fail_fast_3 failed");
+    }
+}
+
 #[cfg(test)]
 mod suite_timeout_macro_tests {
     use test_r::test;
@@ -497,6 +643,15 @@ mod generated {
         }
     }
 
+    #[test_gen]
+    #[ignore = "the generated cases are not ready"]
+    fn generate_ignored_tests(r: &mut DynamicTestRegistration) {
+        r.add_sync_test("test_0", TestProperties::unit_test(), None, |_| {
+            let result = 2 + 2;
+            assert_eq!(result, 4);
+        });
+    }
+
     #[test_gen]
     async fn generate_tests_2(r: &mut DynamicTestRegistration) {
         println!("Generating some tests in an async generator");
@@ -680,7 +835,18 @@ mod generated {
                     ensure_time_control: ReportTimeControl::Disabled,
                     tags: vec!["example".to_string()],
                     is_ignored: false,
+                    ignore_reason: None,
                     detached_panic_policy: DetachedPanicPolicy::FailTest,
+                    is_isolated: false,
+                    resources: vec![],
+                    depends_on: vec![],
+                    memory_limit: None,
+                    env: vec![],
+                    cwd: None,
+                    hook: None,
+                    dependency_types: vec![],
+                    matrix_case: vec![],
+                    display_name: None,
                 },
                 None,
                 move |_| {
@@ -711,7 +877,18 @@ mod generated {
                     ensure_time_control: ReportTimeControl::Disabled,
                     tags: vec!["example".to_string()],
                     is_ignored: false,
+                    ignore_reason: None,
                     detached_panic_policy: DetachedPanicPolicy::FailTest,
+                    is_isolated: false,
+                    resources: vec![],
+                    depends_on: vec![],
+                    memory_limit: None,
+                    env: vec![],
+                    cwd: None,
+                    hook: None,
+                    dependency_types: vec![],
+                    matrix_case: vec![],
+                    display_name: None,
                 },
                 move || async {
                     println!("Running test {i}");
@@ -860,3 +1037,692 @@ mod nested_sequential {
         }
     }
 }
+
+#[cfg(test)]
+mod resource_tests {
+    use std::path::PathBuf;
+    use std::time::Duration;
+    use test_r::uses_resource;
+
+    /// Occupies one of the `limit` slots of a resource by creating a marker file, so
+    /// exceeding the limit is detected even when the tests run in different worker processes
+    struct Slot(PathBuf);
+
+    impl Drop for Slot {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn occupy(resource: &str, limit: usize) -> Slot {
+        for slot in 0..limit {
+            let path =
+                std::env::temp_dir().join(format!("{}-{resource}-{slot}", env!("CARGO_PKG_NAME")));
+            if std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .is_ok()
+            {
+                return Slot(path);
+            }
+        }
+        panic!("More than {limit} tests are using {resource} at the same time!");
+    }
+
+    async fn use_resource(resource: &str, limit: usize) {
+        let _slot = occupy(resource, limit);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    mod database {
+        use super::use_resource;
+        use test_r::{test, uses_resource};
+
+        #[test]
+        #[uses_resource(database)]
+        async fn database_test_1() {
+            use_resource("database", 1).await;
+        }
+
+        #[test]
+        #[uses_resource(database)]
+        async fn database_test_2() {
+            use_resource("database", 1).await;
+        }
+    }
+
+    #[uses_resource(pool, limit = 2)]
+    mod pool {
+        use super::use_resource;
+        use test_r::{test, uses_resource};
+
+        #[test]
+        async fn pool_test_1() {
+            use_resource("pool", 2).await;
+        }
+
+        #[test]
+        async fn pool_test_2() {
+            use_resource("pool", 2).await;
+        }
+
+        #[test]
+        async fn pool_test_3() {
+            use_resource("pool", 2).await;
+        }
+
+        #[test]
+        #[uses_resource(database)]
+        async fn pool_and_database_test() {
+            let _database = super::occupy("database", 1);
+            use_resource("pool", 2).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod depends_on_tests {
+    use test_r::{depends_on, test};
+
+    /// The steps pass their progress through a file, as they may run in different worker processes
+    fn progress_file() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{}-migration-progress", env!("CARGO_PKG_NAME")))
+    }
+
+    #[test]
+    async fn migration_step_1() {
+        std::fs::write(progress_file(), "1").unwrap();
+    }
+
+    #[test]
+    #[depends_on(migration_step_1)]
+    async fn migration_step_2() {
+        assert_eq!(std::fs::read_to_string(progress_file()).unwrap(), "1");
+        std::fs::write(progress_file(), "2").unwrap();
+    }
+
+    #[test]
+    #[depends_on(self::migration_step_2)]
+    async fn migration_step_3() {
+        assert_eq!(std::fs::read_to_string(progress_file()).unwrap(), "2");
+    }
+}
+
+#[cfg(test)]
+mod depends_on_failure_tests {
+    use test_r::{depends_on, test};
+
+    #[test]
+    async fn broken_step() {
+        panic!("broken_step failed");
+    }
+
+    #[test]
+    #[depends_on(broken_step)]
+    async fn after_broken_step() {
+        panic!("after_broken_step should have been skipped");
+    }
+
+    #[test]
+    #[depends_on(crate::depends_on_tests::migration_step_3)]
+    async fn after_migration() {}
+}
+
+#[cfg(test)]
+mod hook_tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use test_r::{after_all, after_each, before_all, before_each, test, test_dep};
+
+    #[derive(Default)]
+    pub struct HookCounters {
+        before_all: AtomicUsize,
+        before_each: AtomicUsize,
+        after_each: AtomicUsize,
+    }
+
+    #[test_dep]
+    fn hook_counters() -> HookCounters {
+        HookCounters::default()
+    }
+
+    #[before_all]
+    async fn start(counters: &HookCounters) {
+        counters.before_all.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[before_each]
+    async fn set_up(counters: &HookCounters) {
+        counters.before_each.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[after_each]
+    fn tear_down(counters: &HookCounters) {
+        counters.after_each.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[after_all]
+    async fn finish(counters: &HookCounters) {
+        assert_eq!(counters.before_all.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            counters.before_each.load(Ordering::SeqCst),
+            counters.after_each.load(Ordering::SeqCst)
+        );
+    }
+
+    fn assert_set_up(counters: &HookCounters) {
+        assert_eq!(counters.before_all.load(Ordering::SeqCst), 1);
+        assert!(
+            counters.before_each.load(Ordering::SeqCst)
+                > counters.after_each.load(Ordering::SeqCst)
+        );
+    }
+
+    #[test]
+    async fn hooks_run_around_test_1(counters: &HookCounters) {
+        assert_set_up(counters);
+    }
+
+    #[test]
+    async fn hooks_run_around_test_2(counters: &HookCounters) {
+        assert_set_up(counters);
+    }
+
+    mod inner {
+        use super::*;
+        use std::sync::atomic::AtomicBool;
+        use test_r::{before_each, test};
+
+        static INNER_SET_UP: AtomicBool = AtomicBool::new(false);
+
+        #[before_each]
+        fn set_up_inner(counters: &HookCounters) {
+            // Outer suites' hooks run first
+            assert!(counters.before_each.load(Ordering::SeqCst) > 0);
+            INNER_SET_UP.store(true, Ordering::SeqCst);
+        }
+
+        #[test]
+        async fn hooks_of_outer_suites_run_too(counters: &HookCounters) {
+            assert!(INNER_SET_UP.load(Ordering::SeqCst));
+            assert_set_up(counters);
+        }
+    }
+}
+
+#[cfg(test)]
+mod failing_hooks {
+    mod failing_before_all {
+        use test_r::{before_all, test};
+
+        #[before_all]
+        async fn broken_start() {
+            panic!("broken_start failed");
+        }
+
+        #[test]
+        async fn skipped_by_before_all() {
+            panic!("skipped_by_before_all should have been skipped");
+        }
+    }
+
+    mod failing_before_each {
+        use test_r::{after_each, before_each, test};
+
+        #[before_each]
+        async fn broken_set_up() {
+            panic!("broken_set_up failed");
+        }
+
+        #[after_each]
+        async fn broken_tear_down() {
+            panic!("broken_tear_down failed");
+        }
+
+        #[test]
+        async fn skipped_by_before_each() {
+            panic!("skipped_by_before_each should have been skipped");
+        }
+    }
+
+    mod failing_after_all {
+        use test_r::{after_all, test};
+
+        #[after_all]
+        async fn broken_finish() -> Result<(), String> {
+            Err("broken_finish failed".to_string())
+        }
+
+        #[test]
+        async fn passes_before_broken_finish() {}
+    }
+}
+
+#[cfg(test)]
+mod fallible_dep_tests {
+    use test_r::core::CloneableDep;
+    use test_r::{test, test_dep};
+
+    pub struct Config {
+        port: u16,
+    }
+
+    pub struct Database;
+
+    pub struct Repository;
+
+    pub struct Cache;
+
+    pub struct Schema(Vec<u8>);
+
+    impl CloneableDep for Schema {
+        fn to_wire(&self) -> Vec<u8> {
+            self.0.clone()
+        }
+
+        fn from_wire(bytes: &[u8]) -> Self {
+            Schema(bytes.to_vec())
+        }
+    }
+
+    #[test_dep]
+    async fn config() -> std::io::Result<Config> {
+        Ok(Config { port: 8080 })
+    }
+
+    #[test_dep]
+    async fn database(_config: &Config) -> Result<Database, String> {
+        Err("could not connect to the database".to_string())
+    }
+
+    #[test_dep]
+    async fn repository(_database: &Database) -> Repository {
+        Repository
+    }
+
+    #[test_dep(scope = PerWorker)]
+    async fn cache() -> Cache {
+        panic!("the cache directory is not writable")
+    }
+
+    #[test_dep(scope = Cloneable)]
+    async fn schema() -> Result<Schema, String> {
+        Err("the schema is invalid".to_string())
+    }
+
+    #[test]
+    async fn fallible_config(config: &Config) {
+        assert_eq!(config.port, 8080);
+    }
+
+    #[test]
+    async fn fallible_database(_database: &Database) {}
+
+    #[test]
+    async fn fallible_repository(_repository: &Repository) {}
+
+    #[test]
+    async fn fallible_cache(_cache: &Cache) {}
+
+    #[test]
+    async fn fallible_schema(_schema: &Schema) {}
+
+    #[test]
+    async fn fallible_independent() {}
+}
+
+#[cfg(test)]
+mod per_test_tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use test_r::{flaky, non_flaky, test, test_dep};
+
+    pub struct Constructions(AtomicUsize);
+
+    pub struct Scratch {
+        id: usize,
+        items: Vec<String>,
+    }
+
+    pub struct Workspace {
+        scratch_id: usize,
+    }
+
+    #[test_dep]
+    fn constructions() -> Constructions {
+        Constructions(AtomicUsize::new(0))
+    }
+
+    #[test_dep(scope = PerTest)]
+    async fn scratch(constructions: &Constructions) -> Scratch {
+        Scratch {
+            id: constructions.0.fetch_add(1, Ordering::SeqCst),
+            items: Vec::new(),
+        }
+    }
+
+    #[test_dep(scope = PerTest)]
+    async fn workspace(scratch: &Scratch) -> Workspace {
+        Workspace {
+            scratch_id: scratch.id,
+        }
+    }
+
+    #[test]
+    async fn per_test_first_mutation(scratch: &mut Scratch) {
+        assert!(scratch.items.is_empty());
+        scratch.items.push("first".to_string());
+        assert_eq!(scratch.items.len(), 1);
+    }
+
+    #[test]
+    async fn per_test_second_mutation(scratch: &mut Scratch) {
+        assert!(scratch.items.is_empty());
+        scratch.items.push("second".to_string());
+        assert_eq!(scratch.items.len(), 1);
+    }
+
+    #[test]
+    async fn per_test_owned(scratch: Scratch) {
+        let mut items = scratch.items;
+        assert!(items.is_empty());
+        items.push("owned".to_string());
+        assert_eq!(items.len(), 1);
+    }
+
+    #[test]
+    async fn per_test_shared_within_test(scratch: &Scratch, workspace: &Workspace) {
+        assert_eq!(workspace.scratch_id, scratch.id);
+    }
+
+    #[test]
+    #[non_flaky(3)]
+    async fn per_test_new_for_each_attempt(scratch: &mut Scratch) {
+        assert!(scratch.items.is_empty());
+        scratch.items.push("attempt".to_string());
+    }
+
+    #[test]
+    #[flaky(3)]
+    async fn per_test_retried(scratch: &mut Scratch, context: &test_r::TestContext) {
+        assert!(scratch.items.is_empty());
+        scratch.items.push("attempt".to_string());
+        assert!(context.attempt() > 1);
+    }
+}
+
+#[cfg(test)]
+mod teardown_tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use test_r::{test, test_dep};
+
+    pub struct Database {
+        open: Arc<AtomicBool>,
+    }
+
+    pub struct Server {
+        database_open: Arc<AtomicBool>,
+        running: AtomicBool,
+    }
+
+    pub struct Lease;
+
+    #[test_dep(teardown = close_database)]
+    fn database() -> Database {
+        Database {
+            open: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    fn close_database(database: &Database) {
+        database.open.store(false, Ordering::SeqCst);
+    }
+
+    #[test_dep(teardown = stop_server)]
+    fn server(database: &Database) -> Server {
+        Server {
+            database_open: database.open.clone(),
+            running: AtomicBool::new(true),
+        }
+    }
+
+    async fn stop_server(server: &Server) -> Result<(), String> {
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        if server.database_open.load(Ordering::SeqCst) {
+            server.running.store(false, Ordering::SeqCst);
+            Ok(())
+        } else {
+            Err("the database was closed before the server was stopped".to_string())
+        }
+    }
+
+    #[test_dep(teardown = release_lease)]
+    fn lease() -> Lease {
+        Lease
+    }
+
+    fn release_lease(_lease: &Lease) -> Result<(), String> {
+        Err("the lease was already released".to_string())
+    }
+
+    #[test]
+    async fn server_is_running(server: &Server) {
+        assert!(server.running.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    async fn database_is_open(database: &Database, _lease: &Lease) {
+        assert!(database.open.load(Ordering::SeqCst));
+    }
+}
+
+#[cfg(test)]
+mod env_tests {
+    use std::path::Path;
+    use test_r::{cwd, env, env_remove, test};
+
+    #[test]
+    #[env(TEST_R_EXAMPLE_VAR = "from the attribute", "TEST_R_EXAMPLE_OTHER" = "other")]
+    async fn env_var_is_set() {
+        assert_eq!(
+            std::env::var("TEST_R_EXAMPLE_VAR").unwrap(),
+            "from the attribute"
+        );
+        assert_eq!(std::env::var("TEST_R_EXAMPLE_OTHER").unwrap(), "other");
+    }
+
+    #[test]
+    async fn env_var_is_restored() {
+        assert!(std::env::var_os("TEST_R_EXAMPLE_VAR").is_none());
+        assert!(std::env::var_os("TEST_R_EXAMPLE_SUITE_VAR").is_none());
+    }
+
+    #[test]
+    #[env_remove(CARGO_PKG_NAME)]
+    async fn env_var_is_removed() {
+        assert!(std::env::var_os("CARGO_PKG_NAME").is_none());
+    }
+
+    #[test]
+    #[cwd("src")]
+    async fn runs_in_working_directory() {
+        assert!(Path::new("lib.rs").exists());
+    }
+
+    #[env(TEST_R_EXAMPLE_SUITE_VAR = "from the suite")]
+    mod suite {
+        use test_r::{env, test};
+
+        #[test]
+        async fn suite_env_var_is_set() {
+            assert_eq!(
+                std::env::var("TEST_R_EXAMPLE_SUITE_VAR").unwrap(),
+                "from the suite"
+            );
+        }
+
+        #[test]
+        #[env(TEST_R_EXAMPLE_SUITE_VAR = "from the test")]
+        async fn test_env_var_overrides_suite() {
+            assert_eq!(
+                std::env::var("TEST_R_EXAMPLE_SUITE_VAR").unwrap(),
+                "from the test"
+            );
+        }
+    }
+
+    mod suite_macro {
+        use test_r::test;
+
+        #[test]
+        async fn suite_cwd_is_applied() {
+            assert!(std::path::Path::new("lib.rs").exists());
+            assert!(std::env::var_os("TEST_R_EXAMPLE_VAR").is_none());
+        }
+    }
+
+    test_r::cwd_suite!(suite_macro, "src");
+    test_r::env_remove_suite!(suite_macro, TEST_R_EXAMPLE_VAR);
+}
+
+#[cfg(test)]
+mod context_tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use test_r::{flaky, tag, test};
+
+    #[test]
+    #[tag(context)]
+    async fn context_describes_the_test(context: &test_r::TestContext) {
+        assert_eq!(
+            context.name(),
+            "test_r_example_tokio::context_tests::context_describes_the_test"
+        );
+        assert_eq!(context.tags(), ["context"]);
+        assert_eq!(context.attempt(), 1);
+        assert_eq!(context.max_attempts(), 1);
+    }
+
+    static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+    #[test]
+    #[flaky(3)]
+    async fn attempts_are_counted(context: &test_r::TestContext) {
+        let attempt = ATTEMPTS.fetch_add(1, Ordering::SeqCst) + 1;
+        assert_eq!(context.attempt(), attempt);
+        assert_eq!(context.max_attempts(), 3);
+        assert!(context.attempt() > 1, "fails in the first attempt");
+    }
+
+    #[test]
+    async fn temp_dir_is_deleted_on_success(context: &test_r::TestContext) {
+        let file = context.temp_dir().join("data.txt");
+        std::fs::write(&file, "data").unwrap();
+        println!("Temporary directory: {}", context.temp_dir().display());
+    }
+}
+
+#[cfg(test)]
+mod failing_context_tests {
+    use test_r::test;
+
+    #[test]
+    async fn temp_dir_is_kept_on_failure(context: &test_r::TestContext) {
+        std::fs::write(context.temp_dir().join("data.txt"), "data").unwrap();
+        panic!("fails to keep the temporary directory");
+    }
+}
+
+#[cfg(test)]
+mod seed_tests {
+    use rand::RngExt;
+    use test_r::test;
+
+    #[test]
+    async fn rng_is_seeded_with_the_test_seed(
+        context: &test_r::TestContext,
+        rng: &mut test_r::TestRng,
+    ) {
+        assert_eq!(rng.seed(), context.seed());
+        let value: u32 = rng.random_range(0..1000);
+        println!("Seed: {}, value: {value}", rng.seed());
+    }
+}
+
+#[cfg(test)]
+mod failing_seed_tests {
+    use rand::RngExt;
+    use test_r::test;
+
+    #[test]
+    async fn random_failure(rng: &mut test_r::TestRng) {
+        let value: u32 = rng.random_range(0..1000);
+        println!("Seed: {}, value: {value}", rng.seed());
+        panic!("failed with {value}");
+    }
+}
+
+#[cfg(test)]
+mod ignore_reason_tests {
+    use test_r::test;
+
+    #[test]
+    #[ignore = "waiting for upstream fix"]
+    async fn ignored_with_reason() {
+        let result = 2 + 2;
+        assert_eq!(result, 4);
+    }
+
+    mod unfinished {
+        use test_r::test;
+
+        #[test]
+        async fn ignored_by_the_suite() {
+            let result = 2 + 2;
+            assert_eq!(result, 4);
+        }
+    }
+
+    test_r::ignore_suite!(unfinished, "the suite is not finished yet");
+}
+
+#[cfg(test)]
+mod display_name_tests {
+    use test_r::{display_name, test};
+
+    #[test]
+    #[display_name("creates user with unicode email")]
+    async fn creates_user_with_unicode_email() {
+        let email = "jürgen@example.com";
+        assert_eq!(email.split_once('@'), Some(("jürgen", "example.com")));
+    }
+}
+
+#[cfg(test)]
+mod memory_limit_tests {
+    use std::hint::black_box;
+    use std::time::{Duration, Instant};
+    use test_r::{memory_limit, test};
+
+    #[test]
+    #[memory_limit("256MiB")]
+    async fn within_memory_limit() {
+        let buffer = black_box(vec![1u8; 16 << 20]);
+        assert_eq!(buffer.len(), 16 << 20);
+    }
+
+    #[test]
+    #[memory_limit("256MiB")]
+    async fn beyond_memory_limit() {
+        let buffer = black_box(vec![1u8; 1 << 30]);
+        assert_eq!(buffer.len(), 1 << 30);
+    }
+
+    #[test]
+    async fn busy_for_ten_seconds() {
+        // Exceeds a CPU time limit given with `--worker-cpu-time-limit`
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(10) {
+            black_box(start.elapsed());
+        }
+    }
+}
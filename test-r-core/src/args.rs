@@ -1,7 +1,10 @@
 use crate::execution::TestSuiteExecution;
+use crate::internal::RegisteredTest;
 use crate::output::TestRunnerOutput;
 use clap::{Parser, ValueEnum};
+use std::collections::{BTreeSet, HashMap};
 use std::ffi::OsString;
+use std::fmt::{Display, Formatter};
 use std::num::NonZero;
 use std::str::FromStr;
 use std::sync::Arc;
@@ -109,10 +112,33 @@ pub struct Arguments {
     #[arg(long = "shuffle-seed", value_name = "SEED", conflicts_with = "shuffle")]
     pub shuffle_seed: Option<u64>,
 
+    /// Seed the random number generators of the tests with SEED. Every test gets its own seed
+    /// derived from SEED and its name. Defaults to the `--shuffle-seed` if set, or a random seed.
+    #[arg(long = "seed", value_name = "SEED")]
+    pub seed: Option<u64>,
+
+    /// Use SEED as the seed of the test named NAME, as printed when it failed (this flag can be
+    /// used multiple times)
+    #[arg(long = "seed-for", value_name = "NAME=SEED")]
+    pub seed_for: Vec<SeedOverride>,
+
     /// Show detailed benchmark statistics for each benchmark
     #[arg(long = "show-stats")]
     pub show_stats: bool,
 
+    /// Show when the dependencies are constructed and dropped, and list the time their
+    /// construction took at the end of the run
+    #[arg(long = "show-deps")]
+    pub show_deps: bool,
+
+    /// Show the tests in an interactive terminal UI, where the captured output of the finished
+    /// tests can be viewed and the selected or failed tests can be run again
+    #[arg(
+        long = "tui",
+        conflicts_with_all = ["nocapture", "quiet", "format", "list", "flaky_run"]
+    )]
+    pub tui: bool,
+
     /// The FILTER string is tested against the name of all tests, and only those
     /// tests whose names contain the filter are run. Multiple filter strings may
     /// be passed, which will run all tests matching any of the filters.
@@ -124,6 +150,76 @@ pub struct Arguments {
     #[arg(long = "flaky-run", value_name = "COUNT")]
     pub flaky_run: Option<usize>,
 
+    /// After running all the selected tests, rerun the failed ones up to COUNT more times.
+    /// Tests passing on a rerun are reported as flaky.
+    #[arg(long = "rerun-failed", value_name = "COUNT")]
+    pub rerun_failed: Option<usize>,
+
+    /// Retry a test up to COUNT times in a fresh worker process if its worker
+    /// process crashed while running it (segfault, abort, `process::exit`, ...).
+    /// Tests failing for any other reason are not retried.
+    #[arg(long = "retry-crashed", value_name = "COUNT")]
+    pub retry_crashed: Option<usize>,
+
+    /// Run every test in its own freshly spawned worker process, so tests touching
+    /// global state cannot affect each other. Tests marked with `#[isolated]` always
+    /// run this way.
+    #[arg(long = "isolate")]
+    pub isolate: bool,
+
+    /// Stop starting new tests after the first failure. Tests already running are
+    /// finished, and the remaining ones are reported as not run.
+    #[arg(long = "fail-fast", conflicts_with = "max_fail")]
+    pub fail_fast: bool,
+
+    /// Stop starting new tests after COUNT tests failed. Tests already running are
+    /// finished, and the remaining ones are reported as not run.
+    #[arg(long = "max-fail", value_name = "COUNT")]
+    pub max_fail: Option<usize>,
+
+    /// Run only the tests that failed when they were last run. Runs all the tests if
+    /// there are no recorded failures.
+    #[arg(long = "last-failed", conflicts_with = "failed_first")]
+    pub last_failed: bool,
+
+    /// Run the tests that failed when they were last run first, then the rest. Also enabled
+    /// by setting the `TEST_R_FAILED_FIRST` environment variable to `1` or `true`, unless
+    /// `--last-failed` is set.
+    #[arg(long = "failed-first")]
+    pub failed_first: bool,
+
+    /// Names of the tests that failed when they were last run, loaded if `--last-failed`
+    /// or `--failed-first` is set
+    #[arg(skip)]
+    pub last_failed_tests: BTreeSet<String>,
+
+    /// Configure the order in which tests are started
+    #[arg(long = "schedule", value_enum, value_name = "definition|longest-first")]
+    pub schedule: Option<ScheduleSetting>,
+
+    /// Execution times of the tests recorded by previous runs, loaded if `--schedule longest-first`
+    /// is set
+    #[arg(skip)]
+    pub test_timings: HashMap<String, Duration>,
+
+    /// Run only the K-th of N partitions of the selected tests, to split them between
+    /// multiple jobs. Tests are assigned to partitions by the hash of their names (`hash:K/N`)
+    /// or by counting them in name order (`count:K/N`).
+    #[arg(long = "partition", value_name = "hash:K/N|count:K/N")]
+    pub partition: Option<Partition>,
+
+    /// Limit the memory each worker process running the tests can allocate, for example
+    /// `512MiB` or `2GB`. Tests running out of it fail with a resource limit exceeded error.
+    /// Tests are always run in worker processes when a limit is set. Only supported on Linux.
+    #[arg(long = "worker-memory-limit", value_name = "SIZE")]
+    pub worker_memory_limit: Option<MemorySize>,
+
+    /// Limit the CPU time each worker process running the tests can use, in seconds or with
+    /// an `s`, `m` or `h` suffix. A worker process may run multiple tests, which all count
+    /// towards its limit, unless combined with `--isolate`. Only supported on Linux.
+    #[arg(long = "worker-cpu-time-limit", value_name = "DURATION")]
+    pub worker_cpu_time_limit: Option<CpuTime>,
+
     /// Run the test suite in worker IPC mode - listening on the given local socket waiting
     /// for the test runner to connect and send test execution requests. The only stdout/stderr
     /// output will be the one emitted by the actual test runs so the test runner can capture them.
@@ -143,6 +239,9 @@ pub struct Arguments {
 }
 
 impl Arguments {
+    /// Environment variable enabling `--failed-first`
+    pub const FAILED_FIRST_ENV_VAR: &'static str = "TEST_R_FAILED_FIRST";
+
     /// Parses the global CLI arguments given to the application.
     ///
     /// If the parsing fails (due to incorrect CLI args), an error is shown and
@@ -155,6 +254,24 @@ impl Arguments {
             result.shuffle_seed = Some(rand::random());
             result.shuffle = false;
         }
+        if result.seed.is_none() {
+            // Same as above, the spawned workers must derive the same seeds for the tests
+            result.seed = Some(result.shuffle_seed.unwrap_or_else(rand::random));
+        }
+        if is_enabled(std::env::var(Self::FAILED_FIRST_ENV_VAR).ok().as_deref())
+            && !result.last_failed
+        {
+            // Set by `cargo test-r watch`, which cannot pass `--failed-first` only to the
+            // test binaries using the test-r harness
+            result.failed_first = true;
+        }
+        if result.last_failed || result.failed_first {
+            // Worker processes load the same list, as it is only updated after the run
+            result.last_failed_tests = crate::last_failed::load();
+        }
+        if result.schedule == Some(ScheduleSetting::LongestFirst) {
+            result.test_timings = crate::timings::load();
+        }
         result
     }
 
@@ -261,15 +378,85 @@ impl Arguments {
             result.push(OsString::from(shuffle_seed.to_string()));
         }
 
+        if let Some(seed) = &self.seed {
+            result.push(OsString::from("--seed"));
+            result.push(OsString::from(seed.to_string()));
+        }
+
+        for seed_for in &self.seed_for {
+            result.push(OsString::from("--seed-for"));
+            result.push(OsString::from(seed_for.to_string()));
+        }
+
         if self.show_stats {
             result.push(OsString::from("--show-stats"));
         }
 
+        if self.show_deps {
+            result.push(OsString::from("--show-deps"));
+        }
+
+        // --tui is not passed on, the workers only report to the parent process
+
         if let Some(flaky_run) = &self.flaky_run {
             result.push(OsString::from("--flaky-run"));
             result.push(OsString::from(flaky_run.to_string()));
         }
 
+        if let Some(rerun_failed) = &self.rerun_failed {
+            result.push(OsString::from("--rerun-failed"));
+            result.push(OsString::from(rerun_failed.to_string()));
+        }
+
+        if let Some(retry_crashed) = &self.retry_crashed {
+            result.push(OsString::from("--retry-crashed"));
+            result.push(OsString::from(retry_crashed.to_string()));
+        }
+
+        if self.isolate {
+            result.push(OsString::from("--isolate"));
+        }
+
+        if self.fail_fast {
+            result.push(OsString::from("--fail-fast"));
+        }
+
+        if let Some(max_fail) = &self.max_fail {
+            result.push(OsString::from("--max-fail"));
+            result.push(OsString::from(max_fail.to_string()));
+        }
+
+        if self.last_failed {
+            result.push(OsString::from("--last-failed"));
+        }
+
+        if let Some(schedule) = self.schedule {
+            result.push(OsString::from("--schedule"));
+            match schedule {
+                ScheduleSetting::Definition => result.push(OsString::from("definition")),
+                ScheduleSetting::LongestFirst => result.push(OsString::from("longest-first")),
+            }
+        }
+
+        if let Some(partition) = &self.partition {
+            result.push(OsString::from("--partition"));
+            result.push(OsString::from(partition.to_string()));
+        }
+
+        if self.failed_first {
+            result.push(OsString::from("--failed-first"));
+        }
+
+        if let Some(worker_memory_limit) = &self.worker_memory_limit {
+            result.push(OsString::from("--worker-memory-limit"));
+            result.push(OsString::from(worker_memory_limit.to_string()));
+        }
+
+        if let Some(worker_cpu_time_limit) = &self.worker_cpu_time_limit {
+            result.push(OsString::from("--worker-cpu-time-limit"));
+            result.push(OsString::from(worker_cpu_time_limit.to_string()));
+        }
+
         if let Some(ipc) = &self.ipc {
             result.push(OsString::from("--ipc"));
             result.push(OsString::from(ipc));
@@ -319,6 +506,67 @@ impl Arguments {
         }
     }
 
+    /// The number of failed tests after which no new tests are started, if
+    /// limited by `--fail-fast` or `--max-fail`. IPC workers only run the tests
+    /// the parent sends them, so the limit is enforced by the parent alone.
+    pub(crate) fn max_failures(&self) -> Option<usize> {
+        if self.ipc.is_some() {
+            None
+        } else if self.fail_fast {
+            Some(1)
+        } else {
+            self.max_fail.map(|max_fail| max_fail.max(1))
+        }
+    }
+
+    /// The number of passes `--rerun-failed` allows, including the first one. Like
+    /// the failure limit, reruns are driven by the parent alone.
+    pub(crate) fn max_rerun_attempts(&self) -> usize {
+        if self.ipc.is_some() {
+            1
+        } else {
+            self.rerun_failed.unwrap_or(0) + 1
+        }
+    }
+
+    /// The number of times `--flaky-run` allows running the whole suite. Every attempt of the
+    /// parent spawns new worker processes, so a worker exits after running its single one.
+    pub(crate) fn flaky_run_attempts(&self) -> usize {
+        if self.ipc.is_some() {
+            1
+        } else {
+            self.flaky_run.unwrap_or(1)
+        }
+    }
+
+    /// Narrows the arguments to the given failed tests for a `--rerun-failed` pass, or to the
+    /// tests selected to run again with `--tui`.
+    /// The worker processes of the pass get the same arguments, so they build the
+    /// same execution plan as the parent.
+    pub(crate) fn for_rerun(&self, failed: &[RegisteredTest]) -> Self {
+        let mut result = self.clone();
+        result.filter = failed.iter().map(|test| test.filterable_name()).collect();
+        result.exact = true;
+        // The failure limit only applies to the first pass
+        result.fail_fast = false;
+        result.max_fail = None;
+        // The failed tests were already selected by the partition of the first pass
+        result.partition = None;
+        result
+    }
+
+    /// Returns `true` if the test failed when it was last run. Only known when
+    /// `--last-failed` or `--failed-first` is set.
+    pub(crate) fn failed_last_time(&self, test: &RegisteredTest) -> bool {
+        self.last_failed_tests
+            .contains(&test.fully_qualified_name())
+    }
+
+    /// Returns `true` if any of the given tests failed when it was last run
+    pub(crate) fn has_last_failed(&self, tests: &[RegisteredTest]) -> bool {
+        tests.iter().any(|test| self.failed_last_time(test))
+    }
+
     /// Returns `true` when this process is the top-level test-suite parent.
     ///
     /// The top-level parent is the only place that may materialise
@@ -337,12 +585,20 @@ impl Arguments {
         output: Arc<dyn TestRunnerOutput>,
     ) {
         let requires_capturing = execution.requires_capturing(!self.nocapture);
+        let requires_isolation = self.isolate || execution.requires_isolation();
+        let requires_limits =
+            self.worker_memory_limit.is_some() || self.worker_cpu_time_limit.is_some();
 
-        if !requires_capturing || self.ipc.is_some() {
-            // If there is no need to capture the output, there are no restrictions to check and apply
+        #[cfg(not(target_os = "linux"))]
+        if requires_limits && self.ipc.is_none() {
+            output.warning("Worker process limits are only supported on Linux, and are ignored.");
+        }
+
+        if !(requires_capturing || requires_isolation || requires_limits) || self.ipc.is_some() {
+            // If there is no need to capture the output, isolate tests or limit their resources, there are no restrictions to check and apply
             // If this is an IPC worker, we don't need to do anything either, as the top level test runner already sets the proper arguments
         } else {
-            // If capture is enabled, we need to spawn at least one worker process
+            // If capture, isolation or limits are enabled, we need to spawn at least one worker process
             self.spawn_workers = true;
 
             if self.test_threads().get() > 1 {
@@ -413,6 +669,198 @@ pub enum FormatSetting {
     Ctrf,
 }
 
+/// Possible values for the `--schedule` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Default)]
+pub enum ScheduleSetting {
+    /// Start the tests in definition order, or in the order given by `--shuffle` (default)
+    #[default]
+    Definition,
+    /// Start the tests that took the longest time in previous runs first
+    LongestFirst,
+}
+
+/// Selects one of the partitions of the tests with `--partition`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Partition {
+    /// Assigns tests to partitions by hashing their fully qualified names, so a test stays
+    /// in the same partition when other tests are added or removed
+    Hash {
+        /// One-based index of the selected partition
+        index: usize,
+        /// Number of partitions
+        count: usize,
+    },
+    /// Assigns tests to partitions in turn, ordered by their fully qualified names, so the
+    /// partitions differ in size by at most one test
+    Count {
+        /// One-based index of the selected partition
+        index: usize,
+        /// Number of partitions
+        count: usize,
+    },
+}
+
+impl FromStr for Partition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected hash:K/N or count:K/N, but got {s}");
+        let (kind, rest) = s.split_once(':').ok_or_else(invalid)?;
+        let (index, count) = rest.split_once('/').ok_or_else(invalid)?;
+        let index = usize::from_str(index).map_err(|_| invalid())?;
+        let count = usize::from_str(count).map_err(|_| invalid())?;
+        if count == 0 {
+            return Err("partition count must be at least 1".to_string());
+        }
+        if index == 0 || index > count {
+            return Err(format!(
+                "partition index must be between 1 and {count}, but got {index}"
+            ));
+        }
+        match kind {
+            "hash" => Ok(Partition::Hash { index, count }),
+            "count" => Ok(Partition::Count { index, count }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Display for Partition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Partition::Hash { index, count } => write!(f, "hash:{index}/{count}"),
+            Partition::Count { index, count } => write!(f, "count:{index}/{count}"),
+        }
+    }
+}
+
+/// Overrides the seed of a single test with `--seed-for NAME=SEED`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedOverride {
+    /// Fully qualified name of the test
+    pub name: String,
+    pub seed: u64,
+}
+
+impl FromStr for SeedOverride {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected NAME=SEED, but got {s}");
+        let (name, seed) = s.rsplit_once('=').ok_or_else(invalid)?;
+        let seed = u64::from_str(seed.trim()).map_err(|_| invalid())?;
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            name: name.to_string(),
+            seed,
+        })
+    }
+}
+
+impl Display for SeedOverride {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.name, self.seed)
+    }
+}
+
+/// An amount of memory, given to `--worker-memory-limit` or `#[memory_limit]` as a number of
+/// bytes with an optional unit, like `512MiB`, `2GB` or `1048576`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct MemorySize {
+    pub bytes: u64,
+}
+
+impl MemorySize {
+    const UNITS: [(&'static str, u64); 9] = [
+        ("TiB", 1 << 40),
+        ("TB", 1_000_000_000_000),
+        ("GiB", 1 << 30),
+        ("GB", 1_000_000_000),
+        ("MiB", 1 << 20),
+        ("MB", 1_000_000),
+        ("KiB", 1 << 10),
+        ("KB", 1_000),
+        ("B", 1),
+    ];
+}
+
+impl FromStr for MemorySize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected a memory size like 512MiB or 2GB, but got {s}");
+        let s = s.trim();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (amount, unit) = s.split_at(split);
+        let amount = u64::from_str(amount).map_err(|_| invalid())?;
+        let unit = unit.trim();
+        let multiplier = if unit.is_empty() {
+            1
+        } else {
+            Self::UNITS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(unit))
+                .map(|(_, multiplier)| *multiplier)
+                .ok_or_else(invalid)?
+        };
+        let bytes = amount.checked_mul(multiplier).ok_or_else(invalid)?;
+        if bytes == 0 {
+            return Err(format!(
+                "memory size must be greater than zero, but got {s}"
+            ));
+        }
+        Ok(Self { bytes })
+    }
+}
+
+impl Display for MemorySize {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (unit, multiplier) = Self::UNITS
+            .iter()
+            .find(|(_, multiplier)| self.bytes.is_multiple_of(*multiplier))
+            .expect("every size is a multiple of one byte");
+        write!(f, "{}{unit}", self.bytes / multiplier)
+    }
+}
+
+/// An amount of CPU time given to `--worker-cpu-time-limit`, as a number of seconds with an
+/// optional `s`, `m` or `h` suffix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CpuTime {
+    pub seconds: u64,
+}
+
+impl FromStr for CpuTime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected a CPU time like 30s, 5m or 1h, but got {s}");
+        let s = s.trim();
+        let (amount, multiplier) = if let Some(amount) = s.strip_suffix('h') {
+            (amount, 60 * 60)
+        } else if let Some(amount) = s.strip_suffix('m') {
+            (amount, 60)
+        } else {
+            (s.strip_suffix('s').unwrap_or(s), 1)
+        };
+        let amount = u64::from_str(amount).map_err(|_| invalid())?;
+        let seconds = amount.checked_mul(multiplier).ok_or_else(invalid)?;
+        if seconds == 0 {
+            return Err(format!("CPU time must be greater than zero, but got {s}"));
+        }
+        Ok(Self { seconds })
+    }
+}
+
+impl Display for CpuTime {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}s", self.seconds)
+    }
+}
+
 /// Structure denoting time limits for test execution.
 ///
 /// From https://github.com/rust-lang/rust/blob/master/library/test/src/time.rs
@@ -474,10 +922,26 @@ impl TimeThreshold {
     }
 }
 
+/// Whether the value of an environment variable toggle turns it on
+fn is_enabled(value: Option<&str>) -> bool {
+    value.is_some_and(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn only_truthy_env_values_are_enabled() {
+        assert!(is_enabled(Some("1")));
+        assert!(is_enabled(Some("true")));
+        assert!(is_enabled(Some("TRUE")));
+        assert!(!is_enabled(Some("0")));
+        assert!(!is_enabled(Some("false")));
+        assert!(!is_enabled(Some("")));
+        assert!(!is_enabled(None));
+    }
+
     #[test]
     fn verify_cli() {
         use clap::CommandFactory;
@@ -521,6 +985,14 @@ mod tests {
         assert_eq!(args.test_threads().get(), 1);
     }
 
+    #[test]
+    fn ipc_workers_run_a_single_flaky_run_attempt() {
+        let mut args: Arguments = Parser::parse_from(["test-bin", "--flaky-run", "3"]);
+        assert_eq!(args.flaky_run_attempts(), 3);
+        args.ipc = Some("test-ipc-socket".to_string());
+        assert_eq!(args.flaky_run_attempts(), 1);
+    }
+
     /// Phase 3.3: regression coverage for the `--worker-index` round trip.
     /// If `to_args` ever drops the field or `Parser::parse_from` ever fails
     /// to populate it, spawned workers would silently fall back to index 0
@@ -563,4 +1035,133 @@ mod tests {
              got {argv_strings:?}"
         );
     }
+
+    #[test]
+    fn partition_round_trips_through_to_args_and_parse() {
+        let args: Arguments = Parser::parse_from(["test-bin", "--partition", "count:2/3"]);
+        assert_eq!(
+            args.partition,
+            Some(Partition::Count { index: 2, count: 3 })
+        );
+
+        let mut argv: Vec<OsString> = vec![OsString::from("test-bin")];
+        argv.extend(args.to_args());
+        let parsed: Arguments = Parser::parse_from(argv);
+        assert_eq!(parsed.partition, args.partition);
+    }
+
+    #[test]
+    fn invalid_partitions_are_rejected() {
+        for value in ["hash:0/2", "hash:3/2", "count:1", "size:1/2", "hash:a/b"] {
+            assert!(
+                Partition::from_str(value).is_err(),
+                "{value} should not be accepted"
+            );
+        }
+        assert_eq!(
+            Partition::from_str("hash:1/0"),
+            Err("partition count must be at least 1".to_string())
+        );
+        assert_eq!(
+            Partition::from_str("hash:1/1"),
+            Ok(Partition::Hash { index: 1, count: 1 })
+        );
+    }
+
+    #[test]
+    fn seeds_round_trip_through_to_args_and_parse() {
+        let args: Arguments = Parser::parse_from([
+            "test-bin",
+            "--seed",
+            "42",
+            "--seed-for",
+            "my_crate::tests::random=1234",
+        ]);
+        assert_eq!(args.seed, Some(42));
+        assert_eq!(
+            args.seed_for,
+            vec![SeedOverride {
+                name: "my_crate::tests::random".to_string(),
+                seed: 1234
+            }]
+        );
+
+        let mut argv: Vec<OsString> = vec![OsString::from("test-bin")];
+        argv.extend(args.to_args());
+        let parsed: Arguments = Parser::parse_from(argv);
+        assert_eq!(parsed.seed, args.seed);
+        assert_eq!(parsed.seed_for, args.seed_for);
+    }
+
+    #[test]
+    fn invalid_seed_overrides_are_rejected() {
+        for value in [
+            "my_crate::test",
+            "my_crate::test=abc",
+            "=12",
+            "my_crate::test=-1",
+        ] {
+            assert!(
+                SeedOverride::from_str(value).is_err(),
+                "{value} should not be accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn worker_limits_round_trip_through_to_args_and_parse() {
+        let args: Arguments = Parser::parse_from([
+            "test-bin",
+            "--worker-memory-limit",
+            "512MiB",
+            "--worker-cpu-time-limit",
+            "2m",
+        ]);
+        assert_eq!(
+            args.worker_memory_limit,
+            Some(MemorySize { bytes: 512 << 20 })
+        );
+        assert_eq!(args.worker_cpu_time_limit, Some(CpuTime { seconds: 120 }));
+
+        let mut argv: Vec<OsString> = vec![OsString::from("test-bin")];
+        argv.extend(args.to_args());
+        let parsed: Arguments = Parser::parse_from(argv);
+        assert_eq!(parsed.worker_memory_limit, args.worker_memory_limit);
+        assert_eq!(parsed.worker_cpu_time_limit, args.worker_cpu_time_limit);
+    }
+
+    #[test]
+    fn memory_sizes_are_parsed_and_displayed_with_units() {
+        for (value, bytes, displayed) in [
+            ("512MiB", 512 << 20, "512MiB"),
+            ("2GB", 2_000_000_000, "2GB"),
+            ("1536 kib", 1536 << 10, "1536KiB"),
+            ("1048576", 1 << 20, "1MiB"),
+            ("1001", 1001, "1001B"),
+        ] {
+            let size = MemorySize::from_str(value).unwrap();
+            assert_eq!(size.bytes, bytes, "{value}");
+            assert_eq!(size.to_string(), displayed, "{value}");
+        }
+        for value in ["", "0MiB", "12 parsecs", "MiB", "-1GiB"] {
+            assert!(
+                MemorySize::from_str(value).is_err(),
+                "{value} should not be accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn cpu_times_are_parsed_in_seconds() {
+        assert_eq!(CpuTime::from_str("30"), Ok(CpuTime { seconds: 30 }));
+        assert_eq!(CpuTime::from_str("30s"), Ok(CpuTime { seconds: 30 }));
+        assert_eq!(CpuTime::from_str("5m"), Ok(CpuTime { seconds: 300 }));
+        assert_eq!(CpuTime::from_str("1h"), Ok(CpuTime { seconds: 3600 }));
+        for value in ["0s", "1.5s", "1d", ""] {
+            assert!(
+                CpuTime::from_str(value).is_err(),
+                "{value} should not be accepted"
+            );
+        }
+    }
 }
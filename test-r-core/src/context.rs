@@ -0,0 +1,163 @@
+//! Information about the running test, injected into test functions taking a `&TestContext`
+//! parameter.
+
+use crate::internal::{DependencyView, FlakinessControl, RegisteredTest, ShouldPanic};
+use std::any::Any;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use uuid::Uuid;
+
+/// Describes the test being run. Test functions can get it by taking a `&TestContext` parameter,
+/// which is resolved by the `#[test]` macro just like a dependency.
+///
+/// A new context is created for every attempt of a test, so the attempt number and the temporary
+/// directory belong to the current attempt.
+#[derive(Debug)]
+pub struct TestContext {
+    name: String,
+    tags: Vec<String>,
+    attempt: usize,
+    max_attempts: usize,
+    worker_index: usize,
+    seed: u64,
+    temp_dir: OnceLock<PathBuf>,
+}
+
+impl TestContext {
+    /// The name the context is stored with in the dependencies of a test
+    pub const DEPENDENCY_NAME: &'static str = "test_r::TestContext";
+
+    pub(crate) fn new(test: &RegisteredTest, attempt: usize) -> Self {
+        let max_attempts = match test.props.flakiness_control {
+            FlakinessControl::None => 1,
+            FlakinessControl::ProveNonFlaky(tries) => tries,
+            FlakinessControl::RetryKnownFlaky(max_retries) => max_retries,
+        };
+        let name = test.fully_qualified_name();
+        Self {
+            seed: crate::seed::test_seed(&name),
+            name,
+            tags: test.props.tags.clone(),
+            attempt,
+            max_attempts: max_attempts.max(1),
+            worker_index: crate::worker_index(),
+            temp_dir: OnceLock::new(),
+        }
+    }
+
+    /// Gets the context from the dependencies passed to a test function. Used by the code
+    /// generated by the `#[test]` macro.
+    pub fn get(dependency_view: &impl DependencyView) -> Arc<TestContext> {
+        dependency_view
+            .get(Self::DEPENDENCY_NAME)
+            .and_then(|value| value.downcast::<TestContext>().ok())
+            .expect("The test context is only available in test functions")
+    }
+
+    /// Fully qualified name of the test, including the crate and module path
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Tags of the test, including the ones of its suites
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// The current attempt of running the test, starting from 1. Tests are run more than once
+    /// when marked with `#[flaky]` or `#[non_flaky]`.
+    pub fn attempt(&self) -> usize {
+        self.attempt
+    }
+
+    /// The maximum number of attempts of running the test
+    pub fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    /// Index of the worker process running the test, see [`crate::worker_index`]
+    pub fn worker_index(&self) -> usize {
+        self.worker_index
+    }
+
+    /// The seed of the test, derived from the `--seed` of the run and the name of the test, or
+    /// set with `--seed-for`. It is printed when the test fails. Every attempt of a `#[flaky]`,
+    /// `#[non_flaky]` or `--rerun-failed` test gets the same seed, so the printed seed reproduces
+    /// the failure whichever attempt failed.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// A temporary directory for the test, created on first use. It is deleted when the test
+    /// passes, and kept for inspection when it fails, in which case its path is printed to the
+    /// test's standard error.
+    pub fn temp_dir(&self) -> &Path {
+        self.temp_dir.get_or_init(|| {
+            let name: String = self
+                .name
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect();
+            let path = std::env::temp_dir().join(format!("test-r-{name}-{}", Uuid::new_v4()));
+            std::fs::create_dir_all(&path).unwrap_or_else(|err| {
+                panic!(
+                    "Failed to create the temporary directory {}: {err}",
+                    path.display()
+                )
+            });
+            path
+        })
+    }
+
+    /// Called when the attempt of the test finished, to delete or keep its temporary directory
+    pub(crate) fn finish(&self, passed: bool) {
+        if let Some(path) = self.temp_dir.get() {
+            if passed {
+                let _ = std::fs::remove_dir_all(path);
+            } else {
+                eprintln!("Temporary directory of the test kept at {}", path.display());
+            }
+        }
+    }
+}
+
+/// Returns true if an attempt of a test with the given `should_panic` expectation passed
+pub(crate) fn attempt_passed<A, E>(
+    should_panic: &ShouldPanic,
+    result: &Result<Result<A, E>, Box<dyn Any + Send>>,
+) -> bool {
+    match should_panic {
+        ShouldPanic::No => matches!(result, Ok(Ok(_))),
+        ShouldPanic::Yes | ShouldPanic::WithMessage(_) => result.is_err(),
+    }
+}
+
+/// The dependencies of a test together with its context
+#[derive(Debug)]
+pub(crate) struct WithTestContext {
+    pub context: Arc<TestContext>,
+    pub dependencies: Arc<dyn DependencyView + Send + Sync>,
+}
+
+impl WithTestContext {
+    /// Wraps the dependencies of a test to also provide its context
+    pub fn wrap(
+        context: Arc<TestContext>,
+        dependencies: Arc<dyn DependencyView + Send + Sync>,
+    ) -> Arc<dyn DependencyView + Send + Sync> {
+        Arc::new(Self {
+            context,
+            dependencies,
+        })
+    }
+}
+
+impl DependencyView for WithTestContext {
+    fn get(&self, name: &str) -> Option<Arc<dyn Any + Send + Sync>> {
+        if name == TestContext::DEPENDENCY_NAME {
+            Some(self.context.clone())
+        } else {
+            self.dependencies.get(name)
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Environment variables and working directory of the tests, set with `#[env]`, `#[env_remove]`
+//! and `#[cwd]` or their suite-level equivalents.
+//!
+//! Both are process-global, so they are changed right before the test function runs and restored
+//! right after it, in the process actually running the test: the test runner itself when the tests
+//! run in-process, or the worker process otherwise. Tests running in-process that change them are
+//! never run in parallel with other tests (see `ResourceSemaphores`). The changes are kept by the
+//! test's `TestGuard`, so a test abandoned after timing out restores them only when it returns.
+
+use crate::execution::TestGuard;
+use crate::internal::{EnvChange, FailureCause, TestProperties, TestResult};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The environment changes of a running test. Dropping it restores the previous environment
+/// variables and working directory.
+pub(crate) struct TestEnvironment {
+    previous_vars: Vec<(String, Option<OsString>)>,
+    previous_cwd: Option<PathBuf>,
+}
+
+impl TestEnvironment {
+    /// Applies the environment changes and the working directory of a test
+    pub fn apply(props: &TestProperties) -> Result<Self, String> {
+        let mut environment = TestEnvironment {
+            previous_vars: Vec::with_capacity(props.env.len()),
+            previous_cwd: None,
+        };
+        if let Some(cwd) = &props.cwd {
+            let previous = std::env::current_dir()
+                .map_err(|err| format!("Failed to get the current working directory: {err}"))?;
+            std::env::set_current_dir(cwd).map_err(|err| {
+                format!(
+                    "Failed to change the working directory to {}: {err}",
+                    cwd.display()
+                )
+            })?;
+            environment.previous_cwd = Some(previous);
+        }
+        for change in &props.env {
+            environment
+                .previous_vars
+                .push((change.key().to_string(), std::env::var_os(change.key())));
+            match change {
+                EnvChange::Set { key, value } => std::env::set_var(key, value),
+                EnvChange::Remove { key } => std::env::remove_var(key),
+            }
+        }
+        Ok(environment)
+    }
+}
+
+impl Drop for TestEnvironment {
+    fn drop(&mut self) {
+        // In reverse order, so a variable changed more than once gets its original value back
+        for (key, previous) in self.previous_vars.drain(..).rev() {
+            match previous {
+                Some(value) => std::env::set_var(key, value),
+                None => std::env::remove_var(key),
+            }
+        }
+        if let Some(previous) = self.previous_cwd.take() {
+            let _ = std::env::set_current_dir(previous);
+        }
+    }
+}
+
+/// Runs `run` with the environment of the test applied, passing it the test's guard keeping the
+/// environment until it is dropped. The test fails without being run if the environment cannot
+/// be applied.
+pub(crate) fn with_test_environment(
+    props: &TestProperties,
+    guard: TestGuard,
+    run: impl FnOnce(TestGuard) -> TestResult,
+) -> TestResult {
+    match TestEnvironment::apply(props) {
+        Ok(environment) => run(guard.with_environment(environment)),
+        Err(message) => TestResult::failed(Duration::ZERO, FailureCause::HarnessError(message)),
+    }
+}
+
+/// Async counterpart of `with_test_environment`
+#[cfg(feature = "tokio")]
+pub(crate) async fn with_test_environment_async<F: std::future::Future<Output = TestResult>>(
+    props: &TestProperties,
+    guard: TestGuard,
+    run: impl FnOnce(TestGuard) -> F,
+) -> TestResult {
+    match TestEnvironment::apply(props) {
+        Ok(environment) => run(guard.with_environment(environment)).await,
+        Err(message) => TestResult::failed(Duration::ZERO, FailureCause::HarnessError(message)),
+    }
+}
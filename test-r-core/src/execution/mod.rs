@@ -1,18 +1,25 @@
+use futures::FutureExt;
 use rand::prelude::{SliceRandom, StdRng};
 use rand::SeedableRng;
 use std::any::Any;
+use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt::{Debug, Formatter};
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 use topological_sort::TopologicalSort;
 
-use crate::args::Arguments;
+use crate::args::{Arguments, ScheduleSetting};
+use crate::environment::TestEnvironment;
 use crate::internal::{
     apply_suite_props_to_tests, filter_registered_tests, DepScope, DependencyConstructor,
-    DependencyView, HostedRpcOwnerCell, RegisteredDependency, RegisteredTest,
-    RegisteredTestSuiteProperty,
+    DependencyEvent, DependencyEventKind, DependencyFailure, DependencyProcess, DependencyTeardown,
+    DependencyView, FailureCause, HookKind, HostedRpcOwnerCell, PerTestValue, RegisteredDependency,
+    RegisteredTest, RegisteredTestSuiteProperty, ShouldPanic, SuiteHook, TestResult,
 };
+use crate::output::TestRunnerOutput;
 
 /// Wire bytes for a single Cloneable / Hosted dependency, keyed by its
 /// fully-qualified id (`{crate}::{module}::{name}`).
@@ -72,6 +79,18 @@ pub struct ParentSharedDependencies {
     /// re-runs its own `Shared`/`PerWorker` constructors fresh, matching the
     /// "one instance per process" semantics those scopes promise.
     pub parent_constructed_shared_values: Vec<(String, Arc<dyn Any + Send + Sync>)>,
+    /// `Cloneable`, `Hosted` and `HostedRpc` deps whose constructor failed in the parent, keyed
+    /// by fully-qualified dep id. The runner installs them in the execution tree in
+    /// no-spawn-workers mode, and ships them to every worker otherwise, so the tests using them
+    /// fail without being run. Failed `Shared`/`PerWorker` deps are kept in
+    /// `parent_constructed_shared_values` like the successfully constructed ones.
+    pub failed_dependencies: Vec<(String, DependencyFailure)>,
+    /// Teardowns of the values constructed in the parent, in construction order. The runner
+    /// runs them in reverse order once every pass has finished.
+    pub teardowns: Vec<PendingTeardown>,
+    /// The values constructed in the parent, in construction order. The runner reports them
+    /// as dropped once every pass has finished.
+    pub constructed: Vec<ConstructedDependency>,
 }
 
 impl ParentSharedDependencies {
@@ -83,6 +102,9 @@ impl ParentSharedDependencies {
             hosted_owners: Vec::new(),
             hosted_rpc_owner_cells: Vec::new(),
             parent_constructed_shared_values: Vec::new(),
+            failed_dependencies: Vec::new(),
+            teardowns: Vec::new(),
+            constructed: Vec::new(),
         }
     }
 }
@@ -118,6 +140,22 @@ pub(crate) struct TestSuiteExecution {
     is_sequential: bool,
     skip_creating_dependencies: bool,
     in_progress: Arc<AtomicUsize>,
+    /// Execution times recorded by previous runs, set with `--schedule longest-first`
+    expected_durations: Option<Arc<HashMap<String, Duration>>>,
+    /// Limited resources used by the tests. Only used on the root level, which passes them
+    /// down when picking the next test.
+    resources: ResourceSemaphores,
+    /// Outcomes of the tests other tests depend on. Only used on the root level, like `resources`.
+    prerequisites: Prerequisites,
+    /// Hooks of this suite, if it has any
+    hooks: Option<Arc<SuiteHooks>>,
+    /// Hooks of every suite, outer suites first. Only used on the root level, like `resources`.
+    suite_hooks: Vec<Arc<SuiteHooks>>,
+    /// Values constructed by this execution plan that have a teardown function. Shared by
+    /// every level of the plan.
+    teardowns: Teardowns,
+    /// Reports the construction and drop of dependencies. Shared by every level of the plan.
+    dependency_events: DependencyEvents,
 }
 
 impl TestSuiteExecution {
@@ -130,6 +168,7 @@ impl TestSuiteExecution {
         let tests_with_props = apply_suite_props_to_tests(tests, props);
         let mut filtered_tests = filter_registered_tests(arguments, &tests_with_props);
         Self::shuffle(arguments, &mut filtered_tests);
+        Self::apply_last_failed(arguments, &mut filtered_tests);
         filtered_tests.reverse();
 
         if filtered_tests.is_empty() {
@@ -165,7 +204,19 @@ impl TestSuiteExecution {
             }
 
             root.propagate_sequential(None);
+            root.suite_hooks = root.create_hooks();
             root.prune_unused_deps();
+            root.resources = ResourceSemaphores::new(&filtered_tests);
+            if arguments.is_top_level_parent() {
+                root.prerequisites = Prerequisites::new(
+                    &tests_with_props,
+                    &filtered_tests,
+                    root.resources.changes.clone(),
+                );
+            }
+            if arguments.schedule == Some(ScheduleSetting::LongestFirst) {
+                root.schedule_longest_first(&Arc::new(arguments.test_timings.clone()));
+            }
 
             (root, filtered_tests)
         }
@@ -178,6 +229,92 @@ impl TestSuiteExecution {
         }
     }
 
+    /// Applies `--last-failed` and `--failed-first`. If none of the selected tests failed
+    /// when last run, all of them run in their original order.
+    fn apply_last_failed(arguments: &Arguments, tests: &mut Vec<RegisteredTest>) {
+        if !arguments.has_last_failed(tests) {
+            return;
+        }
+        if arguments.last_failed {
+            tests.retain(|test| arguments.failed_last_time(test));
+        } else if arguments.failed_first {
+            // Stable, so both groups keep their (possibly shuffled) order
+            tests.sort_by_key(|test| !arguments.failed_last_time(test));
+        }
+    }
+
+    /// Orders the tests of every suite so the ones that took the longest in previous runs are
+    /// picked first. Tests without a recorded time are treated as the fastest ones.
+    fn schedule_longest_first(&mut self, durations: &Arc<HashMap<String, Duration>>) {
+        self.expected_durations = Some(durations.clone());
+        // Tests are popped from the end, and the stable sort keeps equally long tests in order
+        self.tests
+            .sort_by_cached_key(|test| Self::expected_duration(durations, test));
+        for inner in &mut self.inner {
+            inner.schedule_longest_first(durations);
+        }
+    }
+
+    fn expected_duration(durations: &HashMap<String, Duration>, test: &RegisteredTest) -> Duration {
+        durations
+            .get(&test.fully_qualified_name())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Expected duration of the slowest test of this level that was not picked yet
+    fn longest_own_remaining(&self) -> Duration {
+        match (&self.expected_durations, self.tests.last()) {
+            (Some(durations), Some(test)) => Self::expected_duration(durations, test),
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Expected duration of the slowest test of this subtree that was not picked yet
+    fn longest_remaining(&self) -> Duration {
+        self.inner
+            .iter()
+            .map(|inner| inner.longest_remaining())
+            .fold(self.longest_own_remaining(), Duration::max)
+    }
+
+    /// With `--schedule longest-first`, moves the inner suites containing the slowest remaining
+    /// tests to the front, and returns `true` if the slowest of them is expected to take longer
+    /// than the next test of this level. Sequential suites and dependency subtrees keep their
+    /// own constraints, as the tests are still picked through them.
+    fn prefer_inner_tests(&mut self) -> bool {
+        if self.expected_durations.is_none() {
+            return false;
+        }
+        self.inner
+            .sort_by_cached_key(|inner| Reverse(inner.longest_remaining()));
+        self.inner
+            .first()
+            .is_some_and(|inner| inner.longest_remaining() > self.longest_own_remaining())
+    }
+
+    /// Records the results of an earlier `--rerun-failed` pass, so the tests of this pass are
+    /// not skipped because of prerequisites that already passed and are not run again
+    pub fn record_results(&self, results: &[(RegisteredTest, TestResult)]) {
+        for (test, result) in results {
+            // Tests selected again run in this pass, the tests depending on them wait for the new result
+            if !self.prerequisites.is_selected(test) {
+                self.prerequisites.record(test, result);
+            }
+        }
+    }
+
+    /// Returns the hooks of every suite, outer suites first
+    pub fn suite_hooks(&self) -> Vec<Arc<SuiteHooks>> {
+        self.suite_hooks.clone()
+    }
+
+    /// Takes the teardowns of the dependencies constructed by this execution plan so far, in
+    /// reverse construction order. Only called once all the tests using them have finished.
+    pub fn take_teardowns(&self) -> Vec<PendingTeardown> {
+        self.teardowns.take()
+    }
+
     /// Disables creating dependencies when picking the next test. This is useful when the execution plan
     /// is only used to drive spawned workers instead of actually running the tests.
     pub fn skip_creating_dependencies(&mut self) {
@@ -187,6 +324,28 @@ impl TestSuiteExecution {
         }
     }
 
+    /// Sets where the construction and drop of the dependencies are reported
+    pub fn set_dependency_events(&mut self, dependency_events: &DependencyEvents) {
+        self.dependency_events = dependency_events.clone();
+        for inner in &mut self.inner {
+            inner.set_dependency_events(dependency_events);
+        }
+    }
+
+    /// Takes the dependency events buffered in a worker process, to be sent to the parent
+    pub fn take_dependency_events(&self) -> Vec<DependencyEvent> {
+        self.dependency_events.take_buffered()
+    }
+
+    /// Drops the dependencies constructed by this execution plan. Called once all the tests
+    /// have finished, after the teardowns.
+    pub fn release_dependencies(&mut self) {
+        self.drop_deps();
+        for inner in &mut self.inner {
+            inner.release_dependencies();
+        }
+    }
+
     pub fn remaining(&self) -> usize {
         self.remaining_count
     }
@@ -199,6 +358,18 @@ impl TestSuiteExecution {
         self.remaining_count == 0
     }
 
+    /// Removes all the tests that have not been picked yet, in the order they would have been
+    /// executed. Used when the run stops early because of `--fail-fast` or `--max-fail`.
+    pub fn take_remaining_tests(&mut self) -> Vec<RegisteredTest> {
+        let mut result: Vec<RegisteredTest> = self.tests.drain(..).rev().collect();
+        for inner in &mut self.inner {
+            result.extend(inner.take_remaining_tests());
+        }
+        self.inner.clear();
+        self.remaining_count = 0;
+        result
+    }
+
     /// Returns true if either this level, or any of the inner levels have dependencies
     #[allow(dead_code)]
     pub fn has_dependencies(&self) -> bool {
@@ -322,7 +493,31 @@ impl TestSuiteExecution {
                 continue;
             }
 
-            let value = Self::construct_dependency_sync(dep, &dependency_map);
+            if dep.scope == DepScope::PerTest {
+                // Fails the dependencies constructed from it, see `failed_input`
+                dependency_map.insert(
+                    dep.name.clone(),
+                    Arc::new(PerTestDependency((*dep).clone())),
+                );
+                continue;
+            }
+            let value = match Self::failed_input(dep, &dependency_map) {
+                Some(failure) => failure,
+                None => {
+                    let (value, constructed) = Self::construct_dependency_sync(
+                        dep,
+                        &dependency_map,
+                        &self.dependency_events,
+                    );
+                    out.constructed.extend(constructed);
+                    value
+                }
+            };
+            if Self::collect_failure(dep, &value, out) {
+                dependency_map.insert(dep.name.clone(), value);
+                continue;
+            }
+            out.teardowns.extend(PendingTeardown::new(dep, &value));
             match dep.scope {
                 DepScope::Cloneable => {
                     let codec = dep.cloneable_codec.as_ref().unwrap_or_else(|| {
@@ -352,6 +547,7 @@ impl TestSuiteExecution {
                     let cell = (factory.owner_into_cell)(value.clone());
                     out.hosted_rpc_owner_cells.push((dep.qualified_id(), cell));
                 }
+                DepScope::PerTest => unreachable!("PerTest dependencies are skipped above"),
                 DepScope::Shared | DepScope::PerWorker => {
                     // Constructed only because some Cloneable/Hosted/HostedRpc
                     // dep transitively depends on this one. Capture the value
@@ -378,16 +574,98 @@ impl TestSuiteExecution {
         dependency_map
     }
 
-    fn construct_dependency_sync(
+    /// Runs the constructor of `dep`. If the constructor panics, returns a [`DependencyFailure`]
+    /// standing in for the value, like the ones returned by fallible constructors. The
+    /// successfully constructed values are also returned as a [`ConstructedDependency`], to be
+    /// reported as dropped once they are released.
+    async fn construct_dependency(
+        dep: &RegisteredDependency,
+        dependency_map: &HashMap<String, Arc<dyn Any + Send + Sync>>,
+        dependency_events: &DependencyEvents,
+    ) -> (Arc<dyn Any + Send + Sync>, Option<ConstructedDependency>) {
+        let start = dependency_events.construction_started(dep);
+        let value = Self::run_constructor(dep, dependency_map).await;
+        let constructed = dependency_events.construction_finished(dep, start, &value);
+        (value, constructed)
+    }
+
+    async fn run_constructor(
         dep: &RegisteredDependency,
         dependency_map: &HashMap<String, Arc<dyn Any + Send + Sync>>,
     ) -> Arc<dyn Any + Send + Sync> {
-        match &dep.constructor {
-            DependencyConstructor::Sync(cons) => cons(Arc::new(dependency_map.clone())),
-            DependencyConstructor::Async(cons) => {
-                futures::executor::block_on(cons(Arc::new(dependency_map.clone())))
+        let view = Arc::new(dependency_map.clone());
+        let constructed = AssertUnwindSafe(async {
+            match &dep.constructor {
+                DependencyConstructor::Sync(cons) => cons(view),
+                DependencyConstructor::Async(cons) => cons(view).await,
             }
+        })
+        .catch_unwind()
+        .await;
+        constructed.unwrap_or_else(|panic| {
+            let result =
+                TestResult::from_result::<()>(&ShouldPanic::No, Duration::ZERO, Err(panic));
+            Arc::new(DependencyFailure {
+                dependency: dep.qualified_id(),
+                message: result.failure_message().unwrap_or_default(),
+            })
+        })
+    }
+
+    fn construct_dependency_sync(
+        dep: &RegisteredDependency,
+        dependency_map: &HashMap<String, Arc<dyn Any + Send + Sync>>,
+        dependency_events: &DependencyEvents,
+    ) -> (Arc<dyn Any + Send + Sync>, Option<ConstructedDependency>) {
+        futures::executor::block_on(Self::construct_dependency(
+            dep,
+            dependency_map,
+            dependency_events,
+        ))
+    }
+
+    /// The failure of the first input of `dep` that could not be constructed. It stands in for
+    /// the value of `dep` too, without running its constructor. A dependency shared by multiple
+    /// tests cannot be constructed from a `PerTest` dependency either, so it fails the tests
+    /// using it.
+    fn failed_input(
+        dep: &RegisteredDependency,
+        dependency_map: &HashMap<String, Arc<dyn Any + Send + Sync>>,
+    ) -> Option<Arc<dyn Any + Send + Sync>> {
+        dep.dependencies.iter().find_map(|name| {
+            let value = dependency_map.get(name)?;
+            if value.is::<DependencyFailure>() {
+                Some(value.clone())
+            } else if dep.scope != DepScope::PerTest && value.is::<PerTestDependency>() {
+                Some(Arc::new(DependencyFailure {
+                    dependency: dep.qualified_id(),
+                    message: format!("it cannot depend on the PerTest dependency {name}"),
+                }))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Records `value` in `out` if it is the failure of a dependency constructed in the parent.
+    /// Returns `false` if `value` is a successfully constructed value.
+    fn collect_failure(
+        dep: &RegisteredDependency,
+        value: &Arc<dyn Any + Send + Sync>,
+        out: &mut ParentSharedDependencies,
+    ) -> bool {
+        let Some(failure) = value.downcast_ref::<DependencyFailure>() else {
+            return false;
+        };
+        match dep.scope {
+            DepScope::Shared | DepScope::PerWorker => out
+                .parent_constructed_shared_values
+                .push((dep.qualified_id(), value.clone())),
+            _ => out
+                .failed_dependencies
+                .push((dep.qualified_id(), failure.clone())),
         }
+        true
     }
 
     /// Collects only Cloneable wire bytes. The runner uses
@@ -462,12 +740,32 @@ impl TestSuiteExecution {
                     continue;
                 }
 
-                let value = match &dep.constructor {
-                    DependencyConstructor::Sync(cons) => cons(Arc::new(dependency_map.clone())),
-                    DependencyConstructor::Async(cons) => {
-                        cons(Arc::new(dependency_map.clone())).await
+                if dep.scope == DepScope::PerTest {
+                    // See sync counterpart
+                    dependency_map.insert(
+                        dep.name.clone(),
+                        Arc::new(PerTestDependency((*dep).clone())),
+                    );
+                    continue;
+                }
+                let value = match Self::failed_input(dep, &dependency_map) {
+                    Some(failure) => failure,
+                    None => {
+                        let (value, constructed) = Self::construct_dependency(
+                            dep,
+                            &dependency_map,
+                            &self.dependency_events,
+                        )
+                        .await;
+                        out.constructed.extend(constructed);
+                        value
                     }
                 };
+                if Self::collect_failure(dep, &value, out) {
+                    dependency_map.insert(dep.name.clone(), value);
+                    continue;
+                }
+                out.teardowns.extend(PendingTeardown::new(dep, &value));
                 match dep.scope {
                     DepScope::Cloneable => {
                         let codec = dep.cloneable_codec.as_ref().unwrap_or_else(|| {
@@ -498,6 +796,7 @@ impl TestSuiteExecution {
                         let cell = (factory.owner_into_cell)(value.clone());
                         out.hosted_rpc_owner_cells.push((dep.qualified_id(), cell));
                     }
+                    DepScope::PerTest => unreachable!("PerTest dependencies are skipped above"),
                     DepScope::Shared | DepScope::PerWorker => {
                         // See sync counterpart: capture parent-constructed
                         // values so the no-spawn-workers path can pre-install
@@ -597,6 +896,15 @@ impl TestSuiteExecution {
         applied
     }
 
+    /// Marks the dep with the fully-qualified id `dep_id` as failed, for the
+    /// `Cloneable`/`Hosted`/`HostedRpc` deps whose constructor failed in the
+    /// parent. The tests using it, directly or through other deps, fail
+    /// without being run. Returns `true` if a matching dep was found in any
+    /// node of the subtree.
+    pub fn provide_failed_dependency(&mut self, dep_id: &str, failure: DependencyFailure) -> bool {
+        self.provide_cloneable_value(dep_id, Arc::new(failure))
+    }
+
     /// Worker-side pre-population for `Shared` / `PerWorker` deps the parent
     /// already constructed (as transitive inputs of a Cloneable/Hosted/HostedRpc
     /// dep). Used only by the **no-spawn-workers** code path: the in-process
@@ -646,60 +954,141 @@ impl TestSuiteExecution {
             .any(|inner| inner.requires_capturing(capture_by_default))
     }
 
+    /// Makes the tests changing the environment variables or the working directory run alone,
+    /// as both are process-global. Used when the tests run in this process.
+    /// Signals the test threads waiting for one of the remaining tests to become available
+    pub fn availability_changes(&self) -> AvailabilityChanges {
+        self.resources.changes.clone()
+    }
+
+    pub fn run_environment_changes_exclusively(&mut self) {
+        self.resources.exclusive_environment = true;
+    }
+
+    /// Returns true if there are any tests marked to run in their own worker process,
+    /// including the ones with their own memory limit.
+    pub fn requires_isolation(&self) -> bool {
+        self.tests
+            .iter()
+            .any(|test| test.props.is_isolated || test.props.memory_limit.is_some())
+            || self.inner.iter().any(|inner| inner.requires_isolation())
+    }
+
     #[cfg(feature = "tokio")]
     pub async fn pick_next(&mut self) -> Option<TestExecution> {
+        self.pick_next_matching(&|_| true).await
+    }
+
+    /// Picks the next test that satisfies `filter`. Worker processes use it to pick the test
+    /// requested by the parent runner, which may not be the next one in their own order.
+    #[cfg(feature = "tokio")]
+    pub async fn pick_next_matching(
+        &mut self,
+        filter: &(dyn Fn(&RegisteredTest) -> bool + Send + Sync),
+    ) -> Option<TestExecution> {
         if self.is_empty() {
             None
         } else {
-            match self
-                .pick_next_internal(&self.create_dependency_map(&HashMap::new()))
-                .await
-            {
-                Some((test, deps, seq_lock, in_progress_counter)) => {
-                    let index = self.idx;
-                    self.idx += 1;
-                    Some(TestExecution {
-                        test: test.clone(),
-                        deps: Arc::new(deps),
-                        index,
-                        _seq_lock: seq_lock,
-                        in_progress_counter,
-                    })
-                }
-                None => None,
-            }
+            let resources = self.resources.clone();
+            let prerequisites = self.prerequisites.clone();
+            self.pick_next_internal(
+                &self.create_dependency_map(&HashMap::new()),
+                &resources,
+                &prerequisites,
+                filter,
+            )
+            .await
+            .map(|picked| self.start_execution(picked))
         }
     }
 
     pub fn pick_next_sync(&mut self) -> Option<TestExecution> {
-        match self.pick_next_internal_sync(&HashMap::new()) {
-            Some((test, deps, seq_lock, in_progress_counter)) => {
-                let index = self.idx;
-                self.idx += 1;
-                Some(TestExecution {
-                    test: test.clone(),
-                    deps: Arc::new(deps),
-                    index,
-                    _seq_lock: seq_lock,
-                    in_progress_counter,
-                })
+        self.pick_next_matching_sync(&|_| true)
+    }
+
+    /// Sync counterpart of `pick_next_matching`
+    pub fn pick_next_matching_sync(
+        &mut self,
+        filter: &(dyn Fn(&RegisteredTest) -> bool + Send + Sync),
+    ) -> Option<TestExecution> {
+        let resources = self.resources.clone();
+        let prerequisites = self.prerequisites.clone();
+        self.pick_next_internal_sync(&HashMap::new(), &resources, &prerequisites, filter)
+            .map(|picked| self.start_execution(picked))
+    }
+
+    fn start_execution(&mut self, picked: PickedTest) -> TestExecution {
+        let index = self.idx;
+        self.idx += 1;
+        let per_test_dependencies = PerTestDependencies::new(
+            PerTestDependency::used_by(&picked.test, &picked.suites, &picked.deps),
+            &picked.deps,
+            &self.dependency_events,
+        );
+        let dependency_failure = failed_dependency(&picked.test, &picked.suites, &picked.deps);
+        let dependency_map = Arc::new(picked.deps);
+        TestExecution {
+            test: picked.test,
+            deps: dependency_map.clone(),
+            dependency_map,
+            per_test_dependencies,
+            dependency_failure,
+            index,
+            skip_reason: picked.skip_reason,
+            prerequisites: self.prerequisites.clone(),
+            guard: TestGuard {
+                _environment: None,
+                _locks: Arc::new((picked.seq_lock, picked.resource_guard)),
+            },
+            in_progress_counter: picked.in_progress_counter,
+            suites: picked.suites,
+        }
+    }
+
+    /// Index of the next test of this level that can be picked, if any
+    fn next_own_test(
+        &self,
+        resources: &ResourceSemaphores,
+        prerequisites: &Prerequisites,
+        filter: &(dyn Fn(&RegisteredTest) -> bool + Send + Sync),
+    ) -> Option<usize> {
+        // Tests are popped from the end
+        for (idx, test) in self.tests.iter().enumerate().rev() {
+            if !filter(test) || matches!(prerequisites.state(test), PrerequisiteState::Waiting) {
+                continue;
+            }
+            if resources.is_available(test) {
+                return Some(idx);
+            }
+            if resources.waits_for_running_tests(test) {
+                resources.mark_environment_waiting();
+                return None;
             }
-            None => None,
         }
+        None
+    }
+
+    /// Returns true if this subtree has any test not picked yet that satisfies `filter`
+    pub(crate) fn has_matching_test(
+        &self,
+        filter: &(dyn Fn(&RegisteredTest) -> bool + Send + Sync),
+    ) -> bool {
+        self.tests.iter().any(filter)
+            || self
+                .inner
+                .iter()
+                .any(|inner| inner.has_matching_test(filter))
     }
 
     #[cfg(feature = "tokio")]
-    #[allow(clippy::type_complexity)]
     async fn pick_next_internal(
         &mut self,
         materialized_parent_deps: &HashMap<String, Arc<dyn Any + Send + Sync>>,
-    ) -> Option<(
-        RegisteredTest,
-        HashMap<String, Arc<dyn Any + Send + Sync>>,
-        SequentialExecutionLockGuard,
-        Arc<AtomicUsize>,
-    )> {
-        if self.is_empty() {
+        resources: &ResourceSemaphores,
+        prerequisites: &Prerequisites,
+        filter: &(dyn Fn(&RegisteredTest) -> bool + Send + Sync),
+    ) -> Option<PickedTest> {
+        if self.is_empty() || !self.has_matching_test(filter) {
             None
         } else {
             let dependency_map = if !self.is_materialized() {
@@ -709,27 +1098,48 @@ impl TestSuiteExecution {
             };
 
             let locked = self.sequential_lock.is_locked().await;
-            let result = if self.tests.is_empty() || locked {
+            let prefer_inner = self.prefer_inner_tests();
+            let own_test = if locked {
+                None
+            } else {
+                self.next_own_test(resources, prerequisites, filter)
+            };
+            let mut result = None;
+            if own_test.is_none() || prefer_inner {
                 let current = self.inner.iter_mut();
-                let mut result = None;
                 for inner in current {
-                    if let Some((test, deps, seq_lock, in_progress_counter)) =
-                        Box::pin(inner.pick_next_internal(&dependency_map)).await
+                    if let Some(picked) = Box::pin(inner.pick_next_internal(
+                        &dependency_map,
+                        resources,
+                        prerequisites,
+                        filter,
+                    ))
+                    .await
                     {
-                        result = Some((test, deps, seq_lock, in_progress_counter));
+                        result = Some(picked);
                         break;
                     }
                 }
-                self.inner.retain(|inner| !inner.is_empty());
-
-                result
-            } else {
-                let guard = self.sequential_lock.lock(self.is_sequential).await;
+                self.remove_finished_inner();
+            }
+            if let (None, Some(idx)) = (&result, own_test) {
+                let seq_lock = self.sequential_lock.lock(self.is_sequential).await;
                 self.in_progress.fetch_add(1, Ordering::Release);
-                self.tests
-                    .pop()
-                    .map(|test| (test, dependency_map, guard, self.in_progress.clone()))
-            };
+                let test = self.tests.remove(idx);
+                let skip_reason = match prerequisites.state(&test) {
+                    PrerequisiteState::Skip(reason) => Some(reason),
+                    _ => None,
+                };
+                result = Some(PickedTest {
+                    resource_guard: resources.acquire(&test),
+                    skip_reason,
+                    test,
+                    deps: dependency_map,
+                    seq_lock,
+                    in_progress_counter: self.in_progress.clone(),
+                    suites: Vec::new(),
+                });
+            }
             if result.is_none()
                 && self.is_empty()
                 && self.is_materialized()
@@ -738,24 +1148,24 @@ impl TestSuiteExecution {
             {
                 self.drop_deps();
             }
-            if result.is_some() {
+            if let Some(picked) = &mut result {
                 self.remaining_count -= 1;
+                if let Some(hooks) = &self.hooks {
+                    picked.suites.insert(0, hooks.clone());
+                }
             }
             result
         }
     }
 
-    #[allow(clippy::type_complexity)]
     fn pick_next_internal_sync(
         &mut self,
         materialized_parent_deps: &HashMap<String, Arc<dyn Any + Send + Sync>>,
-    ) -> Option<(
-        RegisteredTest,
-        HashMap<String, Arc<dyn Any + Send + Sync>>,
-        SequentialExecutionLockGuard,
-        Arc<AtomicUsize>,
-    )> {
-        if self.is_empty() {
+        resources: &ResourceSemaphores,
+        prerequisites: &Prerequisites,
+        filter: &(dyn Fn(&RegisteredTest) -> bool + Send + Sync),
+    ) -> Option<PickedTest> {
+        if self.is_empty() || !self.has_matching_test(filter) {
             None
         } else {
             let dependency_map = if !self.is_materialized() {
@@ -765,27 +1175,49 @@ impl TestSuiteExecution {
             };
 
             let locked = self.sequential_lock.is_locked_sync();
-            let result = if self.tests.is_empty() || locked {
+            let prefer_inner = self.prefer_inner_tests();
+            let own_test = if locked {
+                None
+            } else {
+                self.next_own_test(resources, prerequisites, filter)
+            };
+            let mut result = None;
+            // Inner suites are also tried when all the tests of this level are waiting for a
+            // resource, or, with `--schedule longest-first`, when they contain slower tests
+            if own_test.is_none() || prefer_inner {
                 let current = self.inner.iter_mut();
-                let mut result = None;
                 for inner in current {
-                    if let Some((test, deps, seq_lock, in_progress_counter)) =
-                        inner.pick_next_internal_sync(&dependency_map)
-                    {
-                        result = Some((test, deps, seq_lock, in_progress_counter));
+                    if let Some(picked) = inner.pick_next_internal_sync(
+                        &dependency_map,
+                        resources,
+                        prerequisites,
+                        filter,
+                    ) {
+                        result = Some(picked);
                         break;
                     }
                 }
 
-                self.inner.retain(|inner| !inner.is_empty());
-                result
-            } else {
-                let guard = self.sequential_lock.lock_sync(self.is_sequential);
+                self.remove_finished_inner();
+            }
+            if let (None, Some(idx)) = (&result, own_test) {
+                let seq_lock = self.sequential_lock.lock_sync(self.is_sequential);
                 self.in_progress.fetch_add(1, Ordering::Release);
-                self.tests
-                    .pop()
-                    .map(|test| (test, dependency_map, guard, self.in_progress.clone()))
-            };
+                let test = self.tests.remove(idx);
+                let skip_reason = match prerequisites.state(&test) {
+                    PrerequisiteState::Skip(reason) => Some(reason),
+                    _ => None,
+                };
+                result = Some(PickedTest {
+                    resource_guard: resources.acquire(&test),
+                    skip_reason,
+                    test,
+                    deps: dependency_map,
+                    seq_lock,
+                    in_progress_counter: self.in_progress.clone(),
+                    suites: Vec::new(),
+                });
+            }
             // `is_empty()` matches `pick_next_internal`: a `None` result
             // can mean "descendant is temporarily locked", not "subtree
             // done" — dropping deps here would force rematerialisation.
@@ -797,8 +1229,11 @@ impl TestSuiteExecution {
             {
                 self.drop_deps();
             }
-            if result.is_some() {
+            if let Some(picked) = &mut result {
                 self.remaining_count -= 1;
+                if let Some(hooks) = &self.hooks {
+                    picked.suites.insert(0, hooks.clone());
+                }
             }
             result
         }
@@ -838,6 +1273,13 @@ impl TestSuiteExecution {
             is_sequential,
             skip_creating_dependencies: false,
             in_progress: Arc::new(AtomicUsize::new(0)),
+            expected_durations: None,
+            resources: ResourceSemaphores::default(),
+            prerequisites: Prerequisites::default(),
+            hooks: None,
+            suite_hooks: Vec::new(),
+            teardowns: Teardowns::default(),
+            dependency_events: DependencyEvents::default(),
         }
     }
 
@@ -868,6 +1310,13 @@ impl TestSuiteExecution {
                     sequential_lock: SequentialExecutionLock::new(),
                     skip_creating_dependencies: false,
                     in_progress: Arc::new(AtomicUsize::new(0)),
+                    expected_durations: None,
+                    resources: ResourceSemaphores::default(),
+                    prerequisites: Prerequisites::default(),
+                    hooks: None,
+                    suite_hooks: Vec::new(),
+                    teardowns: self.teardowns.clone(),
+                    dependency_events: self.dependency_events.clone(),
                 };
                 inner.add_dependency(dep);
                 self.inner.push(inner);
@@ -906,6 +1355,13 @@ impl TestSuiteExecution {
                     sequential_lock: SequentialExecutionLock::new(),
                     skip_creating_dependencies: false,
                     in_progress: Arc::new(AtomicUsize::new(0)),
+                    expected_durations: None,
+                    resources: ResourceSemaphores::default(),
+                    prerequisites: Prerequisites::default(),
+                    hooks: None,
+                    suite_hooks: Vec::new(),
+                    teardowns: self.teardowns.clone(),
+                    dependency_events: self.dependency_events.clone(),
                 };
                 inner.add_test(test);
                 self.inner.push(inner);
@@ -944,6 +1400,13 @@ impl TestSuiteExecution {
                     sequential_lock: SequentialExecutionLock::new(),
                     skip_creating_dependencies: false,
                     in_progress: Arc::new(AtomicUsize::new(0)),
+                    expected_durations: None,
+                    resources: ResourceSemaphores::default(),
+                    prerequisites: Prerequisites::default(),
+                    hooks: None,
+                    suite_hooks: Vec::new(),
+                    teardowns: self.teardowns.clone(),
+                    dependency_events: self.dependency_events.clone(),
                 };
                 inner.add_prop(prop);
                 self.inner.push(inner);
@@ -951,6 +1414,17 @@ impl TestSuiteExecution {
         }
     }
 
+    /// Creates the hooks of the suites in this subtree from their properties, returning them
+    /// outer suites first
+    fn create_hooks(&mut self) -> Vec<Arc<SuiteHooks>> {
+        self.hooks = SuiteHooks::new(&self.props).map(Arc::new);
+        let mut result: Vec<Arc<SuiteHooks>> = self.hooks.iter().cloned().collect();
+        for inner in &mut self.inner {
+            result.extend(inner.create_hooks());
+        }
+        result
+    }
+
     fn is_materialized(&self) -> bool {
         self.skip_creating_dependencies
             || self.materialized_dependencies.len() == self.dependencies.len()
@@ -974,10 +1448,17 @@ impl TestSuiteExecution {
             if deps.contains_key(&dep.name) {
                 continue;
             }
-            let materialized_dep = match &dep.constructor {
-                DependencyConstructor::Sync(cons) => cons(Arc::new(dependency_map.clone())),
-                DependencyConstructor::Async(cons) => cons(Arc::new(dependency_map.clone())).await,
+            let materialized_dep = if let Some(failure) = Self::failed_input(dep, &dependency_map) {
+                failure
+            } else if dep.scope == DepScope::PerTest {
+                // Constructed by the test using it, see `TestExecution::create_per_test_dependencies`
+                Arc::new(PerTestDependency((*dep).clone()))
+            } else {
+                let (value, constructed) =
+                    Self::construct_dependency(dep, &dependency_map, &self.dependency_events).await;
+                TrackedDependency::wrap(value, constructed, &self.dependency_events)
             };
+            self.teardowns.register(dep, &materialized_dep);
             deps.insert(dep.name.clone(), materialized_dep.clone());
             dependency_map.insert(dep.name.clone(), materialized_dep);
         }
@@ -1002,12 +1483,17 @@ impl TestSuiteExecution {
             if deps.contains_key(&dep.name) {
                 continue;
             }
-            let materialized_dep = match &dep.constructor {
-                DependencyConstructor::Sync(cons) => cons(Arc::new(dependency_map.clone())),
-                DependencyConstructor::Async(cons) => {
-                    futures::executor::block_on(cons(Arc::new(dependency_map.clone())))
-                }
+            let materialized_dep = if let Some(failure) = Self::failed_input(dep, &dependency_map) {
+                failure
+            } else if dep.scope == DepScope::PerTest {
+                // Constructed by the test using it, see `TestExecution::create_per_test_dependencies`
+                Arc::new(PerTestDependency((*dep).clone()))
+            } else {
+                let (value, constructed) =
+                    Self::construct_dependency_sync(dep, &dependency_map, &self.dependency_events);
+                TrackedDependency::wrap(value, constructed, &self.dependency_events)
             };
+            self.teardowns.register(dep, &materialized_dep);
             deps.insert(dep.name.clone(), materialized_dep.clone());
             dependency_map.insert(dep.name.clone(), materialized_dep);
         }
@@ -1107,6 +1593,18 @@ impl TestSuiteExecution {
         result
     }
 
+    /// Removes the inner levels that have no more tests to pick, releasing their dependencies
+    fn remove_finished_inner(&mut self) {
+        let (mut finished, remaining): (Vec<_>, Vec<_>) =
+            self.inner.drain(..).partition(|inner| inner.is_empty());
+        self.inner = remaining;
+        for inner in &mut finished {
+            inner.release_dependencies();
+        }
+    }
+
+    /// Releases the dependencies of this level. They are reported as dropped once the tests and
+    /// teardowns still using them release them too, see [`TrackedDependency`].
     fn drop_deps(&mut self) {
         self.materialized_dependencies.clear();
     }
@@ -1131,6 +1629,17 @@ impl TestSuiteExecution {
             }
         }
 
+        // Hooks of the suite get the same dependencies as its tests
+        if let Some(ref mut set) = needed {
+            if self.remaining_count > 0 {
+                for prop in &self.props {
+                    if let RegisteredTestSuiteProperty::Hook { hook, .. } = prop {
+                        set.extend(hook.dependencies.iter().cloned());
+                    }
+                }
+            }
+        }
+
         // Merge children's needs
         for inner in &mut self.inner {
             let child_needs = inner.prune_unused_deps();
@@ -1264,7 +1773,59 @@ impl Debug for TestSuiteExecution {
 
 impl DependencyView for HashMap<String, Arc<dyn Any + Send + Sync>> {
     fn get(&self, name: &str) -> Option<Arc<dyn Any + Send + Sync>> {
-        self.get(name).cloned()
+        self.get(name).map(TrackedDependency::value)
+    }
+}
+
+/// A dependency value constructed by the execution plan, as stored in the dependency maps. The
+/// value is reported as dropped when the last map, test or pending teardown holding it releases
+/// it, right after the value itself is dropped.
+struct TrackedDependency {
+    value: Arc<dyn Any + Send + Sync>,
+    _dropped: DroppedGuard,
+}
+
+impl TrackedDependency {
+    /// Wraps a newly constructed value. Failures are not constructed values, and are stored as
+    /// they are.
+    fn wrap(
+        value: Arc<dyn Any + Send + Sync>,
+        constructed: Option<ConstructedDependency>,
+        dependency_events: &DependencyEvents,
+    ) -> Arc<dyn Any + Send + Sync> {
+        match constructed {
+            Some(constructed) => Arc::new(Self {
+                value,
+                _dropped: DroppedGuard {
+                    constructed: Some(constructed),
+                    dependency_events: dependency_events.clone(),
+                },
+            }),
+            None => value,
+        }
+    }
+
+    /// The value stored in a dependency map
+    fn value(stored: &Arc<dyn Any + Send + Sync>) -> Arc<dyn Any + Send + Sync> {
+        match stored.downcast_ref::<Self>() {
+            Some(tracked) => tracked.value.clone(),
+            None => stored.clone(),
+        }
+    }
+}
+
+/// Reports the dependency as dropped when dropped. The fields of [`TrackedDependency`] are
+/// dropped in order, so this happens after the value is dropped.
+struct DroppedGuard {
+    constructed: Option<ConstructedDependency>,
+    dependency_events: DependencyEvents,
+}
+
+impl Drop for DroppedGuard {
+    fn drop(&mut self) {
+        if let Some(constructed) = self.constructed.take() {
+            self.dependency_events.dropped(constructed);
+        }
     }
 }
 
@@ -1272,16 +1833,863 @@ pub struct TestExecution {
     pub test: RegisteredTest,
     pub deps: Arc<dyn DependencyView + Send + Sync>,
     pub index: usize,
-    _seq_lock: SequentialExecutionLockGuard,
+    /// Set if the test must not be run because one of its prerequisites did not pass
+    pub skip_reason: Option<String>,
+    /// Hooks of the suites containing the test, outer suites first
+    pub suites: Vec<Arc<SuiteHooks>>,
+    /// The dependencies of the suites containing the test, `deps` also has the values of the
+    /// `PerTest` dependencies once they are created
+    dependency_map: Arc<HashMap<String, Arc<dyn Any + Send + Sync>>>,
+    /// The `PerTest` dependencies used by the test or its `before_each` and `after_each` hooks,
+    /// or `None` if it does not use any
+    per_test_dependencies: Option<PerTestDependencies>,
+    /// Set if one of the dependencies used by the test or the hooks of its suites could not be
+    /// constructed
+    dependency_failure: Option<Arc<DependencyFailure>>,
+    prerequisites: Prerequisites,
+    guard: TestGuard,
+    in_progress_counter: Arc<AtomicUsize>,
+}
+
+/// Keeps the sequential execution lock and the resources of a running test, and once applied,
+/// its environment changes, until every clone is dropped. A test timing out in this process
+/// cannot be stopped, so the thread it keeps running on holds a clone, and the next tests
+/// waiting for them start only once it returns.
+#[derive(Clone)]
+pub struct TestGuard {
+    // Declared first, so the environment is restored before the other tests can start
+    _environment: Option<Arc<TestEnvironment>>,
+    _locks: Arc<(SequentialExecutionLockGuard, ResourceGuard)>,
+}
+
+impl TestGuard {
+    /// Keeps the environment changes of the test until the guard is dropped
+    pub(crate) fn with_environment(self, environment: TestEnvironment) -> Self {
+        Self {
+            _environment: Some(Arc::new(environment)),
+            ..self
+        }
+    }
+}
+
+/// A test picked from one level of the execution tree, with everything needed to run it
+struct PickedTest {
+    test: RegisteredTest,
+    skip_reason: Option<String>,
+    deps: HashMap<String, Arc<dyn Any + Send + Sync>>,
+    seq_lock: SequentialExecutionLockGuard,
+    resource_guard: ResourceGuard,
     in_progress_counter: Arc<AtomicUsize>,
+    suites: Vec<Arc<SuiteHooks>>,
+}
+
+impl TestExecution {
+    /// The guard of the running test, to be held by the thread running the test function
+    pub fn guard(&self) -> TestGuard {
+        self.guard.clone()
+    }
+
+    /// Records the result of the test, so the tests depending on it can be started or skipped
+    pub fn record_result(&self, result: &TestResult) {
+        self.prerequisites.record(&self.test, result);
+    }
+
+    /// Creates the values of the `PerTest` dependencies used by the test and adds them to `deps`.
+    /// Returns the failed result of the test if one of the dependencies it uses could not be
+    /// constructed.
+    pub async fn create_per_test_dependencies(&mut self) -> Option<TestResult> {
+        if let Some(failure) = &self.dependency_failure {
+            return Some(TestResult::failed(
+                Duration::ZERO,
+                FailureCause::DependencyFailed((**failure).clone()),
+            ));
+        }
+        let per_test_dependencies = self.per_test_dependencies.as_ref()?;
+        let start = Instant::now();
+        if let Err(cause) = per_test_dependencies.create().await {
+            return Some(TestResult::failed(start.elapsed(), cause));
+        }
+        self.deps = per_test_dependencies.dependency_map.clone();
+        None
+    }
+
+    /// Sync counterpart of `create_per_test_dependencies`, awaiting async constructors without
+    /// an async runtime
+    pub fn create_per_test_dependencies_sync(&mut self) -> Option<TestResult> {
+        futures::executor::block_on(self.create_per_test_dependencies())
+    }
+
+    /// The `PerTest` dependencies used by the test, to create them again for each attempt of a
+    /// flaky or non-flaky test
+    pub fn per_test_dependencies(&self) -> Option<PerTestDependencies> {
+        self.per_test_dependencies.clone()
+    }
+
+    /// Drops the values of the `PerTest` dependencies created for the test. Called when the
+    /// test and its hooks have finished.
+    pub fn release_per_test_dependencies(&mut self) {
+        self.deps = self.dependency_map.clone();
+        if let Some(per_test_dependencies) = &self.per_test_dependencies {
+            per_test_dependencies.release();
+        }
+    }
+}
+
+/// The values of the `PerTest` dependencies used by a test. The test and its hooks share one
+/// set of values, which is created again for each attempt of a flaky or non-flaky test.
+#[derive(Clone)]
+pub struct PerTestDependencies {
+    /// The dependencies in construction order, with the cells holding their values
+    dependencies: Vec<(Arc<PerTestDependency>, Arc<PerTestValue>)>,
+    /// The dependencies of the suites containing the test, with the cells of the `PerTest`
+    /// dependencies in place of their stand-ins
+    dependency_map: Arc<HashMap<String, Arc<dyn Any + Send + Sync>>>,
+    dependency_events: DependencyEvents,
+    /// The values constructed for the current attempt, in construction order
+    constructed: Arc<parking_lot::Mutex<Vec<ConstructedDependency>>>,
+}
+
+impl PerTestDependencies {
+    fn new(
+        dependencies: Vec<Arc<PerTestDependency>>,
+        dependency_map: &HashMap<String, Arc<dyn Any + Send + Sync>>,
+        dependency_events: &DependencyEvents,
+    ) -> Option<Self> {
+        if dependencies.is_empty() {
+            return None;
+        }
+        let mut dependency_map = dependency_map.clone();
+        let dependencies = dependencies
+            .into_iter()
+            .map(|dep| {
+                let cell = Arc::new(PerTestValue::empty());
+                dependency_map.insert(dep.0.name.clone(), cell.clone());
+                (dep, cell)
+            })
+            .collect();
+        Some(Self {
+            dependencies,
+            dependency_map: Arc::new(dependency_map),
+            dependency_events: dependency_events.clone(),
+            constructed: Arc::new(parking_lot::Mutex::new(Vec::new())),
+        })
+    }
+
+    /// Creates new values for the dependencies, dropping the ones of the previous attempt
+    pub async fn create(&self) -> Result<(), FailureCause> {
+        self.release();
+        for (dep, cell) in &self.dependencies {
+            let (value, constructed) = TestSuiteExecution::construct_dependency(
+                &dep.0,
+                &self.dependency_map,
+                &self.dependency_events,
+            )
+            .await;
+            self.constructed.lock().extend(constructed);
+            if let Some(failure) = value.downcast_ref::<DependencyFailure>() {
+                return Err(FailureCause::DependencyFailed(failure.clone()));
+            }
+            cell.set(value);
+        }
+        Ok(())
+    }
+
+    /// Sync counterpart of `create`
+    pub fn create_sync(&self) -> Result<(), FailureCause> {
+        futures::executor::block_on(self.create())
+    }
+
+    fn release(&self) {
+        for (_, cell) in &self.dependencies {
+            cell.clear();
+        }
+        for constructed in self.constructed.lock().drain(..).rev() {
+            self.dependency_events.dropped(constructed);
+        }
+    }
+}
+
+/// The dependencies used by `test` and the `before_each` and `after_each` hooks of its suites,
+/// or `None` if the test was registered without its dependencies
+fn used_dependencies<'a>(
+    test: &'a RegisteredTest,
+    suites: &'a [Arc<SuiteHooks>],
+) -> Option<Vec<&'a String>> {
+    test.dependencies.as_ref().map(|names| {
+        names
+            .iter()
+            .chain(suites.iter().flat_map(|suite| {
+                suite
+                    .hooks(HookKind::BeforeEach)
+                    .chain(suite.hooks(HookKind::AfterEach))
+                    .flat_map(|hook| &hook.dependencies)
+            }))
+            .collect()
+    })
+}
+
+/// The failure of a dependency used by `test` or by any of the hooks of its suites, if one of
+/// them could not be constructed. Tests registered without their dependencies are failed by
+/// any dependency of their suites that could not be constructed.
+fn failed_dependency(
+    test: &RegisteredTest,
+    suites: &[Arc<SuiteHooks>],
+    dependency_map: &HashMap<String, Arc<dyn Any + Send + Sync>>,
+) -> Option<Arc<DependencyFailure>> {
+    let names: Vec<&String> = match &test.dependencies {
+        Some(names) => names
+            .iter()
+            .chain(
+                suites
+                    .iter()
+                    .flat_map(|suite| suite.hooks.iter().flat_map(|hook| &hook.dependencies)),
+            )
+            .collect(),
+        None => dependency_map.keys().collect(),
+    };
+    names
+        .into_iter()
+        .filter_map(|name| dependency_map.get(name))
+        .find_map(|value| value.clone().downcast::<DependencyFailure>().ok())
+}
+
+/// Stands in for a `PerTest` dependency in the dependency map of a suite. The values are
+/// created for each test by [`TestExecution::create_per_test_dependencies`].
+struct PerTestDependency(RegisteredDependency);
+
+impl PerTestDependency {
+    /// The `PerTest` dependencies used by `test` and the `before_each` and `after_each` hooks
+    /// of its suites, directly or through other `PerTest` dependencies, in construction order
+    fn used_by(
+        test: &RegisteredTest,
+        suites: &[Arc<SuiteHooks>],
+        dependency_map: &HashMap<String, Arc<dyn Any + Send + Sync>>,
+    ) -> Vec<Arc<Self>> {
+        // Tests registered without their dependencies get every `PerTest` dependency
+        let names =
+            used_dependencies(test, suites).unwrap_or_else(|| dependency_map.keys().collect());
+        let mut visited = HashSet::new();
+        let mut result = Vec::new();
+        for name in names {
+            Self::visit(name, dependency_map, &mut visited, &mut result);
+        }
+        result
+    }
+
+    fn visit(
+        name: &str,
+        dependency_map: &HashMap<String, Arc<dyn Any + Send + Sync>>,
+        visited: &mut HashSet<String>,
+        result: &mut Vec<Arc<Self>>,
+    ) {
+        if !visited.insert(name.to_string()) {
+            return;
+        }
+        if let Some(dep) = dependency_map
+            .get(name)
+            .and_then(|value| value.clone().downcast::<Self>().ok())
+        {
+            for dep_dep in &dep.0.dependencies {
+                Self::visit(dep_dep, dependency_map, visited, result);
+            }
+            result.push(dep);
+        }
+    }
 }
 
 impl Drop for TestExecution {
     fn drop(&mut self) {
+        self.release_per_test_dependencies();
         self.in_progress_counter.fetch_sub(1, Ordering::Release);
     }
 }
 
+/// Counts the running tests using each limited resource declared with `#[uses_resource]`
+/// or `uses_resource_suite!`. When the tests run in this process, it also keeps the tests
+/// changing the environment or the working directory from running together with other tests.
+#[derive(Clone, Default)]
+struct ResourceSemaphores {
+    limits: Arc<HashMap<String, usize>>,
+    in_use: Arc<parking_lot::Mutex<ResourcesInUse>>,
+    exclusive_environment: bool,
+    changes: AvailabilityChanges,
+}
+
+#[derive(Default)]
+struct ResourcesInUse {
+    counts: HashMap<String, usize>,
+    running: usize,
+    changing_environment: bool,
+    /// Set when a test changing the environment was next in line but had to wait for the
+    /// running tests to finish. No other test is started until it runs, so it is not starved.
+    environment_waiting: bool,
+}
+
+impl ResourceSemaphores {
+    fn new(tests: &[RegisteredTest]) -> Self {
+        let mut limits: HashMap<String, usize> = HashMap::new();
+        for resource in tests.iter().flat_map(|test| &test.props.resources) {
+            limits
+                .entry(resource.name.clone())
+                .and_modify(|limit| *limit = (*limit).min(resource.limit))
+                .or_insert(resource.limit);
+        }
+        Self {
+            limits: Arc::new(limits),
+            in_use: Arc::new(parking_lot::Mutex::new(ResourcesInUse::default())),
+            exclusive_environment: false,
+            changes: AvailabilityChanges::default(),
+        }
+    }
+
+    /// Returns true if the test can be started now
+    fn is_available(&self, test: &RegisteredTest) -> bool {
+        let in_use = self.in_use.lock();
+        if self.exclusive_environment {
+            if in_use.changing_environment {
+                return false;
+            }
+            if test.props.changes_environment() {
+                if in_use.running > 0 {
+                    return false;
+                }
+            } else if in_use.environment_waiting {
+                return false;
+            }
+        }
+        test.props.resources.iter().all(|resource| {
+            let limit = self
+                .limits
+                .get(&resource.name)
+                .copied()
+                .unwrap_or(resource.limit);
+            in_use.counts.get(&resource.name).copied().unwrap_or(0) < limit
+        })
+    }
+
+    /// Returns true if the test has to run alone, so once it is next in line it has to wait for
+    /// the running tests to finish
+    fn waits_for_running_tests(&self, test: &RegisteredTest) -> bool {
+        self.exclusive_environment && test.props.changes_environment()
+    }
+
+    /// Keeps new tests from being started until the test changing the environment, which is
+    /// next in line, can run, so it is not starved
+    fn mark_environment_waiting(&self) {
+        self.in_use.lock().environment_waiting = true;
+    }
+
+    /// Marks the test's resources as used until the returned guard is dropped. Tests are picked
+    /// while holding the lock of the whole execution tree, so no other test can take the
+    /// resources between `is_available` and `acquire`.
+    fn acquire(&self, test: &RegisteredTest) -> ResourceGuard {
+        let names: HashSet<String> = test
+            .props
+            .resources
+            .iter()
+            .map(|resource| resource.name.clone())
+            .collect();
+        let changes_environment = self.exclusive_environment && test.props.changes_environment();
+        let mut in_use = self.in_use.lock();
+        for name in &names {
+            *in_use.counts.entry(name.clone()).or_default() += 1;
+        }
+        in_use.running += 1;
+        if changes_environment {
+            in_use.changing_environment = true;
+            in_use.environment_waiting = false;
+        }
+        ResourceGuard {
+            in_use: self.in_use.clone(),
+            names,
+            changes_environment,
+            changes: self.changes.clone(),
+        }
+    }
+}
+
+/// Held by every picked test until it is released. It is dropped after the sequential execution
+/// lock of the test, so the waiting test threads are only woken up once both are released.
+struct ResourceGuard {
+    in_use: Arc<parking_lot::Mutex<ResourcesInUse>>,
+    names: HashSet<String>,
+    changes_environment: bool,
+    changes: AvailabilityChanges,
+}
+
+impl Drop for ResourceGuard {
+    fn drop(&mut self) {
+        {
+            let mut in_use = self.in_use.lock();
+            for name in &self.names {
+                if let Some(count) = in_use.counts.get_mut(name) {
+                    *count -= 1;
+                }
+            }
+            in_use.running -= 1;
+            if self.changes_environment {
+                in_use.changing_environment = false;
+            }
+        }
+        self.changes.notify();
+    }
+}
+
+/// Wakes up the test threads waiting for one of the remaining tests to become available, when a
+/// running test is released or records its result. The threads remember `count` before trying
+/// to pick a test, so a change happening before they start waiting is not missed.
+#[derive(Clone, Default)]
+pub struct AvailabilityChanges {
+    inner: Arc<AvailabilityChangesInner>,
+}
+
+#[derive(Default)]
+struct AvailabilityChangesInner {
+    count: parking_lot::Mutex<u64>,
+    changed: parking_lot::Condvar,
+    #[cfg(feature = "tokio")]
+    changed_async: tokio::sync::Notify,
+}
+
+impl AvailabilityChanges {
+    /// The number of changes so far
+    pub fn count(&self) -> u64 {
+        *self.inner.count.lock()
+    }
+
+    fn notify(&self) {
+        *self.inner.count.lock() += 1;
+        self.inner.changed.notify_all();
+        #[cfg(feature = "tokio")]
+        self.inner.changed_async.notify_waiters();
+    }
+
+    /// Blocks until there were more changes than `seen`
+    pub fn wait_sync(&self, seen: u64) {
+        let mut count = self.inner.count.lock();
+        while *count == seen {
+            self.inner.changed.wait(&mut count);
+        }
+    }
+
+    /// Async counterpart of `wait_sync`
+    #[cfg(feature = "tokio")]
+    pub async fn wait(&self, seen: u64) {
+        loop {
+            // Registered before checking the count, so a change in between still wakes it up
+            let changed = self.inner.changed_async.notified();
+            if self.count() != seen {
+                return;
+            }
+            changed.await;
+        }
+    }
+}
+
+/// Dependency values constructed by an execution plan that have to be torn down when the run
+/// finishes, in construction order
+#[derive(Clone, Default)]
+struct Teardowns {
+    pending: Arc<parking_lot::Mutex<Vec<PendingTeardown>>>,
+}
+
+impl Teardowns {
+    fn register(&self, dep: &RegisteredDependency, value: &Arc<dyn Any + Send + Sync>) {
+        if let Some(teardown) = PendingTeardown::new(dep, value) {
+            self.pending.lock().push(teardown);
+        }
+    }
+
+    fn take(&self) -> Vec<PendingTeardown> {
+        let mut pending = std::mem::take(&mut *self.pending.lock());
+        pending.reverse();
+        pending
+    }
+}
+
+/// Reports the construction and drop of dependency values to the test runner output. Worker
+/// processes buffer the events instead, and send them to the parent with the test results.
+#[derive(Clone, Default)]
+pub struct DependencyEvents {
+    target: Option<DependencyEventTarget>,
+}
+
+#[derive(Clone)]
+enum DependencyEventTarget {
+    Output(Arc<dyn TestRunnerOutput>),
+    Buffer(
+        DependencyProcess,
+        Arc<parking_lot::Mutex<Vec<DependencyEvent>>>,
+    ),
+}
+
+impl DependencyEvents {
+    pub fn new(arguments: &Arguments, output: &Arc<dyn TestRunnerOutput>) -> Self {
+        let target = if arguments.ipc.is_some() {
+            DependencyEventTarget::Buffer(
+                DependencyProcess::Worker {
+                    index: arguments.worker_index.unwrap_or_default(),
+                },
+                Arc::new(parking_lot::Mutex::new(Vec::new())),
+            )
+        } else {
+            DependencyEventTarget::Output(output.clone())
+        };
+        Self {
+            target: Some(target),
+        }
+    }
+
+    fn construction_started(&self, dep: &RegisteredDependency) -> Instant {
+        self.report(
+            dep.qualified_id(),
+            dep.scope,
+            DependencyEventKind::ConstructionStarted,
+        );
+        Instant::now()
+    }
+
+    fn construction_finished(
+        &self,
+        dep: &RegisteredDependency,
+        start: Instant,
+        value: &Arc<dyn Any + Send + Sync>,
+    ) -> Option<ConstructedDependency> {
+        let failed = value.is::<DependencyFailure>();
+        self.report(
+            dep.qualified_id(),
+            dep.scope,
+            DependencyEventKind::ConstructionFinished {
+                duration: start.elapsed(),
+                failed,
+            },
+        );
+        (!failed).then(|| ConstructedDependency {
+            dependency: dep.qualified_id(),
+            scope: dep.scope,
+            constructed_at: Instant::now(),
+        })
+    }
+
+    pub fn dropped(&self, constructed: ConstructedDependency) {
+        self.report(
+            constructed.dependency,
+            constructed.scope,
+            DependencyEventKind::Dropped {
+                lifetime: constructed.constructed_at.elapsed(),
+            },
+        );
+    }
+
+    fn report(&self, dependency: String, scope: DepScope, kind: DependencyEventKind) {
+        match &self.target {
+            None => {}
+            Some(DependencyEventTarget::Output(output)) => {
+                output.dependency_event(&DependencyEvent {
+                    dependency,
+                    scope,
+                    process: DependencyProcess::Parent,
+                    kind,
+                });
+            }
+            Some(DependencyEventTarget::Buffer(process, buffer)) => {
+                buffer.lock().push(DependencyEvent {
+                    dependency,
+                    scope,
+                    process: *process,
+                    kind,
+                });
+            }
+        }
+    }
+
+    fn take_buffered(&self) -> Vec<DependencyEvent> {
+        match &self.target {
+            Some(DependencyEventTarget::Buffer(_, buffer)) => std::mem::take(&mut *buffer.lock()),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// A successfully constructed dependency value, reported as dropped when the runner releases it
+pub struct ConstructedDependency {
+    dependency: String,
+    scope: DepScope,
+    constructed_at: Instant,
+}
+
+/// A constructed dependency value waiting for its `#[test_dep(teardown = ...)]` function.
+/// Keeps the value alive until the teardown has run.
+pub struct PendingTeardown {
+    dep_id: String,
+    value: Arc<dyn Any + Send + Sync>,
+    teardown: DependencyTeardown,
+}
+
+impl PendingTeardown {
+    fn new(dep: &RegisteredDependency, value: &Arc<dyn Any + Send + Sync>) -> Option<Self> {
+        if value.is::<DependencyFailure>() {
+            return None;
+        }
+        dep.teardown.as_ref().map(|teardown| Self {
+            dep_id: dep.qualified_id(),
+            value: value.clone(),
+            teardown: teardown.clone(),
+        })
+    }
+
+    /// Runs the teardown function and drops the value. Returns a warning describing the
+    /// failure if the teardown returned an error or panicked.
+    pub async fn run(self) -> Option<String> {
+        let start = Instant::now();
+        // The stored value is only released once the teardown has finished
+        let value = TrackedDependency::value(&self.value);
+        let result = AssertUnwindSafe((self.teardown.0)(value))
+            .catch_unwind()
+            .await;
+        drop(self.value);
+        TestResult::from_result(&ShouldPanic::No, start.elapsed(), result)
+            .failure_cause()
+            .map(|cause| {
+                format!(
+                    "Teardown of dependency {} failed: {}",
+                    self.dep_id,
+                    cause.render()
+                )
+            })
+    }
+
+    /// Runs the teardown function without an async runtime, see [`PendingTeardown::run`]
+    pub fn run_sync(self) -> Option<String> {
+        futures::executor::block_on(self.run())
+    }
+}
+
+/// The setup and teardown hooks of a test suite. The `before_all` and `after_all` hooks run once
+/// in every process running tests of the suite, so their state is tracked here.
+pub(crate) struct SuiteHooks {
+    crate_name: String,
+    module_path: String,
+    hooks: Vec<SuiteHook>,
+    /// Name of the first failed `before_all` hook, set once the `before_all` hooks have run
+    before_all: OnceLock<Option<String>>,
+    #[cfg(feature = "tokio")]
+    before_all_async: tokio::sync::OnceCell<Option<String>>,
+    /// Dependencies of the first test of the suite, kept for the `after_all` hooks
+    deps: parking_lot::Mutex<Option<Arc<dyn DependencyView + Send + Sync>>>,
+    after_all_started: AtomicBool,
+}
+
+impl SuiteHooks {
+    fn new(props: &[RegisteredTestSuiteProperty]) -> Option<Self> {
+        let mut suite = None;
+        let mut hooks = Vec::new();
+        for prop in props {
+            if let RegisteredTestSuiteProperty::Hook {
+                name,
+                crate_name,
+                module_path,
+                hook,
+            } = prop
+            {
+                let module_path = [module_path, name]
+                    .into_iter()
+                    .filter(|s| !s.is_empty())
+                    .cloned()
+                    .collect::<Vec<String>>()
+                    .join("::");
+                suite = Some((crate_name.clone(), module_path));
+                hooks.push(hook.clone());
+            }
+        }
+        let (crate_name, module_path) = suite?;
+        Some(Self {
+            crate_name,
+            module_path,
+            hooks,
+            before_all: OnceLock::new(),
+            #[cfg(feature = "tokio")]
+            before_all_async: tokio::sync::OnceCell::new(),
+            deps: parking_lot::Mutex::new(None),
+            after_all_started: AtomicBool::new(false),
+        })
+    }
+
+    /// The hooks of the given kind, in the order of their registration
+    pub fn hooks(&self, kind: HookKind) -> impl Iterator<Item = &SuiteHook> {
+        self.hooks.iter().filter(move |hook| hook.kind == kind)
+    }
+
+    /// The entry reporting a run of `hook`. See [`SuiteHook::entry`].
+    pub fn entry(&self, hook: &SuiteHook, test: Option<&RegisteredTest>) -> RegisteredTest {
+        hook.entry(&self.crate_name, &self.module_path, test)
+    }
+
+    /// Runs the `before_all` hooks with `run` unless they already ran in this process, waiting
+    /// for them if another test thread is running them. Returns the name of the failed
+    /// `before_all` hook, if any.
+    pub fn before_all_sync(
+        &self,
+        deps: &Arc<dyn DependencyView + Send + Sync>,
+        run: impl FnOnce() -> Option<String>,
+    ) -> Option<String> {
+        self.before_all
+            .get_or_init(|| {
+                self.keep_deps(deps);
+                run()
+            })
+            .clone()
+    }
+
+    /// Async counterpart of `before_all_sync`
+    #[cfg(feature = "tokio")]
+    pub async fn before_all(
+        &self,
+        deps: &Arc<dyn DependencyView + Send + Sync>,
+        run: impl std::future::Future<Output = Option<String>>,
+    ) -> Option<String> {
+        self.before_all_async
+            .get_or_init(|| async {
+                self.keep_deps(deps);
+                run.await
+            })
+            .await
+            .clone()
+    }
+
+    fn keep_deps(&self, deps: &Arc<dyn DependencyView + Send + Sync>) {
+        if self.hooks(HookKind::AfterAll).next().is_some() {
+            *self.deps.lock() = Some(deps.clone());
+        }
+    }
+
+    /// Returns the dependencies to run the `after_all` hooks with, if tests of the suite were
+    /// run in this process and the `after_all` hooks have not been started yet
+    pub fn start_after_all(&self) -> Option<Arc<dyn DependencyView + Send + Sync>> {
+        if self.after_all_started.swap(true, Ordering::AcqRel) {
+            None
+        } else {
+            self.deps.lock().take()
+        }
+    }
+}
+
+/// Tracks the outcome of the tests other tests depend on with `#[depends_on]`. Only enforced by
+/// the top-level runner; worker processes run whatever test the runner requests.
+#[derive(Clone, Default)]
+struct Prerequisites {
+    enforced: bool,
+    /// Tests selected to run in this execution
+    selected: Arc<HashSet<String>>,
+    /// Tests that depend on each other, directly or through other tests
+    cyclic: Arc<HashSet<String>>,
+    /// For every prerequisite that is known to be finished, `None` if it passed, or the
+    /// reason for skipping the tests depending on it
+    outcomes: Arc<parking_lot::Mutex<HashMap<String, Option<String>>>>,
+    changes: AvailabilityChanges,
+}
+
+enum PrerequisiteState {
+    Ready,
+    Waiting,
+    Skip(String),
+}
+
+impl Prerequisites {
+    fn new(
+        all_tests: &[RegisteredTest],
+        selected_tests: &[RegisteredTest],
+        changes: AvailabilityChanges,
+    ) -> Self {
+        let existing: HashSet<String> = all_tests
+            .iter()
+            .map(|test| test.fully_qualified_name())
+            .collect();
+        let selected: HashSet<String> = selected_tests
+            .iter()
+            .map(|test| test.fully_qualified_name())
+            .collect();
+
+        let mut outcomes = HashMap::new();
+        let mut ordering = TopologicalSort::<String>::new();
+        for test in selected_tests {
+            let name = test.fully_qualified_name();
+            ordering.insert(name.clone());
+            for prerequisite in &test.props.depends_on {
+                ordering.add_dependency(prerequisite.clone(), name.clone());
+                if !existing.contains(prerequisite) {
+                    outcomes.insert(
+                        prerequisite.clone(),
+                        Some(format!("prerequisite {prerequisite} does not exist")),
+                    );
+                } else if !selected.contains(prerequisite) {
+                    outcomes.insert(
+                        prerequisite.clone(),
+                        Some(format!("prerequisite {prerequisite} was filtered out")),
+                    );
+                }
+            }
+        }
+        // Whatever cannot be ordered is part of a cycle, or depends on one
+        let mut ordered = HashSet::new();
+        loop {
+            let next = ordering.pop_all();
+            if next.is_empty() {
+                break;
+            }
+            ordered.extend(next);
+        }
+        let cyclic = selected.difference(&ordered).cloned().collect();
+
+        Self {
+            enforced: true,
+            selected: Arc::new(selected),
+            cyclic: Arc::new(cyclic),
+            outcomes: Arc::new(parking_lot::Mutex::new(outcomes)),
+            changes,
+        }
+    }
+
+    fn state(&self, test: &RegisteredTest) -> PrerequisiteState {
+        if !self.enforced || test.props.depends_on.is_empty() {
+            return PrerequisiteState::Ready;
+        }
+        if self.cyclic.contains(&test.fully_qualified_name()) {
+            return PrerequisiteState::Skip("its prerequisites depend on each other".to_string());
+        }
+        let outcomes = self.outcomes.lock();
+        let mut state = PrerequisiteState::Ready;
+        for prerequisite in &test.props.depends_on {
+            match outcomes.get(prerequisite) {
+                Some(Some(reason)) => return PrerequisiteState::Skip(reason.clone()),
+                Some(None) => {}
+                None => state = PrerequisiteState::Waiting,
+            }
+        }
+        state
+    }
+
+    fn is_selected(&self, test: &RegisteredTest) -> bool {
+        self.selected.contains(&test.fully_qualified_name())
+    }
+
+    fn record(&self, test: &RegisteredTest, result: &TestResult) {
+        if !self.enforced {
+            return;
+        }
+        let name = test.fully_qualified_name();
+        let outcome = match result {
+            TestResult::Passed { .. } | TestResult::Benchmarked { .. } => None,
+            TestResult::Failed { .. } => Some(format!("prerequisite {name} failed")),
+            TestResult::Ignored { .. } => Some(format!("prerequisite {name} was ignored")),
+            TestResult::Skipped { .. } => Some(format!("prerequisite {name} was skipped")),
+            TestResult::NotRun { .. } => Some(format!("prerequisite {name} was not run")),
+        };
+        self.outcomes.lock().insert(name, outcome);
+        self.changes.notify();
+    }
+}
+
 #[allow(dead_code)]
 enum SequentialExecutionLockGuard {
     None,
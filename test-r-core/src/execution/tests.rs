@@ -1,7 +1,8 @@
 use super::*;
 use crate::internal::{
-    CloneableCodec, DependencyConstructor, RegisteredDependency, RegisteredTest,
-    RegisteredTestSuiteProperty, TestFunction, TestProperties,
+    CloneableCodec, DependencyConstructor, DependencyEventKind, DependencyFailure, FailureCause,
+    HookKind, PerTestValue, RegisteredDependency, RegisteredTest, RegisteredTestSuiteProperty,
+    SuiteHook, TestFunction, TestProperties,
 };
 use std::sync::atomic::{AtomicUsize, Ordering};
 
@@ -17,6 +18,7 @@ fn registered_test_in_module(name: &str, module_path: &str, deps: Vec<String>) -
         run: TestFunction::Sync(Arc::new(|_| Box::new(()))),
         props: TestProperties::default(),
         dependencies: Some(deps),
+        location: None,
     }
 }
 
@@ -66,6 +68,7 @@ fn registered_cloneable_dep_in(
         hosted_codec: None,
         rpc_factory: None,
         companions: Vec::new(),
+        teardown: None,
     }
 }
 
@@ -96,6 +99,7 @@ fn registered_perworker_counting_dep(
         hosted_codec: None,
         rpc_factory: None,
         companions: Vec::new(),
+        teardown: None,
     }
 }
 
@@ -192,6 +196,368 @@ fn perworker_dep_not_rematerialised_when_descendant_subtree_is_locked() {
     );
 }
 
+fn hook_prop(
+    suite: &str,
+    module_path: &str,
+    name: &str,
+    deps: Vec<String>,
+) -> RegisteredTestSuiteProperty {
+    RegisteredTestSuiteProperty::Hook {
+        name: suite.to_string(),
+        crate_name: "tcrate".to_string(),
+        module_path: module_path.to_string(),
+        hook: SuiteHook {
+            name: name.to_string(),
+            kind: HookKind::BeforeEach,
+            run: TestFunction::Sync(Arc::new(|_| Box::new(()))),
+            dependencies: deps,
+        },
+    }
+}
+
+/// A picked test carries the hooks of every suite containing it, outer
+/// suites first, and the dependencies only used by hooks are not pruned.
+#[test]
+fn picked_test_carries_hooks_of_enclosing_suites() {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let dep = registered_perworker_counting_dep("hook_dep", "parent", counter.clone());
+    let test = registered_test_in_module("t", "parent::child", vec![]);
+    let other = registered_test_in_module("other", "sibling", vec![]);
+    let props = [
+        hook_prop("child", "parent", "inner_hook", vec![]),
+        hook_prop("parent", "", "outer_hook", vec!["hook_dep".to_string()]),
+    ];
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&Arguments::default(), &[dep], &[test, other], &props);
+    assert_eq!(execution.suite_hooks().len(), 2);
+
+    let mut picked = Vec::new();
+    while let Some(next) = execution.pick_next_sync() {
+        picked.push(next);
+    }
+    let next = picked.iter().find(|next| next.test.name == "t").unwrap();
+    let suites: Vec<_> = next
+        .suites
+        .iter()
+        .map(|suite| suite.module_path.as_str())
+        .collect();
+    assert_eq!(suites, ["parent", "parent::child"]);
+    assert_eq!(
+        next.suites[0]
+            .hooks(HookKind::BeforeEach)
+            .map(|hook| hook.name.as_str())
+            .collect::<Vec<_>>(),
+        ["outer_hook"]
+    );
+    assert!(next.suites[0].hooks(HookKind::AfterEach).next().is_none());
+    assert_eq!(counter.load(Ordering::SeqCst), 1);
+
+    let other = picked
+        .iter()
+        .find(|next| next.test.name == "other")
+        .unwrap();
+    assert!(other.suites.is_empty());
+}
+
+/// A `PerWorker` dep whose teardown records its name in `torn_down`, failing for `"failing"`
+fn registered_dep_with_teardown(
+    name: &str,
+    module_path: &str,
+    torn_down: Arc<parking_lot::Mutex<Vec<String>>>,
+) -> RegisteredDependency {
+    let mut dep =
+        registered_perworker_counting_dep(name, module_path, Arc::new(AtomicUsize::new(0)));
+    let dep_name = name.to_string();
+    dep.teardown = Some(DependencyTeardown(Arc::new(move |_value| {
+        let dep_name = dep_name.clone();
+        let torn_down = torn_down.clone();
+        Box::pin(async move {
+            torn_down.lock().push(dep_name.clone());
+            if dep_name == "failing" {
+                Err(FailureCause::ReturnedMessage("could not stop".to_string()))
+            } else {
+                Ok(())
+            }
+        })
+    })));
+    dep
+}
+
+/// Teardowns are collected for the dependencies the plan constructed, and run in reverse
+/// construction order, outer suites last.
+#[test]
+fn teardowns_run_in_reverse_construction_order() {
+    let torn_down = Arc::new(parking_lot::Mutex::new(Vec::new()));
+    let mut inner = registered_dep_with_teardown("inner", "parent::child", torn_down.clone());
+    inner.dependencies = vec!["failing".to_string()];
+    let deps = [
+        registered_dep_with_teardown("outer", "parent", torn_down.clone()),
+        inner,
+        registered_dep_with_teardown("failing", "parent::child", torn_down.clone()),
+    ];
+    let tests = [
+        registered_test_in_module("outer_test", "parent", vec!["outer".to_string()]),
+        registered_test_in_module("inner_test", "parent::child", vec!["inner".to_string()]),
+    ];
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&Arguments::default(), &deps, &tests, &[]);
+    let mut picked = Vec::new();
+    while let Some(next) = execution.pick_next_sync() {
+        picked.push(next);
+    }
+    drop(picked);
+    assert!(torn_down.lock().is_empty());
+
+    let warnings: Vec<_> = execution
+        .take_teardowns()
+        .into_iter()
+        .filter_map(PendingTeardown::run_sync)
+        .collect();
+    assert_eq!(*torn_down.lock(), ["inner", "failing", "outer"]);
+    assert_eq!(
+        warnings,
+        ["Teardown of dependency tcrate::parent::child::failing failed: could not stop"]
+    );
+    assert!(execution.take_teardowns().is_empty());
+}
+
+/// Dependencies are reported as dropped once the tests still using them finished, not when the
+/// level of the plan constructing them has no more tests to pick
+#[test]
+fn dependencies_are_dropped_once_the_running_tests_release_them() {
+    let deps = [registered_perworker_counting_dep(
+        "inner",
+        "parent::child",
+        Arc::new(AtomicUsize::new(0)),
+    )];
+    let tests = [
+        registered_test_in_module("first", "parent::child", vec!["inner".to_string()]),
+        registered_test_in_module("second", "parent::child", vec!["inner".to_string()]),
+        registered_test_in_module("outer", "parent", vec![]),
+    ];
+    // Worker processes buffer the events, so they can be inspected here
+    let worker_args = Arguments {
+        ipc: Some("test".to_string()),
+        ..Default::default()
+    };
+    let output = crate::output::test_runner_output(&worker_args);
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&Arguments::default(), &deps, &tests, &[]);
+    execution.set_dependency_events(&DependencyEvents::new(&worker_args, &output));
+    let dropped = |execution: &TestSuiteExecution| {
+        execution
+            .take_dependency_events()
+            .into_iter()
+            .filter(|event| matches!(event.kind, DependencyEventKind::Dropped { .. }))
+            .map(|event| event.dependency)
+            .collect::<Vec<_>>()
+    };
+
+    let mut picked = Vec::new();
+    while let Some(next) = execution.pick_next_sync() {
+        picked.push(next);
+    }
+    assert!(dropped(&execution).is_empty());
+    drop(picked);
+    assert_eq!(dropped(&execution), ["tcrate::parent::child::inner"]);
+}
+
+/// A `PerTest` dep whose constructor records its name in `constructed` and returns the
+/// number of constructions so far
+fn registered_per_test_dep(
+    name: &str,
+    dependencies: Vec<String>,
+    constructed: Arc<parking_lot::Mutex<Vec<String>>>,
+) -> RegisteredDependency {
+    let mut dep = registered_perworker_counting_dep(name, "", Arc::new(AtomicUsize::new(0)));
+    let dep_name = name.to_string();
+    dep.constructor = DependencyConstructor::Sync(Arc::new(move |_view| {
+        let mut constructed = constructed.lock();
+        constructed.push(dep_name.clone());
+        Arc::new(constructed.len()) as Arc<dyn Any + Send + Sync>
+    }));
+    dep.dependencies = dependencies;
+    dep.scope = DepScope::PerTest;
+    dep
+}
+
+/// `PerTest` deps are not constructed when the tests are picked, but for every test when
+/// `create_per_test_dependencies_sync` is called, dependencies first.
+#[test]
+fn per_test_deps_are_constructed_for_every_test() {
+    let constructed = Arc::new(parking_lot::Mutex::new(Vec::new()));
+    let deps = [
+        registered_per_test_dep("outer", vec!["inner".to_string()], constructed.clone()),
+        registered_per_test_dep("inner", Vec::new(), constructed.clone()),
+        registered_per_test_dep("unused", Vec::new(), constructed.clone()),
+    ];
+    let tests = [
+        registered_test("first", vec!["outer".to_string()]),
+        registered_test("second", vec!["outer".to_string()]),
+    ];
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&Arguments::default(), &deps, &tests, &[]);
+    let mut picked = Vec::new();
+    while let Some(next) = execution.pick_next_sync() {
+        picked.push(next);
+    }
+    assert_eq!(picked.len(), 2);
+    assert!(constructed.lock().is_empty());
+
+    let mut values = Vec::new();
+    for next in &mut picked {
+        assert!(next.create_per_test_dependencies_sync().is_none());
+        let value = PerTestValue::take(&next.deps, "outer")
+            .downcast::<usize>()
+            .unwrap();
+        values.push(*value);
+    }
+    assert_eq!(*constructed.lock(), ["inner", "outer", "inner", "outer"]);
+    assert_eq!(values, [2, 4]);
+}
+
+/// A dep shared by multiple tests cannot be constructed from a `PerTest` dep, so the tests using
+/// it fail instead of the whole run
+#[test]
+fn shared_deps_using_per_test_deps_fail_their_tests() {
+    let constructed = Arc::new(parking_lot::Mutex::new(Vec::new()));
+    let counter = Arc::new(AtomicUsize::new(0));
+    let mut shared = registered_perworker_counting_dep("shared", "", counter.clone());
+    shared.dependencies = vec!["per_test".to_string()];
+    let deps = [
+        shared,
+        registered_per_test_dep("per_test", Vec::new(), constructed.clone()),
+    ];
+    let tests = [
+        registered_test("uses_shared", vec!["shared".to_string()]),
+        registered_test("uses_per_test", vec!["per_test".to_string()]),
+    ];
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&Arguments::default(), &deps, &tests, &[]);
+    let mut messages = Vec::new();
+    while let Some(mut next) = execution.pick_next_sync() {
+        let result = next.create_per_test_dependencies_sync();
+        messages.push((
+            next.test.name.clone(),
+            result.and_then(|result| result.failure_message()),
+        ));
+    }
+    messages.sort();
+    assert_eq!(
+        messages,
+        [
+            ("uses_per_test".to_string(), None),
+            (
+                "uses_shared".to_string(),
+                Some(
+                    "Dependency tcrate::shared failed: it cannot depend on the PerTest dependency per_test"
+                        .to_string()
+                )
+            ),
+        ]
+    );
+    assert_eq!(counter.load(Ordering::SeqCst), 0);
+    assert_eq!(*constructed.lock(), ["per_test"]);
+}
+
+/// A dep whose constructor returns an error fails every test depending on it, directly or
+/// through another dep, without constructing the dependent deps; independent tests are
+/// unaffected.
+#[test]
+fn failed_dependency_fails_dependent_tests() {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let mut broken = registered_perworker_counting_dep("broken", "", Arc::new(AtomicUsize::new(0)));
+    broken.constructor = DependencyConstructor::Sync(Arc::new(|_view| {
+        DependencyFailure::from_error("broken".to_string(), "no connection")
+    }));
+    let mut dependent = registered_perworker_counting_dep("dependent", "", counter.clone());
+    dependent.dependencies = vec!["broken".to_string()];
+    let tests = [
+        registered_test("direct", vec!["broken".to_string()]),
+        registered_test("transitive", vec!["dependent".to_string()]),
+        registered_test("independent", Vec::new()),
+    ];
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&Arguments::default(), &[broken, dependent], &tests, &[]);
+    let mut messages = Vec::new();
+    while let Some(mut next) = execution.pick_next_sync() {
+        let result = next.create_per_test_dependencies_sync();
+        messages.push((
+            next.test.name.clone(),
+            result.and_then(|result| result.failure_message()),
+        ));
+    }
+    messages.sort();
+    assert_eq!(
+        messages,
+        [
+            (
+                "direct".to_string(),
+                Some("Dependency broken failed: no connection".to_string())
+            ),
+            ("independent".to_string(), None),
+            (
+                "transitive".to_string(),
+                Some("Dependency broken failed: no connection".to_string())
+            ),
+        ]
+    );
+    assert_eq!(counter.load(Ordering::SeqCst), 0);
+}
+
+/// A dep that could not be constructed fails the tests of a suite whose `before_all` hook uses
+/// it, and the tests registered without their dependencies, before any hook runs.
+#[test]
+fn failed_dependency_of_suite_hook_fails_tests() {
+    let mut broken = registered_perworker_counting_dep("broken", "", Arc::new(AtomicUsize::new(0)));
+    broken.constructor = DependencyConstructor::Sync(Arc::new(|_view| {
+        DependencyFailure::from_error("broken".to_string(), "no connection")
+    }));
+    let mut before_all = hook_prop("suite", "", "start", vec!["broken".to_string()]);
+    if let RegisteredTestSuiteProperty::Hook { hook, .. } = &mut before_all {
+        hook.kind = HookKind::BeforeAll;
+    }
+    let mut unknown = registered_test("unknown", Vec::new());
+    unknown.dependencies = None;
+    let tests = [
+        registered_test_in_module("in_suite", "suite", Vec::new()),
+        unknown,
+        registered_test("independent", Vec::new()),
+    ];
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&Arguments::default(), &[broken], &tests, &[before_all]);
+    let mut messages = Vec::new();
+    while let Some(mut next) = execution.pick_next_sync() {
+        let result = next.create_per_test_dependencies_sync();
+        messages.push((
+            next.test.name.clone(),
+            result.and_then(|result| result.failure_message()),
+        ));
+    }
+    messages.sort();
+    assert_eq!(
+        messages,
+        [
+            (
+                "in_suite".to_string(),
+                Some("Dependency broken failed: no connection".to_string())
+            ),
+            ("independent".to_string(), None),
+            (
+                "unknown".to_string(),
+                Some("Dependency broken failed: no connection".to_string())
+            ),
+        ]
+    );
+}
+
 /// Async-path counterpart of
 /// [`perworker_dep_not_rematerialised_when_descendant_subtree_is_locked`].
 ///
@@ -513,6 +879,7 @@ fn async_cloneable_wire_collection_awaits_async_constructor() {
         hosted_codec: None,
         rpc_factory: None,
         companions: Vec::new(),
+        teardown: None,
     };
     let test = registered_test("t1", vec!["clone_dep".to_string()]);
 
@@ -700,6 +1067,7 @@ fn registered_hosted_dep_in(
         hosted_codec: Some(codec),
         rpc_factory: None,
         companions: Vec::new(),
+        teardown: None,
     }
 }
 
@@ -958,6 +1326,7 @@ fn hosted_no_spawn_workers_uses_worker_side_handle() {
         hosted_codec: Some(codec.clone()),
         rpc_factory: None,
         companions: Vec::new(),
+        teardown: None,
     };
     let test = registered_test("t1", vec!["hosted_dep".to_string()]);
 
@@ -1268,6 +1637,7 @@ fn async_hosted_descriptor_collection_awaits_async_constructor() {
         hosted_codec: Some(codec),
         rpc_factory: None,
         companions: Vec::new(),
+        teardown: None,
     };
     let test = registered_test("t1", vec!["hosted_async".to_string()]);
 
@@ -1394,6 +1764,7 @@ fn registered_hosted_rpc_dep(
         hosted_codec: None,
         rpc_factory: Some(factory),
         companions: Vec::new(),
+        teardown: None,
     }
 }
 
@@ -1963,3 +2334,438 @@ fn hosted_rpc_in_process_transport_multiplexes_concurrent_calls_from_threads() {
         all.len()
     );
 }
+
+fn longest_first_arguments(timings: &[(&str, u64)]) -> Arguments {
+    Arguments {
+        schedule: Some(ScheduleSetting::LongestFirst),
+        test_timings: timings
+            .iter()
+            .map(|(name, secs)| (name.to_string(), Duration::from_secs(*secs)))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn longest_first_schedule_picks_slowest_tests_across_modules() {
+    let tests = vec![
+        registered_test("root_fast", vec![]),
+        registered_test_in_module("a_fast", "a", vec![]),
+        registered_test_in_module("a_unknown", "a", vec![]),
+        registered_test_in_module("b_slow", "b", vec![]),
+        registered_test("root_medium", vec![]),
+        registered_test_in_module("a_slowest", "a", vec![]),
+    ];
+    let args = longest_first_arguments(&[
+        ("tcrate::root_fast", 1),
+        ("tcrate::a::a_fast", 2),
+        ("tcrate::b::b_slow", 60),
+        ("tcrate::root_medium", 30),
+        ("tcrate::a::a_slowest", 90),
+    ]);
+
+    let (mut execution, _filtered) = TestSuiteExecution::construct(&args, &[], &tests, &[]);
+    let mut picked = Vec::new();
+    while let Some(test) = execution.pick_next_sync() {
+        picked.push(test.test.name.clone());
+    }
+    assert_eq!(
+        picked,
+        vec![
+            "a_slowest",
+            "b_slow",
+            "root_medium",
+            "a_fast",
+            "root_fast",
+            "a_unknown"
+        ]
+    );
+}
+
+#[test]
+fn longest_first_schedule_respects_sequential_suites() {
+    let tests = vec![
+        registered_test_in_module("seq_slow_1", "seq", vec![]),
+        registered_test_in_module("seq_slow_2", "seq", vec![]),
+        registered_test("root_fast", vec![]),
+    ];
+    let sequential_prop = RegisteredTestSuiteProperty::Sequential {
+        name: "seq".to_string(),
+        crate_name: "tcrate".to_string(),
+        module_path: "".to_string(),
+    };
+    let args = longest_first_arguments(&[
+        ("tcrate::seq::seq_slow_1", 50),
+        ("tcrate::seq::seq_slow_2", 60),
+        ("tcrate::root_fast", 1),
+    ]);
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&args, &[], &tests, &[sequential_prop]);
+    let first = execution.pick_next_sync().expect("a test should be picked");
+    assert_eq!(first.test.name, "seq_slow_2");
+
+    // The sequential suite is locked while its first test runs, so the faster test
+    // is picked instead of waiting for it
+    let second = execution.pick_next_sync().expect("a test should be picked");
+    assert_eq!(second.test.name, "root_fast");
+    assert!(execution.pick_next_sync().is_none());
+
+    drop(first);
+    let third = execution.pick_next_sync().expect("a test should be picked");
+    assert_eq!(third.test.name, "seq_slow_1");
+}
+
+#[test]
+fn guards_of_abandoned_tests_keep_their_suite_and_resources_locked() {
+    let tests = vec![
+        registered_test_in_module("seq_1", "seq", vec![]),
+        registered_test_in_module("seq_2", "seq", vec![]),
+        registered_test_using("db_1", "a", "database", 1),
+        registered_test_using("db_2", "b", "database", 1),
+    ];
+    let sequential_prop = RegisteredTestSuiteProperty::Sequential {
+        name: "seq".to_string(),
+        crate_name: "tcrate".to_string(),
+        module_path: "".to_string(),
+    };
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&Arguments::default(), &[], &tests, &[sequential_prop]);
+    let mut picked = Vec::new();
+    while let Some(next) = execution.pick_next_sync() {
+        picked.push(next);
+    }
+    assert_eq!(picked.len(), 2);
+
+    // The threads of the timed out tests still hold their guards
+    let guards: Vec<_> = picked.iter().map(|next| next.guard()).collect();
+    drop(picked);
+    assert!(execution.pick_next_sync().is_none());
+
+    drop(guards);
+    let mut remaining = Vec::new();
+    while let Some(next) = execution.pick_next_sync() {
+        remaining.push(next);
+    }
+    assert_eq!(remaining.len(), 2);
+}
+
+fn registered_test_using(
+    name: &str,
+    module_path: &str,
+    resource: &str,
+    limit: usize,
+) -> RegisteredTest {
+    let mut test = registered_test_in_module(name, module_path, vec![]);
+    test.props
+        .resources
+        .push(crate::internal::ResourceUsage::new(resource, limit));
+    test
+}
+
+#[test]
+fn tests_sharing_a_resource_are_not_picked_beyond_its_limit() {
+    let tests = vec![
+        registered_test_using("db_1", "a", "database", 1),
+        registered_test_using("db_2", "b", "database", 1),
+        registered_test("unrelated", vec![]),
+    ];
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&Arguments::default(), &[], &tests, &[]);
+    let first = execution.pick_next_sync().expect("a test should be picked");
+    let second = execution.pick_next_sync().expect("a test should be picked");
+    assert!(first.test.name == "unrelated" || second.test.name == "unrelated");
+
+    // The other database test waits until the database is released, even though it is
+    // in a different module
+    assert!(execution.pick_next_sync().is_none());
+    let holder = if first.test.name == "unrelated" {
+        second
+    } else {
+        first
+    };
+    let held = holder.test.name.clone();
+    drop(holder);
+    let third = execution.pick_next_sync().expect("a test should be picked");
+    assert!(third.test.name.starts_with("db_"));
+    assert_ne!(third.test.name, held);
+}
+
+#[test]
+fn tests_waiting_for_a_resource_are_still_contained() {
+    let tests = vec![
+        registered_test_using("db_1", "a", "database", 1),
+        registered_test_using("db_2", "b", "database", 1),
+    ];
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&Arguments::default(), &[], &tests, &[]);
+    let first = execution.pick_next_sync().expect("a test should be picked");
+    let waiting = if first.test.name == "db_1" {
+        "db_2"
+    } else {
+        "db_1"
+    };
+    let is_waiting = |test: &RegisteredTest| test.name == waiting;
+
+    // A worker asked to run the waiting test retries until the resource is released
+    assert!(execution.pick_next_matching_sync(&is_waiting).is_none());
+    assert!(execution.has_matching_test(&is_waiting));
+    assert!(!execution.has_matching_test(&|test| test.name == "missing"));
+    drop(first);
+    let next = execution
+        .pick_next_matching_sync(&is_waiting)
+        .expect("the waiting test should be picked");
+    assert_eq!(next.test.name, waiting);
+    assert!(!execution.has_matching_test(&is_waiting));
+}
+
+#[test]
+fn releasing_a_test_wakes_up_the_waiting_threads() {
+    let tests = vec![
+        registered_test_using("db_1", "a", "database", 1),
+        registered_test_using("db_2", "b", "database", 1),
+    ];
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&Arguments::default(), &[], &tests, &[]);
+    let changes = execution.availability_changes();
+    let first = execution.pick_next_sync().expect("a test should be picked");
+    let seen = changes.count();
+    assert!(execution.pick_next_sync().is_none());
+
+    let waiting = std::thread::spawn(move || changes.wait_sync(seen));
+    drop(first);
+    waiting.join().unwrap();
+    assert!(execution.pick_next_sync().is_some());
+}
+
+#[test]
+fn suite_level_resources_apply_the_smallest_limit() {
+    let tests = vec![
+        registered_test_in_module("pool_1", "pool", vec![]),
+        registered_test_in_module("pool_2", "pool", vec![]),
+        registered_test_using("pool_3", "pool", "pool", 3),
+    ];
+    let resource_prop = RegisteredTestSuiteProperty::Resource {
+        name: "pool".to_string(),
+        crate_name: "tcrate".to_string(),
+        module_path: "".to_string(),
+        resource: crate::internal::ResourceUsage::new("pool", 2),
+    };
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&Arguments::default(), &[], &tests, &[resource_prop]);
+    let first = execution.pick_next_sync().expect("a test should be picked");
+    let _second = execution.pick_next_sync().expect("a test should be picked");
+    assert!(execution.pick_next_sync().is_none());
+
+    drop(first);
+    assert!(execution.pick_next_sync().is_some());
+}
+
+#[test]
+fn tests_changing_the_environment_run_alone_in_process() {
+    let mut env_test = registered_test_in_module("with_env", "env", vec![]);
+    env_test
+        .props
+        .env
+        .push(crate::internal::EnvChange::set("TEST_R_VAR", "value"));
+    let tests = vec![
+        registered_test_in_module("other_1", "a", vec![]),
+        env_test,
+        registered_test_in_module("other_2", "b", vec![]),
+    ];
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&Arguments::default(), &[], &tests, &[]);
+    execution.run_environment_changes_exclusively();
+
+    // Tests are popped from the end, so the first one waits for nothing
+    let first = execution.pick_next_sync().expect("a test should be picked");
+    assert_eq!(first.test.name, "other_2");
+    // The test changing the environment waits until no other test is running, and no other
+    // test is started meanwhile, so it is not starved
+    assert!(execution.pick_next_sync().is_none());
+    drop(first);
+    let second = execution.pick_next_sync().expect("a test should be picked");
+    assert_eq!(second.test.name, "with_env");
+    assert!(execution.pick_next_sync().is_none());
+    drop(second);
+    let third = execution.pick_next_sync().expect("a test should be picked");
+    assert_eq!(third.test.name, "other_1");
+}
+
+#[test]
+fn abandoned_tests_keep_their_environment_until_they_return() {
+    let mut env_test = registered_test_in_module("with_env", "env", vec![]);
+    env_test.props.env.push(crate::internal::EnvChange::set(
+        "TEST_R_ABANDONED_TEST_VAR",
+        "value",
+    ));
+    let tests = vec![registered_test_in_module("other", "a", vec![]), env_test];
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&Arguments::default(), &[], &tests, &[]);
+    execution.run_environment_changes_exclusively();
+
+    let next = execution.pick_next_sync().expect("a test should be picked");
+    assert_eq!(next.test.name, "with_env");
+    // The thread of the timed out test keeps a clone of the guard it was given
+    let mut abandoned = None;
+    crate::environment::with_test_environment(&next.test.props, next.guard(), |guard| {
+        abandoned = Some(guard);
+        TestResult::passed(Duration::ZERO)
+    });
+    drop(next);
+    assert_eq!(
+        std::env::var("TEST_R_ABANDONED_TEST_VAR").as_deref(),
+        Ok("value")
+    );
+    assert!(execution.pick_next_sync().is_none());
+
+    drop(abandoned);
+    assert!(std::env::var("TEST_R_ABANDONED_TEST_VAR").is_err());
+    let next = execution.pick_next_sync().expect("a test should be picked");
+    assert_eq!(next.test.name, "other");
+}
+
+#[test]
+fn checking_the_availability_of_a_test_changing_the_environment_does_not_block_others() {
+    let mut env_test = registered_test_in_module("with_env", "env", vec![]);
+    env_test
+        .props
+        .env
+        .push(crate::internal::EnvChange::set("TEST_R_VAR", "value"));
+    let other = registered_test_in_module("other", "a", vec![]);
+    let mut resources = ResourceSemaphores::new(&[env_test.clone(), other.clone()]);
+    resources.exclusive_environment = true;
+
+    let _running = resources.acquire(&other);
+    assert!(!resources.is_available(&env_test));
+    assert!(resources.is_available(&other));
+
+    // Only the picker marks the test as waiting, once it is next in line
+    resources.mark_environment_waiting();
+    assert!(!resources.is_available(&other));
+}
+
+fn registered_test_depending_on(name: &str, prerequisites: &[&str]) -> RegisteredTest {
+    let mut test = registered_test(name, vec![]);
+    test.props.depends_on = prerequisites.iter().map(|p| p.to_string()).collect();
+    test
+}
+
+fn failed() -> TestResult {
+    TestResult::failed(
+        Duration::ZERO,
+        crate::internal::FailureCause::HarnessError("failed".to_string()),
+    )
+}
+
+#[test]
+fn tests_are_picked_after_their_prerequisites_passed() {
+    let tests = vec![
+        registered_test_depending_on("step_2", &["tcrate::step_1"]),
+        registered_test("step_1", vec![]),
+        registered_test("unrelated", vec![]),
+    ];
+
+    let (mut execution, _filtered) =
+        TestSuiteExecution::construct(&Arguments::default(), &[], &tests, &[]);
+    let first = execution.pick_next_sync().expect("a test should be picked");
+    assert_eq!(first.test.name, "step_1");
+    let second = execution.pick_next_sync().expect("a test should be picked");
+    assert_eq!(second.test.name, "unrelated");
+    // step_2 waits until step_1 has finished
+    assert!(execution.pick_next_sync().is_none());
+
+    let changes = execution.availability_changes();
+    let seen = changes.count();
+    first.record_result(&TestResult::passed(Duration::ZERO));
+    assert_ne!(changes.count(), seen);
+    drop(first);
+    let third = execution.pick_next_sync().expect("a test should be picked");
+    assert_eq!(third.test.name, "step_2");
+    assert_eq!(third.skip_reason, None);
+}
+
+#[test]
+fn tests_are_skipped_if_their_prerequisites_did_not_pass() {
+    let tests = vec![
+        registered_test("step_1", vec![]),
+        registered_test_depending_on("step_2", &["tcrate::step_1"]),
+        registered_test_depending_on("step_3", &["tcrate::step_2"]),
+        registered_test_depending_on("missing", &["tcrate::no_such_test"]),
+        registered_test_depending_on("filtered", &["tcrate::filtered_out"]),
+        registered_test("filtered_out", vec![]),
+        registered_test_depending_on("cycle_a", &["tcrate::cycle_b"]),
+        registered_test_depending_on("cycle_b", &["tcrate::cycle_a"]),
+    ];
+    let args = Arguments {
+        skip: vec!["filtered_out".to_string()],
+        exact: true,
+        ..Default::default()
+    };
+
+    let (mut execution, _filtered) = TestSuiteExecution::construct(&args, &[], &tests, &[]);
+    let mut skipped = HashMap::new();
+    let mut picks = 0;
+    while let Some(next) = execution.pick_next_sync() {
+        picks += 1;
+        assert!(picks <= 7, "too many tests were picked");
+        let result = match &next.skip_reason {
+            Some(reason) => TestResult::skipped(reason.clone()),
+            None if next.test.name == "step_1" => failed(),
+            None => panic!("{} should have been skipped", next.test.name),
+        };
+        if let Some(reason) = &next.skip_reason {
+            skipped.insert(next.test.name.clone(), reason.clone());
+        }
+        next.record_result(&result);
+    }
+
+    assert_eq!(skipped["step_2"], "prerequisite tcrate::step_1 failed");
+    assert_eq!(skipped["step_3"], "prerequisite tcrate::step_2 was skipped");
+    assert_eq!(
+        skipped["missing"],
+        "prerequisite tcrate::no_such_test does not exist"
+    );
+    assert_eq!(
+        skipped["filtered"],
+        "prerequisite tcrate::filtered_out was filtered out"
+    );
+    assert_eq!(skipped["cycle_a"], "its prerequisites depend on each other");
+    assert_eq!(skipped["cycle_b"], "its prerequisites depend on each other");
+    assert!(execution.is_done());
+}
+
+#[test]
+fn rerun_prerequisites_are_waited_for_again() {
+    let tests = vec![
+        registered_test("step_1", vec![]),
+        registered_test_depending_on("step_2", &["tcrate::step_1"]),
+    ];
+    let earlier = vec![
+        (tests[0].clone(), failed()),
+        (
+            tests[1].clone(),
+            TestResult::skipped("prerequisite tcrate::step_1 failed".to_string()),
+        ),
+    ];
+
+    let args = Arguments::default().for_rerun(&tests);
+    let (mut execution, _filtered) = TestSuiteExecution::construct(&args, &[], &tests, &[]);
+    execution.record_results(&earlier);
+
+    let first = execution.pick_next_sync().expect("a test should be picked");
+    assert_eq!(first.test.name, "step_1");
+    assert!(execution.pick_next_sync().is_none());
+    first.record_result(&TestResult::passed(Duration::ZERO));
+
+    let second = execution.pick_next_sync().expect("a test should be picked");
+    assert_eq!(second.test.name, "step_2");
+    assert_eq!(second.skip_reason, None);
+}
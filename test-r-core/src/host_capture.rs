@@ -1252,6 +1252,7 @@ mod tests {
             run: TestFunction::Sync(Arc::new(|_| Box::new(()))),
             props: TestProperties::default(),
             dependencies: None,
+            location: None,
         }
     }
 
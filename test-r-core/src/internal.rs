@@ -1,15 +1,18 @@
-use crate::args::{Arguments, TimeThreshold};
+use crate::args::{Arguments, CpuTime, MemorySize, Partition, TimeThreshold};
 use crate::bench::Bencher;
 use crate::stats::Summary;
+use desert_rust::BinaryCodec;
 use std::any::{Any, TypeId};
 use std::backtrace::Backtrace;
 use std::cmp::{max, Ordering};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 use std::future::Future;
 use std::hash::Hash;
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::process::ExitCode;
+use std::sync::atomic::AtomicUsize;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
 
@@ -102,6 +105,14 @@ pub enum FailureCause {
     Panic(PanicCause),
     /// Framework error (join failure, timeout, IPC deserialization, etc.)
     HarnessError(String),
+    /// The spawned worker process running the test exited before reporting
+    /// a result (segfault, abort, `process::exit`, etc.)
+    WorkerCrashed(WorkerCrash),
+    /// The spawned worker process running the test was stopped because it exceeded
+    /// one of its limits
+    ResourceLimitExceeded(WorkerLimit, WorkerCrash),
+    /// The test was not run because one of its dependencies could not be created
+    DependencyFailed(DependencyFailure),
 }
 
 #[derive(Debug, Clone)]
@@ -111,6 +122,78 @@ pub struct PanicCause {
     pub backtrace: Option<Arc<Backtrace>>,
 }
 
+#[derive(Debug, Clone)]
+pub struct WorkerCrash {
+    /// Exit code of the worker process, if it exited with one
+    pub exit_code: Option<i32>,
+    /// Signal that terminated the worker process (Unix only)
+    pub signal: Option<i32>,
+    /// The last lines the worker process wrote to its standard error
+    pub stderr_tail: Vec<String>,
+    /// Whether the worker reported a failed memory allocation anywhere on its standard error,
+    /// even if the line is no longer part of `stderr_tail` (for example because a backtrace
+    /// was printed after it)
+    pub allocation_failed: bool,
+}
+
+/// A limit applied to the spawned worker processes with `--worker-memory-limit`,
+/// `--worker-cpu-time-limit` or `#[memory_limit]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerLimit {
+    Memory(MemorySize),
+    CpuTime(CpuTime),
+}
+
+impl Display for WorkerLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkerLimit::Memory(size) => write!(f, "memory limit of {size}"),
+            WorkerLimit::CpuTime(time) => write!(f, "CPU time limit of {time}"),
+        }
+    }
+}
+
+/// Stands in for the value of a dependency whose constructor returned an error or panicked,
+/// and for the values of the dependencies constructed from it
+#[derive(Debug, Clone)]
+pub struct DependencyFailure {
+    /// Fully qualified name of the dependency whose constructor failed
+    pub dependency: String,
+    /// The rendered error or panic of the constructor
+    pub message: String,
+}
+
+impl DependencyFailure {
+    /// Called by the constructors generated for `#[test_dep]` functions returning `Result`
+    pub fn from_error<E: Display + Debug + Send + Sync + 'static>(
+        dependency: String,
+        error: E,
+    ) -> Arc<dyn Any + Send + Sync> {
+        Arc::new(Self {
+            dependency,
+            message: FailureCause::from_error(error).render(),
+        })
+    }
+
+    pub fn render(&self) -> String {
+        format!("Dependency {} failed: {}", self.dependency, self.message)
+    }
+
+    /// Called by the getters generated for `#[test_dep]` functions to downcast the value of a
+    /// dependency, panicking with the failure of its constructor if it could not be constructed
+    pub fn downcast_dependency<T: Send + Sync + 'static>(
+        value: Arc<dyn Any + Send + Sync>,
+    ) -> Arc<T> {
+        match value.downcast::<T>() {
+            Ok(value) => value,
+            Err(value) => match value.downcast_ref::<Self>() {
+                Some(failure) => panic!("{}", failure.render()),
+                None => panic!("Dependency type mismatch"),
+            },
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PanicLocation {
     pub file: String,
@@ -127,6 +210,15 @@ impl std::fmt::Debug for FailureCause {
             FailureCause::ReturnedMessage(s) => f.debug_tuple("ReturnedMessage").field(s).finish(),
             FailureCause::Panic(p) => f.debug_tuple("Panic").field(p).finish(),
             FailureCause::HarnessError(s) => f.debug_tuple("HarnessError").field(s).finish(),
+            FailureCause::WorkerCrashed(c) => f.debug_tuple("WorkerCrashed").field(c).finish(),
+            FailureCause::ResourceLimitExceeded(l, c) => f
+                .debug_tuple("ResourceLimitExceeded")
+                .field(l)
+                .field(c)
+                .finish(),
+            FailureCause::DependencyFailed(d) => {
+                f.debug_tuple("DependencyFailed").field(d).finish()
+            }
         }
     }
 }
@@ -169,9 +261,23 @@ impl FailureCause {
             FailureCause::ReturnedMessage(s) => s.clone(),
             FailureCause::Panic(p) => p.render(),
             FailureCause::HarnessError(s) => s.clone(),
+            FailureCause::WorkerCrashed(c) => c.render(),
+            FailureCause::ResourceLimitExceeded(l, c) => {
+                let mut out =
+                    format!("Resource limit exceeded: the worker process ran out of its {l}");
+                c.render_stderr_tail(&mut out);
+                out
+            }
+            FailureCause::DependencyFailed(d) => d.render(),
         }
     }
 
+    /// Returns `true` if the test failed because its worker process crashed,
+    /// rather than because of the test's own result.
+    pub fn is_worker_crash(&self) -> bool {
+        matches!(self, FailureCause::WorkerCrashed(_))
+    }
+
     /// Get the message string for ShouldPanic matching (without backtrace)
     pub fn panic_message(&self) -> Option<&str> {
         match self {
@@ -197,6 +303,53 @@ impl PanicCause {
     }
 }
 
+impl WorkerCrash {
+    /// Number of stderr lines kept from a crashed worker process.
+    pub const STDERR_TAIL_LINES: usize = 20;
+
+    pub(crate) fn new(status: Option<std::process::ExitStatus>, stderr: &[String]) -> Self {
+        #[cfg(unix)]
+        let signal = {
+            use std::os::unix::process::ExitStatusExt;
+            status.and_then(|status| status.signal())
+        };
+        #[cfg(not(unix))]
+        let signal = None;
+
+        let tail_start = stderr.len().saturating_sub(Self::STDERR_TAIL_LINES);
+        Self {
+            exit_code: status.and_then(|status| status.code()),
+            signal,
+            stderr_tail: stderr[tail_start..].to_vec(),
+            allocation_failed: stderr.iter().any(|line| is_allocation_failure(line)),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = match (self.signal, self.exit_code) {
+            (Some(signal), _) => format!("Worker process crashed (signal {signal})"),
+            (None, Some(code)) => format!("Worker process crashed (exit code {code})"),
+            (None, None) => "Worker process crashed".to_string(),
+        };
+        self.render_stderr_tail(&mut out);
+        out
+    }
+
+    fn render_stderr_tail(&self, out: &mut String) {
+        if !self.stderr_tail.is_empty() {
+            out.push_str("\n\nLast lines of the worker's stderr:\n");
+            out.push_str(&self.stderr_tail.join("\n"));
+        }
+    }
+}
+
+/// Recognizes the line printed by the default allocation error handler of the standard library
+/// right before it aborts the process
+pub(crate) fn is_allocation_failure(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("memory allocation of") && line.ends_with("failed")
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ShouldPanic {
     No,
@@ -268,7 +421,32 @@ pub struct TestProperties {
     pub ensure_time_control: ReportTimeControl,
     pub tags: Vec<String>,
     pub is_ignored: bool,
+    /// Why the test is ignored, given with `#[ignore = "..."]` or `ignore_suite!`
+    pub ignore_reason: Option<String>,
     pub detached_panic_policy: DetachedPanicPolicy,
+    pub is_isolated: bool,
+    pub resources: Vec<ResourceUsage>,
+    /// Fully qualified names of the tests that must pass before this one is started,
+    /// declared with `#[depends_on]`
+    pub depends_on: Vec<String>,
+    /// Memory limit of the worker process running this test, declared with `#[memory_limit]`
+    pub memory_limit: Option<MemorySize>,
+    /// Environment variables set or removed while the test runs, declared with `#[env]` and
+    /// `#[env_remove]`
+    pub env: Vec<EnvChange>,
+    /// Working directory of the test while it runs, declared with `#[cwd]`
+    pub cwd: Option<PathBuf>,
+    /// Set on the entries reporting a failed suite hook, which are not registered tests
+    pub hook: Option<HookKind>,
+    /// Types of the dependencies the test function takes as parameters, as written in its
+    /// signature
+    pub dependency_types: Vec<String>,
+    /// The matrix dimensions and their cases this test was generated for, as
+    /// `(dimension, case)` pairs, by `#[dimension]` parameters or `matrix_suite!`
+    pub matrix_case: Vec<(String, String)>,
+    /// Human-readable name shown in the reports instead of the test's path, declared with
+    /// `#[display_name]`
+    pub display_name: Option<String>,
 }
 
 impl TestProperties {
@@ -285,6 +463,12 @@ impl TestProperties {
             ..Default::default()
         }
     }
+
+    /// Returns true if the test changes the process-global environment variables or working
+    /// directory while it runs
+    pub fn changes_environment(&self) -> bool {
+        !self.env.is_empty() || self.cwd.is_some()
+    }
 }
 
 impl Default for TestProperties {
@@ -299,11 +483,87 @@ impl Default for TestProperties {
             ensure_time_control: ReportTimeControl::Default,
             tags: Vec::new(),
             is_ignored: false,
+            ignore_reason: None,
             detached_panic_policy: DetachedPanicPolicy::FailTest,
+            is_isolated: false,
+            resources: Vec::new(),
+            depends_on: Vec::new(),
+            memory_limit: None,
+            env: Vec::new(),
+            cwd: None,
+            hook: None,
+            dependency_types: Vec::new(),
+            matrix_case: Vec::new(),
+            display_name: None,
+        }
+    }
+}
+
+/// A limited resource used by a test, declared with `#[uses_resource]` or `uses_resource_suite!`.
+/// At most `limit` tests using the same resource run at the same time. If the resource is declared
+/// with different limits, the smallest one applies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceUsage {
+    pub name: String,
+    pub limit: usize,
+}
+
+impl ResourceUsage {
+    pub fn new(name: impl Into<String>, limit: usize) -> Self {
+        Self {
+            name: name.into(),
+            limit: limit.max(1),
         }
     }
 }
 
+/// A change of an environment variable applied while a test runs, declared with `#[env]`,
+/// `#[env_remove]`, `env_suite!` or `env_remove_suite!`. The previous value is restored when the
+/// test finishes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvChange {
+    Set { key: String, value: String },
+    Remove { key: String },
+}
+
+impl EnvChange {
+    pub fn set(key: impl Into<String>, value: impl Into<String>) -> Self {
+        EnvChange::Set {
+            key: key.into(),
+            value: value.into(),
+        }
+    }
+
+    pub fn remove(key: impl Into<String>) -> Self {
+        EnvChange::Remove { key: key.into() }
+    }
+
+    pub fn key(&self) -> &str {
+        match self {
+            EnvChange::Set { key, .. } => key,
+            EnvChange::Remove { key } => key,
+        }
+    }
+}
+
+/// Resolves the path of a test given to `#[depends_on]` to its fully qualified name, the same way
+/// Rust resolves paths in the module `module_path`: relative to it, or starting with `crate`,
+/// `self` or `super`.
+pub fn resolve_test_path(module_path: &str, path: &str) -> String {
+    let mut segments: Vec<&str> = module_path.split("::").collect();
+    for (idx, segment) in path.split("::").map(str::trim).enumerate() {
+        match segment {
+            "crate" if idx == 0 => segments.truncate(1),
+            "self" if idx == 0 => {}
+            "super" if segments.len() > 1 => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("::")
+}
+
 #[derive(Clone)]
 pub struct RegisteredTest {
     pub name: String,
@@ -312,6 +572,7 @@ pub struct RegisteredTest {
     pub run: TestFunction,
     pub props: TestProperties,
     pub dependencies: Option<Vec<String>>,
+    pub location: Option<SourceLocation>,
 }
 
 impl RegisteredTest {
@@ -340,6 +601,33 @@ impl RegisteredTest {
             .collect::<Vec<String>>()
             .join("::")
     }
+
+    /// The name of the test shown in the reports, its display name if it has one, otherwise its
+    /// fully qualified name
+    pub fn display_name(&self) -> String {
+        self.props
+            .display_name
+            .clone()
+            .unwrap_or_else(|| self.fully_qualified_name())
+    }
+
+    /// The name of the test in the lists of the summary: its display name followed by its fully
+    /// qualified name, so the test can be found and selected with a filter
+    pub fn summary_name(&self) -> String {
+        match &self.props.display_name {
+            Some(display_name) => format!("{display_name} ({})", self.fully_qualified_name()),
+            None => self.fully_qualified_name(),
+        }
+    }
+
+    /// The random seed of the test, if it takes a `&TestContext` or `&mut TestRng` parameter
+    pub fn seed(&self) -> Option<u64> {
+        self.dependencies
+            .iter()
+            .flatten()
+            .any(|dep| dep == crate::TestContext::DEPENDENCY_NAME)
+            .then(|| crate::seed::test_seed(&self.fully_qualified_name()))
+    }
 }
 
 impl Debug for RegisteredTest {
@@ -354,6 +642,31 @@ impl Debug for RegisteredTest {
 
 pub static REGISTERED_TESTS: Mutex<Vec<RegisteredTest>> = Mutex::new(Vec::new());
 
+/// Where a test is defined in the source code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Path of the source file, as returned by `file!()`
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl SourceLocation {
+    pub fn new(file: impl AsRef<str>, line: u32, column: u32) -> Self {
+        Self {
+            file: file.as_ref().to_string(),
+            line,
+            column,
+        }
+    }
+}
+
+impl From<&std::panic::Location<'_>> for SourceLocation {
+    fn from(location: &std::panic::Location<'_>) -> Self {
+        Self::new(location.file(), location.line(), location.column())
+    }
+}
+
 #[derive(Clone)]
 #[allow(clippy::type_complexity)]
 pub enum DependencyConstructor {
@@ -377,6 +690,166 @@ pub enum DependencyConstructor {
     ),
 }
 
+/// Teardown function of a dependency, set with `#[test_dep(teardown = ...)]`. It is called with
+/// the dependency's value before the value is dropped. Synchronous teardown functions are wrapped
+/// in a ready future by the macro.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub struct DependencyTeardown(
+    pub  Arc<
+        dyn (Fn(
+                Arc<dyn Any + Send + Sync>,
+            ) -> Pin<Box<dyn Future<Output = Result<(), FailureCause>>>>)
+            + Send
+            + Sync
+            + 'static,
+    >,
+);
+
+/// Implemented by the scope markers of the dependencies that can be injected as `&mut T` or by
+/// value. Generic in the dependency view, so a missing implementation is only reported where a
+/// dependency is taken, not where the code generated for `#[test_dep]` forwards it.
+#[diagnostic::on_unimplemented(
+    message = "only `scope = PerTest` dependencies can be injected as `&mut` or by value",
+    label = "this dependency can only be injected by reference"
+)]
+pub trait InjectableByValue<V> {}
+
+/// Scope marker of the `PerTest` dependencies
+pub struct PerTestScope;
+
+impl<V> InjectableByValue<V> for PerTestScope {}
+
+/// Scope marker of the dependencies shared between tests, which can only be injected by reference
+pub struct SharedValueScope;
+
+/// Value of a `PerTest` dependency created for a single test. Parameters taking the dependency by
+/// reference share the value, while the first parameter taking it as `&mut T` or by value takes
+/// it out of the cell.
+pub struct PerTestValue(parking_lot::Mutex<Option<Arc<dyn Any + Send + Sync>>>);
+
+impl PerTestValue {
+    pub fn new(value: Arc<dyn Any + Send + Sync>) -> Self {
+        Self(parking_lot::Mutex::new(Some(value)))
+    }
+
+    pub(crate) fn empty() -> Self {
+        Self(parking_lot::Mutex::new(None))
+    }
+
+    pub(crate) fn set(&self, value: Arc<dyn Any + Send + Sync>) {
+        *self.0.lock() = Some(value);
+    }
+
+    pub(crate) fn clear(&self) {
+        self.0.lock().take();
+    }
+
+    /// Gets the value of the `PerTest` dependency `name` from `dependency_view` to inject it by
+    /// reference
+    pub fn get(dependency_view: &impl DependencyView, name: &str) -> Arc<dyn Any + Send + Sync> {
+        Self::cell(dependency_view, name)
+            .0
+            .lock()
+            .clone()
+            .unwrap_or_else(|| {
+                panic!("PerTest dependency {name} was already moved into another parameter")
+            })
+    }
+
+    /// Takes the value of the `PerTest` dependency `name` from `dependency_view` to inject it as
+    /// `&mut T` or by value
+    pub fn take(dependency_view: &impl DependencyView, name: &str) -> Arc<dyn Any + Send + Sync> {
+        Self::cell(dependency_view, name)
+            .0
+            .lock()
+            .take()
+            .unwrap_or_else(|| {
+                panic!("PerTest dependency {name} was already moved into another parameter")
+            })
+    }
+
+    fn cell(dependency_view: &impl DependencyView, name: &str) -> Arc<Self> {
+        dependency_view
+            .get(name)
+            .expect("Dependency not found")
+            .downcast::<Self>()
+            .unwrap_or_else(|_| {
+                panic!(
+                    "PerTest dependency {name} can only be used by tests and PerTest dependencies"
+                )
+            })
+    }
+}
+
+/// **Hidden macro-support helper.** Holds the value returned by a teardown function, so the
+/// `#[test_dep]` macro can turn it into a future with [`TeardownViaFuture`] if it is a future
+/// (an `async` teardown function), or with [`TeardownViaValue`] otherwise.
+#[doc(hidden)]
+pub struct TeardownOutput<T>(Mutex<Option<T>>);
+
+impl<T> TeardownOutput<T> {
+    pub fn new(output: T) -> Self {
+        Self(Mutex::new(Some(output)))
+    }
+
+    fn take(&self) -> T {
+        self.0
+            .lock()
+            .unwrap()
+            .take()
+            .expect("The teardown output is only taken once")
+    }
+}
+
+#[doc(hidden)]
+pub trait TeardownViaFuture<'a> {
+    fn teardown_result(&self) -> Pin<Box<dyn Future<Output = Result<(), FailureCause>> + 'a>>;
+}
+
+impl<'a, F> TeardownViaFuture<'a> for TeardownOutput<F>
+where
+    F: Future + 'a,
+    F::Output: TestReturnValue,
+{
+    fn teardown_result(&self) -> Pin<Box<dyn Future<Output = Result<(), FailureCause>> + 'a>> {
+        let future = self.take();
+        Box::pin(async move { Box::new(future.await).into_result() })
+    }
+}
+
+#[doc(hidden)]
+pub trait TeardownViaValue<'a> {
+    fn teardown_result(&self) -> Pin<Box<dyn Future<Output = Result<(), FailureCause>> + 'a>>;
+}
+
+impl<'a, R: TestReturnValue> TeardownViaValue<'a> for &TeardownOutput<R> {
+    fn teardown_result(&self) -> Pin<Box<dyn Future<Output = Result<(), FailureCause>> + 'a>> {
+        let result = Box::new(self.take()).into_result();
+        Box::pin(std::future::ready(result))
+    }
+}
+
+/// **Hidden macro-support helper.** Implemented by the values a teardown function can return
+/// without the tokio runtime, where the teardowns run outside of any async runtime.
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "async teardown functions are only supported with the tokio runtime",
+    label = "the teardown function must not be async"
+)]
+pub trait SyncTeardownValue: TestReturnValue {}
+
+impl<R: TestReturnValue> SyncTeardownValue for R {}
+
+/// **Hidden macro-support helper.** Used by the `#[test_dep]` macro instead of
+/// [`TeardownViaValue`] without the tokio runtime, to reject async teardown functions.
+#[doc(hidden)]
+pub fn sync_teardown_result<R: SyncTeardownValue>(
+    output: R,
+) -> Pin<Box<dyn Future<Output = Result<(), FailureCause>>>> {
+    Box::pin(std::future::ready(Box::new(output).into_result()))
+}
+
 /// User-facing trait that opts a dependency value into the `Cloneable`
 /// sharing strategy. The parent calls [`to_wire`](CloneableDep::to_wire) once
 /// and ships the bytes to each worker via IPC. Each worker calls
@@ -1445,7 +1918,7 @@ pub struct RpcFactory {
 /// interacts with output capturing and parallel test execution.
 ///
 /// See `book/src/design/sharing-strategy.md` for the full description.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Default)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Default, BinaryCodec)]
 pub enum DepScope {
     /// Today's behaviour: a single materialized instance shared by every test.
     /// Forces single-threaded execution when output capturing is on, because
@@ -1475,6 +1948,11 @@ pub enum DepScope {
     /// an [`AsyncHostedRpcDep`] impl (async owners) — on the owner type
     /// with a stub type, a method dispatch function, and a stub builder.
     HostedRpc,
+    /// The constructor runs for every test using the dependency, in the
+    /// process running the test, right before the test starts. The value is
+    /// not shared with other tests, so it can also be injected as `&mut T`
+    /// or by value.
+    PerTest,
 }
 
 impl DepScope {
@@ -1497,6 +1975,51 @@ impl DepScope {
     }
 }
 
+impl Display for DepScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self, f)
+    }
+}
+
+/// The process a dependency was constructed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BinaryCodec)]
+pub enum DependencyProcess {
+    /// The test runner process. It also runs the tests when no worker processes are spawned.
+    Parent,
+    /// A worker process spawned by the test runner
+    Worker { index: usize },
+}
+
+impl Display for DependencyProcess {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DependencyProcess::Parent => write!(f, "parent"),
+            DependencyProcess::Worker { index } => write!(f, "worker {index}"),
+        }
+    }
+}
+
+/// A step in the lifecycle of a dependency value, reported to the test runner output
+#[derive(Debug, Clone, BinaryCodec)]
+pub struct DependencyEvent {
+    /// Fully qualified name of the dependency
+    pub dependency: String,
+    pub scope: DepScope,
+    pub process: DependencyProcess,
+    pub kind: DependencyEventKind,
+}
+
+#[derive(Debug, Clone, BinaryCodec)]
+pub enum DependencyEventKind {
+    /// The constructor of the dependency was called
+    ConstructionStarted,
+    /// The constructor returned, `failed` is set if it returned an error or panicked
+    ConstructionFinished { duration: Duration, failed: bool },
+    /// The value was dropped, once no more tests, dependencies or teardowns were using it.
+    /// `lifetime` is the time elapsed since it was constructed.
+    Dropped { lifetime: Duration },
+}
+
 /// Function pointer-equivalent used by the worker side of a `Cloneable`
 /// dependency. Receives the deserialized wire payload (boxed as `Any` for
 /// type erasure) plus the current dependency view, and produces the
@@ -1592,6 +2115,9 @@ pub struct RegisteredDependency {
     /// resolver would panic. Pairing the two as companions guarantees
     /// the Hosted half is retained whenever either half is needed.
     pub companions: Vec<String>,
+    /// Set with `#[test_dep(teardown = ...)]`. Runs on the value before it is dropped, after
+    /// all the tests using it have finished.
+    pub teardown: Option<DependencyTeardown>,
 }
 
 impl RegisteredDependency {
@@ -1617,6 +2143,7 @@ impl RegisteredDependency {
             hosted_codec: None,
             rpc_factory: None,
             companions: Vec::new(),
+            teardown: None,
         }
     }
 }
@@ -1676,6 +2203,7 @@ pub static REGISTERED_DEPENDENCY_CONSTRUCTORS: Mutex<Vec<RegisteredDependency>>
 /// `matrix_suite!` registration time by reading the
 /// `test_r_get_dep_tags_<dim>()` helper emitted by `define_matrix_dimension!`.
 ///
+/// `dimension` is the name of the dimension and `case_label` the name of the case.
 /// `dep_name` is the case-specific tagged dependency name (the entry that
 /// appears in the multiplied test's `dependencies` list, so the dependency
 /// materializer constructs the right backend). `auto_tag` is the
@@ -1684,6 +2212,7 @@ pub static REGISTERED_DEPENDENCY_CONSTRUCTORS: Mutex<Vec<RegisteredDependency>>
 /// etc.
 #[derive(Debug, Clone)]
 pub struct MatrixCase {
+    pub dimension: String,
     pub case_label: String,
     pub dep_name: String,
     pub auto_tag: String,
@@ -1708,6 +2237,31 @@ pub enum RegisteredTestSuiteProperty {
         module_path: String,
         timeout: Duration,
     },
+    Resource {
+        name: String,
+        crate_name: String,
+        module_path: String,
+        resource: ResourceUsage,
+    },
+    Env {
+        name: String,
+        crate_name: String,
+        module_path: String,
+        change: EnvChange,
+    },
+    Cwd {
+        name: String,
+        crate_name: String,
+        module_path: String,
+        path: PathBuf,
+    },
+    /// Every test of the suite is ignored, declared with `ignore_suite!`
+    Ignore {
+        name: String,
+        crate_name: String,
+        module_path: String,
+        reason: Option<String>,
+    },
     /// Runtime matrix-suite dimension (Strategy B). Every registered test
     /// under the suite's module prefix whose `dependencies` contain
     /// `dep_name` is multiplied into one `RegisteredTest` per case at
@@ -1721,6 +2275,14 @@ pub enum RegisteredTestSuiteProperty {
         dep_name: String,
         cases: Vec<MatrixCase>,
     },
+    /// A setup or teardown hook of the suite, declared with `#[before_all]`, `#[after_all]`,
+    /// `#[before_each]` or `#[after_each]` on a function of the suite's module
+    Hook {
+        name: String,
+        crate_name: String,
+        module_path: String,
+        hook: SuiteHook,
+    },
 }
 
 impl RegisteredTestSuiteProperty {
@@ -1729,7 +2291,12 @@ impl RegisteredTestSuiteProperty {
             RegisteredTestSuiteProperty::Sequential { crate_name, .. } => crate_name,
             RegisteredTestSuiteProperty::Tag { crate_name, .. } => crate_name,
             RegisteredTestSuiteProperty::Timeout { crate_name, .. } => crate_name,
+            RegisteredTestSuiteProperty::Resource { crate_name, .. } => crate_name,
+            RegisteredTestSuiteProperty::Env { crate_name, .. } => crate_name,
+            RegisteredTestSuiteProperty::Cwd { crate_name, .. } => crate_name,
+            RegisteredTestSuiteProperty::Ignore { crate_name, .. } => crate_name,
             RegisteredTestSuiteProperty::Matrix { crate_name, .. } => crate_name,
+            RegisteredTestSuiteProperty::Hook { crate_name, .. } => crate_name,
         }
     }
 
@@ -1738,7 +2305,12 @@ impl RegisteredTestSuiteProperty {
             RegisteredTestSuiteProperty::Sequential { module_path, .. } => module_path,
             RegisteredTestSuiteProperty::Tag { module_path, .. } => module_path,
             RegisteredTestSuiteProperty::Timeout { module_path, .. } => module_path,
+            RegisteredTestSuiteProperty::Resource { module_path, .. } => module_path,
+            RegisteredTestSuiteProperty::Env { module_path, .. } => module_path,
+            RegisteredTestSuiteProperty::Cwd { module_path, .. } => module_path,
+            RegisteredTestSuiteProperty::Ignore { module_path, .. } => module_path,
             RegisteredTestSuiteProperty::Matrix { module_path, .. } => module_path,
+            RegisteredTestSuiteProperty::Hook { module_path, .. } => module_path,
         }
     }
 
@@ -1747,7 +2319,12 @@ impl RegisteredTestSuiteProperty {
             RegisteredTestSuiteProperty::Sequential { name, .. } => name,
             RegisteredTestSuiteProperty::Tag { name, .. } => name,
             RegisteredTestSuiteProperty::Timeout { name, .. } => name,
+            RegisteredTestSuiteProperty::Resource { name, .. } => name,
+            RegisteredTestSuiteProperty::Env { name, .. } => name,
+            RegisteredTestSuiteProperty::Cwd { name, .. } => name,
+            RegisteredTestSuiteProperty::Ignore { name, .. } => name,
             RegisteredTestSuiteProperty::Matrix { name, .. } => name,
+            RegisteredTestSuiteProperty::Hook { name, .. } => name,
         }
     }
 
@@ -1761,6 +2338,78 @@ impl RegisteredTestSuiteProperty {
     }
 }
 
+/// The kinds of setup and teardown hooks of a test suite
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BinaryCodec)]
+pub enum HookKind {
+    /// Runs before the first test of the suite, once in every process running its tests
+    BeforeAll,
+    /// Runs after the last test of the suite, once in every process running its tests
+    AfterAll,
+    /// Runs before each test of the suite
+    BeforeEach,
+    /// Runs after each test of the suite, even if the test failed
+    AfterEach,
+}
+
+impl Display for HookKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookKind::BeforeAll => write!(f, "before_all"),
+            HookKind::AfterAll => write!(f, "after_all"),
+            HookKind::BeforeEach => write!(f, "before_each"),
+            HookKind::AfterEach => write!(f, "after_each"),
+        }
+    }
+}
+
+/// A setup or teardown hook of a test suite. The hook function gets the suite's dependencies
+/// just like a test, and fails the same way a test does, by panicking or returning an error.
+#[derive(Clone)]
+pub struct SuiteHook {
+    pub name: String,
+    pub kind: HookKind,
+    pub run: TestFunction,
+    pub dependencies: Vec<String>,
+}
+
+impl SuiteHook {
+    /// The entry reporting a run of this hook in the suite `crate_name::module_path`. The
+    /// `before_each` and `after_each` hooks are run for `test`, which is part of the name.
+    pub(crate) fn entry(
+        &self,
+        crate_name: &str,
+        module_path: &str,
+        test: Option<&RegisteredTest>,
+    ) -> RegisteredTest {
+        let name = match test {
+            Some(test) => format!("{} [{} of {}]", self.name, self.kind, test.name),
+            None => format!("{} [{}]", self.name, self.kind),
+        };
+        RegisteredTest {
+            name,
+            crate_name: crate_name.to_string(),
+            module_path: module_path.to_string(),
+            run: self.run.clone(),
+            props: TestProperties {
+                hook: Some(self.kind),
+                ..Default::default()
+            },
+            dependencies: Some(self.dependencies.clone()),
+            location: None,
+        }
+    }
+}
+
+impl Debug for SuiteHook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SuiteHook")
+            .field("name", &self.name)
+            .field("kind", &self.kind)
+            .field("dependencies", &self.dependencies)
+            .finish()
+    }
+}
+
 pub static REGISTERED_TESTSUITE_PROPS: Mutex<Vec<RegisteredTestSuiteProperty>> =
     Mutex::new(Vec::new());
 
@@ -1797,6 +2446,8 @@ impl DynamicTestRegistration {
         self.tests
     }
 
+    /// Adds a test, located where this method is called
+    #[track_caller]
     pub fn add_sync_test<R: TestReturnValue + 'static>(
         &mut self,
         name: impl AsRef<str>,
@@ -1811,10 +2462,13 @@ impl DynamicTestRegistration {
             })),
             props,
             dependencies,
+            location: Some(std::panic::Location::caller().into()),
         });
     }
 
+    /// Adds an async test, located where this method is called
     #[cfg(feature = "tokio")]
+    #[track_caller]
     pub fn add_async_test<R: TestReturnValue + 'static>(
         &mut self,
         name: impl AsRef<str>,
@@ -1837,8 +2491,17 @@ impl DynamicTestRegistration {
             })),
             props,
             dependencies,
+            location: Some(std::panic::Location::caller().into()),
         });
     }
+
+    /// Overrides the source location of the last added test, for example to point to the
+    /// data file the test was generated from
+    pub fn set_location(&mut self, location: SourceLocation) {
+        if let Some(test) = self.tests.last_mut() {
+            test.location = Some(location);
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -1847,6 +2510,7 @@ pub struct GeneratedTest {
     pub run: TestFunction,
     pub props: TestProperties,
     pub dependencies: Option<Vec<String>>,
+    pub location: Option<SourceLocation>,
 }
 
 #[derive(Clone)]
@@ -1856,6 +2520,8 @@ pub struct RegisteredTestGenerator {
     pub module_path: String,
     pub run: TestGeneratorFunction,
     pub is_ignored: bool,
+    pub ignore_reason: Option<String>,
+    pub location: Option<SourceLocation>,
 }
 
 impl RegisteredTestGenerator {
@@ -1916,18 +2582,26 @@ pub(crate) fn apply_suite_props_to_tests(
         // Collect the matrix dimensions that apply to this test (by module
         // prefix). A test may match several suites; each matching Matrix
         // property multiplies the test independently (Cartesian product across
-        // dimensions), while Tag/Timeout/Sequential props are applied to every
-        // produced test.
+        // dimensions), while Tag/Timeout/Resource/Env/Cwd/Ignore/Sequential props are applied to
+        // every produced test.
         let mut matrix_dims: Vec<&RegisteredTestSuiteProperty> = Vec::new();
         let mut tag_timeout_sequential: Vec<&RegisteredTestSuiteProperty> = Vec::new();
+        let mut environment: Vec<(usize, &RegisteredTestSuiteProperty)> = Vec::new();
         for (prefix, prop) in &props_with_prefix {
             if test.crate_and_module().starts_with(prefix) {
                 match prop {
                     RegisteredTestSuiteProperty::Matrix { .. } => matrix_dims.push(prop),
+                    RegisteredTestSuiteProperty::Env { .. }
+                    | RegisteredTestSuiteProperty::Cwd { .. } => {
+                        environment.push((prefix.len(), prop))
+                    }
                     _ => tag_timeout_sequential.push(prop),
                 }
             }
         }
+        // The innermost suite setting a variable or the working directory wins, regardless of
+        // the order the suites were registered in
+        environment.sort_by_key(|(prefix_len, _)| std::cmp::Reverse(*prefix_len));
 
         // Expand the test across all matching matrix dimensions. Tests that
         // match no Matrix property produce a single clone (preserving the
@@ -1957,8 +2631,8 @@ pub(crate) fn apply_suite_props_to_tests(
             expanded = next;
         }
 
-        // Apply the non-multiplying props (Tag/Timeout/Sequential) to every
-        // produced test. Sequential is recorded for the execution grouping
+        // Apply the non-multiplying props (Tag/Timeout/Resource/Env/Cwd/Ignore/Sequential) to
+        // every produced test. Sequential is recorded for the execution grouping
         // elsewhere; here it is a no-op.
         for mut t in expanded {
             for prop in &tag_timeout_sequential {
@@ -1971,8 +2645,37 @@ pub(crate) fn apply_suite_props_to_tests(
                             t.props.timeout = Some(*timeout);
                         }
                     }
-                    RegisteredTestSuiteProperty::Sequential { .. } => {}
-                    RegisteredTestSuiteProperty::Matrix { .. } => unreachable!(),
+                    RegisteredTestSuiteProperty::Resource { resource, .. } => {
+                        t.props.resources.push(resource.clone());
+                    }
+                    RegisteredTestSuiteProperty::Ignore { reason, .. } => {
+                        // The reason given to the test itself is more specific
+                        if !t.props.is_ignored {
+                            t.props.is_ignored = true;
+                            t.props.ignore_reason = reason.clone();
+                        }
+                    }
+                    RegisteredTestSuiteProperty::Sequential { .. }
+                    | RegisteredTestSuiteProperty::Hook { .. } => {}
+                    RegisteredTestSuiteProperty::Matrix { .. }
+                    | RegisteredTestSuiteProperty::Env { .. }
+                    | RegisteredTestSuiteProperty::Cwd { .. } => unreachable!(),
+                }
+            }
+            for (_, prop) in &environment {
+                match prop {
+                    RegisteredTestSuiteProperty::Env { change, .. } => {
+                        // The test's own attributes and inner suites take precedence
+                        if !t.props.env.iter().any(|own| own.key() == change.key()) {
+                            t.props.env.push(change.clone());
+                        }
+                    }
+                    RegisteredTestSuiteProperty::Cwd { path, .. } => {
+                        if t.props.cwd.is_none() {
+                            t.props.cwd = Some(path.clone());
+                        }
+                    }
+                    _ => unreachable!(),
                 }
             }
             result.push(t);
@@ -1985,7 +2688,8 @@ pub(crate) fn apply_suite_props_to_tests(
 ///
 /// The clone:
 /// - is named `<test.name>_<case.case_label>`;
-/// - has the case's `<dim>_<case>` auto-tag appended to `props.tags`;
+/// - has the case's `<dim>_<case>` auto-tag appended to `props.tags`, and the
+///   dimension and case appended to `props.matrix_case`;
 /// - has its `dependencies` rewritten so the untagged `dep_name` entry is
 ///   replaced by the case-specific `case.dep_name` (so the dependency
 ///   materializer constructs the right backend); and
@@ -2001,6 +2705,10 @@ fn multiply_test_for_case(
     let mut clone = test.clone();
     clone.name = format!("{}_{}", test.name, case.case_label);
     clone.props.tags.push(case.auto_tag.clone());
+    clone
+        .props
+        .matrix_case
+        .push((case.dimension.clone(), case.case_label.clone()));
     if let Some(deps) = clone.dependencies.as_mut() {
         for d in deps.iter_mut() {
             if d == dep_name {
@@ -2082,7 +2790,7 @@ pub(crate) fn filter_registered_tests(
     args: &Arguments,
     registered_tests: &[RegisteredTest],
 ) -> Vec<RegisteredTest> {
-    registered_tests
+    let filtered = registered_tests
         .iter()
         .filter(|registered_test| {
             !args
@@ -2106,7 +2814,110 @@ pub(crate) fn filter_registered_tests(
             !args.exclude_should_panic || registered_test.props.should_panic == ShouldPanic::No
         })
         .cloned()
-        .collect::<Vec<_>>()
+        .collect::<Vec<_>>();
+
+    match &args.partition {
+        Some(partition) => partition_tests(partition, filtered),
+        None => filtered,
+    }
+}
+
+/// Returns the number of tests selected by the filters that `--partition` assigned to other partitions
+pub(crate) fn tests_in_other_partitions(
+    args: &Arguments,
+    registered_tests: &[RegisteredTest],
+) -> usize {
+    if args.partition.is_none() {
+        0
+    } else {
+        let unpartitioned = Arguments {
+            partition: None,
+            ..args.clone()
+        };
+        filter_registered_tests(&unpartitioned, registered_tests).len()
+            - filter_registered_tests(args, registered_tests).len()
+    }
+}
+
+/// Keeps the tests belonging to the selected partition, preserving their order. Tests generated
+/// by matrices and test generators are already expanded here, so every case is assigned separately.
+/// Tests connected by `depends_on` are assigned together, so a prerequisite always runs in the
+/// same partition as the tests depending on it.
+fn partition_tests(partition: &Partition, tests: Vec<RegisteredTest>) -> Vec<RegisteredTest> {
+    let groups = partition_groups(&tests);
+    match *partition {
+        Partition::Hash { index, count } => tests
+            .into_iter()
+            .zip(groups)
+            .filter_map(|(test, group)| {
+                (stable_hash(&group) % count as u64 == (index - 1) as u64).then_some(test)
+            })
+            .collect(),
+        Partition::Count { index, count } => {
+            let mut by_name = groups.iter().collect::<Vec<_>>();
+            by_name.sort();
+            by_name.dedup();
+            let selected_groups = by_name
+                .into_iter()
+                .enumerate()
+                .filter_map(|(position, group)| (position % count == index - 1).then_some(group))
+                .cloned()
+                .collect::<HashSet<_>>();
+            tests
+                .into_iter()
+                .zip(groups)
+                .filter_map(|(test, group)| selected_groups.contains(&group).then_some(test))
+                .collect()
+        }
+    }
+}
+
+/// Names the group of every test that has to be assigned to the same partition: the smallest
+/// fully qualified name among the tests connected to it through `depends_on`
+fn partition_groups(tests: &[RegisteredTest]) -> Vec<String> {
+    let names = tests
+        .iter()
+        .map(|test| test.fully_qualified_name())
+        .collect::<Vec<_>>();
+    let indices = names
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| (name.as_str(), idx))
+        .collect::<HashMap<_, _>>();
+
+    fn root(parents: &mut [usize], mut idx: usize) -> usize {
+        while parents[idx] != idx {
+            parents[idx] = parents[parents[idx]];
+            idx = parents[idx];
+        }
+        idx
+    }
+
+    let mut parents = (0..tests.len()).collect::<Vec<_>>();
+    for (idx, test) in tests.iter().enumerate() {
+        for prerequisite in &test.props.depends_on {
+            if let Some(&other) = indices.get(prerequisite.as_str()) {
+                let (a, b) = (root(&mut parents, idx), root(&mut parents, other));
+                // The root of a group is always its smallest name
+                if names[a] < names[b] {
+                    parents[b] = a;
+                } else {
+                    parents[a] = b;
+                }
+            }
+        }
+    }
+    (0..tests.len())
+        .map(|idx| names[root(&mut parents, idx)].clone())
+        .collect()
+}
+
+/// 64-bit FNV-1a hash, which unlike `DefaultHasher` is guaranteed to be the same across
+/// Rust versions and platforms, so every CI job computes the same partitions and seeds
+pub(crate) fn stable_hash(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
 }
 
 fn add_generated_tests(
@@ -2115,7 +2926,10 @@ fn add_generated_tests(
     generated: Vec<GeneratedTest>,
 ) {
     target.extend(generated.into_iter().map(|mut test| {
-        test.props.is_ignored |= generator.is_ignored;
+        if generator.is_ignored && !test.props.is_ignored {
+            test.props.is_ignored = true;
+            test.props.ignore_reason = generator.ignore_reason.clone();
+        }
         RegisteredTest {
             name: format!("{}::{}", generator.name, test.name),
             crate_name: generator.crate_name.clone(),
@@ -2123,6 +2937,7 @@ fn add_generated_tests(
             run: test.run,
             props: test.props,
             dependencies: test.dependencies,
+            location: test.location.or_else(|| generator.location.clone()),
         }
     }));
 }
@@ -2177,11 +2992,151 @@ pub(crate) fn get_ensure_time(args: &Arguments, test: &RegisteredTest) -> Option
     }
 }
 
+/// Timeout the in-process runner has to enforce for a test. Inside a spawned
+/// worker the parent owns the timeout instead: a synchronous test cannot be
+/// cancelled, so the parent kills and replaces the whole worker rather than
+/// leaving a stuck test thread behind in it.
+pub(crate) fn get_in_process_timeout(args: &Arguments, test: &RegisteredTest) -> Option<Duration> {
+    if args.ipc.is_some() {
+        None
+    } else {
+        test.props.timeout
+    }
+}
+
+/// How long the parent waits for a spawned worker to report a test's result
+/// before killing the worker. The timeout applies to each attempt, so the
+/// budget covers every attempt the test's flakiness control allows.
+pub(crate) fn get_worker_timeout(test: &RegisteredTest) -> Option<Duration> {
+    let attempts = match test.props.flakiness_control {
+        FlakinessControl::None => 1,
+        FlakinessControl::ProveNonFlaky(n) | FlakinessControl::RetryKnownFlaky(n) => n.max(1),
+    };
+    test.props
+        .timeout
+        .map(|timeout| timeout.saturating_mul(attempts as u32))
+}
+
+/// How long the parent waits for a retired worker to run its `after_all` hooks
+/// and dependency teardowns before killing it.
+pub(crate) const WORKER_FINISH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long the parent waits for a finished worker to exit on its own before
+/// killing it.
+pub(crate) const WORKER_EXIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the parent keeps reading the output of an exited worker. A process
+/// started by one of its tests may hold the worker's stdout or stderr open.
+pub(crate) const WORKER_OUTPUT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Counts the failed tests of a run against the `--fail-fast` / `--max-fail`
+/// limit. Shared by all test threads, which stop picking new tests once the
+/// limit is reached.
+pub(crate) struct FailureLimit {
+    max_failures: Option<usize>,
+    failures: AtomicUsize,
+}
+
+impl FailureLimit {
+    pub fn new(args: &Arguments) -> Self {
+        Self {
+            max_failures: args.max_failures(),
+            failures: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn record(&self, result: &TestResult) {
+        if result.is_failed() {
+            self.failures
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    pub fn is_reached(&self) -> bool {
+        self.max_failures
+            .is_some_and(|max| self.failures.load(std::sync::atomic::Ordering::SeqCst) >= max)
+    }
+}
+
+/// Identifies a `--rerun-failed` pass, which reruns the tests failed in the
+/// previous pass. The first pass is attempt 1.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RerunAttempt {
+    pub attempt: usize,
+    pub max_attempts: usize,
+}
+
+/// Selects the tests to run again after a `--rerun-failed` pass: the failed tests, and the tests
+/// skipped because one of their prerequisites (directly or through other skipped tests) failed,
+/// so they get to run if the prerequisite passes in the next pass.
+pub(crate) fn tests_to_rerun(results: &[(RegisteredTest, TestResult)]) -> Vec<RegisteredTest> {
+    let mut rerun: HashSet<String> = results
+        .iter()
+        .filter(|(test, result)| result.is_failed() && test.props.hook.is_none())
+        .map(|(test, _)| test.fully_qualified_name())
+        .collect();
+    loop {
+        let dependents = results
+            .iter()
+            .filter(|(test, result)| {
+                result.is_skipped()
+                    && test.props.hook.is_none()
+                    && !rerun.contains(&test.fully_qualified_name())
+                    && test
+                        .props
+                        .depends_on
+                        .iter()
+                        .any(|prerequisite| rerun.contains(prerequisite))
+            })
+            .map(|(test, _)| test.fully_qualified_name())
+            .collect::<Vec<_>>();
+        if dependents.is_empty() {
+            break;
+        }
+        rerun.extend(dependents);
+    }
+    results
+        .iter()
+        .filter(|(test, _)| rerun.contains(&test.fully_qualified_name()))
+        .map(|(test, _)| test.clone())
+        .collect()
+}
+
+/// Merges the results of all the `--rerun-failed` passes, in the order they
+/// were produced, into one result per test. A test keeps the position of its
+/// first result and the outcome of its last run, and is marked flaky if it
+/// passed after failing in an earlier pass.
+pub(crate) fn merge_rerun_results(
+    results: Vec<(RegisteredTest, TestResult)>,
+) -> Vec<(RegisteredTest, TestResult)> {
+    let mut merged: Vec<(RegisteredTest, TestResult)> = Vec::with_capacity(results.len());
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for (test, mut result) in results {
+        match positions.get(&test.fully_qualified_name()) {
+            Some(&idx) => {
+                if merged[idx].1.is_failed() {
+                    if let TestResult::Passed { flaky, .. } = &mut result {
+                        *flaky = true;
+                    }
+                }
+                merged[idx].1 = result;
+            }
+            None => {
+                positions.insert(test.fully_qualified_name(), merged.len());
+                merged.push((test, result));
+            }
+        }
+    }
+    merged
+}
+
 #[derive(Clone)]
 pub enum TestResult {
     Passed {
         captured: Vec<CapturedOutput>,
         exec_time: Duration,
+        /// The test failed in an earlier pass of `--rerun-failed` and passed when rerun
+        flaky: bool,
     },
     Benchmarked {
         captured: Vec<CapturedOutput>,
@@ -2197,6 +3152,16 @@ pub enum TestResult {
     Ignored {
         captured: Vec<CapturedOutput>,
     },
+    /// The test was not started because the run stopped early after reaching
+    /// the `--fail-fast` / `--max-fail` limit.
+    NotRun {
+        captured: Vec<CapturedOutput>,
+    },
+    /// The test was not started because one of its `#[depends_on]` prerequisites did not pass
+    Skipped {
+        reason: String,
+        captured: Vec<CapturedOutput>,
+    },
 }
 
 impl TestResult {
@@ -2204,6 +3169,7 @@ impl TestResult {
         TestResult::Passed {
             captured: Vec::new(),
             exec_time,
+            flaky: false,
         }
     }
 
@@ -2230,6 +3196,19 @@ impl TestResult {
         }
     }
 
+    pub fn not_run() -> Self {
+        TestResult::NotRun {
+            captured: Vec::new(),
+        }
+    }
+
+    pub fn skipped(reason: impl Into<String>) -> Self {
+        TestResult::Skipped {
+            reason: reason.into(),
+            captured: Vec::new(),
+        }
+    }
+
     pub(crate) fn is_passed(&self) -> bool {
         matches!(self, TestResult::Passed { .. })
     }
@@ -2246,12 +3225,45 @@ impl TestResult {
         matches!(self, TestResult::Ignored { .. })
     }
 
+    pub(crate) fn is_not_run(&self) -> bool {
+        matches!(self, TestResult::NotRun { .. })
+    }
+
+    pub(crate) fn is_skipped(&self) -> bool {
+        matches!(self, TestResult::Skipped { .. })
+    }
+
+    pub(crate) fn skip_reason(&self) -> Option<&str> {
+        match self {
+            TestResult::Skipped { reason, .. } => Some(reason),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_flaky(&self) -> bool {
+        matches!(self, TestResult::Passed { flaky: true, .. })
+    }
+
+    /// Execution time of the test, if it was run
+    pub(crate) fn exec_time(&self) -> Option<Duration> {
+        match self {
+            TestResult::Passed { exec_time, .. }
+            | TestResult::Failed { exec_time, .. }
+            | TestResult::Benchmarked { exec_time, .. } => Some(*exec_time),
+            TestResult::Ignored { .. } | TestResult::NotRun { .. } | TestResult::Skipped { .. } => {
+                None
+            }
+        }
+    }
+
     pub(crate) fn captured_output(&self) -> &Vec<CapturedOutput> {
         match self {
             TestResult::Passed { captured, .. } => captured,
             TestResult::Failed { captured, .. } => captured,
             TestResult::Ignored { captured, .. } => captured,
             TestResult::Benchmarked { captured, .. } => captured,
+            TestResult::NotRun { captured, .. } => captured,
+            TestResult::Skipped { captured, .. } => captured,
         }
     }
 
@@ -2279,6 +3291,13 @@ impl TestResult {
                 captured: captured_ref,
                 ..
             } => *captured_ref = captured,
+            TestResult::NotRun {
+                captured: captured_ref,
+            } => *captured_ref = captured,
+            TestResult::Skipped {
+                captured: captured_ref,
+                ..
+            } => *captured_ref = captured,
         }
     }
 
@@ -2377,6 +3396,9 @@ pub struct SuiteResult {
     pub failed: usize,
     pub ignored: usize,
     pub measured: usize,
+    pub not_run: usize,
+    pub skipped: usize,
+    pub flaky: usize,
     pub filtered_out: usize,
     pub exec_time: Duration,
 }
@@ -2403,13 +3425,33 @@ impl SuiteResult {
             .iter()
             .filter(|(_, result)| result.is_ignored())
             .count();
-        let filtered_out = registered_tests.len() - results.len();
+        let not_run = results
+            .iter()
+            .filter(|(_, result)| result.is_not_run())
+            .count();
+        let skipped = results
+            .iter()
+            .filter(|(_, result)| result.is_skipped())
+            .count();
+        let flaky = results
+            .iter()
+            .filter(|(_, result)| result.is_flaky())
+            .count();
+        // The entries of failed suite hooks are not registered tests
+        let tests = results
+            .iter()
+            .filter(|(test, _)| test.props.hook.is_none())
+            .count();
+        let filtered_out = registered_tests.len() - tests;
 
         Self {
             passed,
             failed,
             ignored,
             measured,
+            not_run,
+            skipped,
+            flaky,
             filtered_out,
             exec_time,
         }
@@ -2628,6 +3670,39 @@ mod error_reporting_tests {
         }
     }
 
+    #[test]
+    fn worker_crash_render_keeps_stderr_tail() {
+        let stderr: Vec<String> = (0..30).map(|i| format!("line {i}")).collect();
+        let crash = WorkerCrash::new(None, &stderr);
+        assert_eq!(crash.stderr_tail.len(), WorkerCrash::STDERR_TAIL_LINES);
+
+        let cause = FailureCause::WorkerCrashed(crash);
+        assert!(cause.is_worker_crash());
+        let msg = cause.render();
+        assert!(
+            msg.starts_with(
+                "Worker process crashed\n\nLast lines of the worker's stderr:\nline 10\n"
+            ),
+            "Expected truncated stderr tail, got: {msg}"
+        );
+        assert!(msg.ends_with("line 29"), "Expected stderr tail, got: {msg}");
+    }
+
+    #[test]
+    fn worker_crash_remembers_allocation_failure_before_the_tail() {
+        let mut stderr = vec!["memory allocation of 1073741824 bytes failed".to_string()];
+        stderr.extend((0..30).map(|i| format!("  {i}: backtrace frame")));
+        let crash = WorkerCrash::new(None, &stderr);
+        assert!(!crash
+            .stderr_tail
+            .iter()
+            .any(|line| is_allocation_failure(line)));
+        assert!(crash.allocation_failed);
+
+        let crash = WorkerCrash::new(None, &stderr[1..]);
+        assert!(!crash.allocation_failed);
+    }
+
     #[test]
     fn panic_render_includes_location() {
         let result = simulate_runner(|| {
@@ -2902,21 +3977,24 @@ mod error_reporting_tests {
     }
 }
 
+/// Creates a test of the `mycrate` crate doing nothing, for the unit tests
+#[cfg(test)]
+pub(crate) fn make_test(name: &str, module_path: &str) -> RegisteredTest {
+    RegisteredTest {
+        name: name.to_string(),
+        crate_name: "mycrate".to_string(),
+        module_path: module_path.to_string(),
+        run: TestFunction::Sync(Arc::new(|_| Box::new(()))),
+        props: TestProperties::default(),
+        dependencies: None,
+        location: None,
+    }
+}
+
 #[cfg(test)]
 mod filter_tests {
     use super::*;
 
-    fn make_test(name: &str, module_path: &str) -> RegisteredTest {
-        RegisteredTest {
-            name: name.to_string(),
-            crate_name: "mycrate".to_string(),
-            module_path: module_path.to_string(),
-            run: TestFunction::Sync(Arc::new(|_| Box::new(()))),
-            props: TestProperties::default(),
-            dependencies: None,
-        }
-    }
-
     fn make_tagged_test(name: &str, module_path: &str, tags: Vec<&str>) -> RegisteredTest {
         let mut test = make_test(name, module_path);
         test.props.tags = tags.into_iter().map(String::from).collect();
@@ -3206,4 +4284,414 @@ mod filter_tests {
         let args = make_args(vec![":tag:db_sqlite"], vec![], false);
         assert_eq!(filtered_names(&args, &tests), vec!["m::t_sqlite"]);
     }
+
+    // --- partitioning ---
+
+    fn partition_args(partition: &str) -> Arguments {
+        Arguments {
+            partition: Some(partition.parse().unwrap()),
+            ..Default::default()
+        }
+    }
+
+    fn assert_disjoint_and_covering(partitions: &[Vec<String>], all: &[String]) {
+        let mut assigned: Vec<String> = partitions.iter().flatten().cloned().collect();
+        assigned.sort();
+        let mut expected = all.to_vec();
+        expected.sort();
+        assert_eq!(assigned, expected);
+    }
+
+    #[test]
+    fn hash_partitions_are_disjoint_and_stable() {
+        let tests: Vec<_> = (0..50)
+            .map(|i| make_test(&format!("test_{i}"), "mod1"))
+            .collect();
+        let all = filtered_names(&Arguments::default(), &tests);
+
+        let partitions: Vec<_> = (1..=3)
+            .map(|k| filtered_names(&partition_args(&format!("hash:{k}/3")), &tests))
+            .collect();
+        assert_disjoint_and_covering(&partitions, &all);
+
+        // Assignment depends only on the test's own name
+        let fewer_tests = &tests[10..20];
+        let partition_of_fewer = filtered_names(&partition_args("hash:2/3"), fewer_tests);
+        let expected: Vec<_> = partitions[1]
+            .iter()
+            .filter(|name| fewer_tests.iter().any(|t| &t.filterable_name() == *name))
+            .cloned()
+            .collect();
+        assert_eq!(partition_of_fewer, expected);
+    }
+
+    #[test]
+    fn count_partitions_are_balanced_and_keep_order() {
+        // Matrix cases are separate tests by the time they are partitioned
+        let tests = vec![
+            make_test("t_postgres", "m"),
+            make_test("a", "m"),
+            make_test("t_sqlite", "m"),
+            make_test("b", "m"),
+            make_test("c", "m"),
+        ];
+        let all = filtered_names(&Arguments::default(), &tests);
+
+        let partitions: Vec<_> = (1..=2)
+            .map(|k| filtered_names(&partition_args(&format!("count:{k}/2")), &tests))
+            .collect();
+        assert_disjoint_and_covering(&partitions, &all);
+        assert_eq!(partitions[0], vec!["m::a", "m::t_sqlite", "m::c"]);
+        assert_eq!(partitions[1], vec!["m::t_postgres", "m::b"]);
+    }
+
+    #[test]
+    fn partitioning_applies_after_filtering() {
+        let tests: Vec<_> = (0..10)
+            .map(|i| {
+                make_test(
+                    &format!("test_{i}"),
+                    if i < 4 { "selected" } else { "other" },
+                )
+            })
+            .collect();
+        let mut args = partition_args("count:1/2");
+        args.filter = vec!["selected".to_string()];
+        assert_eq!(
+            filtered_names(&args, &tests),
+            vec!["selected::test_0", "selected::test_2"]
+        );
+        assert_eq!(tests_in_other_partitions(&args, &tests), 2);
+        assert_eq!(tests_in_other_partitions(&Arguments::default(), &tests), 0);
+    }
+
+    #[test]
+    fn dependent_tests_share_the_partition_of_their_prerequisites() {
+        let mut tests: Vec<_> = (0..20)
+            .map(|i| make_test(&format!("test_{i}"), "mod1"))
+            .collect();
+        for (i, test) in tests.iter_mut().enumerate().take(5).skip(1) {
+            test.props.depends_on = vec![format!("mycrate::mod1::test_{}", i - 1)];
+        }
+        tests[10].props.depends_on = vec!["mycrate::mod1::test_19".to_string()];
+        let all = filtered_names(&Arguments::default(), &tests);
+
+        for kind in ["hash", "count"] {
+            let partitions: Vec<_> = (1..=3)
+                .map(|k| filtered_names(&partition_args(&format!("{kind}:{k}/3")), &tests))
+                .collect();
+            assert_disjoint_and_covering(&partitions, &all);
+            for group in [
+                vec![
+                    "mod1::test_0",
+                    "mod1::test_1",
+                    "mod1::test_2",
+                    "mod1::test_3",
+                    "mod1::test_4",
+                ],
+                vec!["mod1::test_10", "mod1::test_19"],
+            ] {
+                assert!(
+                    partitions.iter().any(|partition| group
+                        .iter()
+                        .all(|name| partition.contains(&name.to_string()))),
+                    "{group:?} was split between {kind} partitions"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod rerun_tests {
+    use super::*;
+
+    fn failed() -> TestResult {
+        TestResult::failed(
+            Duration::ZERO,
+            FailureCause::HarnessError("failed".to_string()),
+        )
+    }
+
+    #[test]
+    fn rerun_args_select_only_the_failed_tests() {
+        let tests = vec![
+            make_test("test_a", "mod1"),
+            make_test("test_ab", "mod1"),
+            make_test("test_a", "mod2"),
+        ];
+        let args = Arguments {
+            filter: vec!["test_a".to_string()],
+            ..Default::default()
+        };
+        let rerun_args = args.for_rerun(&[tests[0].clone()]);
+        let names: Vec<String> = filter_registered_tests(&rerun_args, &tests)
+            .into_iter()
+            .map(|t| t.filterable_name())
+            .collect();
+        assert_eq!(names, vec!["mod1::test_a"]);
+    }
+
+    #[test]
+    fn rerun_args_ignore_the_partition() {
+        let tests = vec![
+            make_test("test_1", "mod1"),
+            make_test("test_2", "mod1"),
+            make_test("test_3", "mod1"),
+        ];
+        let args = Arguments {
+            partition: Some(Partition::Count { index: 1, count: 2 }),
+            ..Default::default()
+        };
+        let selected = filter_registered_tests(&args, &tests);
+        assert_eq!(selected.len(), 2);
+
+        let rerun_args = args.for_rerun(&selected);
+        let names: Vec<String> = filter_registered_tests(&rerun_args, &tests)
+            .into_iter()
+            .map(|t| t.filterable_name())
+            .collect();
+        assert_eq!(names, vec!["mod1::test_1", "mod1::test_3"]);
+    }
+
+    #[test]
+    fn merged_results_keep_the_last_outcome() {
+        let a = make_test("a", "mod1");
+        let b = make_test("b", "mod1");
+        let c = make_test("c", "mod1");
+        let merged = merge_rerun_results(vec![
+            (a.clone(), failed()),
+            (b.clone(), TestResult::passed(Duration::ZERO)),
+            (c.clone(), failed()),
+            (a.clone(), failed()),
+            (c.clone(), failed()),
+            (a.clone(), TestResult::passed(Duration::ZERO)),
+        ]);
+
+        let names: Vec<String> = merged.iter().map(|(t, _)| t.name.clone()).collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+        assert!(merged[0].1.is_flaky());
+        assert!(merged[1].1.is_passed() && !merged[1].1.is_flaky());
+        assert!(merged[2].1.is_failed());
+    }
+
+    #[test]
+    fn tests_skipped_by_failed_prerequisites_are_rerun() {
+        let mut step_1 = make_test("step_1", "mod1");
+        let mut step_2 = make_test("step_2", "mod1");
+        let mut step_3 = make_test("step_3", "mod1");
+        let mut skipped_by_hook = make_test("skipped_by_hook", "mod1");
+        let passed = make_test("passed", "mod1");
+        step_1.props.depends_on = vec!["mycrate::mod1::passed".to_string()];
+        step_2.props.depends_on = vec!["mycrate::mod1::step_1".to_string()];
+        step_3.props.depends_on = vec!["mycrate::mod1::step_2".to_string()];
+        skipped_by_hook.props.depends_on = vec!["mycrate::mod1::passed".to_string()];
+        let skipped = || TestResult::skipped("skipped".to_string());
+
+        let rerun = tests_to_rerun(&[
+            (passed, TestResult::passed(Duration::ZERO)),
+            (step_3, skipped()),
+            (step_1, failed()),
+            (step_2, skipped()),
+            (skipped_by_hook, skipped()),
+        ]);
+        let names: Vec<String> = rerun.iter().map(|t| t.name.clone()).collect();
+        assert_eq!(names, vec!["step_3", "step_1", "step_2"]);
+    }
+
+    #[test]
+    fn not_run_results_are_replaced_by_later_passes() {
+        let a = make_test("a", "mod1");
+        let b = make_test("b", "mod1");
+        let merged = merge_rerun_results(vec![
+            (a.clone(), failed()),
+            (b.clone(), TestResult::not_run()),
+            (b.clone(), TestResult::passed(Duration::ZERO)),
+        ]);
+
+        let names: Vec<String> = merged.iter().map(|(t, _)| t.name.clone()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+        assert!(merged[1].1.is_passed() && !merged[1].1.is_flaky());
+    }
+}
+
+#[cfg(test)]
+mod depends_on_tests {
+    use super::resolve_test_path;
+
+    #[test]
+    fn test_paths_are_resolved_like_rust_paths() {
+        let module = "mycrate::migrations::steps";
+        assert_eq!(
+            resolve_test_path(module, "step_1"),
+            "mycrate::migrations::steps::step_1"
+        );
+        assert_eq!(
+            resolve_test_path(module, "self::step_1"),
+            "mycrate::migrations::steps::step_1"
+        );
+        assert_eq!(
+            resolve_test_path(module, "inner::step_1"),
+            "mycrate::migrations::steps::inner::step_1"
+        );
+        assert_eq!(
+            resolve_test_path(module, "super::setup"),
+            "mycrate::migrations::setup"
+        );
+        assert_eq!(
+            resolve_test_path(module, "super::super::setup"),
+            "mycrate::setup"
+        );
+        assert_eq!(
+            resolve_test_path(module, "crate::other::setup"),
+            "mycrate::other::setup"
+        );
+        assert_eq!(resolve_test_path("mycrate", "setup"), "mycrate::setup");
+    }
+}
+
+#[cfg(test)]
+mod location_tests {
+    use super::*;
+
+    #[test]
+    fn generated_tests_are_located_where_they_are_added() {
+        let mut registration = DynamicTestRegistration::new();
+        let line = line!() + 1;
+        registration.add_sync_test("a", TestProperties::unit_test(), None, |_| ());
+        registration.add_sync_test("b", TestProperties::unit_test(), None, |_| ());
+        registration.set_location(SourceLocation::new("cases/b.json", 3, 1));
+        let mut generated = registration.to_vec();
+        generated.push(GeneratedTest {
+            name: "c".to_string(),
+            location: None,
+            ..generated[0].clone()
+        });
+
+        let generator = RegisteredTestGenerator {
+            name: "generator".to_string(),
+            crate_name: "mycrate".to_string(),
+            module_path: "module".to_string(),
+            run: TestGeneratorFunction::Sync(Arc::new(Vec::new)),
+            is_ignored: false,
+            ignore_reason: None,
+            location: Some(SourceLocation::new("src/lib.rs", 10, 8)),
+        };
+        let mut tests = Vec::new();
+        add_generated_tests(&mut tests, &generator, generated);
+        let locations: Vec<_> = tests.into_iter().map(|test| test.location).collect();
+        assert_eq!(
+            locations,
+            vec![
+                Some(SourceLocation::new(file!(), line, 22)),
+                Some(SourceLocation::new("cases/b.json", 3, 1)),
+                Some(SourceLocation::new("src/lib.rs", 10, 8)),
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod ignore_tests {
+    use super::*;
+
+    #[test]
+    fn ignored_suites_keep_the_reasons_of_their_tests() {
+        let mut ignored = make_test("b", "suite");
+        ignored.props.is_ignored = true;
+        ignored.props.ignore_reason = Some("own reason".to_string());
+        let tests = vec![make_test("a", "suite"), ignored];
+        let props = vec![RegisteredTestSuiteProperty::Ignore {
+            name: "suite".to_string(),
+            crate_name: "mycrate".to_string(),
+            module_path: String::new(),
+            reason: Some("suite reason".to_string()),
+        }];
+        let reasons: Vec<_> = apply_suite_props_to_tests(&tests, &props)
+            .into_iter()
+            .map(|test| (test.props.is_ignored, test.props.ignore_reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (true, Some("suite reason".to_string())),
+                (true, Some("own reason".to_string())),
+            ]
+        );
+    }
+}
+
+#[cfg(test)]
+mod display_name_tests {
+    use super::*;
+
+    #[test]
+    fn summaries_show_the_path_next_to_the_display_name() {
+        let mut test = make_test("creates_user", "users");
+        assert_eq!(test.summary_name(), "mycrate::users::creates_user");
+        test.props.display_name = Some("creates a user".to_string());
+        assert_eq!(test.display_name(), "creates a user");
+        assert_eq!(
+            test.summary_name(),
+            "creates a user (mycrate::users::creates_user)"
+        );
+    }
+}
+
+#[cfg(test)]
+mod suite_environment_tests {
+    use super::*;
+
+    fn env_prop(name: &str, module_path: &str, value: &str) -> RegisteredTestSuiteProperty {
+        RegisteredTestSuiteProperty::Env {
+            name: name.to_string(),
+            crate_name: "mycrate".to_string(),
+            module_path: module_path.to_string(),
+            change: EnvChange::set("MODE", value),
+        }
+    }
+
+    fn cwd_prop(name: &str, module_path: &str, path: &str) -> RegisteredTestSuiteProperty {
+        RegisteredTestSuiteProperty::Cwd {
+            name: name.to_string(),
+            crate_name: "mycrate".to_string(),
+            module_path: module_path.to_string(),
+            path: PathBuf::from(path),
+        }
+    }
+
+    #[test]
+    fn innermost_suite_wins_regardless_of_registration_order() {
+        let tests = vec![make_test("a", "outer::inner"), make_test("b", "outer")];
+        let outer = [
+            env_prop("outer", "", "outer"),
+            cwd_prop("outer", "", "outer"),
+        ];
+        let inner = [
+            env_prop("inner", "outer", "inner"),
+            cwd_prop("inner", "outer", "outer/inner"),
+        ];
+        for props in [
+            [outer.clone(), inner.clone()].concat(),
+            [inner.clone(), outer.clone()].concat(),
+        ] {
+            let applied: Vec<_> = apply_suite_props_to_tests(&tests, &props)
+                .into_iter()
+                .map(|test| (test.props.env, test.props.cwd))
+                .collect();
+            assert_eq!(
+                applied,
+                vec![
+                    (
+                        vec![EnvChange::set("MODE", "inner")],
+                        Some(PathBuf::from("outer/inner"))
+                    ),
+                    (
+                        vec![EnvChange::set("MODE", "outer")],
+                        Some(PathBuf::from("outer"))
+                    ),
+                ]
+            );
+        }
+    }
 }
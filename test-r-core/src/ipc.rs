@@ -1,10 +1,14 @@
-use crate::internal::{CapturedOutput, FailureCause, TestResult};
+use crate::internal::{
+    CapturedOutput, DependencyEvent, FailureCause, HookKind, RegisteredTest, TestFunction,
+    TestProperties, TestResult,
+};
 use crate::stats::Summary;
 use desert_rust::BinaryCodec;
 use interprocess::local_socket::{
     GenericFilePath, GenericNamespaced, Name, NameType, ToFsName, ToNsName,
 };
 use std::io::{self, Read, Write};
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Length-prefix width used to frame all IPC messages. A `u32` allows payloads
@@ -84,6 +88,11 @@ pub enum IpcCommand {
     /// worker reconstructor) instead of being treated as the dep value
     /// directly. The `dep_id` is the dep's fully-qualified id.
     ProvideHostedDescriptor { dep_id: String, wire_bytes: Vec<u8> },
+    /// Tells the worker that the constructor of a `Cloneable`, `Hosted` or
+    /// `HostedRpc` dependency failed in the parent, so the tests using it
+    /// fail without being run. Not acknowledged: the worker handles it before
+    /// the next [`Self::RunTest`].
+    ProvideFailedDependency { dep_id: String, message: String },
     /// Phase 1C: parent's response to a worker-initiated
     /// [`IpcResponse::HostedRpcCall`]. Carries the same `request_id`
     /// echoed back so the worker's stub can match the reply to the
@@ -94,6 +103,11 @@ pub enum IpcCommand {
         request_id: u64,
         body: HostedRpcReplyBody,
     },
+    /// Asks the worker to run the `after_all` hooks of the suites it ran
+    /// tests of, then the teardowns of the dependencies it constructed. Sent
+    /// before the worker is retired; the worker answers with
+    /// [`IpcResponse::Finished`].
+    Finish,
 }
 
 /// Body of a [`IpcCommand::HostedRpcReply`]. Either the serialized return
@@ -120,6 +134,10 @@ pub enum SerializableTestResult {
         rendered_failure_cause: String,
     },
     Ignored,
+    NotRun,
+    Skipped {
+        reason: String,
+    },
 }
 
 impl SerializableTestResult {
@@ -160,6 +178,10 @@ impl From<&TestResult> for SerializableTestResult {
                 rendered_failure_cause: cause.render(),
             },
             TestResult::Ignored { .. } => SerializableTestResult::Ignored,
+            TestResult::NotRun { .. } => SerializableTestResult::NotRun,
+            TestResult::Skipped { reason, .. } => SerializableTestResult::Skipped {
+                reason: reason.clone(),
+            },
         }
     }
 }
@@ -176,6 +198,8 @@ impl From<SerializableTestResult> for TestResult {
                 FailureCause::HarnessError(rendered_failure_cause),
             ),
             SerializableTestResult::Ignored => TestResult::ignored(),
+            SerializableTestResult::NotRun => TestResult::not_run(),
+            SerializableTestResult::Skipped { reason } => TestResult::skipped(reason),
             SerializableTestResult::Benchmarked {
                 exec_time,
                 ns_iter_summ,
@@ -185,6 +209,51 @@ impl From<SerializableTestResult> for TestResult {
     }
 }
 
+/// A suite hook that failed in a worker process, reported by the primary
+/// test runner as its own entry
+#[derive(Debug, BinaryCodec)]
+pub struct FailedHook {
+    pub name: String,
+    pub crate_name: String,
+    pub module_path: String,
+    pub kind: HookKind,
+    pub result: SerializableTestResult,
+}
+
+impl From<&(RegisteredTest, TestResult)> for FailedHook {
+    fn from((entry, result): &(RegisteredTest, TestResult)) -> Self {
+        Self {
+            name: entry.name.clone(),
+            crate_name: entry.crate_name.clone(),
+            module_path: entry.module_path.clone(),
+            kind: entry
+                .props
+                .hook
+                .expect("Only the entries of suite hooks are reported as failed hooks"),
+            result: result.into(),
+        }
+    }
+}
+
+impl From<FailedHook> for (RegisteredTest, TestResult) {
+    fn from(hook: FailedHook) -> Self {
+        let entry = RegisteredTest {
+            name: hook.name,
+            crate_name: hook.crate_name,
+            module_path: hook.module_path,
+            // The hook itself only runs in the worker process
+            run: TestFunction::Sync(Arc::new(|_| Box::new(()))),
+            props: TestProperties {
+                hook: Some(hook.kind),
+                ..Default::default()
+            },
+            dependencies: Some(Vec::new()),
+            location: None,
+        };
+        (entry, hook.result.into())
+    }
+}
+
 /// Responses sent from the spawned worker processes to the primary test
 /// runner.
 #[derive(Debug, BinaryCodec)]
@@ -192,6 +261,19 @@ pub enum IpcResponse {
     TestFinished {
         result: SerializableTestResult,
         finish_marker: String,
+        /// The `before_all`, `before_each` and `after_each` hooks that failed
+        /// around the test
+        failed_hooks: Vec<FailedHook>,
+        /// The dependencies constructed and dropped since the previous response
+        dependency_events: Vec<DependencyEvent>,
+    },
+    /// Answers [`IpcCommand::Finish`] with the hooks that failed, the
+    /// warnings of the failed dependency teardowns and the dependencies
+    /// dropped
+    Finished {
+        failed_hooks: Vec<FailedHook>,
+        teardown_warnings: Vec<String>,
+        dependency_events: Vec<DependencyEvent>,
     },
     /// Acknowledges a [`IpcCommand::ProvideCloneable`]. Echoes back the
     /// fully-qualified `dep_id` the command carried.
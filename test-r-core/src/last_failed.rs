@@ -0,0 +1,153 @@
+//! Persists the names of the failed tests between runs of a test binary, to support
+//! `--last-failed` and `--failed-first`.
+//!
+//! The names are stored one per line in a state file next to the build artifacts of
+//! the binary (see [`crate::state::state_file`]). Worker processes read the same
+//! file as the parent, which is only updated after the whole run has finished.
+
+use crate::internal::{HookKind, RegisteredTest, TestResult};
+use crate::state::state_file;
+use std::collections::BTreeSet;
+
+/// Loads the names of the tests that failed when they were last run
+pub(crate) fn load() -> BTreeSet<String> {
+    state_file("last-failed")
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|contents| {
+            contents
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Updates the stored names with the results of a run. Tests that were not run
+/// (filtered out, ignored or not started) keep their previous state, except the ones
+/// skipped because a `before_all` hook of their suite failed, which are stored as failed.
+pub(crate) fn update(results: &[(RegisteredTest, TestResult)]) {
+    let Some(path) = state_file("last-failed") else {
+        return;
+    };
+    let failed = merge(load(), results);
+
+    let write_result = if failed.is_empty() {
+        match std::fs::remove_file(&path) {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            other => other,
+        }
+    } else {
+        let contents: String = failed.iter().map(|name| format!("{name}\n")).collect();
+        path.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(&path, contents))
+    };
+    if let Err(err) = write_result {
+        eprintln!(
+            "Failed to update the list of failed tests in {}: {err}",
+            path.display()
+        );
+    }
+}
+
+fn merge(
+    mut failed: BTreeSet<String>,
+    results: &[(RegisteredTest, TestResult)],
+) -> BTreeSet<String> {
+    let failed_suites: Vec<&RegisteredTest> = results
+        .iter()
+        .filter(|(test, result)| result.is_failed() && test.props.hook == Some(HookKind::BeforeAll))
+        .map(|(test, _)| test)
+        .collect();
+    let skipped_by_failed_hook = |test: &RegisteredTest| {
+        failed_suites.iter().any(|suite| {
+            suite.crate_name == test.crate_name
+                && (test.module_path == suite.module_path
+                    || test
+                        .module_path
+                        .starts_with(&format!("{}::", suite.module_path)))
+        })
+    };
+
+    // Suite hooks are reported like tests, but they cannot be selected to run
+    for (test, result) in results.iter().filter(|(test, _)| test.props.hook.is_none()) {
+        if result.is_failed() || (result.is_skipped() && skipped_by_failed_hook(test)) {
+            failed.insert(test.fully_qualified_name());
+        } else if result.is_passed() || result.is_benchmarked() {
+            failed.remove(&test.fully_qualified_name());
+        }
+    }
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::{make_test, FailureCause};
+    use std::time::Duration;
+
+    #[test]
+    fn merge_keeps_the_state_of_tests_not_run() {
+        let previous: BTreeSet<String> = [
+            "mycrate::mod1::fixed",
+            "mycrate::mod1::still_failing",
+            "mycrate::mod1::not_selected",
+            "mycrate::mod1::now_ignored",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        let results = vec![
+            (
+                make_test("fixed", "mod1"),
+                TestResult::passed(Duration::ZERO),
+            ),
+            (
+                make_test("still_failing", "mod1"),
+                TestResult::failed(Duration::ZERO, FailureCause::HarnessError("x".into())),
+            ),
+            (
+                make_test("new_failure", "mod1"),
+                TestResult::failed(Duration::ZERO, FailureCause::HarnessError("x".into())),
+            ),
+            (make_test("now_ignored", "mod1"), TestResult::ignored()),
+        ];
+
+        let merged: Vec<String> = merge(previous, &results).into_iter().collect();
+        assert_eq!(
+            merged,
+            vec![
+                "mycrate::mod1::new_failure",
+                "mycrate::mod1::not_selected",
+                "mycrate::mod1::now_ignored",
+                "mycrate::mod1::still_failing",
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_records_the_tests_skipped_by_a_failed_before_all_hook() {
+        let mut hook = make_test("setup [before_all]", "mod1");
+        hook.props.hook = Some(HookKind::BeforeAll);
+        let skipped = TestResult::skipped("before_all hook setup failed");
+        let results = vec![
+            (
+                hook,
+                TestResult::failed(Duration::ZERO, FailureCause::HarnessError("x".into())),
+            ),
+            (make_test("in_suite", "mod1"), skipped.clone()),
+            (make_test("in_inner_suite", "mod1::inner"), skipped.clone()),
+            (make_test("elsewhere", "mod10"), skipped),
+        ];
+
+        let merged: Vec<String> = merge(BTreeSet::new(), &results).into_iter().collect();
+        assert_eq!(
+            merged,
+            vec![
+                "mycrate::mod1::in_suite",
+                "mycrate::mod1::inner::in_inner_suite",
+            ]
+        );
+    }
+}
@@ -1,13 +1,20 @@
 pub mod args;
 pub mod bench;
+mod context;
+mod environment;
 mod execution;
 mod host_capture;
 pub mod internal;
 mod ipc;
+mod last_failed;
+mod limits;
 mod output;
 mod panic_hook;
+mod seed;
 pub mod spawn;
+mod state;
 mod stats;
+mod timings;
 #[cfg(feature = "tokio")]
 mod tokio;
 pub mod worker;
@@ -21,6 +28,8 @@ pub use sync::test_runner;
 #[cfg(feature = "tokio")]
 pub use tokio::test_runner;
 
+pub use context::TestContext;
+pub use seed::TestRng;
 pub use worker::worker_index;
 
 /// Re-export of [`desert_rust`] so that proc-macros emitted by
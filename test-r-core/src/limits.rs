@@ -0,0 +1,216 @@
+//! Resource limits of the spawned worker processes, set with `--worker-memory-limit`,
+//! `--worker-cpu-time-limit` and `#[memory_limit]`.
+//!
+//! The limits are applied with `setrlimit` in the worker process right before it starts
+//! executing the test binary, so they are enforced by the kernel. The memory limit is
+//! `RLIMIT_DATA`, which covers the heap and every private writable mapping, so reserved but
+//! unused address space (such as the allocator's per-thread arenas) does not count towards
+//! it. The CPU time limit is `RLIMIT_CPU`, which accumulates over all tests run by the same
+//! worker process. Limits are only supported on Linux, other platforms run the tests without
+//! them after a warning.
+
+use crate::args::{Arguments, CpuTime, MemorySize};
+use crate::internal::{RegisteredTest, WorkerCrash, WorkerLimit};
+use std::process::Command;
+
+/// The limits of a single worker process
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct WorkerLimits {
+    memory: Option<MemorySize>,
+    cpu_time: Option<CpuTime>,
+}
+
+impl WorkerLimits {
+    /// The limits of a worker process running a test with the given `#[memory_limit]`. The
+    /// smaller one of the test's own limit and `--worker-memory-limit` applies.
+    pub fn new(args: &Arguments, memory_limit: Option<MemorySize>) -> Self {
+        let memory = match (args.worker_memory_limit, memory_limit) {
+            (Some(worker), Some(test)) => Some(worker.min(test)),
+            (worker, test) => worker.or(test),
+        };
+        Self {
+            memory,
+            cpu_time: args.worker_cpu_time_limit,
+        }
+    }
+
+    /// Sets up `command` to apply the limits to the spawned process
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self, command: &mut Command) {
+        use std::os::unix::process::CommandExt;
+
+        if *self == Self::default() {
+            return;
+        }
+        let limits = *self;
+        // SAFETY: the closure runs in the forked child before `exec`, and only calls
+        // `getrlimit` and `setrlimit`, which are async-signal-safe.
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(memory) = limits.memory {
+                    set_limit(libc::RLIMIT_DATA, memory.bytes, memory.bytes)?;
+                }
+                if let Some(cpu_time) = limits.cpu_time {
+                    // The soft limit sends SIGXCPU, which is recognized as exceeding the limit,
+                    // and the hard limit kills the process if it handles SIGXCPU
+                    set_limit(libc::RLIMIT_CPU, cpu_time.seconds, cpu_time.seconds + 1)?;
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn apply(&self, _command: &mut Command) {}
+
+    /// Returns the limit that was exceeded by a crashed worker process with these limits
+    pub fn exceeded_by(&self, crash: &WorkerCrash) -> Option<WorkerLimit> {
+        const SIGXCPU: i32 = 24;
+
+        // Only recognized from the allocation failure printed on stderr; any other abort, or
+        // one whose stderr was not captured (`--nocapture`), is reported as the plain signal
+        if let Some(memory) = self.memory {
+            if crash.allocation_failed {
+                return Some(WorkerLimit::Memory(memory));
+            }
+        }
+        match self.cpu_time {
+            Some(cpu_time) if crash.signal == Some(SIGXCPU) => Some(WorkerLimit::CpuTime(cpu_time)),
+            _ => None,
+        }
+    }
+}
+
+/// Returns a warning if worker limits were requested on a platform they are not supported on,
+/// so the tests are not run without them unnoticed
+pub(crate) fn unsupported_limits_warning(
+    args: &Arguments,
+    tests: &[RegisteredTest],
+) -> Option<String> {
+    let requested = requested_limits(args, tests);
+    if cfg!(target_os = "linux") || requested.is_empty() {
+        None
+    } else {
+        Some(format!(
+            "Worker limits are only supported on Linux, ignoring {}",
+            requested.join(", ")
+        ))
+    }
+}
+
+/// The options and attributes setting worker limits used by this run
+fn requested_limits(args: &Arguments, tests: &[RegisteredTest]) -> Vec<&'static str> {
+    let mut requested = Vec::new();
+    if args.worker_memory_limit.is_some() {
+        requested.push("--worker-memory-limit");
+    }
+    if args.worker_cpu_time_limit.is_some() {
+        requested.push("--worker-cpu-time-limit");
+    }
+    if tests.iter().any(|test| test.props.memory_limit.is_some()) {
+        requested.push("#[memory_limit]");
+    }
+    requested
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(all(target_os = "linux", not(target_env = "gnu")))]
+type Resource = libc::c_int;
+
+/// Lowers the soft and hard limits of `resource`, without raising them above the current
+/// hard limit
+#[cfg(target_os = "linux")]
+fn set_limit(resource: Resource, soft: u64, hard: u64) -> std::io::Result<()> {
+    let mut current = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: `current` is a valid, writable `rlimit`
+    if unsafe { libc::getrlimit(resource, &mut current) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let limit = libc::rlimit {
+        rlim_cur: soft.min(current.rlim_max),
+        rlim_max: hard.min(current.rlim_max),
+    };
+    // SAFETY: `limit` is a valid `rlimit`
+    if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::is_allocation_failure;
+    use clap::Parser;
+
+    fn crash(signal: Option<i32>, stderr: &[&str]) -> WorkerCrash {
+        WorkerCrash {
+            exit_code: None,
+            signal,
+            stderr_tail: stderr.iter().map(|line| line.to_string()).collect(),
+            allocation_failed: stderr.iter().any(|line| is_allocation_failure(line)),
+        }
+    }
+
+    #[test]
+    fn the_smaller_memory_limit_applies() {
+        let args: Arguments = Parser::parse_from(["test-bin", "--worker-memory-limit", "1GiB"]);
+        let limits = WorkerLimits::new(&args, Some(MemorySize { bytes: 1 << 20 }));
+        assert_eq!(limits.memory, Some(MemorySize { bytes: 1 << 20 }));
+        let limits = WorkerLimits::new(&args, Some(MemorySize { bytes: 2 << 30 }));
+        assert_eq!(limits.memory, Some(MemorySize { bytes: 1 << 30 }));
+        let limits = WorkerLimits::new(&Arguments::default(), None);
+        assert_eq!(limits, WorkerLimits::default());
+    }
+
+    #[test]
+    fn requested_limits_are_listed_for_the_warning() {
+        let args: Arguments = Parser::parse_from(["test-bin", "--worker-cpu-time-limit", "10s"]);
+        let mut limited = crate::internal::make_test("limited", "m");
+        limited.props.memory_limit = Some(MemorySize { bytes: 1 << 20 });
+        assert_eq!(
+            requested_limits(&args, &[limited.clone()]),
+            vec!["--worker-cpu-time-limit", "#[memory_limit]"]
+        );
+        assert!(requested_limits(
+            &Arguments::default(),
+            &[crate::internal::make_test("t", "m")]
+        )
+        .is_empty());
+        assert_eq!(
+            unsupported_limits_warning(&args, &[limited]).is_some(),
+            !cfg!(target_os = "linux")
+        );
+    }
+
+    #[test]
+    fn exceeded_limits_are_recognized_from_the_crash() {
+        let limits = WorkerLimits {
+            memory: Some(MemorySize { bytes: 1 << 20 }),
+            cpu_time: Some(CpuTime { seconds: 10 }),
+        };
+        assert_eq!(
+            limits.exceeded_by(&crash(
+                Some(6),
+                &["memory allocation of 1048576 bytes failed"]
+            )),
+            Some(WorkerLimit::Memory(MemorySize { bytes: 1 << 20 }))
+        );
+        assert_eq!(
+            limits.exceeded_by(&crash(Some(24), &[])),
+            Some(WorkerLimit::CpuTime(CpuTime { seconds: 10 }))
+        );
+        assert_eq!(limits.exceeded_by(&crash(Some(6), &[])), None);
+        assert_eq!(limits.exceeded_by(&crash(Some(6), &["aborted"])), None);
+        assert_eq!(limits.exceeded_by(&crash(Some(11), &["segfault"])), None);
+        assert_eq!(
+            WorkerLimits::default()
+                .exceeded_by(&crash(Some(6), &["memory allocation of 16 bytes failed"])),
+            None
+        );
+    }
+}
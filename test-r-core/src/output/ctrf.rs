@@ -1,6 +1,10 @@
-use crate::internal::{FlakinessControl, RegisteredTest, TestResult};
+use crate::internal::{
+    DependencyEvent, DependencyEventKind, FlakinessControl, RegisteredTest, TestResult, TestType,
+};
 use crate::output::progress::StderrProgress;
-use crate::output::{write_failure_summary_to_stderr, LogFile, StdoutOrLogFile, TestRunnerOutput};
+use crate::output::{
+    test_metadata, write_failure_summary_to_stderr, LogFile, StdoutOrLogFile, TestRunnerOutput,
+};
 use ctrf_rs::test::{Status, Test};
 use serde_json::json;
 use std::collections::{HashMap, HashSet};
@@ -51,7 +55,7 @@ impl TestRunnerOutput for Ctrf {
     fn start_running_test(&self, registered_test: &RegisteredTest, idx: usize, count: usize) {
         let mut state = self.state.lock().unwrap();
         let mut test = Test::new(
-            registered_test.fully_qualified_name(),
+            registered_test.display_name(),
             Status::Pending,
             Duration::ZERO,
         );
@@ -203,11 +207,77 @@ impl TestRunnerOutput for Ctrf {
         write_ctrf_snapshot(&mut state, true);
         // Clear the start marker now that the suite has truly finished.
         state.start = None;
+        state.dependency_events.clear();
 
         write_failure_summary_to_stderr(results, exec_time);
     }
 
-    fn test_list(&self, _registered_tests: &[RegisteredTest]) {}
+    fn test_list(&self, registered_tests: &[RegisteredTest]) {
+        let mut state = self.state.lock().unwrap();
+        state.start.replace(SystemTime::now());
+        // The listed tests are reported with the `other` status, the properties not covered by
+        // the CTRF schema are in their `extra` field
+        for registered_test in registered_tests {
+            let mut test = Test::new(
+                registered_test.display_name(),
+                Status::Other,
+                Duration::ZERO,
+            );
+            test.suite = Some(registered_test.crate_and_module());
+            test.tags = registered_test.props.tags.clone();
+            test.test_type = Some(
+                match registered_test.props.test_type {
+                    TestType::UnitTest => "unit",
+                    TestType::IntegrationTest => "integration",
+                }
+                .to_string(),
+            );
+            test.parameters = registered_test
+                .props
+                .matrix_case
+                .iter()
+                .map(|(dimension, case)| (dimension.clone(), json!(case)))
+                .collect();
+            let mut value = serde_json::to_value(&test).expect("Failed to serialize CTRF test");
+            insert_location(&mut value, registered_test);
+            value
+                .as_object_mut()
+                .unwrap()
+                .insert("extra".to_string(), test_metadata(registered_test).into());
+            state.tests.push(value);
+            state.summary.other += 1;
+            if let Some(suite) = test.suite {
+                state.suites.insert(suite);
+            }
+        }
+        write_ctrf_snapshot(&mut state, true);
+    }
+
+    fn dependency_event(&self, event: &DependencyEvent) {
+        let mut value = json!({
+            "name": event.dependency,
+            "scope": event.scope.to_string(),
+            "process": event.process.to_string(),
+        });
+        let fields = value.as_object_mut().unwrap();
+        match &event.kind {
+            DependencyEventKind::ConstructionStarted => {
+                fields.insert("event".to_string(), json!("started"));
+            }
+            DependencyEventKind::ConstructionFinished { duration, failed } => {
+                fields.insert(
+                    "event".to_string(),
+                    json!(if *failed { "failed" } else { "constructed" }),
+                );
+                fields.insert("duration".to_string(), json!(duration.as_millis() as u64));
+            }
+            DependencyEventKind::Dropped { lifetime } => {
+                fields.insert("event".to_string(), json!("dropped"));
+                fields.insert("lifetime".to_string(), json!(lifetime.as_millis() as u64));
+            }
+        }
+        self.state.lock().unwrap().dependency_events.push(value);
+    }
 }
 
 /// Builds a CTRF `Test` JSON value from a finished test result, applying the
@@ -225,18 +295,22 @@ fn build_test_value(
     stop: Option<u64>,
 ) -> (Status, Option<String>, serde_json::Value) {
     let mut test = Test::new(
-        registered_test.fully_qualified_name(),
+        registered_test.display_name(),
         match result {
             TestResult::Passed { .. } => Status::Passed,
             TestResult::Benchmarked { .. } => Status::Passed,
             TestResult::Failed { .. } => Status::Failed,
             TestResult::Ignored { .. } => Status::Skipped,
+            TestResult::NotRun { .. } => Status::Skipped,
+            TestResult::Skipped { .. } => Status::Skipped,
         },
         match result {
             TestResult::Passed { exec_time, .. } => *exec_time,
             TestResult::Failed { exec_time, .. } => *exec_time,
             TestResult::Benchmarked { exec_time, .. } => *exec_time,
             TestResult::Ignored { .. } => Duration::ZERO,
+            TestResult::NotRun { .. } => Duration::ZERO,
+            TestResult::Skipped { .. } => Duration::ZERO,
         },
     );
 
@@ -261,19 +335,44 @@ fn build_test_value(
         test.stderr = stderr_lines;
     }
 
-    test.message = result.failure_message();
+    test.message = if result.is_not_run() {
+        Some("Not run because the run stopped after too many failures".to_string())
+    } else if result.is_ignored() {
+        registered_test
+            .props
+            .ignore_reason
+            .as_ref()
+            .map(|reason| format!("Ignored because {reason}"))
+    } else if let Some(reason) = result.skip_reason() {
+        Some(format!("Skipped because {reason}"))
+    } else {
+        result.failure_message()
+    };
     test.suite = Some(registered_test.crate_and_module());
-    test.flaky = flaky;
+    // Passing on a `--rerun-failed` pass proves the test flaky
+    test.flaky = if result.is_flaky() { Some(true) } else { flaky };
     test.retries = retries;
     test.start = start;
     test.stop = stop;
 
     let status = test.status();
     let suite = test.suite.clone();
-    let value = serde_json::to_value(&test).expect("Failed to serialize CTRF test");
+    let mut value = serde_json::to_value(&test).expect("Failed to serialize CTRF test");
+    insert_location(&mut value, registered_test);
     (status, suite, value)
 }
 
+/// Adds the `filePath` and `line` fields of a CTRF test, if the test's location is known.
+/// `ctrf_rs::test::Test` serializes its file path as `filepath` instead of the `filePath`
+/// field of the CTRF schema, so both are inserted here directly.
+fn insert_location(value: &mut serde_json::Value, registered_test: &RegisteredTest) {
+    if let Some(location) = &registered_test.location {
+        let fields = value.as_object_mut().unwrap();
+        fields.insert("filePath".to_string(), json!(location.file));
+        fields.insert("line".to_string(), json!(location.line));
+    }
+}
+
 /// Serializes the currently accumulated CTRF tests and writes them to the
 /// configured output. When the target is a log file the file is truncated
 /// first so each snapshot is a complete, valid CTRF document. For stdout the
@@ -327,7 +426,7 @@ fn write_ctrf_snapshot(state: &mut CtrfState, is_final: bool) {
             .insert("suites".to_string(), json!(state.suites.len()));
     }
 
-    let report = json!({
+    let mut report = json!({
         "reportFormat": ctrf_rs::report::REPORT_FORMAT,
         "specVersion": ctrf_rs::report::SPEC_VERSION.to_string(),
         "timestamp": format!("{now:?}"),
@@ -338,6 +437,14 @@ fn write_ctrf_snapshot(state: &mut CtrfState, is_final: bool) {
             "tests": state.tests,
         },
     });
+    // The construction and drop of the dependencies have no place in the CTRF schema, so
+    // they are listed in the `extra` field of the results
+    if !state.dependency_events.is_empty() {
+        report["results"].as_object_mut().unwrap().insert(
+            "extra".to_string(),
+            json!({ "dependencyEvents": state.dependency_events }),
+        );
+    }
 
     let raw = serde_json::to_string(&report).expect("Failed to serialize CTRF document");
     let out = &mut state.target;
@@ -370,6 +477,9 @@ struct CtrfState {
     /// preserves true per-test stop times.
     pub pending_stops: HashMap<String, u64>,
     pub start: Option<SystemTime>,
+    /// The dependency events of the run, in the order they were reported. Not reset by
+    /// `start_suite`, as the parent constructs some of the dependencies before the suite starts.
+    pub dependency_events: Vec<serde_json::Value>,
 }
 
 impl CtrfState {
@@ -382,6 +492,7 @@ impl CtrfState {
             pending_tests: HashMap::new(),
             pending_stops: HashMap::new(),
             start: None,
+            dependency_events: Vec::new(),
         }
     }
 }
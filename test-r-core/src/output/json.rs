@@ -1,5 +1,7 @@
-use crate::internal::{RegisteredTest, SuiteResult, TestResult};
-use crate::output::{LogFile, StdoutOrLogFile, TestRunnerOutput};
+use crate::internal::{
+    DependencyEvent, DependencyEventKind, RegisteredTest, SuiteResult, TestResult,
+};
+use crate::output::{test_metadata, LogFile, StdoutOrLogFile, TestRunnerOutput};
 use std::io::Write;
 use std::path::PathBuf;
 use std::sync::Mutex;
@@ -23,6 +25,19 @@ impl Json {
     }
 }
 
+/// The `file`, `line` and `column` fields of a test event, if the test's location is known
+fn location_fields(test: &RegisteredTest) -> String {
+    match &test.location {
+        Some(location) => format!(
+            r#", "file": "{}", "line": {}, "column": {}"#,
+            escape8259::escape(&location.file),
+            location.line,
+            location.column
+        ),
+        None => String::new(),
+    }
+}
+
 impl TestRunnerOutput for Json {
     fn start_suite(&self, tests: &[RegisteredTest]) {
         let mut out = self.target.lock().unwrap();
@@ -38,8 +53,9 @@ impl TestRunnerOutput for Json {
         let mut out = self.target.lock().unwrap();
         writeln!(
             out,
-            r#"{{ "type": "test", "event": "started", "name": "{}" }}"#,
-            escape8259::escape(test.fully_qualified_name())
+            r#"{{ "type": "test", "event": "started", "name": "{}"{} }}"#,
+            escape8259::escape(test.fully_qualified_name()),
+            location_fields(test)
         )
         .expect("Failed to write to output");
     }
@@ -67,6 +83,8 @@ impl TestRunnerOutput for Json {
             TestResult::Passed { .. } => Some("ok"),
             TestResult::Failed { .. } => Some("failed"),
             TestResult::Ignored { .. } => Some("ignored"),
+            TestResult::NotRun { .. } => Some("not_run"),
+            TestResult::Skipped { .. } => Some("skipped"),
             TestResult::Benchmarked { .. } => None,
         };
 
@@ -86,6 +104,15 @@ impl TestRunnerOutput for Json {
                 })
                 .collect::<Vec<_>>();
 
+            let reason = if result.is_ignored() {
+                test.props.ignore_reason.as_deref()
+            } else {
+                result.skip_reason()
+            };
+            let message = match reason {
+                Some(reason) => format!(r#", "message": "{}""#, escape8259::escape(reason)),
+                None => String::new(),
+            };
             let extra = match result.failure_message() {
                 Some(msg) => {
                     stdout_lines.push(format!("Error: {msg}"));
@@ -102,10 +129,15 @@ impl TestRunnerOutput for Json {
                     }
                 }
             };
+            let seed = match test.seed() {
+                Some(seed) => format!(r#", "seed": {seed}"#),
+                None => String::new(),
+            };
             writeln!(
                 out,
-                r#"{{ "type": "test", "event": "{event}", "name": "{}"{extra} }}"#,
-                escape8259::escape(test.fully_qualified_name())
+                r#"{{ "type": "test", "event": "{event}", "name": "{}"{}{seed}{message}{extra} }}"#,
+                escape8259::escape(test.fully_qualified_name()),
+                location_fields(test)
             )
             .expect("Failed to write to output");
         } else if let TestResult::Benchmarked {
@@ -135,31 +167,97 @@ impl TestRunnerOutput for Json {
         exec_time: Duration,
     ) {
         let mut out = self.target.lock().unwrap();
+        // Tests skipped by `--fail-fast` / `--max-fail` never reach
+        // `finished_running_test`, so their events are emitted here
+        for (test, _) in results.iter().filter(|(_, result)| result.is_not_run()) {
+            writeln!(
+                out,
+                r#"{{ "type": "test", "event": "not_run", "name": "{}"{} }}"#,
+                escape8259::escape(test.fully_qualified_name()),
+                location_fields(test)
+            )
+            .expect("Failed to write to output");
+        }
+        // With `--rerun-failed` the final outcome of a rerun test is only
+        // known once all the passes are done
+        for (test, _) in results.iter().filter(|(_, result)| result.is_flaky()) {
+            writeln!(
+                out,
+                r#"{{ "type": "test", "event": "flaky", "name": "{}"{} }}"#,
+                escape8259::escape(test.fully_qualified_name()),
+                location_fields(test)
+            )
+            .expect("Failed to write to output");
+        }
+
         let result = SuiteResult::from_test_results(registered_tests, results, exec_time);
         let event = if result.failed == 0 { "ok" } else { "failed" };
         let passed = result.passed;
         let failed = result.failed;
         let ignored = result.ignored;
         let measured = result.measured;
+        let not_run = result.not_run;
+        let skipped = result.skipped;
+        let flaky = result.flaky;
         let filtered_out = result.filtered_out;
         let exec_time = result.exec_time.as_secs_f64();
 
         writeln!(out,
-            r#"{{ "type": "suite", "event": "{event}", "passed": "{passed}", "failed": {failed}, "ignored": {ignored}, "measured": {measured}, "filtered_out": {filtered_out}, "exec_time": {exec_time} }}"#
+            r#"{{ "type": "suite", "event": "{event}", "passed": "{passed}", "failed": {failed}, "ignored": {ignored}, "measured": {measured}, "not_run": {not_run}, "skipped": {skipped}, "flaky": {flaky}, "filtered_out": {filtered_out}, "exec_time": {exec_time} }}"#
         ).expect("Failed to write to output");
     }
 
     fn test_list(&self, registered_tests: &[RegisteredTest]) {
         let mut out = self.target.lock().unwrap();
-        writeln!(out, r#"["#).expect("Failed to write to output");
+        writeln!(out, r#"{{ "type": "suite", "event": "discovery" }}"#)
+            .expect("Failed to write to output");
         for test in registered_tests {
+            let metadata = serde_json::Value::Object(test_metadata(test)).to_string();
             writeln!(
                 out,
-                r#""{}","#,
-                escape8259::escape(test.fully_qualified_name())
+                r#"{{ "type": "test", "event": "discovered", "name": "{}", {} }}"#,
+                escape8259::escape(test.fully_qualified_name()),
+                &metadata[1..metadata.len() - 1]
             )
             .expect("Failed to write to output");
         }
-        writeln!(out, r#"]"#).expect("Failed to write to output");
+        let benchmarks = registered_tests
+            .iter()
+            .filter(|test| test.run.is_bench())
+            .count();
+        let ignored = registered_tests
+            .iter()
+            .filter(|test| test.props.is_ignored)
+            .count();
+        writeln!(
+            out,
+            r#"{{ "type": "suite", "event": "completed", "tests": {}, "benchmarks": {benchmarks}, "total": {}, "ignored": {ignored} }}"#,
+            registered_tests.len() - benchmarks,
+            registered_tests.len(),
+        )
+        .expect("Failed to write to output");
+    }
+
+    fn dependency_event(&self, event: &DependencyEvent) {
+        let mut out = self.target.lock().unwrap();
+        let (event_name, extra) = match &event.kind {
+            DependencyEventKind::ConstructionStarted => ("started", String::new()),
+            DependencyEventKind::ConstructionFinished { duration, failed } => (
+                if *failed { "failed" } else { "constructed" },
+                format!(r#", "exec_time": {}"#, duration.as_secs_f64()),
+            ),
+            DependencyEventKind::Dropped { lifetime } => (
+                "dropped",
+                format!(r#", "lifetime": {}"#, lifetime.as_secs_f64()),
+            ),
+        };
+        writeln!(
+            out,
+            r#"{{ "type": "dependency", "event": "{event_name}", "name": "{}", "scope": "{}", "process": "{}"{extra} }}"#,
+            escape8259::escape(&event.dependency),
+            event.scope,
+            event.process,
+        )
+        .expect("Failed to write to output");
     }
 }
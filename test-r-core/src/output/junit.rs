@@ -105,8 +105,14 @@ impl JUnit {
                         .with_attribute(("id", "0"))
                         .with_attribute(("errors", "0"))
                         .with_attribute(("failures", result.failed.to_string().as_str()))
-                        .with_attribute(("tests", registered_tests.len().to_string().as_str()))
-                        .with_attribute(("skipped", result.ignored.to_string().as_str()))
+                        // Every result is a `<testcase>`, including the failed suite hooks
+                        .with_attribute(("tests", results.len().to_string().as_str()))
+                        .with_attribute((
+                            "skipped",
+                            (result.ignored + result.not_run + result.skipped)
+                                .to_string()
+                                .as_str(),
+                        ))
                         .with_attribute(("time", exec_time.as_secs_f64().to_string().as_str()))
                         .write_inner_content(|writer| {
                             for (test, result) in results {
@@ -117,15 +123,20 @@ impl JUnit {
                                     _ => test.crate_and_module(),
                                 };
 
+                                let location = location_attributes(test);
                                 let testcase = writer
                                     .create_element("testcase")
-                                    .with_attribute(("name", test.name.as_str()))
-                                    .with_attribute(("classname", classname.as_str()));
+                                    .with_attribute(("name", testcase_name(test)))
+                                    .with_attribute(("classname", classname.as_str()))
+                                    .with_attributes(
+                                        location.iter().map(|(key, value)| (*key, value.as_str())),
+                                    );
 
                                 match result {
                                     TestResult::Passed {
                                         exec_time,
                                         captured,
+                                        ..
                                     }
                                     | TestResult::Benchmarked {
                                         exec_time,
@@ -163,6 +174,22 @@ impl JUnit {
                                                 exec_time.as_secs_f64().to_string().as_str(),
                                             ))
                                             .write_inner_content(|writer| {
+                                                if let Some(seed) = test.seed() {
+                                                    writer
+                                                        .create_element("properties")
+                                                        .write_inner_content(|writer| {
+                                                            writer
+                                                                .create_element("property")
+                                                                .with_attribute(("name", "seed"))
+                                                                .with_attribute((
+                                                                    "value",
+                                                                    seed.to_string().as_str(),
+                                                                ))
+                                                                .write_empty()?;
+                                                            Ok::<(), std::io::Error>(())
+                                                        })?;
+                                                }
+
                                                 let mut failure = writer
                                                     .create_element("failure")
                                                     .with_attribute(("type", "assert"));
@@ -184,7 +211,35 @@ impl JUnit {
                                                 Ok::<(), std::io::Error>(())
                                             })?;
                                     }
-                                    TestResult::Ignored { .. } => {}
+                                    TestResult::Ignored { .. } => {
+                                        testcase.write_inner_content(|writer| {
+                                            let mut skipped = writer.create_element("skipped");
+                                            if let Some(reason) = &test.props.ignore_reason {
+                                                skipped = skipped
+                                                    .with_attribute(("message", reason.as_str()));
+                                            }
+                                            skipped.write_empty()?;
+                                            Ok::<(), std::io::Error>(())
+                                        })?;
+                                    }
+                                    TestResult::NotRun { .. } => {
+                                        testcase.write_inner_content(|writer| {
+                                            writer
+                                                .create_element("skipped")
+                                                .with_attribute(("message", "not run"))
+                                                .write_empty()?;
+                                            Ok::<(), std::io::Error>(())
+                                        })?;
+                                    }
+                                    TestResult::Skipped { reason, .. } => {
+                                        testcase.write_inner_content(|writer| {
+                                            writer
+                                                .create_element("skipped")
+                                                .with_attribute(("message", reason.as_str()))
+                                                .write_empty()?;
+                                            Ok::<(), std::io::Error>(())
+                                        })?;
+                                    }
                                 };
                             }
                             Ok::<(), std::io::Error>(())
@@ -268,10 +323,14 @@ impl TestRunnerOutput for JUnit {
                     .with_attribute(("id", "0"))
                     .write_inner_content(|writer| {
                         for test in registered_tests {
+                            let location = location_attributes(test);
                             writer
                                 .create_element("testcase")
-                                .with_attribute(("name", test.name.as_str()))
+                                .with_attribute(("name", testcase_name(test)))
                                 .with_attribute(("classname", test.crate_and_module().as_str()))
+                                .with_attributes(
+                                    location.iter().map(|(key, value)| (*key, value.as_str())),
+                                )
                                 .write_empty()?;
                         }
                         Ok::<(), std::io::Error>(())
@@ -282,6 +341,23 @@ impl TestRunnerOutput for JUnit {
     }
 }
 
+/// The name of a test case, the display name of the test if it has one, as the class name
+/// already contains the module path
+fn testcase_name(test: &RegisteredTest) -> &str {
+    test.props.display_name.as_deref().unwrap_or(&test.name)
+}
+
+/// The `file` and `line` attributes of a test case, if the test's location is known
+fn location_attributes(test: &RegisteredTest) -> Vec<(&'static str, String)> {
+    match &test.location {
+        Some(location) => vec![
+            ("file", location.file.clone()),
+            ("line", location.line.to_string()),
+        ],
+        None => Vec::new(),
+    }
+}
+
 struct IntermediateState {
     start: Instant,
     tests: Vec<RegisteredTest>,
@@ -5,11 +5,18 @@ mod junit;
 mod pretty;
 mod progress;
 mod term_progress;
+mod terminal;
 mod terse;
+mod tui;
 
 use crate::args::{Arguments, FormatSetting};
 use crate::host_capture::{TerminalStderr, TerminalStdout};
-use crate::internal::{RegisteredTest, TestResult};
+use crate::internal::{
+    CaptureControl, DependencyEvent, FlakinessControl, RegisteredTest, ShouldPanic, TestResult,
+    TestType,
+};
+use serde_json::json;
+use std::collections::HashSet;
 use std::io::{Seek, SeekFrom, Write};
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -46,6 +53,106 @@ pub trait TestRunnerOutput: Send + Sync {
         let mut err = TerminalStderr;
         let _ = writeln!(err, "{message}");
     }
+
+    /// Called before the suite starts when `--partition` is used, with the number of selected
+    /// tests that were assigned to other partitions
+    fn tests_in_other_partitions(&self, _count: usize) {}
+
+    /// Called when a dependency is constructed or dropped, in this process or in a worker
+    /// process. The dependencies of `Cloneable`, `Hosted` and `HostedRpc` scopes are constructed
+    /// before the suite starts.
+    fn dependency_event(&self, _event: &DependencyEvent) {}
+
+    /// Called when a run of the suite finished, before the dependencies are dropped. Interactive
+    /// outputs block until the user selects the tests to run again, returning their fully
+    /// qualified names. Returning `None` ends the session.
+    fn tests_to_rerun(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    /// True once the user aborted the session of an interactive output. No more tests are
+    /// started, and the runner exits with code 130 after the suite finished.
+    fn is_aborted(&self) -> bool {
+        false
+    }
+}
+
+/// Mentions the seed of a failed test taking a `&TestContext` or `&mut TestRng` parameter in
+/// the list of failed tests, so it can be rerun with the same seed using `--seed-for`
+fn seed_suffix(test: &RegisteredTest) -> String {
+    match test.seed() {
+        Some(seed) => format!(" [seed: {seed}]"),
+        None => String::new(),
+    }
+}
+
+/// Describes the properties of a test for the machine-readable `--list` outputs. The name of
+/// the test is not included, as the outputs report the fully qualified name already.
+fn test_metadata(test: &RegisteredTest) -> serde_json::Map<String, serde_json::Value> {
+    let props = &test.props;
+    let mut metadata = serde_json::Map::new();
+    metadata.insert("display_name".to_string(), json!(props.display_name));
+    metadata.insert("crate".to_string(), json!(test.crate_name));
+    metadata.insert("module_path".to_string(), json!(test.module_path));
+    metadata.insert(
+        "kind".to_string(),
+        json!(if test.run.is_bench() { "bench" } else { "test" }),
+    );
+    metadata.insert(
+        "test_type".to_string(),
+        json!(match props.test_type {
+            TestType::UnitTest => "unit",
+            TestType::IntegrationTest => "integration",
+        }),
+    );
+    metadata.insert("tags".to_string(), json!(props.tags));
+    metadata.insert("ignored".to_string(), json!(props.is_ignored));
+    metadata.insert("ignore_reason".to_string(), json!(props.ignore_reason));
+    metadata.insert(
+        "should_panic".to_string(),
+        match &props.should_panic {
+            ShouldPanic::No => json!(false),
+            ShouldPanic::Yes => json!(true),
+            ShouldPanic::WithMessage(message) => json!({ "expected": message }),
+        },
+    );
+    metadata.insert(
+        "timeout".to_string(),
+        json!(props.timeout.map(|timeout| timeout.as_secs_f64())),
+    );
+    metadata.insert(
+        "flakiness_control".to_string(),
+        match props.flakiness_control {
+            FlakinessControl::None => json!(null),
+            FlakinessControl::ProveNonFlaky(count) => {
+                json!({ "kind": "prove_non_flaky", "count": count })
+            }
+            FlakinessControl::RetryKnownFlaky(count) => {
+                json!({ "kind": "retry_known_flaky", "count": count })
+            }
+        },
+    );
+    metadata.insert(
+        "capture_control".to_string(),
+        json!(match props.capture_control {
+            CaptureControl::Default => "default",
+            CaptureControl::AlwaysCapture => "always_capture",
+            CaptureControl::NeverCapture => "never_capture",
+        }),
+    );
+    metadata.insert("dependencies".to_string(), json!(props.dependency_types));
+    let matrix_case: serde_json::Map<String, serde_json::Value> = props
+        .matrix_case
+        .iter()
+        .map(|(dimension, case)| (dimension.clone(), json!(case)))
+        .collect();
+    metadata.insert("matrix_case".to_string(), json!(matrix_case));
+    if let Some(location) = &test.location {
+        metadata.insert("file".to_string(), json!(location.file));
+        metadata.insert("line".to_string(), json!(location.line));
+        metadata.insert("column".to_string(), json!(location.column));
+    }
+    metadata
 }
 
 pub(crate) fn write_failure_summary_to_stderr(
@@ -67,15 +174,33 @@ pub(crate) fn write_failure_summary_to_stderr(
         .iter()
         .filter(|(_, result)| result.is_ignored())
         .count();
+    let not_run = match results
+        .iter()
+        .filter(|(_, result)| result.is_not_run())
+        .count()
+    {
+        0 => String::new(),
+        n => format!("{n} not run; "),
+    };
+    let skipped = match results
+        .iter()
+        .filter(|(_, result)| result.is_skipped())
+        .count()
+    {
+        0 => String::new(),
+        n => format!("{n} skipped; "),
+    };
 
     let mut err = TerminalStderr;
     let _ = writeln!(err);
     let _ = writeln!(
         err,
-        "test result: FAILED; {} passed; {} failed; {} ignored; finished in {:.3}s",
+        "test result: FAILED; {} passed; {} failed; {} ignored; {}{}finished in {:.3}s",
         passed,
         failed.len(),
         ignored,
+        not_run,
+        skipped,
         exec_time.as_secs_f64()
     );
     let _ = writeln!(err);
@@ -83,20 +208,49 @@ pub(crate) fn write_failure_summary_to_stderr(
     for (test, result) in &failed {
         let _ = writeln!(
             err,
-            " - {} ({})",
-            test.fully_qualified_name(),
+            " - {}{} ({})",
+            test.summary_name(),
+            seed_suffix(test),
             result.failure_message().as_deref().unwrap_or("???"),
         );
     }
     let _ = writeln!(err);
 }
 
+/// Waits for the user of an interactive output to select the tests to run again, returning
+/// `None` when the session ends
+pub(crate) fn interactive_rerun(
+    output: &Arc<dyn TestRunnerOutput>,
+    tests: &[RegisteredTest],
+) -> Option<Vec<RegisteredTest>> {
+    loop {
+        let names: HashSet<String> = output.tests_to_rerun()?.into_iter().collect();
+        let selected: Vec<RegisteredTest> = tests
+            .iter()
+            .filter(|test| names.contains(&test.fully_qualified_name()))
+            .cloned()
+            .collect();
+        if !selected.is_empty() {
+            return Some(selected);
+        }
+    }
+}
+
 pub fn test_runner_output(args: &Arguments) -> Arc<dyn TestRunnerOutput> {
     if args.ipc.is_some() {
         Arc::new(ipc::IpcWorkerOutput::new())
     } else if args.quiet {
         Arc::new(terse::Terse::new())
+    } else if args.tui && terminal::is_interactive() {
+        Arc::new(tui::Tui::new(args))
     } else {
+        if args.tui {
+            let mut err = TerminalStderr;
+            let _ = writeln!(
+                err,
+                "--tui requires an interactive terminal, falling back to the pretty output"
+            );
+        }
         let logfile = args.logfile.as_ref().map(PathBuf::from);
         match args.format.unwrap_or_default() {
             FormatSetting::Pretty => Arc::new(pretty::Pretty::new(
@@ -107,6 +261,7 @@ pub fn test_runner_output(args: &Arguments) -> Arc<dyn TestRunnerOutput> {
                 args.unit_test_threshold(),
                 args.integration_test_threshold(),
                 args.show_stats,
+                args.show_deps,
             )),
             FormatSetting::Terse => Arc::new(terse::Terse::new()),
             FormatSetting::Json => Arc::new(json::Json::new(args.show_output, logfile)),
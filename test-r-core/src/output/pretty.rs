@@ -1,9 +1,13 @@
 use crate::args::{ColorSetting, TimeThreshold};
 use crate::host_capture::{TerminalStderr, TerminalStdout};
-use crate::internal::{RegisteredTest, ReportTimeControl, SuiteResult, TestResult};
+use crate::internal::{
+    DependencyEvent, DependencyEventKind, RegisteredTest, ReportTimeControl, SuiteResult,
+    TestResult,
+};
 use crate::output::term_progress::TermProgress;
-use crate::output::{LogFile, TestRunnerOutput};
+use crate::output::{seed_suffix, LogFile, TestRunnerOutput};
 use anstyle::{AnsiColor, Style};
+use std::cmp::Reverse;
 use std::io::Write;
 use std::path::PathBuf;
 use std::sync::{Mutex, MutexGuard};
@@ -26,6 +30,7 @@ pub(crate) struct Pretty {
     unit_test_threshold: TimeThreshold,
     integ_test_threshold: TimeThreshold,
     show_stats: bool,
+    show_deps: bool,
 }
 
 struct PrettyImpl {
@@ -34,9 +39,13 @@ struct PrettyImpl {
     pub count: usize,
     pub index_field_length: usize,
     pub longest_name: usize,
+    pub in_other_partitions: Option<usize>,
+    /// The finished dependency constructions, listed at the end of the suite with `--show-deps`
+    pub dependency_constructions: Vec<DependencyEvent>,
 }
 
 impl Pretty {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         color: ColorSetting,
         show_output: bool,
@@ -45,6 +54,7 @@ impl Pretty {
         unit_test_threshold: TimeThreshold,
         integ_test_threshold: TimeThreshold,
         show_stats: bool,
+        show_deps: bool,
     ) -> Self {
         let logfile = logfile_path.map(|path| LogFile::new(path, false));
 
@@ -70,6 +80,8 @@ impl Pretty {
                 count: 0,
                 longest_name: 0,
                 index_field_length: 0,
+                in_other_partitions: None,
+                dependency_constructions: Vec::new(),
             }),
             term_progress: Mutex::new(TermProgress::new()),
             show_output,
@@ -77,6 +89,7 @@ impl Pretty {
             unit_test_threshold,
             integ_test_threshold,
             show_stats,
+            show_deps,
         }
     }
 
@@ -99,7 +112,7 @@ impl Pretty {
     ) {
         for (test, result) in results {
             if !result.captured_output().is_empty() {
-                writeln!(out, "---- {} stdout/err ----", test.fully_qualified_name()).unwrap();
+                writeln!(out, "---- {} stdout/err ----", test.display_name()).unwrap();
                 for line in result.captured_output() {
                     match line {
                         crate::internal::CapturedOutput::Stdout { line, .. } => {
@@ -207,12 +220,7 @@ impl Pretty {
 
     fn write_success_stats(&self, out: &mut PrettyImpl, results: &[(RegisteredTest, TestResult)]) {
         for (test, result) in results.iter().filter(|(_, result)| result.is_benchmarked()) {
-            writeln!(
-                out,
-                "---- {} benchmark stats ----",
-                test.fully_qualified_name()
-            )
-            .unwrap();
+            writeln!(out, "---- {} benchmark stats ----", test.display_name()).unwrap();
             if let Some(stats) = result.stats() {
                 writeln!(
                     out,
@@ -330,6 +338,35 @@ impl Pretty {
             writeln!(out).unwrap();
         }
     }
+
+    fn write_dependency_constructions(&self, out: &mut PrettyImpl) {
+        let mut constructions = std::mem::take(&mut out.dependency_constructions);
+        if constructions.is_empty() {
+            return;
+        }
+        constructions.sort_by_key(|event| match event.kind {
+            DependencyEventKind::ConstructionFinished { duration, .. } => Reverse(duration),
+            _ => Reverse(Duration::ZERO),
+        });
+        writeln!(out, "Dependency construction times:").unwrap();
+        for event in constructions {
+            if let DependencyEventKind::ConstructionFinished { duration, failed } = event.kind {
+                writeln!(
+                    out,
+                    " - {}{:.3}s{} {} ({} in {}){}",
+                    self.style_progress.render(),
+                    duration.as_secs_f64(),
+                    self.style_progress.render_reset(),
+                    event.dependency,
+                    event.scope,
+                    event.process,
+                    if failed { " [FAILED]" } else { "" },
+                )
+                .unwrap();
+            }
+        }
+        writeln!(out).unwrap();
+    }
 }
 
 impl Write for PrettyImpl {
@@ -406,7 +443,7 @@ impl TestRunnerOutput for Pretty {
         out.count = tests.len();
         out.longest_name = tests
             .iter()
-            .map(|test| test.fully_qualified_name().len())
+            .map(|test| test.display_name().len())
             .max()
             .unwrap_or(0);
         out.index_field_length = format!("{}/{}", out.count, out.count).len();
@@ -425,7 +462,7 @@ impl TestRunnerOutput for Pretty {
             padding,
             index_field,
             self.style_progress.render_reset(),
-            test.fully_qualified_name()
+            test.display_name()
         )
         .unwrap();
     }
@@ -442,7 +479,8 @@ impl TestRunnerOutput for Pretty {
         let mut out = self.lock.lock().unwrap();
         let index_field = format!("{}/{}", idx + 1, count);
         let padding = " ".repeat(out.index_field_length - index_field.len());
-        let result_padding = " ".repeat(out.longest_name - test.fully_qualified_name().len() + 1);
+        let result_padding =
+            " ".repeat(out.longest_name.saturating_sub(test.display_name().len()) + 1);
 
         writeln!(
             out,
@@ -451,7 +489,7 @@ impl TestRunnerOutput for Pretty {
             padding,
             index_field,
             self.style_progress.render_reset(),
-            test.fully_qualified_name(),
+            test.display_name(),
             reason,
             self.style_progress.render(),
             attempt,
@@ -520,8 +558,27 @@ impl TestRunnerOutput for Pretty {
                     )
                 }
             }
-            TestResult::Ignored { .. } => format!(
-                "[{}IGNORED{}]",
+            TestResult::Ignored { .. } => match &test.props.ignore_reason {
+                Some(reason) => format!(
+                    "{}ignored, {reason}{}",
+                    self.style_ignored.render(),
+                    self.style_ignored.render_reset()
+                ),
+                None => format!(
+                    "[{}IGNORED{}]",
+                    self.style_ignored.render(),
+                    self.style_ignored.render_reset()
+                ),
+            },
+            TestResult::NotRun { .. } => format!(
+                "[{}NOT RUN{}]",
+                self.style_ignored.render(),
+                self.style_ignored.render_reset()
+            ),
+            TestResult::Skipped { reason, .. } => format!(
+                "[{}SKIPPED{}]        {}({reason}){}",
+                self.style_ignored.render(),
+                self.style_ignored.render_reset(),
                 self.style_ignored.render(),
                 self.style_ignored.render_reset()
             ),
@@ -529,7 +586,8 @@ impl TestRunnerOutput for Pretty {
 
         let index_field = format!("{}/{}", idx + 1, count);
         let padding = " ".repeat(out.index_field_length - index_field.len());
-        let result_padding = " ".repeat(out.longest_name - test.fully_qualified_name().len() + 1);
+        let result_padding =
+            " ".repeat(out.longest_name.saturating_sub(test.display_name().len()) + 1);
 
         writeln!(
             out,
@@ -538,7 +596,7 @@ impl TestRunnerOutput for Pretty {
             padding,
             index_field,
             self.style_progress.render_reset(),
-            test.fully_qualified_name()
+            test.display_name()
         )
         .unwrap();
     }
@@ -562,6 +620,9 @@ impl TestRunnerOutput for Pretty {
             self.write_success_stats(&mut out, results);
         }
         self.write_failure_outputs(&mut out, results);
+        if self.show_deps {
+            self.write_dependency_constructions(&mut out);
+        }
 
         let overall = if result.failed == 0 {
             format!(
@@ -577,21 +638,77 @@ impl TestRunnerOutput for Pretty {
             )
         };
 
+        // Only mentioned when the run stopped early, skipped or reran tests,
+        // keeping the line identical to libtest's otherwise
+        let not_run = if result.not_run > 0 {
+            format!("{} not run; ", result.not_run)
+        } else {
+            String::new()
+        };
+        let skipped = if result.skipped > 0 {
+            format!("{} skipped; ", result.skipped)
+        } else {
+            String::new()
+        };
+        let flaky = if result.flaky > 0 {
+            format!("{} flaky; ", result.flaky)
+        } else {
+            String::new()
+        };
+        let (in_other_partitions, filtered_out) = match out.in_other_partitions {
+            Some(count) => (
+                format!("{count} in other partitions; "),
+                result.filtered_out.saturating_sub(count),
+            ),
+            None => (String::new(), result.filtered_out),
+        };
+
         writeln!(out).unwrap();
         writeln!(
             out,
-            "test result: {}; {} passed; {} failed; {} ignored; {} measured; {} filtered out; finished in {:.3}s",
-            overall, result.passed, result.failed, result.ignored, result.measured, result.filtered_out, result.exec_time.as_secs_f64()
+            "test result: {}; {} passed; {} failed; {} ignored; {} measured; {}{}{}{}{} filtered out; finished in {:.3}s",
+            overall, result.passed, result.failed, result.ignored, result.measured, not_run, skipped, flaky, in_other_partitions, filtered_out, result.exec_time.as_secs_f64()
         )
         .unwrap();
+        if result.not_run > 0 {
+            writeln!(
+                out,
+                "Stopped after {} failed test(s), {} test(s) were not run",
+                result.failed, result.not_run
+            )
+            .unwrap();
+        }
         writeln!(out).unwrap();
+        if result.skipped > 0 {
+            writeln!(out, "Skipped tests:").unwrap();
+            for (test, result) in results.iter().filter(|(_, result)| result.is_skipped()) {
+                writeln!(
+                    out,
+                    " - {} {}({}){}",
+                    test.summary_name(),
+                    self.style_ignored.render(),
+                    result.skip_reason().unwrap_or_default(),
+                    self.style_ignored.render_reset(),
+                )
+                .unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+        if result.flaky > 0 {
+            writeln!(out, "Flaky tests (passed when rerun):").unwrap();
+            for (test, _) in results.iter().filter(|(_, result)| result.is_flaky()) {
+                writeln!(out, " - {}", test.summary_name()).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
         if result.failed > 0 {
             writeln!(out, "Failed tests:").unwrap();
             for failed in results.iter().filter(|(_, result)| result.is_failed()) {
                 writeln!(
                     out,
-                    " - {} {}({}){}",
-                    failed.0.fully_qualified_name(),
+                    " - {}{} {}({}){}",
+                    failed.0.summary_name(),
+                    seed_suffix(&failed.0),
                     self.style_ignored.render(),
                     failed.1.failure_message().as_deref().unwrap_or("???"),
                     self.style_ignored.render_reset(),
@@ -612,6 +729,49 @@ impl TestRunnerOutput for Pretty {
         writeln!(out, "{} tests", registered_tests.len()).unwrap();
     }
 
+    fn tests_in_other_partitions(&self, count: usize) {
+        self.lock.lock().unwrap().in_other_partitions = Some(count);
+    }
+
+    fn dependency_event(&self, event: &DependencyEvent) {
+        if !self.show_deps {
+            return;
+        }
+        let mut out = self.lock.lock().unwrap();
+        let description = match &event.kind {
+            DependencyEventKind::ConstructionStarted => {
+                format!("Constructing dependency: {}", event.dependency)
+            }
+            DependencyEventKind::ConstructionFinished { duration, failed } => {
+                out.dependency_constructions.push(event.clone());
+                format!(
+                    "{} dependency: {} <{:.3}s>",
+                    if *failed {
+                        "Failed to construct"
+                    } else {
+                        "Constructed"
+                    },
+                    event.dependency,
+                    duration.as_secs_f64()
+                )
+            }
+            DependencyEventKind::Dropped { lifetime } => format!(
+                "Dropped dependency: {} after {:.3}s",
+                event.dependency,
+                lifetime.as_secs_f64()
+            ),
+        };
+        writeln!(
+            out,
+            "{}[deps]{} {description} ({} in {})",
+            self.style_progress.render(),
+            self.style_progress.render_reset(),
+            event.scope,
+            event.process,
+        )
+        .unwrap();
+    }
+
     fn warning(&self, message: &str) {
         let mut err = TerminalStderr;
         let _ = writeln!(
@@ -87,7 +87,7 @@ impl StderrProgress {
             idx + 1,
             count,
             style.render_reset(),
-            test.fully_qualified_name(),
+            test.display_name(),
         ));
     }
 
@@ -117,6 +117,14 @@ impl StderrProgress {
                 let s = Self::style_ignored();
                 format!("[{}IGNORED{}]", s.render(), s.render_reset())
             }
+            TestResult::NotRun { .. } => {
+                let s = Self::style_ignored();
+                format!("[{}NOT RUN{}]", s.render(), s.render_reset())
+            }
+            TestResult::Skipped { .. } => {
+                let s = Self::style_ignored();
+                format!("[{}SKIPPED{}]", s.render(), s.render_reset())
+            }
         };
 
         Self::write_line(&format!(
@@ -126,7 +134,7 @@ impl StderrProgress {
             idx + 1,
             count,
             progress.render_reset(),
-            test.fully_qualified_name(),
+            test.display_name(),
         ));
     }
 
@@ -0,0 +1,273 @@
+//! Raw keyboard input and screen size of the terminal, used by the interactive `--tui` mode.
+//!
+//! Only the standard input is switched to raw mode. The screen is drawn with plain ANSI escape
+//! sequences written to [`crate::host_capture::TerminalStdout`], like the other outputs.
+
+use std::io;
+use std::io::{IsTerminal, Read};
+
+/// A key pressed in the interactive mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Key {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    Escape,
+    /// Ctrl+C, which does not raise a signal in raw mode
+    Interrupt,
+    Char(char),
+}
+
+impl Key {
+    /// Parses the keys from a chunk of bytes read from the terminal. Escape sequences of keys
+    /// not used by the interactive mode are skipped.
+    pub fn parse(bytes: &[u8]) -> Vec<Key> {
+        let mut keys = Vec::new();
+        let mut idx = 0;
+        while idx < bytes.len() {
+            match bytes[idx] {
+                0x1b if matches!(bytes.get(idx + 1), Some(b'[') | Some(b'O')) => {
+                    // A CSI or SS3 sequence, terminated by a byte in the 0x40..=0x7e range
+                    let start = idx + 2;
+                    let Some(end) = bytes[start..]
+                        .iter()
+                        .position(|byte| (0x40..=0x7e).contains(byte))
+                        .map(|position| start + position)
+                    else {
+                        break;
+                    };
+                    let key = match (&bytes[start..end], bytes[end]) {
+                        (_, b'A') => Some(Key::Up),
+                        (_, b'B') => Some(Key::Down),
+                        (_, b'H') | (b"1", b'~') => Some(Key::Home),
+                        (_, b'F') | (b"4", b'~') => Some(Key::End),
+                        (b"5", b'~') => Some(Key::PageUp),
+                        (b"6", b'~') => Some(Key::PageDown),
+                        _ => None,
+                    };
+                    keys.extend(key);
+                    idx = end + 1;
+                }
+                0x1b => {
+                    keys.push(Key::Escape);
+                    idx += 1;
+                }
+                b'\r' | b'\n' => {
+                    keys.push(Key::Enter);
+                    idx += 1;
+                }
+                0x03 => {
+                    keys.push(Key::Interrupt);
+                    idx += 1;
+                }
+                byte => {
+                    if byte.is_ascii_graphic() || byte == b' ' {
+                        keys.push(Key::Char(byte as char));
+                    }
+                    idx += 1;
+                }
+            }
+        }
+        keys
+    }
+}
+
+/// Returns true if both the standard input and the terminal output are interactive terminals
+pub(crate) fn is_interactive() -> bool {
+    io::stdin().is_terminal() && crate::host_capture::terminal_stdout_is_terminal()
+}
+
+/// Reads the keys pressed on the standard input, blocking until there is some input. Returns
+/// `None` when the input is closed.
+pub(crate) fn read_keys() -> Option<Vec<Key>> {
+    let mut buffer = [0u8; 64];
+    match io::stdin().lock().read(&mut buffer) {
+        Ok(0) | Err(_) => None,
+        Ok(count) => Some(Key::parse(&buffer[..count])),
+    }
+}
+
+/// Size of the terminal as (columns, rows), falling back to 80x24 if it cannot be determined
+pub(crate) fn size() -> (usize, usize) {
+    imp::size().unwrap_or((80, 24))
+}
+
+/// Keeps the standard input in raw mode, reading every key press without echoing it, until
+/// dropped or restored
+pub(crate) struct RawMode {
+    original: imp::Mode,
+}
+
+impl RawMode {
+    pub fn enable() -> io::Result<Self> {
+        Ok(Self {
+            original: imp::enable_raw_mode()?,
+        })
+    }
+
+    /// Restores the original mode of the terminal. Also called on drop, but processes exiting
+    /// with `std::process::exit` have to call it explicitly.
+    pub fn restore(&self) {
+        imp::restore(&self.original);
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::io;
+    use std::mem::MaybeUninit;
+
+    pub type Mode = libc::termios;
+
+    pub fn enable_raw_mode() -> io::Result<Mode> {
+        let mut termios = MaybeUninit::<libc::termios>::uninit();
+        // SAFETY: tcgetattr fully initializes the termios struct when it succeeds
+        let original = unsafe {
+            if libc::tcgetattr(libc::STDIN_FILENO, termios.as_mut_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            termios.assume_init()
+        };
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+        raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        // SAFETY: raw is a valid termios struct
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(original)
+    }
+
+    pub fn restore(original: &Mode) {
+        // SAFETY: original is the valid termios struct returned by tcgetattr
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, original);
+        }
+    }
+
+    pub fn size() -> Option<(usize, usize)> {
+        let mut size = MaybeUninit::<libc::winsize>::zeroed();
+        // SAFETY: TIOCGWINSZ fills the winsize struct, which is zero-initialized otherwise.
+        // The standard input is queried, as the standard output may be redirected by the host
+        // output capture.
+        let size = unsafe {
+            if libc::ioctl(libc::STDIN_FILENO, libc::TIOCGWINSZ, size.as_mut_ptr()) != 0 {
+                return None;
+            }
+            size.assume_init()
+        };
+        (size.ws_col > 0 && size.ws_row > 0).then_some((size.ws_col as usize, size.ws_row as usize))
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::io;
+    use std::mem::MaybeUninit;
+    use windows_sys::Win32::System::Console::{
+        GetConsoleMode, GetConsoleScreenBufferInfo, GetStdHandle, SetConsoleMode, CONSOLE_MODE,
+        CONSOLE_SCREEN_BUFFER_INFO, ENABLE_ECHO_INPUT, ENABLE_LINE_INPUT, ENABLE_PROCESSED_INPUT,
+        ENABLE_VIRTUAL_TERMINAL_INPUT, STD_ERROR_HANDLE, STD_INPUT_HANDLE,
+    };
+
+    pub type Mode = CONSOLE_MODE;
+
+    pub fn enable_raw_mode() -> io::Result<Mode> {
+        let mut original: CONSOLE_MODE = 0;
+        // SAFETY: the handle returned by GetStdHandle is only passed to console functions,
+        // which fail on invalid handles
+        unsafe {
+            let input = GetStdHandle(STD_INPUT_HANDLE);
+            if GetConsoleMode(input, &mut original) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // With virtual terminal input the special keys arrive as the same escape
+            // sequences as on Unix terminals
+            let raw = (original
+                & !(ENABLE_LINE_INPUT | ENABLE_ECHO_INPUT | ENABLE_PROCESSED_INPUT))
+                | ENABLE_VIRTUAL_TERMINAL_INPUT;
+            if SetConsoleMode(input, raw) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(original)
+    }
+
+    pub fn restore(original: &Mode) {
+        // SAFETY: see enable_raw_mode
+        unsafe {
+            SetConsoleMode(GetStdHandle(STD_INPUT_HANDLE), *original);
+        }
+    }
+
+    pub fn size() -> Option<(usize, usize)> {
+        let mut info = MaybeUninit::<CONSOLE_SCREEN_BUFFER_INFO>::zeroed();
+        // SAFETY: GetConsoleScreenBufferInfo fills the struct when it succeeds
+        let info = unsafe {
+            if GetConsoleScreenBufferInfo(GetStdHandle(STD_ERROR_HANDLE), info.as_mut_ptr()) == 0 {
+                return None;
+            }
+            info.assume_init()
+        };
+        let columns = info.srWindow.Right - info.srWindow.Left + 1;
+        let rows = info.srWindow.Bottom - info.srWindow.Top + 1;
+        (columns > 0 && rows > 0).then_some((columns as usize, rows as usize))
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod imp {
+    use std::io;
+
+    pub type Mode = ();
+
+    pub fn enable_raw_mode() -> io::Result<Mode> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "raw terminal mode is not supported on this platform",
+        ))
+    }
+
+    pub fn restore(_original: &Mode) {}
+
+    pub fn size() -> Option<(usize, usize)> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Key;
+
+    #[test]
+    fn keys_and_escape_sequences_are_parsed() {
+        assert_eq!(
+            Key::parse(b"q\x1b[A\x1b[B\x1bOA\r\x1b[5~\x1b[6~\x03"),
+            vec![
+                Key::Char('q'),
+                Key::Up,
+                Key::Down,
+                Key::Up,
+                Key::Enter,
+                Key::PageUp,
+                Key::PageDown,
+                Key::Interrupt,
+            ]
+        );
+        assert_eq!(Key::parse(b"\x1b"), vec![Key::Escape]);
+        // Unknown sequences (here F5 and Delete) are skipped
+        assert_eq!(Key::parse(b"\x1b[15~\x1b[3~r"), vec![Key::Char('r')]);
+    }
+}
@@ -25,6 +25,7 @@ impl Terse {
                 TimeThreshold::default(),
                 TimeThreshold::default(),
                 false,
+                false,
             ),
             state: Arc::new(Mutex::new(TerseOutputState::default())),
         }
@@ -77,7 +78,7 @@ impl TestRunnerOutput for Terse {
                 &mut *out,
                 &format!(
                     "{} --- {}FAILED{}",
-                    test.fully_qualified_name(),
+                    test.display_name(),
                     self.pretty.style_failed.render(),
                     self.pretty.style_failed.render_reset()
                 ),
@@ -85,6 +86,12 @@ impl TestRunnerOutput for Terse {
             TestResult::Ignored { .. } => {
                 state.print_char(&mut *out, &self.pretty.style_ignored, '.')
             }
+            TestResult::NotRun { .. } => {
+                state.print_char(&mut *out, &self.pretty.style_ignored, 'n')
+            }
+            TestResult::Skipped { .. } => {
+                state.print_char(&mut *out, &self.pretty.style_ignored, 's')
+            }
         };
     }
 
@@ -106,6 +113,10 @@ impl TestRunnerOutput for Terse {
     fn test_list(&self, registered_tests: &[RegisteredTest]) {
         self.pretty.test_list(registered_tests)
     }
+
+    fn tests_in_other_partitions(&self, count: usize) {
+        self.pretty.tests_in_other_partitions(count)
+    }
 }
 
 #[derive(Debug, Default)]
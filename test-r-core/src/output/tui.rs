@@ -0,0 +1,985 @@
+//! Interactive terminal UI of the `--tui` mode.
+//!
+//! While the tests run, it shows the tree of the selected tests with their state, the running
+//! tests with their elapsed times and the number of passed and failed tests. Once a run finished,
+//! the failure cause and captured output of the finished tests can be viewed, and tests can be
+//! run again without restarting the test binary. The runner keeps the dependencies created for
+//! the first run, including the `Hosted` owners, alive for the reruns. Quitting the session
+//! prints the summary of the pretty output.
+
+use crate::args::Arguments;
+use crate::host_capture::TerminalStdout;
+use crate::internal::{CapturedOutput, RegisteredTest, TestResult};
+use crate::output::pretty::Pretty;
+use crate::output::term_progress::TermProgress;
+use crate::output::terminal::{self, Key, RawMode};
+use crate::output::TestRunnerOutput;
+use anstyle::{AnsiColor, Style};
+use std::collections::HashMap;
+use std::io::Write;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+const ENTER_SCREEN: &str = "\x1b[?1049h\x1b[?25l";
+const LEAVE_SCREEN: &str = "\x1b[?25h\x1b[?1049l";
+const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+/// Maximum number of running tests listed above the tree
+const MAX_RUNNING_LINES: usize = 5;
+
+pub(crate) struct Tui {
+    state: Arc<Mutex<State>>,
+    /// Tests selected to run again by the user, or the end of the session
+    reruns: Mutex<Receiver<Action>>,
+    rerun_sender: Sender<Action>,
+    ui_thread: Mutex<Option<JoinHandle<()>>>,
+    pretty: Pretty,
+}
+
+impl Tui {
+    pub fn new(args: &Arguments) -> Self {
+        let (rerun_sender, reruns) = channel();
+        Self {
+            state: Arc::new(Mutex::new(State::new())),
+            reruns: Mutex::new(reruns),
+            rerun_sender,
+            ui_thread: Mutex::new(None),
+            pretty: Pretty::new(
+                args.color.unwrap_or_default(),
+                args.show_output,
+                args.logfile.as_ref().map(PathBuf::from),
+                args.report_time,
+                args.unit_test_threshold(),
+                args.integration_test_threshold(),
+                args.show_stats,
+                false,
+            ),
+        }
+    }
+}
+
+impl TestRunnerOutput for Tui {
+    fn start_suite(&self, tests: &[RegisteredTest]) {
+        self.state.lock().unwrap().start_suite(tests);
+        let mut ui_thread = self.ui_thread.lock().unwrap();
+        if ui_thread.is_none() {
+            let state = self.state.clone();
+            let reruns = self.rerun_sender.clone();
+            *ui_thread = Some(std::thread::spawn(move || run_ui(state, reruns)));
+        }
+    }
+
+    fn start_running_test(&self, test: &RegisteredTest, _idx: usize, _count: usize) {
+        self.state
+            .lock()
+            .unwrap()
+            .set_status(test, Status::Running(Instant::now()), None);
+    }
+
+    fn repeat_running_test(
+        &self,
+        test: &RegisteredTest,
+        _idx: usize,
+        _count: usize,
+        _attempt: usize,
+        _max_attempts: usize,
+        _reason: &str,
+    ) {
+        self.state
+            .lock()
+            .unwrap()
+            .set_status(test, Status::Running(Instant::now()), None);
+    }
+
+    fn finished_running_test(
+        &self,
+        test: &RegisteredTest,
+        _idx: usize,
+        _count: usize,
+        result: &TestResult,
+    ) {
+        self.state
+            .lock()
+            .unwrap()
+            .set_status(test, Status::of(result), Some(result.clone()));
+    }
+
+    fn finished_suite(
+        &self,
+        registered_tests: &[RegisteredTest],
+        results: &[(RegisteredTest, TestResult)],
+        exec_time: Duration,
+    ) {
+        self.state.lock().unwrap().closed = true;
+        if let Some(ui_thread) = self.ui_thread.lock().unwrap().take() {
+            let _ = ui_thread.join();
+        }
+        self.pretty
+            .finished_suite(registered_tests, results, exec_time);
+    }
+
+    fn test_list(&self, registered_tests: &[RegisteredTest]) {
+        self.pretty.test_list(registered_tests)
+    }
+
+    fn warning(&self, message: &str) {
+        self.state.lock().unwrap().message = Some(message.to_string());
+    }
+
+    fn tests_in_other_partitions(&self, count: usize) {
+        self.pretty.tests_in_other_partitions(count)
+    }
+
+    fn tests_to_rerun(&self) -> Option<Vec<String>> {
+        if self.ui_thread.lock().unwrap().is_none() {
+            return None;
+        }
+        self.state.lock().unwrap().finish_run();
+        match self.reruns.lock().unwrap().recv() {
+            Ok(Action::Rerun(tests)) => Some(tests),
+            _ => None,
+        }
+    }
+
+    fn is_aborted(&self) -> bool {
+        self.state.lock().unwrap().aborted
+    }
+}
+
+/// Draws the screen and handles the keys until the session is closed by `finished_suite`
+fn run_ui(state: Arc<Mutex<State>>, reruns: Sender<Action>) {
+    let _raw_mode = RawMode::enable().ok();
+    let mut out = TerminalStdout;
+    let _ = write!(out, "{ENTER_SCREEN}");
+
+    let (keys_sender, keys) = channel();
+    std::thread::spawn(move || {
+        while let Some(pressed) = terminal::read_keys() {
+            if keys_sender.send(pressed).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut progress = TermProgress::new();
+    loop {
+        let pressed = match keys.recv_timeout(REFRESH_INTERVAL) {
+            Ok(pressed) => pressed,
+            Err(RecvTimeoutError::Timeout) => Vec::new(),
+            Err(RecvTimeoutError::Disconnected) => {
+                // The input was closed, so there is nobody to select tests to rerun
+                std::thread::sleep(REFRESH_INTERVAL);
+                vec![Key::Char('q')]
+            }
+        };
+        for key in pressed {
+            let action = state.lock().unwrap().handle_key(key);
+            // An abort ends the session once the running tests finished
+            if !matches!(action, Action::None) {
+                let _ = reruns.send(action);
+            }
+        }
+
+        let state = state.lock().unwrap();
+        if state.closed {
+            break;
+        }
+        let counts = state.counts();
+        if counts.failed > 0 {
+            progress.mark_failure();
+        }
+        progress.update(counts.finished(), state.tests.len());
+        let (width, height) = terminal::size();
+        let screen = state.render(width, height, Instant::now());
+        drop(state);
+        let _ = write!(out, "{screen}");
+        let _ = out.flush();
+    }
+
+    progress.clear();
+    let _ = write!(out, "{LEAVE_SCREEN}");
+    let _ = out.flush();
+}
+
+enum Action {
+    None,
+    Rerun(Vec<String>),
+    Quit,
+    Abort,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Status {
+    Pending,
+    Running(Instant),
+    Passed(Duration),
+    Benchmarked(Duration),
+    Failed(Duration),
+    Ignored,
+    Skipped,
+    NotRun,
+}
+
+impl Status {
+    fn of(result: &TestResult) -> Self {
+        match result {
+            TestResult::Passed { exec_time, .. } => Status::Passed(*exec_time),
+            TestResult::Benchmarked { exec_time, .. } => Status::Benchmarked(*exec_time),
+            TestResult::Failed { exec_time, .. } => Status::Failed(*exec_time),
+            TestResult::Ignored { .. } => Status::Ignored,
+            TestResult::NotRun { .. } => Status::NotRun,
+            TestResult::Skipped { .. } => Status::Skipped,
+        }
+    }
+
+    fn symbol(&self) -> char {
+        match self {
+            Status::Pending => '○',
+            Status::Running(_) => '●',
+            Status::Passed(_) | Status::Benchmarked(_) => '✓',
+            Status::Failed(_) => '✗',
+            Status::Ignored | Status::Skipped | Status::NotRun => '-',
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            Status::Pending => "pending",
+            Status::Running(_) => "running",
+            Status::Passed(_) => "passed",
+            Status::Benchmarked(_) => "benchmarked",
+            Status::Failed(_) => "FAILED",
+            Status::Ignored => "ignored",
+            Status::Skipped => "skipped",
+            Status::NotRun => "not run",
+        }
+    }
+
+    fn style(&self) -> Style {
+        match self {
+            Status::Pending => Style::new().dimmed(),
+            Status::Running(_) => Style::new().fg_color(Some(AnsiColor::Cyan.into())),
+            Status::Passed(_) | Status::Benchmarked(_) => {
+                Style::new().fg_color(Some(AnsiColor::Green.into()))
+            }
+            Status::Failed(_) => Style::new().bold().fg_color(Some(AnsiColor::Red.into())),
+            Status::Ignored | Status::Skipped | Status::NotRun => Style::new()
+                .dimmed()
+                .fg_color(Some(AnsiColor::Yellow.into())),
+        }
+    }
+
+    fn time(&self, now: Instant) -> Option<Duration> {
+        match self {
+            Status::Running(start) => Some(now.duration_since(*start)),
+            Status::Passed(time) | Status::Benchmarked(time) | Status::Failed(time) => Some(*time),
+            _ => None,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        !matches!(self, Status::Pending | Status::Running(_))
+    }
+}
+
+struct TestEntry {
+    /// Fully qualified name of the test
+    name: String,
+    /// The crate and module path of the test
+    path: Vec<String>,
+    label: String,
+    status: Status,
+    result: Option<TestResult>,
+    /// False for the entries not corresponding to a selected test, like failed hooks
+    rerunnable: bool,
+}
+
+impl TestEntry {
+    fn new(test: &RegisteredTest, rerunnable: bool) -> Self {
+        Self {
+            name: test.fully_qualified_name(),
+            path: test
+                .crate_and_module()
+                .split("::")
+                .map(|segment| segment.to_string())
+                .collect(),
+            label: test
+                .props
+                .display_name
+                .clone()
+                .unwrap_or_else(|| test.name.clone()),
+            status: Status::Pending,
+            result: None,
+            rerunnable,
+        }
+    }
+}
+
+enum Row {
+    Group {
+        depth: usize,
+        label: String,
+        tests: Range<usize>,
+    },
+    Test {
+        depth: usize,
+        test: usize,
+    },
+}
+
+enum View {
+    Tree,
+    Details { test: usize, scroll: usize },
+}
+
+#[derive(Default)]
+struct Counts {
+    passed: usize,
+    failed: usize,
+    ignored: usize,
+    running: usize,
+    pending: usize,
+}
+
+impl Counts {
+    fn finished(&self) -> usize {
+        self.passed + self.failed + self.ignored
+    }
+}
+
+struct State {
+    /// The tests ordered by their fully qualified names
+    tests: Vec<TestEntry>,
+    by_name: HashMap<String, usize>,
+    rows: Vec<Row>,
+    selected: usize,
+    scroll: usize,
+    view: View,
+    running: bool,
+    run_started: Instant,
+    run_time: Option<Duration>,
+    message: Option<String>,
+    /// Set by `finished_suite` to stop the UI thread
+    closed: bool,
+    /// Set when the user pressed Ctrl+C
+    aborted: bool,
+}
+
+impl State {
+    fn new() -> Self {
+        Self {
+            tests: Vec::new(),
+            by_name: HashMap::new(),
+            rows: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            view: View::Tree,
+            running: false,
+            run_started: Instant::now(),
+            run_time: None,
+            message: None,
+            closed: false,
+            aborted: false,
+        }
+    }
+
+    fn start_suite(&mut self, tests: &[RegisteredTest]) {
+        self.tests = tests
+            .iter()
+            .map(|test| TestEntry::new(test, true))
+            .collect();
+        self.rebuild();
+        self.running = true;
+        self.run_started = Instant::now();
+        self.run_time = None;
+    }
+
+    /// Sorts the tests and builds the rows of the tree from them
+    fn rebuild(&mut self) {
+        self.tests
+            .sort_by(|a, b| a.path.cmp(&b.path).then(a.name.cmp(&b.name)));
+        self.by_name = self
+            .tests
+            .iter()
+            .enumerate()
+            .map(|(idx, test)| (test.name.clone(), idx))
+            .collect();
+
+        self.rows.clear();
+        // The currently open groups, as (segment, row index)
+        let mut open: Vec<(String, usize)> = Vec::new();
+        for (idx, test) in self.tests.iter().enumerate() {
+            let common = open
+                .iter()
+                .zip(&test.path)
+                .take_while(|((open_segment, _), segment)| open_segment == *segment)
+                .count();
+            for (_, row) in open.drain(common..) {
+                close_group(&mut self.rows[row], idx);
+            }
+            for segment in &test.path[common..] {
+                self.rows.push(Row::Group {
+                    depth: open.len(),
+                    label: segment.clone(),
+                    tests: idx..idx,
+                });
+                open.push((segment.clone(), self.rows.len() - 1));
+            }
+            self.rows.push(Row::Test {
+                depth: open.len(),
+                test: idx,
+            });
+        }
+        for (_, row) in open {
+            close_group(&mut self.rows[row], self.tests.len());
+        }
+        self.selected = self.selected.min(self.rows.len().saturating_sub(1));
+    }
+
+    fn set_status(&mut self, test: &RegisteredTest, status: Status, result: Option<TestResult>) {
+        let name = test.fully_qualified_name();
+        if !self.by_name.contains_key(&name) {
+            self.tests.push(TestEntry::new(test, false));
+            self.rebuild();
+        }
+        let entry = &mut self.tests[self.by_name[&name]];
+        entry.status = status;
+        if result.is_some() {
+            entry.result = result;
+        }
+    }
+
+    /// Called when a run finished and the runner waits for the tests to rerun
+    fn finish_run(&mut self) {
+        self.running = false;
+        self.run_time = Some(self.run_started.elapsed());
+        // Tests not started because the run stopped early
+        for test in &mut self.tests {
+            if !test.status.is_finished() {
+                test.status = Status::NotRun;
+            }
+        }
+    }
+
+    fn counts(&self) -> Counts {
+        let mut counts = Counts::default();
+        for test in &self.tests {
+            match test.status {
+                Status::Pending => counts.pending += 1,
+                Status::Running(_) => counts.running += 1,
+                Status::Passed(_) | Status::Benchmarked(_) => counts.passed += 1,
+                Status::Failed(_) => counts.failed += 1,
+                Status::Ignored | Status::Skipped | Status::NotRun => counts.ignored += 1,
+            }
+        }
+        counts
+    }
+
+    fn handle_key(&mut self, key: Key) -> Action {
+        if self.aborted {
+            return Action::None;
+        }
+        if key == Key::Interrupt {
+            self.aborted = true;
+            self.message = Some("Aborting, waiting for the running tests to finish".to_string());
+            return Action::Abort;
+        }
+        self.message = None;
+        match self.view {
+            View::Tree => self.handle_tree_key(key),
+            View::Details { test, scroll } => match key {
+                Key::Up | Key::Char('k') => {
+                    self.view = View::Details {
+                        test,
+                        scroll: scroll.saturating_sub(1),
+                    };
+                    Action::None
+                }
+                Key::Down | Key::Char('j') => {
+                    self.view = View::Details {
+                        test,
+                        scroll: scroll + 1,
+                    };
+                    Action::None
+                }
+                Key::PageUp => {
+                    self.view = View::Details {
+                        test,
+                        scroll: scroll.saturating_sub(page_size()),
+                    };
+                    Action::None
+                }
+                Key::PageDown => {
+                    self.view = View::Details {
+                        test,
+                        scroll: scroll + page_size(),
+                    };
+                    Action::None
+                }
+                Key::Char('r') => self.rerun(vec![test]),
+                Key::Escape | Key::Enter | Key::Char('q') => {
+                    self.view = View::Tree;
+                    Action::None
+                }
+                _ => Action::None,
+            },
+        }
+    }
+
+    fn handle_tree_key(&mut self, key: Key) -> Action {
+        let last = self.rows.len().saturating_sub(1);
+        match key {
+            Key::Up | Key::Char('k') => self.selected = self.selected.saturating_sub(1),
+            Key::Down | Key::Char('j') => self.selected = (self.selected + 1).min(last),
+            Key::PageUp => self.selected = self.selected.saturating_sub(page_size()),
+            Key::PageDown => self.selected = (self.selected + page_size()).min(last),
+            Key::Home => self.selected = 0,
+            Key::End => self.selected = last,
+            Key::Char('n') => {
+                if let Some(row) =
+                    (self.selected + 1..self.rows.len()).find(|row| self.is_failed_row(*row))
+                {
+                    self.selected = row;
+                }
+            }
+            Key::Char('p') => {
+                if let Some(row) = (0..self.selected)
+                    .rev()
+                    .find(|row| self.is_failed_row(*row))
+                {
+                    self.selected = row;
+                }
+            }
+            Key::Enter => {
+                if let Some(Row::Test { test, .. }) = self.rows.get(self.selected) {
+                    if self.tests[*test].result.is_some() {
+                        self.view = View::Details {
+                            test: *test,
+                            scroll: 0,
+                        };
+                    }
+                }
+            }
+            Key::Char('r') => {
+                let tests = match self.rows.get(self.selected) {
+                    Some(Row::Group { tests, .. }) => tests.clone().collect(),
+                    Some(Row::Test { test, .. }) => vec![*test],
+                    None => Vec::new(),
+                };
+                return self.rerun(tests);
+            }
+            Key::Char('f') => {
+                let failed = (0..self.tests.len())
+                    .filter(|test| matches!(self.tests[*test].status, Status::Failed(_)))
+                    .collect();
+                return self.rerun(failed);
+            }
+            Key::Char('q') | Key::Escape => {
+                if self.running {
+                    self.message =
+                        Some("The tests are still running, press Ctrl+C to abort".to_string());
+                } else {
+                    return Action::Quit;
+                }
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn is_failed_row(&self, row: usize) -> bool {
+        matches!(&self.rows[row], Row::Test { test, .. } if matches!(self.tests[*test].status, Status::Failed(_)))
+    }
+
+    fn rerun(&mut self, tests: Vec<usize>) -> Action {
+        if self.running {
+            self.message = Some("Wait for the running tests to finish".to_string());
+            return Action::None;
+        }
+        let tests: Vec<usize> = tests
+            .into_iter()
+            .filter(|test| self.tests[*test].rerunnable)
+            .collect();
+        if tests.is_empty() {
+            self.message = Some("No tests to run again".to_string());
+            return Action::None;
+        }
+        for test in &tests {
+            self.tests[*test].status = Status::Pending;
+        }
+        self.running = true;
+        self.run_started = Instant::now();
+        self.run_time = None;
+        self.view = View::Tree;
+        Action::Rerun(
+            tests
+                .into_iter()
+                .map(|test| self.tests[test].name.clone())
+                .collect(),
+        )
+    }
+
+    fn render(&self, width: usize, height: usize, now: Instant) -> String {
+        let lines = match self.view {
+            View::Tree => self.render_tree(width, height, now),
+            View::Details { test, scroll } => self.render_details(test, scroll, width, height, now),
+        };
+        let mut screen = String::from("\x1b[H");
+        screen.push_str(&lines.join("\x1b[K\r\n"));
+        screen.push_str("\x1b[K\x1b[J");
+        screen
+    }
+
+    fn render_tree(&self, width: usize, height: usize, now: Instant) -> Vec<String> {
+        let counts = self.counts();
+        let time = self
+            .run_time
+            .unwrap_or_else(|| now.duration_since(self.run_started));
+        let mut lines = vec![styled(
+            Style::new().bold(),
+            &format!(
+                "test-r  {} passed; {} failed; {} ignored; {} running; {} pending  {:.1}s",
+                counts.passed,
+                counts.failed,
+                counts.ignored,
+                counts.running,
+                counts.pending,
+                time.as_secs_f64()
+            ),
+            width,
+            false,
+        )];
+
+        let running: Vec<&TestEntry> = self
+            .tests
+            .iter()
+            .filter(|test| matches!(test.status, Status::Running(_)))
+            .collect();
+        if !running.is_empty() {
+            lines.push(String::new());
+            for test in running.iter().take(MAX_RUNNING_LINES) {
+                let elapsed = test.status.time(now).unwrap_or_default();
+                lines.push(styled(
+                    test.status.style(),
+                    &format!(
+                        "{} {} {:.1}s",
+                        test.status.symbol(),
+                        test.name,
+                        elapsed.as_secs_f64()
+                    ),
+                    width,
+                    false,
+                ));
+            }
+            if running.len() > MAX_RUNNING_LINES {
+                lines.push(format!("  and {} more", running.len() - MAX_RUNNING_LINES));
+            }
+        }
+        lines.push(String::new());
+
+        let footer = self.footer(
+            width,
+            "↑/↓ select  n/p next/previous failure  enter details  r rerun  f rerun failures  q quit",
+        );
+        let viewport = height.saturating_sub(lines.len() + footer.len()).max(1);
+        let scroll = scroll_to_show(self.scroll, self.selected, viewport);
+        for (idx, row) in self.rows.iter().enumerate().skip(scroll).take(viewport) {
+            let selected = idx == self.selected;
+            let line = match row {
+                Row::Group {
+                    depth,
+                    label,
+                    tests,
+                } => {
+                    let failed = self.tests[tests.clone()]
+                        .iter()
+                        .filter(|test| matches!(test.status, Status::Failed(_)))
+                        .count();
+                    let (style, failures) = if failed > 0 {
+                        (
+                            Status::Failed(Duration::ZERO).style(),
+                            format!(" ({failed} failed)"),
+                        )
+                    } else {
+                        (Style::new().bold(), String::new())
+                    };
+                    styled(
+                        style,
+                        &format!("{}▾ {label}{failures}", "  ".repeat(*depth)),
+                        width,
+                        selected,
+                    )
+                }
+                Row::Test { depth, test } => {
+                    let test = &self.tests[*test];
+                    let time = match test.status.time(now) {
+                        Some(time) => format!(" {:.3}s", time.as_secs_f64()),
+                        None => String::new(),
+                    };
+                    styled(
+                        test.status.style(),
+                        &format!(
+                            "{}{} {}{time}",
+                            "  ".repeat(*depth),
+                            test.status.symbol(),
+                            test.label
+                        ),
+                        width,
+                        selected,
+                    )
+                }
+            };
+            lines.push(line);
+        }
+        lines.resize(height.saturating_sub(footer.len()), String::new());
+        lines.extend(footer);
+        lines
+    }
+
+    fn render_details(
+        &self,
+        test: usize,
+        scroll: usize,
+        width: usize,
+        height: usize,
+        now: Instant,
+    ) -> Vec<String> {
+        let test = &self.tests[test];
+        let mut lines = vec![styled(Style::new().bold(), &test.name, width, false)];
+        let status = match test.status.time(now) {
+            Some(time) => format!(
+                "{} in {:.3}s",
+                test.status.description(),
+                time.as_secs_f64()
+            ),
+            None => test.status.description().to_string(),
+        };
+        lines.push(styled(test.status.style(), &status, width, false));
+        lines.push(String::new());
+
+        let mut body = Vec::new();
+        if let Some(result) = &test.result {
+            if let Some(message) = result.failure_message() {
+                body.push(styled(Style::new().bold(), "Failure:", width, false));
+                body.extend(message.lines().map(|line| truncated(line, width)));
+                body.push(String::new());
+            }
+            if let Some(reason) = result.skip_reason() {
+                body.push(truncated(&format!("Skipped: {reason}"), width));
+                body.push(String::new());
+            }
+            body.push(styled(Style::new().bold(), "Output:", width, false));
+            if result.captured_output().is_empty() {
+                body.push(styled(
+                    Style::new().dimmed(),
+                    "(no captured output)",
+                    width,
+                    false,
+                ));
+            }
+            for captured in result.captured_output() {
+                body.push(match captured {
+                    CapturedOutput::Stdout { line, .. } => truncated(line, width),
+                    CapturedOutput::Stderr { line, .. } => styled(
+                        Style::new().fg_color(Some(AnsiColor::Yellow.into())),
+                        line,
+                        width,
+                        false,
+                    ),
+                    CapturedOutput::Host { line, .. } => styled(
+                        Style::new()
+                            .dimmed()
+                            .fg_color(Some(AnsiColor::BrightBlue.into())),
+                        &format!("[host] {line}"),
+                        width,
+                        false,
+                    ),
+                });
+            }
+        }
+
+        let footer = self.footer(width, "↑/↓ scroll  r rerun  esc back");
+        let viewport = height.saturating_sub(lines.len() + footer.len()).max(1);
+        let scroll = scroll.min(body.len().saturating_sub(viewport));
+        lines.extend(body.into_iter().skip(scroll).take(viewport));
+        lines.resize(height.saturating_sub(footer.len()), String::new());
+        lines.extend(footer);
+        lines
+    }
+
+    fn footer(&self, width: usize, keys: &str) -> Vec<String> {
+        let message = match (&self.message, self.running) {
+            (Some(message), _) => message.clone(),
+            (None, true) => "Running tests...".to_string(),
+            (None, false) => "Finished".to_string(),
+        };
+        vec![
+            styled(
+                Style::new().fg_color(Some(AnsiColor::Yellow.into())),
+                &message,
+                width,
+                false,
+            ),
+            styled(Style::new().dimmed(), keys, width, false),
+        ]
+    }
+}
+
+fn close_group(row: &mut Row, end: usize) {
+    if let Row::Group { tests, .. } = row {
+        tests.end = end;
+    }
+}
+
+/// Number of rows moved by Page Up and Page Down
+fn page_size() -> usize {
+    terminal::size().1.saturating_sub(8).max(1)
+}
+
+/// Returns the first visible row of a viewport of the given height, so that the selected row is
+/// visible while scrolling as little as possible from `scroll`
+fn scroll_to_show(scroll: usize, selected: usize, viewport: usize) -> usize {
+    if selected < scroll {
+        selected
+    } else if selected >= scroll + viewport {
+        selected + 1 - viewport
+    } else {
+        scroll
+    }
+}
+
+fn truncated(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// Renders a line with the given style, truncated to the width of the screen. Selected lines are
+/// inverted and padded to the full width.
+fn styled(style: Style, text: &str, width: usize, selected: bool) -> String {
+    let mut text = truncated(text, width);
+    let style = if selected {
+        let padding = width.saturating_sub(text.chars().count());
+        text.extend(std::iter::repeat_n(' ', padding));
+        style.invert()
+    } else {
+        style
+    };
+    format!("{}{text}{}", style.render(), style.render_reset())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::{make_test, FailureCause};
+
+    fn tree(state: &State) -> Vec<String> {
+        state
+            .rows
+            .iter()
+            .map(|row| match row {
+                Row::Group {
+                    depth,
+                    label,
+                    tests,
+                } => format!("{}{label} {tests:?}", "  ".repeat(*depth)),
+                Row::Test { depth, test } => {
+                    format!("{}{}", "  ".repeat(*depth), state.tests[*test].label)
+                }
+            })
+            .collect()
+    }
+
+    fn suite() -> (State, Vec<RegisteredTest>) {
+        let tests = vec![
+            make_test("test3", "b"),
+            make_test("test2", "a::inner"),
+            make_test("test1", "a"),
+            make_test("test4", "b"),
+        ];
+        let mut state = State::new();
+        state.start_suite(&tests);
+        (state, tests)
+    }
+
+    #[test]
+    fn tests_are_grouped_by_module() {
+        let (state, _) = suite();
+        assert_eq!(
+            tree(&state),
+            vec![
+                "mycrate 0..4",
+                "  a 0..2",
+                "    test1",
+                "    inner 1..2",
+                "      test2",
+                "  b 2..4",
+                "    test3",
+                "    test4",
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_tests_can_be_selected_and_rerun_once_the_run_finished() {
+        let (mut state, tests) = suite();
+        for test in &tests {
+            let result = if test.name == "test2" || test.name == "test4" {
+                TestResult::failed(Duration::ZERO, FailureCause::HarnessError("x".into()))
+            } else {
+                TestResult::passed(Duration::ZERO)
+            };
+            state.set_status(test, Status::of(&result), Some(result));
+        }
+
+        assert!(matches!(state.handle_key(Key::Char('f')), Action::None));
+        assert!(matches!(state.handle_key(Key::Char('q')), Action::None));
+        state.finish_run();
+
+        state.handle_key(Key::Char('n'));
+        assert_eq!(state.selected, 4);
+        state.handle_key(Key::Char('n'));
+        assert_eq!(state.selected, 7);
+        state.handle_key(Key::Char('p'));
+        assert_eq!(state.selected, 4);
+
+        state.handle_key(Key::Enter);
+        assert!(matches!(state.view, View::Details { test: 1, .. }));
+        match state.handle_key(Key::Char('r')) {
+            Action::Rerun(tests) => assert_eq!(tests, vec!["mycrate::a::inner::test2"]),
+            _ => panic!("expected a rerun"),
+        }
+        assert!(matches!(state.view, View::Tree));
+        state.finish_run();
+
+        match state.handle_key(Key::Char('f')) {
+            Action::Rerun(tests) => assert_eq!(tests, vec!["mycrate::b::test4"]),
+            _ => panic!("expected a rerun"),
+        }
+        state.finish_run();
+
+        state.handle_key(Key::Home);
+        state.handle_key(Key::Down);
+        match state.handle_key(Key::Char('r')) {
+            Action::Rerun(tests) => {
+                assert_eq!(tests, vec!["mycrate::a::test1", "mycrate::a::inner::test2"])
+            }
+            _ => panic!("expected a rerun"),
+        }
+        state.finish_run();
+
+        assert!(matches!(state.handle_key(Key::Char('q')), Action::Quit));
+    }
+
+    #[test]
+    fn aborting_ignores_the_keys_pressed_afterwards() {
+        let (mut state, _) = suite();
+        assert!(matches!(state.handle_key(Key::Interrupt), Action::Abort));
+        assert!(state.aborted);
+        assert!(matches!(state.handle_key(Key::Interrupt), Action::None));
+        state.finish_run();
+        assert!(matches!(state.handle_key(Key::Char('f')), Action::None));
+        assert!(matches!(state.handle_key(Key::Char('q')), Action::None));
+    }
+}
@@ -0,0 +1,87 @@
+//! Reproducible random seeds of the tests, configured with `--seed` and `--seed-for`.
+//!
+//! Every test gets its own seed derived from the seed of the run and the test's fully qualified
+//! name, so a test gets the same seed no matter which other tests run, in which order, or in
+//! which worker process. The attempt is deliberately not part of it: retrying a test does not
+//! change its seed, so the seed reported for a failure is the one that reproduces it.
+
+use crate::args::Arguments;
+use crate::context::TestContext;
+use crate::internal::stable_hash;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::OnceLock;
+
+static SEEDS: OnceLock<Seeds> = OnceLock::new();
+
+struct Seeds {
+    run_seed: u64,
+    overrides: HashMap<String, u64>,
+}
+
+/// Sets the seeds for the current process. Called by the test runner entry points at startup,
+/// the worker processes get the same arguments as their parent.
+pub(crate) fn set_seeds(args: &Arguments) {
+    let _ = SEEDS.set(Seeds {
+        run_seed: args.seed.unwrap_or_default(),
+        overrides: args
+            .seed_for
+            .iter()
+            .map(|seed_for| (seed_for.name.clone(), seed_for.seed))
+            .collect(),
+    });
+}
+
+/// Returns the seed of the test with the given fully qualified name
+pub(crate) fn test_seed(name: &str) -> u64 {
+    let (run_seed, overridden) = match SEEDS.get() {
+        Some(seeds) => (seeds.run_seed, seeds.overrides.get(name).copied()),
+        None => (0, None),
+    };
+    overridden.unwrap_or_else(|| stable_hash(&format!("{run_seed}:{name}")))
+}
+
+/// A random number generator seeded with the seed of the test. Test functions can get one by
+/// taking a `&mut TestRng` parameter, which is resolved by the `#[test]` macro just like a
+/// dependency.
+///
+/// It dereferences to [`StdRng`], so it can be used with the traits of the `rand` crate. The
+/// seed is printed when the test fails, and the failure can be reproduced by running the test
+/// with `--seed-for NAME=SEED`.
+#[derive(Debug)]
+pub struct TestRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl TestRng {
+    /// Creates a random number generator seeded with the seed of the test. Used by the code
+    /// generated by the `#[test]` macro.
+    pub fn from_context(context: &TestContext) -> Self {
+        Self {
+            seed: context.seed(),
+            rng: StdRng::seed_from_u64(context.seed()),
+        }
+    }
+
+    /// The seed the generator was created with
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Deref for TestRng {
+    type Target = StdRng;
+
+    fn deref(&self) -> &Self::Target {
+        &self.rng
+    }
+}
+
+impl DerefMut for TestRng {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.rng
+    }
+}
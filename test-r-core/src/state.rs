@@ -0,0 +1,21 @@
+//! Location of the state files test-r keeps between runs of a test binary.
+
+use std::path::PathBuf;
+
+/// `<target>/<profile>/test-r/<binary>.<extension>`, where test binaries built by cargo
+/// live in `<target>/<profile>/deps`
+pub(crate) fn state_file(extension: &str) -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    let exe_dir = exe.parent()?;
+    let profile_dir = if exe_dir.file_name().is_some_and(|name| name == "deps") {
+        exe_dir.parent()?
+    } else {
+        exe_dir
+    };
+    let binary_name = exe.file_stem()?.to_string_lossy();
+    Some(
+        profile_dir
+            .join("test-r")
+            .join(format!("{binary_name}.{extension}")),
+    )
+}
@@ -1,15 +1,26 @@
 use crate::args::{Arguments, TimeThreshold};
 use crate::bench::Bencher;
-use crate::execution::{DepWireBytes, TestExecution, TestSuiteExecution};
+use crate::context::{attempt_passed, TestContext, WithTestContext};
+use crate::environment::with_test_environment;
+use crate::execution::{
+    DepWireBytes, DependencyEvents, PendingTeardown, PerTestDependencies, TestExecution, TestGuard,
+    TestSuiteExecution,
+};
 use crate::internal;
 use crate::internal::{
-    generate_tests_sync, get_ensure_time, CapturedOutput, CloneableCodec, DepScope, FailureCause,
-    FlakinessControl, HostedRpcChannel, HostedRpcError, HostedRpcOwnerCell, HostedRpcTransport,
-    InProcessHostedRpcTransport, RegisteredDependency, RegisteredTest, RpcFactory, SuiteResult,
-    TestFunction, TestResult, WorkerReconstructor,
+    generate_tests_sync, get_ensure_time, get_in_process_timeout, get_worker_timeout,
+    merge_rerun_results, tests_to_rerun, CapturedOutput, CloneableCodec, DepScope,
+    DependencyFailure, FailureCause, FailureLimit, FlakinessControl, HookKind, HostedRpcChannel,
+    HostedRpcError, HostedRpcOwnerCell, HostedRpcTransport, InProcessHostedRpcTransport,
+    RegisteredDependency, RegisteredTest, RerunAttempt, RpcFactory, SuiteResult, TestFunction,
+    TestResult, WorkerCrash, WorkerReconstructor, WORKER_EXIT_TIMEOUT, WORKER_FINISH_TIMEOUT,
+    WORKER_OUTPUT_TIMEOUT,
+};
+use crate::ipc::{
+    ipc_name, read_frame, write_frame, FailedHook, HostedRpcReplyBody, IpcCommand, IpcResponse,
 };
-use crate::ipc::{ipc_name, read_frame, write_frame, HostedRpcReplyBody, IpcCommand, IpcResponse};
-use crate::output::{test_runner_output, TestRunnerOutput};
+use crate::limits::WorkerLimits;
+use crate::output::{interactive_rerun, test_runner_output, TestRunnerOutput};
 use desert_rust::{deserialize, serialize_to_byte_vec};
 use interprocess::local_socket::prelude::*;
 use interprocess::local_socket::{GenericNamespaced, ListenerOptions, Stream, ToNsName};
@@ -18,11 +29,12 @@ use std::collections::HashMap;
 use std::collections::VecDeque;
 use std::io::{BufRead, BufReader, Write};
 use std::panic::{catch_unwind, AssertUnwindSafe};
-use std::process::{Child, Command, ExitCode, Stdio};
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::process::{Child, Command, ExitCode, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread::{spawn, JoinHandle};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 pub fn test_runner() -> ExitCode {
@@ -34,6 +46,7 @@ pub fn test_runner() -> ExitCode {
     if let Some(idx) = args.worker_index {
         crate::worker::set_worker_index(idx);
     }
+    crate::seed::set_seeds(&args);
     // Host-side output capture is installed PER retry attempt (inside
     // the `while remaining_retries > 0` loop below), AFTER
     // `finalize_for_execution` has decided whether worker subprocesses
@@ -43,6 +56,7 @@ pub fn test_runner() -> ExitCode {
     // the host-capture cost. See `crate::host_capture` for the
     // pipeline.
     let output = test_runner_output(&args);
+    let dependency_events = DependencyEvents::new(&args, &output);
 
     let registered_tests = internal::REGISTERED_TESTS.lock().unwrap();
     let registered_dependency_constructors =
@@ -67,7 +81,23 @@ pub fn test_runner() -> ExitCode {
         output.test_list(&tests_with_props);
         ExitCode::SUCCESS
     } else {
-        let mut remaining_retries = args.flaky_run.unwrap_or(1);
+        // Matrix suites multiply their tests, so the summary counts these instead of `all_tests`
+        let tests_with_props =
+            internal::apply_suite_props_to_tests(&all_tests, &registered_testsuite_props);
+        if args.partition.is_some() && args.is_top_level_parent() {
+            output.tests_in_other_partitions(internal::tests_in_other_partitions(
+                &args,
+                &tests_with_props,
+            ));
+        }
+
+        if args.is_top_level_parent() {
+            if let Some(warning) = crate::limits::unsupported_limits_warning(&args, &all_tests) {
+                output.warning(&warning);
+            }
+        }
+
+        let mut remaining_retries = args.flaky_run_attempts();
         let mut exit_code = ExitCode::from(101);
 
         while remaining_retries > 0 {
@@ -77,6 +107,16 @@ pub fn test_runner() -> ExitCode {
                 &all_tests,
                 registered_testsuite_props.as_slice(),
             );
+            execution.set_dependency_events(&dependency_events);
+            if args.last_failed
+                && args.is_top_level_parent()
+                && !filtered_tests.is_empty()
+                && !args.has_last_failed(&filtered_tests)
+            {
+                output.warning(
+                    "None of the selected tests failed when last run, running all of them",
+                );
+            }
             args.finalize_for_execution(&execution, output.clone());
             // Install host-side capture for this attempt, NOW that
             // `finalize_for_execution` has decided whether workers
@@ -108,6 +148,9 @@ pub fn test_runner() -> ExitCode {
                     hosted_owners: Vec::new(),
                     hosted_rpc_owner_cells: Vec::new(),
                     parent_constructed_shared_values: Vec::new(),
+                    failed_dependencies: Vec::new(),
+                    teardowns: Vec::new(),
+                    constructed: Vec::new(),
                 }
             };
             let cloneable_wire_bytes = parent_shared.cloneable_wire_bytes;
@@ -117,6 +160,9 @@ pub fn test_runner() -> ExitCode {
             let hosted_rpc_owner_cells: HashMap<String, Arc<HostedRpcOwnerCell>> =
                 parent_shared.hosted_rpc_owner_cells.into_iter().collect();
             let parent_constructed_shared_values = parent_shared.parent_constructed_shared_values;
+            let failed_dependencies = Arc::new(parent_shared.failed_dependencies);
+            let parent_teardowns = parent_shared.teardowns;
+            let parent_constructed = parent_shared.constructed;
             // Build a Cloneable/Hosted codec/worker lookup table now, before
             // `test_thread` workers are spawned, so the test_thread workers
             // do not need to lock the global REGISTERED_DEPENDENCY_CONSTRUCTORS
@@ -132,70 +178,28 @@ pub fn test_runner() -> ExitCode {
             // REGISTERED_DEPENDENCY_CONSTRUCTORS.
             let rpc_factories: HashMap<String, RpcFactory> =
                 build_rpc_factories(&registered_dependency_constructors);
-            // Mode-consistent Cloneable semantics for the no-spawn-workers
-            // path: reuse the parent-constructed value directly instead of
-            // re-running the user constructor in `materialize_deps_sync`.
-            // Without this, a Cloneable dep's constructor would run twice
-            // (once for parent-side `collect_parent_shared_dependencies_sync`
-            // and once for the in-process test execution), which both
-            // violates the "constructor runs once" expectation and can
-            // deadlock when the constructor takes a runtime-wide lock.
-            if is_top_level_parent && !args.spawn_workers && !cloneable_local_values.is_empty() {
-                apply_cloneable_values_locally(&mut execution, &cloneable_local_values);
-            }
-            // Mode-consistent Hosted semantics: when this is the top-level
-            // parent AND we do NOT spawn workers (e.g. --nocapture, single
-            // process), the test functions run in this same process, but
-            // they must still see the *worker-side handle* produced by
-            // `HostedDep::from_descriptor` — not the raw owner value.
-            // Reconstruct each handle locally via the descriptor round-trip
-            // and pre-populate the execution tree, so `materialize_deps_sync`
-            // skips re-running the constructor.
-            if is_top_level_parent && !args.spawn_workers && !hosted_descriptor_bytes.is_empty() {
-                apply_hosted_descriptors_locally(
-                    &mut execution,
-                    &wire_codecs,
-                    &hosted_descriptor_bytes,
-                );
-            }
-            // Mode-consistent HostedRpc semantics for the no-spawn-workers
-            // path: install in-process stubs that route straight to the
-            // parent-held owner cells, so tests see the same `Stub` value
-            // whether or not the runner spawns workers.
-            if is_top_level_parent && !args.spawn_workers && !hosted_rpc_owner_cells.is_empty() {
-                install_local_hosted_rpc_stubs(
-                    &mut execution,
-                    &rpc_factories,
-                    &hosted_rpc_owner_cells,
-                );
-            }
-            // Mode-consistent `Shared`/`PerWorker` semantics for the
-            // no-spawn-workers path: any such dep that the parent had to
-            // construct as a transitive input to a Cloneable/Hosted/HostedRpc
-            // owner is pre-installed into the execution tree, so the
-            // in-process test thread's `materialize_deps_sync` reuses the
-            // parent value instead of running the constructor a second time
-            // in the same process. (In spawn-workers mode this list is
-            // unused: each worker process re-runs its own constructors.)
-            if is_top_level_parent
-                && !args.spawn_workers
-                && !parent_constructed_shared_values.is_empty()
-            {
-                apply_parent_constructed_shared_values_locally(
-                    &mut execution,
-                    &parent_constructed_shared_values,
-                );
-            }
-            if args.spawn_workers {
-                execution.skip_creating_dependencies();
-            }
+            let cloneable_wire_bytes = Arc::new(cloneable_wire_bytes);
+            let hosted_descriptor_bytes = Arc::new(hosted_descriptor_bytes);
+            let wire_codecs = Arc::new(wire_codecs);
+            let rpc_factories = Arc::new(rpc_factories);
+            let hosted_rpc_owner_cells = Arc::new(hosted_rpc_owner_cells);
+            prepare_execution(
+                &args,
+                &mut execution,
+                &wire_codecs,
+                &rpc_factories,
+                &cloneable_local_values,
+                &hosted_descriptor_bytes,
+                &hosted_rpc_owner_cells,
+                &parent_constructed_shared_values,
+                &failed_dependencies,
+            );
 
             // println!("Execution plan: {execution:?}");
             // println!("Final args: {args:?}");
             // println!("Has dependencies: {:?}", execution.has_dependencies());
 
-            let count = execution.remaining();
-            let mut results = Vec::with_capacity(count);
+            let mut results = Vec::with_capacity(execution.remaining());
             // Per-test execution windows captured in the parent. Used
             // after all test_threads join to attribute host-log records
             // (collected by `crate::host_capture`) to the test(s) whose
@@ -206,60 +210,71 @@ pub fn test_runner() -> ExitCode {
             let start = Instant::now();
             output.start_suite(&filtered_tests);
 
-            let execution = Arc::new(Mutex::new(execution));
-            let cloneable_wire_bytes = Arc::new(cloneable_wire_bytes);
-            let hosted_descriptor_bytes = Arc::new(hosted_descriptor_bytes);
-            let wire_codecs = Arc::new(wire_codecs);
-            let rpc_factories = Arc::new(rpc_factories);
-            let hosted_rpc_owner_cells = Arc::new(hosted_rpc_owner_cells);
-            let threads = args.test_threads().get();
-            let mut handles = Vec::with_capacity(threads);
-            for worker_idx in 0..threads {
-                let execution_clone = execution.clone();
-                let output_clone = output.clone();
-                // Stamp each test-thread's args with the worker index it will
-                // hand to its spawned child via `--worker-index <N>`. The
-                // parent process itself never observes this field (only
-                // children read it back through `worker::set_worker_index`).
-                let mut args_clone = args.clone();
-                if args_clone.spawn_workers {
-                    args_clone.worker_index = Some(worker_idx);
-                }
-                let wire_bytes_clone = cloneable_wire_bytes.clone();
-                let hosted_bytes_clone = hosted_descriptor_bytes.clone();
-                let codecs_clone = wire_codecs.clone();
-                let rpc_factories_clone = rpc_factories.clone();
-                let hosted_rpc_owner_cells_clone = hosted_rpc_owner_cells.clone();
-                handles.push(spawn(move || {
-                    test_thread(
-                        args_clone,
-                        execution_clone,
-                        output_clone,
-                        count,
-                        wire_bytes_clone,
-                        hosted_bytes_clone,
-                        codecs_clone,
-                        rpc_factories_clone,
-                        hosted_rpc_owner_cells_clone,
-                        host_capture_epoch,
-                    )
-                }));
-            }
-
-            for handle in handles {
-                let (thread_results, thread_windows) = handle.join().unwrap();
-                // Results and windows from a single thread are
-                // 1:1-aligned; preserve that as we merge them into the
-                // suite-wide vecs. The window vec stays the same length
-                // as `results` so an absolute test index in `results`
-                // also indexes into `host_windows`.
-                debug_assert_eq!(thread_results.len(), thread_windows.len());
-                results.extend(thread_results);
-                host_windows.extend(thread_windows);
+            // With `--rerun-failed`, the tests failed in a pass are run again
+            // in a new pass until they pass or the attempts run out. The
+            // parent-side dependencies (including the Hosted owners) are
+            // created once and shared by all the passes, including the
+            // passes of the tests selected to run again with `--tui`.
+            let max_attempts = args.max_rerun_attempts();
+            let mut pass_args = args.clone();
+            let mut rerun_attempt: Option<RerunAttempt> = None;
+            loop {
+                let pass = run_pass(
+                    &pass_args,
+                    execution,
+                    &output,
+                    rerun_attempt,
+                    &cloneable_wire_bytes,
+                    &hosted_descriptor_bytes,
+                    &failed_dependencies,
+                    &wire_codecs,
+                    &rpc_factories,
+                    &hosted_rpc_owner_cells,
+                    host_capture_epoch,
+                );
+                let failed = tests_to_rerun(&pass.results);
+                results.extend(pass.results);
+                host_windows.extend(pass.host_windows);
+
+                let attempt = rerun_attempt.map_or(1, |rerun| rerun.attempt) + 1;
+                let rerun = if failed.is_empty() || pass.stopped_early || attempt > max_attempts {
+                    match interactive_rerun(&output, &filtered_tests) {
+                        Some(selected) => {
+                            rerun_attempt = None;
+                            selected
+                        }
+                        None => break,
+                    }
+                } else {
+                    rerun_attempt = Some(RerunAttempt {
+                        attempt,
+                        max_attempts,
+                    });
+                    failed
+                };
+                pass_args = args.for_rerun(&rerun);
+                (execution, _) = TestSuiteExecution::construct(
+                    &pass_args,
+                    registered_dependency_constructors.as_slice(),
+                    &all_tests,
+                    registered_testsuite_props.as_slice(),
+                );
+                execution.set_dependency_events(&dependency_events);
+                execution.record_results(&results);
+                pass_args.finalize_for_execution(&execution, output.clone());
+                prepare_execution(
+                    &pass_args,
+                    &mut execution,
+                    &wire_codecs,
+                    &rpc_factories,
+                    &cloneable_local_values,
+                    &hosted_descriptor_bytes,
+                    &hosted_rpc_owner_cells,
+                    &parent_constructed_shared_values,
+                    &failed_dependencies,
+                );
             }
 
-            drop(execution);
-
             // Drop the parent-side Hosted/HostedRpc owners BEFORE
             // finalising host capture so any `Drop` impls that
             // shutdown background threads / subprocesses get a chance
@@ -267,8 +282,16 @@ pub fn test_runner() -> ExitCode {
             // capture pipe. If we restored fd 1/2 first, those late
             // lines would either land on the about-to-render
             // structured output or be swallowed entirely.
+            for teardown in parent_teardowns.into_iter().rev() {
+                if let Some(warning) = teardown.run_sync() {
+                    output.warning(&warning);
+                }
+            }
             drop(hosted_rpc_owner_cells);
             drop(_hosted_owners);
+            for constructed in parent_constructed.into_iter().rev() {
+                dependency_events.dropped(constructed);
+            }
 
             // Finalise host capture (if any) BEFORE rendering the
             // suite. This stops the reader thread, drains the spill
@@ -285,17 +308,221 @@ pub fn test_runner() -> ExitCode {
                     &mut results,
                 );
             }
+            let results = merge_rerun_results(results);
 
-            output.finished_suite(&all_tests, &results, start.elapsed());
+            output.finished_suite(&tests_with_props, &results, start.elapsed());
             exit_code = SuiteResult::exit_code(&results);
-
-            if exit_code == ExitCode::SUCCESS {
+            remaining_retries -= 1;
+
+            if exit_code == ExitCode::SUCCESS || remaining_retries == 0 || output.is_aborted() {
+                // Recorded only after the final attempt, so the worker processes
+                // of every attempt see the same list of failed tests as the parent
+                if args.is_top_level_parent() {
+                    crate::last_failed::update(&results);
+                    crate::timings::update(&results);
+                }
                 break;
-            } else {
-                remaining_retries -= 1;
             }
         }
-        exit_code
+        if output.is_aborted() {
+            ExitCode::from(130)
+        } else {
+            exit_code
+        }
+    }
+}
+
+/// Prepares the execution plan of a pass for running in this process or for driving
+/// worker processes, reusing the dependencies the parent has already created.
+#[allow(clippy::too_many_arguments)]
+fn prepare_execution(
+    args: &Arguments,
+    execution: &mut TestSuiteExecution,
+    wire_codecs: &HashMap<String, (CloneableCodec, WorkerReconstructor)>,
+    rpc_factories: &HashMap<String, RpcFactory>,
+    cloneable_local_values: &[(String, Arc<dyn Any + Send + Sync>)],
+    hosted_descriptor_bytes: &[DepWireBytes],
+    hosted_rpc_owner_cells: &HashMap<String, Arc<HostedRpcOwnerCell>>,
+    parent_constructed_shared_values: &[(String, Arc<dyn Any + Send + Sync>)],
+    failed_dependencies: &[(String, DependencyFailure)],
+) {
+    let is_top_level_parent = args.is_top_level_parent();
+    // Mode-consistent Cloneable semantics for the no-spawn-workers
+    // path: reuse the parent-constructed value directly instead of
+    // re-running the user constructor in `materialize_deps_sync`.
+    // Without this, a Cloneable dep's constructor would run twice
+    // (once for parent-side `collect_parent_shared_dependencies_sync`
+    // and once for the in-process test execution), which both
+    // violates the "constructor runs once" expectation and can
+    // deadlock when the constructor takes a runtime-wide lock.
+    if is_top_level_parent && !args.spawn_workers && !cloneable_local_values.is_empty() {
+        apply_cloneable_values_locally(execution, cloneable_local_values);
+    }
+    // Mode-consistent Hosted semantics: when this is the top-level
+    // parent AND we do NOT spawn workers (e.g. --nocapture, single
+    // process), the test functions run in this same process, but
+    // they must still see the *worker-side handle* produced by
+    // `HostedDep::from_descriptor` — not the raw owner value.
+    // Reconstruct each handle locally via the descriptor round-trip
+    // and pre-populate the execution tree, so `materialize_deps_sync`
+    // skips re-running the constructor.
+    if is_top_level_parent && !args.spawn_workers && !hosted_descriptor_bytes.is_empty() {
+        apply_hosted_descriptors_locally(execution, wire_codecs, hosted_descriptor_bytes);
+    }
+    // Mode-consistent HostedRpc semantics for the no-spawn-workers
+    // path: install in-process stubs that route straight to the
+    // parent-held owner cells, so tests see the same `Stub` value
+    // whether or not the runner spawns workers.
+    if is_top_level_parent && !args.spawn_workers && !hosted_rpc_owner_cells.is_empty() {
+        install_local_hosted_rpc_stubs(execution, rpc_factories, hosted_rpc_owner_cells);
+    }
+    // Mode-consistent `Shared`/`PerWorker` semantics for the
+    // no-spawn-workers path: any such dep that the parent had to
+    // construct as a transitive input to a Cloneable/Hosted/HostedRpc
+    // owner is pre-installed into the execution tree, so the
+    // in-process test thread's `materialize_deps_sync` reuses the
+    // parent value instead of running the constructor a second time
+    // in the same process. (In spawn-workers mode this list is
+    // unused: each worker process re-runs its own constructors.)
+    if is_top_level_parent && !args.spawn_workers && !parent_constructed_shared_values.is_empty() {
+        apply_parent_constructed_shared_values_locally(execution, parent_constructed_shared_values);
+    }
+    // The tests using a dep whose constructor failed in the parent fail
+    // without being run. Worker processes get the failures over IPC instead.
+    if is_top_level_parent && !args.spawn_workers {
+        apply_failed_dependencies_locally(execution, failed_dependencies);
+    }
+    if args.spawn_workers {
+        execution.skip_creating_dependencies();
+    } else {
+        // The worker processes run a single test at a time, but in this process the tests
+        // changing the process-global environment must not overlap with any other test
+        execution.run_environment_changes_exclusively();
+    }
+}
+
+/// The results of a single pass over the selected tests.
+struct PassOutcome {
+    results: Vec<(RegisteredTest, TestResult)>,
+    /// 1:1 with `results`
+    host_windows: Vec<crate::host_capture::HostWindow>,
+    stopped_early: bool,
+}
+
+/// Runs all the tests of a prepared execution plan on `--test-threads` test threads.
+#[allow(clippy::too_many_arguments)]
+fn run_pass(
+    args: &Arguments,
+    execution: TestSuiteExecution,
+    output: &Arc<dyn TestRunnerOutput>,
+    rerun_attempt: Option<RerunAttempt>,
+    cloneable_wire_bytes: &Arc<Vec<DepWireBytes>>,
+    hosted_descriptor_bytes: &Arc<Vec<DepWireBytes>>,
+    failed_dependencies: &Arc<Vec<(String, DependencyFailure)>>,
+    wire_codecs: &Arc<HashMap<String, (CloneableCodec, WorkerReconstructor)>>,
+    rpc_factories: &Arc<HashMap<String, RpcFactory>>,
+    hosted_rpc_owner_cells: &Arc<HashMap<String, Arc<HostedRpcOwnerCell>>>,
+    host_capture_epoch: Option<Instant>,
+) -> PassOutcome {
+    let count = execution.remaining();
+    let mut results = Vec::with_capacity(count);
+    let mut host_windows = Vec::with_capacity(count);
+
+    let execution = Arc::new(Mutex::new(execution));
+    let failure_limit = Arc::new(FailureLimit::new(args));
+    let threads = args.test_threads().get();
+    let mut handles = Vec::with_capacity(threads);
+    for worker_idx in 0..threads {
+        let execution_clone = execution.clone();
+        let output_clone = output.clone();
+        // Stamp each test-thread's args with the worker index it will
+        // hand to its spawned child via `--worker-index <N>`. The
+        // parent process itself never observes this field (only
+        // children read it back through `worker::set_worker_index`).
+        let mut args_clone = args.clone();
+        if args_clone.spawn_workers {
+            args_clone.worker_index = Some(worker_idx);
+        }
+        let wire_bytes_clone = cloneable_wire_bytes.clone();
+        let hosted_bytes_clone = hosted_descriptor_bytes.clone();
+        let failed_dependencies_clone = failed_dependencies.clone();
+        let codecs_clone = wire_codecs.clone();
+        let rpc_factories_clone = rpc_factories.clone();
+        let hosted_rpc_owner_cells_clone = hosted_rpc_owner_cells.clone();
+        let failure_limit_clone = failure_limit.clone();
+        handles.push(spawn(move || {
+            test_thread(
+                args_clone,
+                execution_clone,
+                output_clone,
+                failure_limit_clone,
+                rerun_attempt,
+                count,
+                wire_bytes_clone,
+                hosted_bytes_clone,
+                failed_dependencies_clone,
+                codecs_clone,
+                rpc_factories_clone,
+                hosted_rpc_owner_cells_clone,
+                host_capture_epoch,
+            )
+        }));
+    }
+
+    for handle in handles {
+        let (thread_results, thread_windows) = handle.join().unwrap();
+        // Results and windows from a single thread are
+        // 1:1-aligned; preserve that as we merge them into the
+        // suite-wide vecs. The window vec stays the same length
+        // as `results` so an absolute test index in `results`
+        // also indexes into `host_windows`.
+        debug_assert_eq!(thread_results.len(), thread_windows.len());
+        results.extend(thread_results);
+        host_windows.extend(thread_windows);
+    }
+
+    // Worker processes run the `after_all` hooks when the test threads
+    // retire them
+    if !args.spawn_workers {
+        let failed_hooks = run_after_all_hooks(output, &execution, count);
+        report_failed_hooks(output, &failed_hooks, count.saturating_sub(1), count);
+        host_windows.extend(
+            failed_hooks
+                .iter()
+                .map(|_| crate::host_capture::HostWindow {
+                    start: Duration::ZERO,
+                    end: Duration::ZERO,
+                }),
+        );
+        results.extend(failed_hooks);
+        for warning in run_teardowns(&execution) {
+            output.warning(&warning);
+        }
+    }
+
+    // Tests left in the plan were not scheduled because the failure
+    // limit was reached. They are added to the results of this pass, so
+    // running them in a later pass (with `--tui`) replaces the not run
+    // result, and they get empty execution windows.
+    let not_run_tests = execution.lock().unwrap().take_remaining_tests();
+    host_windows.extend(
+        not_run_tests
+            .iter()
+            .map(|_| crate::host_capture::HostWindow {
+                start: Duration::ZERO,
+                end: Duration::ZERO,
+            }),
+    );
+    results.extend(
+        not_run_tests
+            .into_iter()
+            .map(|test| (test, TestResult::not_run())),
+    );
+
+    PassOutcome {
+        results,
+        host_windows,
+        stopped_early: failure_limit.is_reached() || output.is_aborted(),
     }
 }
 
@@ -304,9 +531,12 @@ fn test_thread(
     args: Arguments,
     execution: Arc<Mutex<TestSuiteExecution>>,
     output: Arc<dyn TestRunnerOutput>,
+    failure_limit: Arc<FailureLimit>,
+    rerun_attempt: Option<RerunAttempt>,
     count: usize,
     cloneable_wire_bytes: Arc<Vec<DepWireBytes>>,
     hosted_descriptor_bytes: Arc<Vec<DepWireBytes>>,
+    failed_dependencies: Arc<Vec<(String, DependencyFailure)>>,
     wire_codecs: Arc<HashMap<String, (CloneableCodec, WorkerReconstructor)>>,
     rpc_factories: Arc<HashMap<String, RpcFactory>>,
     hosted_rpc_owner_cells: Arc<HashMap<String, Arc<HostedRpcOwnerCell>>>,
@@ -315,14 +545,14 @@ fn test_thread(
     Vec<(RegisteredTest, TestResult)>,
     Vec<crate::host_capture::HostWindow>,
 ) {
-    let mut worker = spawn_worker_if_needed(&args);
-    // Parent dispatches incoming `HostedRpcCall` frames against the owner
-    // cells materialised in the top-level parent. Workers don't need the owner
-    // cells (they own stubs instead), so they receive an empty map and the
-    // dispatch code path is never reached in subprocesses.
-    if let Some(worker) = worker.as_mut() {
-        worker.set_hosted_rpc_owner_cells(hosted_rpc_owner_cells.clone());
-    }
+    let worker_factory = WorkerFactory {
+        args: args.clone(),
+        cloneable_wire_bytes,
+        hosted_descriptor_bytes,
+        failed_dependencies,
+        hosted_rpc_owner_cells,
+    };
+    let mut worker = worker_factory.spawn_if_needed(WorkerLimits::new(&args, None));
     let connection_arc = if let Some(ref name) = args.ipc {
         let name = ipc_name(name.clone());
         let stream = Stream::connect(name).expect("Failed to connect to IPC socket");
@@ -331,24 +561,6 @@ fn test_thread(
         None
     };
 
-    // If we own a worker (parent side), eagerly ship every Cloneable wire
-    // payload to it. Workers stash them into the execution tree as
-    // pre-materialised values so the original constructor never runs on the
-    // worker side. The `dep_id` carried across the wire is the dep's
-    // fully-qualified id, not its local `name`, so same-named deps in different
-    // modules don't collide.
-    if let Some(worker) = worker.as_mut() {
-        for (dep_id, wire_bytes) in cloneable_wire_bytes.iter() {
-            worker.provide_cloneable(dep_id.clone(), wire_bytes.clone());
-        }
-        // Ship every Hosted dep's descriptor bytes too. Workers run the
-        // registered worker_fn (HostedDep::from_descriptor) to build a
-        // per-worker handle pointing at the parent-held owner.
-        for (dep_id, descriptor_bytes) in hosted_descriptor_bytes.iter() {
-            worker.provide_hosted_descriptor(dep_id.clone(), descriptor_bytes.clone());
-        }
-    }
-
     // Worker subprocess side: build a stub for every HostedRpc dep registered
     // in this binary using the IPC-backed transport sharing the same socket as
     // the main IPC loop. Install the stubs in the execution tree so
@@ -370,8 +582,14 @@ fn test_thread(
     // can map host-log records onto the right test(s).
     let mut host_windows: Vec<crate::host_capture::HostWindow> = Vec::with_capacity(count);
     let mut expected_test = None;
-
-    while !is_done(&execution) {
+    let availability_changes = execution.lock().unwrap().availability_changes();
+
+    // Worker processes keep serving commands until the parent retires them,
+    // as it may still ask them to run the `after_all` hooks
+    'serving: while (connection_arc.is_some() || !is_done(&execution))
+        && !failure_limit.is_reached()
+        && !output.is_aborted()
+    {
         if let Some(connection) = connection_arc.as_ref() {
             while expected_test.is_none() {
                 let command_bytes = {
@@ -428,6 +646,20 @@ fn test_thread(
                         let mut conn = connection.lock().unwrap();
                         write_frame(&mut *conn, &msg).expect("Failed to write IPC response frame");
                     }
+                    IpcCommand::ProvideFailedDependency { dep_id, message } => {
+                        let failure = DependencyFailure {
+                            dependency: dep_id.clone(),
+                            message,
+                        };
+                        let applied = execution
+                            .lock()
+                            .unwrap()
+                            .provide_failed_dependency(&dep_id, failure);
+                        assert!(
+                            applied,
+                            "ProvideFailedDependency for dep '{dep_id}' did not match any registered dep in this worker"
+                        );
+                    }
                     IpcCommand::HostedRpcReply { .. } => {
                         // Replies for worker-initiated HostedRpc calls are
                         // consumed inline by the IPC transport during test
@@ -440,95 +672,416 @@ fn test_thread(
                              `RunTest`/`Provide*` command — IPC protocol out of sync"
                         );
                     }
+                    IpcCommand::Finish => {
+                        let failed_hooks = run_after_all_hooks(&output, &execution, count);
+                        let teardown_warnings = run_teardowns(&execution);
+                        let response = IpcResponse::Finished {
+                            failed_hooks: failed_hooks.iter().map(FailedHook::from).collect(),
+                            teardown_warnings,
+                            dependency_events: execution.lock().unwrap().take_dependency_events(),
+                        };
+                        let msg = serialize_to_byte_vec(&response)
+                            .expect("Failed to encode IPC response");
+                        let mut conn = connection.lock().unwrap();
+                        write_frame(&mut *conn, &msg).expect("Failed to write IPC response frame");
+                        // The parent waits for the worker process to exit
+                        break 'serving;
+                    }
                 }
             }
         }
 
-        if let Some(next) = pick_next(&execution) {
-            let skip = if let Some((name, crate_name, module_path)) = &expected_test {
-                next.test.name != *name
-                    || next.test.crate_name != *crate_name
-                    || next.test.module_path != *module_path
+        let seen_changes = availability_changes.count();
+        let next = match &expected_test {
+            Some(expected) => match pick_expected(&execution, expected) {
+                Some(next) => {
+                    expected_test = None;
+                    Some(next)
+                }
+                None => {
+                    availability_changes.wait_sync(seen_changes);
+                    continue;
+                }
+            },
+            None => pick_next(&execution),
+        };
+        if let Some(mut next) = next {
+            // Snapshot the parent's monotonic-clock view of the
+            // test start. The matching end-instant is captured
+            // after `finished_running_test`, and the pair becomes
+            // a `HostWindow` for record attribution.
+            let window_start = Instant::now();
+
+            start_running_test(&output, rerun_attempt, &next.test, next.index, count);
+
+            let (result, failed_hooks) = if let Some(reason) = &next.skip_reason {
+                (TestResult::skipped(reason.clone()), Vec::new())
+            } else if next.test.props.is_ignored && !args.include_ignored {
+                (
+                    TestResult::Ignored {
+                        captured: Vec::new(),
+                    },
+                    Vec::new(),
+                )
+            } else if args.spawn_workers {
+                run_test_in_worker(
+                    &worker_factory,
+                    &mut worker,
+                    &output,
+                    &next.test,
+                    next.index,
+                    count,
+                )
+            } else if let Some(result) = next.create_per_test_dependencies_sync() {
+                (result, Vec::new())
             } else {
-                false
+                let ensure_time = get_ensure_time(&args, &next.test);
+                let timeout = get_in_process_timeout(&args, &next.test);
+                run_with_hooks(&output, &next, count, || {
+                    with_test_environment(&next.test.props, next.guard(), |guard| {
+                        run_sync_test_function(
+                            output.clone(),
+                            &next.test,
+                            next.index,
+                            count,
+                            ensure_time,
+                            timeout,
+                            next.deps.clone(),
+                            next.per_test_dependencies(),
+                            Some(guard),
+                        )
+                    })
+                })
             };
 
-            if !skip {
-                expected_test = None;
-
-                // Snapshot the parent's monotonic-clock view of the
-                // test start. The matching end-instant is captured
-                // after `finished_running_test`, and the pair becomes
-                // a `HostWindow` for record attribution.
-                let window_start = Instant::now();
-
-                output.start_running_test(&next.test, next.index, count);
-
-                let result = if next.test.props.is_ignored && !args.include_ignored {
-                    TestResult::Ignored {
-                        captured: Vec::new(),
-                    }
-                } else if let Some(worker) = worker.as_mut() {
-                    worker.run_test(args.nocapture, &next.test)
-                } else {
-                    let ensure_time = get_ensure_time(&args, &next.test);
-                    run_sync_test_function(
-                        output.clone(),
-                        &next.test,
-                        next.index,
-                        count,
-                        ensure_time,
-                        next.deps.clone(),
-                    )
+            next.release_per_test_dependencies();
+            report_failed_hooks(&output, &failed_hooks, next.index, count);
+            output.finished_running_test(&next.test, next.index, count, &result);
+            let window_end = Instant::now();
+
+            if let Some(connection) = connection_arc.as_ref() {
+                let finish_marker = Uuid::new_v4().to_string();
+                let finish_marker_line = format!("{finish_marker}\n");
+                std::io::stdout()
+                    .write_all(finish_marker_line.as_bytes())
+                    .unwrap();
+                std::io::stderr()
+                    .write_all(finish_marker_line.as_bytes())
+                    .unwrap();
+
+                std::io::stdout().flush().unwrap();
+                std::io::stderr().flush().unwrap();
+
+                let response = IpcResponse::TestFinished {
+                    result: (&result).into(),
+                    finish_marker,
+                    failed_hooks: failed_hooks.iter().map(FailedHook::from).collect(),
+                    dependency_events: execution.lock().unwrap().take_dependency_events(),
                 };
 
-                output.finished_running_test(&next.test, next.index, count, &result);
-                let window_end = Instant::now();
-
-                if let Some(connection) = connection_arc.as_ref() {
-                    let finish_marker = Uuid::new_v4().to_string();
-                    let finish_marker_line = format!("{finish_marker}\n");
-                    std::io::stdout()
-                        .write_all(finish_marker_line.as_bytes())
-                        .unwrap();
-                    std::io::stderr()
-                        .write_all(finish_marker_line.as_bytes())
-                        .unwrap();
-
-                    std::io::stdout().flush().unwrap();
-                    std::io::stderr().flush().unwrap();
-
-                    let response = IpcResponse::TestFinished {
-                        result: (&result).into(),
-                        finish_marker,
-                    };
+                let msg = serialize_to_byte_vec(&response).expect("Failed to encode IPC response");
+                let mut conn = connection.lock().unwrap();
+                write_frame(&mut *conn, &msg).expect("Failed to write IPC response frame");
+            }
 
-                    let msg =
-                        serialize_to_byte_vec(&response).expect("Failed to encode IPC response");
-                    let mut conn = connection.lock().unwrap();
-                    write_frame(&mut *conn, &msg).expect("Failed to write IPC response frame");
-                }
+            // Always push a window, even when no host capture is
+            // installed (epoch is `None`): a zero-width default
+            // keeps `results` and `host_windows` in lock-step so
+            // the suite-runner can attribute-or-ignore without
+            // index arithmetic.
+            let window = crate::host_capture::HostWindow::from_instants(
+                host_capture_epoch,
+                window_start,
+                window_end,
+            )
+            .unwrap_or(crate::host_capture::HostWindow {
+                start: std::time::Duration::ZERO,
+                end: std::time::Duration::ZERO,
+            });
+            for (entry, hook_result) in failed_hooks {
+                failure_limit.record(&hook_result);
+                results.push((entry, hook_result));
+                host_windows.push(window);
+            }
+            failure_limit.record(&result);
+            next.record_result(&result);
+            results.push((next.test.clone(), result));
+            host_windows.push(window);
+        } else {
+            // The remaining tests wait for their prerequisites or resources, which are released
+            // by the other test threads. If the last test was picked by another thread since the
+            // loop condition was checked, there is nothing left to wait for.
+            if !is_done(&execution) {
+                availability_changes.wait_sync(seen_changes);
+            }
+        }
+    }
 
-                results.push((next.test.clone(), result));
-                // Always push a window, even when no host capture is
-                // installed (epoch is `None`): a zero-width default
-                // keeps `results` and `host_windows` in lock-step so
-                // the suite-runner can attribute-or-ignore without
-                // index arithmetic.
-                let window = crate::host_capture::HostWindow::from_instants(
-                    host_capture_epoch,
-                    window_start,
-                    window_end,
-                )
-                .unwrap_or(crate::host_capture::HostWindow {
+    if let Some(mut worker) = worker.take() {
+        let failed_hooks = worker.finish(&output);
+        report_failed_hooks(&output, &failed_hooks, count.saturating_sub(1), count);
+        host_windows.extend(
+            failed_hooks
+                .iter()
+                .map(|_| crate::host_capture::HostWindow {
                     start: std::time::Duration::ZERO,
                     end: std::time::Duration::ZERO,
-                });
-                host_windows.push(window);
+                }),
+        );
+        results.extend(failed_hooks);
+    }
+    (results, host_windows)
+}
+
+/// Runs a test in this process surrounded by the hooks of its suites, and returns its result
+/// together with the hooks that failed. The `before_all` hooks of a suite run before its first
+/// test, and if one of them fails, all the tests of the suite are skipped. If a `before_each`
+/// hook fails, the test is skipped, but the `after_each` hooks run even then.
+fn run_with_hooks(
+    output: &Arc<dyn TestRunnerOutput>,
+    next: &TestExecution,
+    count: usize,
+    run_test: impl FnOnce() -> TestResult,
+) -> (TestResult, Vec<(RegisteredTest, TestResult)>) {
+    let mut failed_hooks = Vec::new();
+    let mut run_hook = |entry: RegisteredTest| {
+        let result = run_sync_test_function(
+            output.clone(),
+            &entry,
+            next.index,
+            count,
+            None,
+            None,
+            next.deps.clone(),
+            None,
+            None,
+        );
+        let passed = !result.is_failed();
+        if !passed {
+            failed_hooks.push((entry, result));
+        }
+        passed
+    };
+
+    for suite in &next.suites {
+        let failed = suite.before_all_sync(&next.deps, || {
+            suite
+                .hooks(HookKind::BeforeAll)
+                .find(|hook| !run_hook(suite.entry(hook, None)))
+                .map(|hook| hook.name.clone())
+        });
+        if let Some(hook) = failed {
+            let result = TestResult::skipped(format!("before_all hook {hook} failed"));
+            return (result, failed_hooks);
+        }
+    }
+
+    let mut skip_reason = None;
+    'suites: for suite in &next.suites {
+        for hook in suite.hooks(HookKind::BeforeEach) {
+            if !run_hook(suite.entry(hook, Some(&next.test))) {
+                skip_reason = Some(format!("before_each hook {} failed", hook.name));
+                break 'suites;
             }
         }
     }
-    (results, host_windows)
+    let result = match skip_reason {
+        Some(reason) => TestResult::skipped(reason),
+        None => run_test(),
+    };
+    for suite in next.suites.iter().rev() {
+        for hook in suite.hooks(HookKind::AfterEach) {
+            run_hook(suite.entry(hook, Some(&next.test)));
+        }
+    }
+    (result, failed_hooks)
+}
+
+/// Runs the `after_all` hooks of the suites that had tests running in this process, inner
+/// suites first, and returns the ones that failed
+fn run_after_all_hooks(
+    output: &Arc<dyn TestRunnerOutput>,
+    execution: &Arc<Mutex<TestSuiteExecution>>,
+    count: usize,
+) -> Vec<(RegisteredTest, TestResult)> {
+    let suites = execution.lock().unwrap().suite_hooks();
+    let mut failed_hooks = Vec::new();
+    for suite in suites.iter().rev() {
+        if let Some(deps) = suite.start_after_all() {
+            for hook in suite.hooks(HookKind::AfterAll) {
+                let entry = suite.entry(hook, None);
+                let result = run_sync_test_function(
+                    output.clone(),
+                    &entry,
+                    count.saturating_sub(1),
+                    count,
+                    None,
+                    None,
+                    deps.clone(),
+                    None,
+                    None,
+                );
+                if result.is_failed() {
+                    failed_hooks.push((entry, result));
+                }
+            }
+        }
+    }
+    failed_hooks
+}
+
+/// Runs the teardowns of the dependencies constructed in this process, in reverse construction
+/// order, then drops the dependencies. Returns the warnings of the teardowns that failed.
+fn run_teardowns(execution: &Arc<Mutex<TestSuiteExecution>>) -> Vec<String> {
+    let teardowns = execution.lock().unwrap().take_teardowns();
+    let warnings = teardowns
+        .into_iter()
+        .filter_map(PendingTeardown::run_sync)
+        .collect();
+    execution.lock().unwrap().release_dependencies();
+    warnings
+}
+
+/// Reports each failed hook as its own entry
+pub(crate) fn report_failed_hooks(
+    output: &Arc<dyn TestRunnerOutput>,
+    failed_hooks: &[(RegisteredTest, TestResult)],
+    idx: usize,
+    count: usize,
+) {
+    for (entry, result) in failed_hooks {
+        output.start_running_test(entry, idx, count);
+        output.finished_running_test(entry, idx, count, result);
+    }
+}
+
+/// Everything a test thread needs to spawn worker processes and prepare them
+/// for running tests. Kept for the lifetime of the thread so a killed or
+/// crashed worker can be replaced by an identically prepared one.
+struct WorkerFactory {
+    args: Arguments,
+    cloneable_wire_bytes: Arc<Vec<DepWireBytes>>,
+    hosted_descriptor_bytes: Arc<Vec<DepWireBytes>>,
+    failed_dependencies: Arc<Vec<(String, DependencyFailure)>>,
+    hosted_rpc_owner_cells: Arc<HashMap<String, Arc<HostedRpcOwnerCell>>>,
+}
+
+impl WorkerFactory {
+    /// Spawns a worker process (if `--spawn-workers` is on) with the given
+    /// limits and prepares it for running tests: installs the parent-held
+    /// HostedRpc owner cells and ships every Cloneable payload and Hosted
+    /// descriptor to it.
+    fn spawn_if_needed(&self, limits: WorkerLimits) -> Option<Worker> {
+        let mut worker = spawn_worker_if_needed(&self.args, limits)?;
+        // Parent dispatches incoming `HostedRpcCall` frames against the owner
+        // cells materialised in the top-level parent. Workers don't need the
+        // owner cells (they own stubs instead), so they receive an empty map
+        // and the dispatch code path is never reached in subprocesses.
+        worker.set_hosted_rpc_owner_cells(self.hosted_rpc_owner_cells.clone());
+
+        // Eagerly ship every Cloneable wire payload to the worker. Workers
+        // stash them into the execution tree as pre-materialised values so the
+        // original constructor never runs on the worker side. The `dep_id`
+        // carried across the wire is the dep's fully-qualified id, not its
+        // local `name`, so same-named deps in different modules don't collide.
+        for (dep_id, wire_bytes) in self.cloneable_wire_bytes.iter() {
+            worker.provide_cloneable(dep_id.clone(), wire_bytes.clone());
+        }
+        // Ship every Hosted dep's descriptor bytes too. Workers run the
+        // registered worker_fn (HostedDep::from_descriptor) to build a
+        // per-worker handle pointing at the parent-held owner.
+        for (dep_id, descriptor_bytes) in self.hosted_descriptor_bytes.iter() {
+            worker.provide_hosted_descriptor(dep_id.clone(), descriptor_bytes.clone());
+        }
+        // And the deps whose constructor failed, so the worker fails the
+        // tests using them instead of running their constructors again.
+        for (dep_id, failure) in self.failed_dependencies.iter() {
+            worker.provide_failed_dependency(dep_id.clone(), failure.message.clone());
+        }
+        Some(worker)
+    }
+}
+
+/// Runs a test in the test thread's worker process. A worker that was killed
+/// on a timeout or crashed is replaced by a fresh one when the next test needs
+/// it, and a test whose worker crashed is retried in a fresh worker up to
+/// `--retry-crashed` times. Isolated tests (`#[isolated]` or `--isolate`) and
+/// tests with their own `#[memory_limit]` get a worker that has not run any
+/// other test, which is retired right after. Returns the test's result and
+/// the suite hooks that failed in the worker around it, or while a worker was
+/// retired.
+fn run_test_in_worker(
+    worker_factory: &WorkerFactory,
+    worker: &mut Option<Worker>,
+    output: &Arc<dyn TestRunnerOutput>,
+    test: &RegisteredTest,
+    idx: usize,
+    count: usize,
+) -> (TestResult, Vec<(RegisteredTest, TestResult)>) {
+    let isolated =
+        worker_factory.args.isolate || test.props.is_isolated || test.props.memory_limit.is_some();
+    let limits = WorkerLimits::new(&worker_factory.args, test.props.memory_limit);
+    let max_attempts = worker_factory.args.retry_crashed.unwrap_or(0) + 1;
+    let mut attempt = 1;
+    let mut all_failed_hooks = Vec::new();
+    loop {
+        if let Some(mut unsuitable) =
+            worker.take_if(|worker| (isolated && worker.has_run_tests()) || worker.limits != limits)
+        {
+            all_failed_hooks.extend(unsuitable.finish(output));
+        }
+        if worker.is_none() {
+            *worker = worker_factory.spawn_if_needed(limits);
+        }
+        let current = worker
+            .as_mut()
+            .expect("No worker process to run the test in");
+        let (result, failed_hooks) = current.run_test(output, worker_factory.args.nocapture, test);
+        all_failed_hooks.extend(failed_hooks);
+        if isolated {
+            all_failed_hooks.extend(current.finish(output));
+        }
+        if current.has_exited() {
+            *worker = None;
+        }
+
+        let crashed = result
+            .failure_cause()
+            .is_some_and(FailureCause::is_worker_crash);
+        if crashed && attempt < max_attempts {
+            attempt += 1;
+            output.repeat_running_test(
+                test,
+                idx,
+                count,
+                attempt,
+                max_attempts,
+                "because its worker process crashed",
+            );
+        } else {
+            break (result, all_failed_hooks);
+        }
+    }
+}
+
+fn start_running_test(
+    output: &Arc<dyn TestRunnerOutput>,
+    rerun_attempt: Option<RerunAttempt>,
+    test: &RegisteredTest,
+    idx: usize,
+    count: usize,
+) {
+    match rerun_attempt {
+        Some(rerun) => output.repeat_running_test(
+            test,
+            idx,
+            count,
+            rerun.attempt,
+            rerun.max_attempts,
+            "because it failed in the previous pass",
+        ),
+        None => output.start_running_test(test, idx, count),
+    }
 }
 
 fn is_done(execution: &Arc<Mutex<TestSuiteExecution>>) -> bool {
@@ -603,6 +1156,22 @@ fn apply_cloneable_values_locally(
     }
 }
 
+/// No-spawn-workers helper for the `Cloneable`/`Hosted`/`HostedRpc` deps
+/// whose constructor failed in the parent: marks them as failed at the node
+/// where they are registered, so the tests using them fail without being run.
+fn apply_failed_dependencies_locally(
+    execution: &mut TestSuiteExecution,
+    failed_dependencies: &[(String, DependencyFailure)],
+) {
+    for (dep_id, failure) in failed_dependencies {
+        let applied = execution.provide_failed_dependency(dep_id, failure.clone());
+        assert!(
+            applied,
+            "Failed dep '{dep_id}' could not be marked as failed locally"
+        );
+    }
+}
+
 /// No-spawn-workers helper for `Shared`/`PerWorker` deps whose constructor
 /// already ran in the parent's collection context (as a transitive input
 /// to a Cloneable/Hosted/HostedRpc dep). Installs each value at the node
@@ -830,17 +1399,36 @@ fn pick_next(execution: &Arc<Mutex<TestSuiteExecution>>) -> Option<TestExecution
     execution.pick_next_sync()
 }
 
+/// Worker-side: picks the test requested by the parent runner. The parent schedules the tests
+/// dynamically (sequential suites, resource limits), so it is not necessarily the next one in
+/// the worker's own order. Returns `None` if the test cannot start yet, for example because a
+/// resource it uses is still held in the worker.
+fn pick_expected(
+    execution: &Arc<Mutex<TestSuiteExecution>>,
+    (name, crate_name, module_path): &(String, String, String),
+) -> Option<TestExecution> {
+    let is_expected = |test: &RegisteredTest| {
+        &test.name == name && &test.crate_name == crate_name && &test.module_path == module_path
+    };
+    let mut execution = execution.lock().unwrap();
+    let next = execution.pick_next_matching_sync(&is_expected);
+    if next.is_none() && !execution.has_matching_test(&is_expected) {
+        panic!("Test {crate_name}::{module_path}::{name} requested by the test runner was not found in the worker")
+    }
+    next
+}
+
 fn run_with_flakiness_control(
     output: Arc<dyn TestRunnerOutput>,
     test_description: &RegisteredTest,
     idx: usize,
     count: usize,
-    test: impl Fn(Instant) -> Result<Result<(), FailureCause>, Box<dyn Any + Send>>,
+    test: impl Fn(Instant, usize) -> Result<Result<(), FailureCause>, Box<dyn Any + Send>>,
 ) -> Result<Result<(), FailureCause>, Box<dyn Any + Send>> {
     match &test_description.props.flakiness_control {
         FlakinessControl::None => {
             let start = Instant::now();
-            test(start)
+            test(start, 1)
         }
         FlakinessControl::ProveNonFlaky(tries) => {
             for n in 0..*tries {
@@ -855,7 +1443,7 @@ fn run_with_flakiness_control(
                     );
                 }
                 let start = Instant::now();
-                match test(start) {
+                match test(start, n + 1) {
                     Ok(Ok(())) => {}
                     Ok(Err(e)) => return Ok(Err(e)),
                     Err(e) => return Err(e),
@@ -867,7 +1455,7 @@ fn run_with_flakiness_control(
             let mut tries = 1;
             loop {
                 let start = Instant::now();
-                let result = test(start);
+                let result = test(start, tries);
 
                 if result.is_err() && tries < *max_retries {
                     tries += 1;
@@ -888,38 +1476,77 @@ fn run_with_flakiness_control(
 }
 
 #[allow(unreachable_patterns)]
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn run_sync_test_function(
     output: Arc<dyn TestRunnerOutput>,
     test_description: &RegisteredTest,
     idx: usize,
     count: usize,
     ensure_time: Option<TimeThreshold>,
+    timeout: Option<Duration>,
     dependency_view: Arc<dyn internal::DependencyView + Send + Sync>,
+    per_test_dependencies: Option<PerTestDependencies>,
+    guard: Option<TestGuard>,
 ) -> TestResult {
     let start = Instant::now();
     match test_description.run.clone() {
         TestFunction::Sync(test_fn) => {
             let detached_panic_policy = test_description.props.detached_panic_policy.clone();
-            let result =
-                run_with_flakiness_control(output, test_description, idx, count, move |start| {
-                    let dependency_view = dependency_view.clone();
+            let should_panic = test_description.props.should_panic.clone();
+            let thread_name = test_description.name.clone();
+            let result = run_with_flakiness_control(
+                output,
+                test_description,
+                idx,
+                count,
+                move |start, attempt| {
+                    if attempt > 1 {
+                        if let Some(per_test_dependencies) = &per_test_dependencies {
+                            if let Err(cause) = per_test_dependencies.create_sync() {
+                                return Ok(Err(cause));
+                            }
+                        }
+                    }
+                    let context = Arc::new(TestContext::new(test_description, attempt));
+                    let dependency_view =
+                        WithTestContext::wrap(context.clone(), dependency_view.clone());
                     let test_fn = test_fn.clone();
                     let test_id = crate::panic_hook::next_test_id();
                     crate::panic_hook::set_current_test_id(test_id);
                     crate::panic_hook::create_detached_collector(test_id);
-                    catch_unwind(AssertUnwindSafe(move || {
-                        test_fn(dependency_view).into_result()?;
-                        if let Some(ensure_time) = ensure_time {
-                            let elapsed = start.elapsed();
-                            if ensure_time.is_critical(&elapsed) {
-                                return Err(FailureCause::HarnessError(format!(
-                                    "Test run time exceeds critical threshold: {elapsed:?}"
-                                )));
-                            }
-                        };
-                        Ok(())
-                    }))
-                });
+                    let run = move || {
+                        catch_unwind(AssertUnwindSafe(move || {
+                            test_fn(dependency_view).into_result()?;
+                            if let Some(ensure_time) = ensure_time {
+                                let elapsed = start.elapsed();
+                                if ensure_time.is_critical(&elapsed) {
+                                    return Err(FailureCause::HarnessError(format!(
+                                        "Test run time exceeds critical threshold: {elapsed:?}"
+                                    )));
+                                }
+                            };
+                            Ok(())
+                        }))
+                    };
+                    let result = match timeout {
+                        None => run(),
+                        Some(timeout) => run_with_timeout(
+                            thread_name.clone(),
+                            test_id,
+                            timeout,
+                            guard.clone(),
+                            run,
+                        )
+                        .unwrap_or_else(|| {
+                            Ok(Err(FailureCause::HarnessError(
+                                "Test timed out".to_string(),
+                            )))
+                        }),
+                    };
+                    context.finish(attempt_passed(&should_panic, &result));
+                    result
+                },
+            );
             let mut test_result = TestResult::from_result(
                 &test_description.props.should_panic,
                 start.elapsed(),
@@ -1005,9 +1632,39 @@ pub(crate) fn run_sync_test_function(
     }
 }
 
+/// Runs one attempt of a synchronous test on a dedicated thread and waits at
+/// most `timeout` for it. Returns `None` if the test did not finish in time.
+///
+/// A running thread cannot be cancelled, so a timed out test is abandoned and
+/// keeps running in the background while the runner moves on. It holds the
+/// test's `guard` until it returns, so the tests waiting for the same
+/// sequential suite or resources do not start while it is still running. The
+/// helper thread inherits the caller's panic-hook test id so a panic is still
+/// attributed to the test by `TestResult::from_result` on the calling thread.
+fn run_with_timeout<R: Send + 'static>(
+    thread_name: String,
+    test_id: u64,
+    timeout: Duration,
+    guard: Option<TestGuard>,
+    run: impl FnOnce() -> R + Send + 'static,
+) -> Option<R> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::Builder::new()
+        .name(thread_name)
+        .spawn(move || {
+            let _guard = guard;
+            crate::panic_hook::set_current_test_id(test_id);
+            let _ = sender.send(run());
+        })
+        .expect("Failed to spawn test thread");
+    receiver.recv_timeout(timeout).ok()
+}
+
 struct Worker {
     listener: interprocess::local_socket::Listener,
-    process: Child,
+    /// Shared with the [`Watchdog`] so it can kill the worker while the
+    /// parent is blocked reading the IPC stream.
+    process: Arc<Mutex<Child>>,
     out_handle: JoinHandle<()>,
     err_handle: JoinHandle<()>,
     out_lines: Arc<Mutex<VecDeque<CapturedOutput>>>,
@@ -1018,9 +1675,24 @@ struct Worker {
     /// to dispatch incoming `IpcResponse::HostedRpcCall` frames from the worker
     /// subprocess back to the right owner.
     hosted_rpc_owner_cells: Arc<HashMap<String, Arc<HostedRpcOwnerCell>>>,
+    /// Set once the worker process has been killed. The test thread replaces
+    /// an exited worker before running its next test.
+    exited: bool,
+    /// Set once the worker has been asked to run a test
+    used: bool,
+    /// Resource limits the worker process was spawned with
+    limits: WorkerLimits,
 }
 
 impl Worker {
+    fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    fn has_run_tests(&self) -> bool {
+        self.used
+    }
+
     /// Installs the parent-side map of HostedRpc owner cells so this worker can
     /// route incoming `IpcResponse::HostedRpcCall` frames to the right
     /// `HostedRpcOwnerCell` while waiting for a worker subprocess response.
@@ -1034,12 +1706,11 @@ impl Worker {
     /// `IpcCommand::HostedRpcReply` back to the worker subprocess.
     fn handle_hosted_rpc_call(
         &mut self,
-        dump_on_ipc_failure: &DumpOnFailure,
         request_id: u64,
         dep_id: String,
         method_idx: u32,
         args_bytes: Vec<u8>,
-    ) {
+    ) -> std::io::Result<()> {
         let body = match self.hosted_rpc_owner_cells.get(&dep_id) {
             Some(cell) => match cell.dispatch(method_idx, &args_bytes) {
                 Ok(result_bytes) => HostedRpcReplyBody::Ok { result_bytes },
@@ -1053,10 +1724,16 @@ impl Worker {
         };
         let reply = IpcCommand::HostedRpcReply { request_id, body };
         let msg = serialize_to_byte_vec(&reply).expect("Failed to encode HostedRpcReply");
-        dump_on_ipc_failure.run(write_frame(&mut self.connection, &msg));
+        write_frame(&mut self.connection, &msg)
     }
 
-    pub fn run_test(&mut self, nocapture: bool, test: &RegisteredTest) -> TestResult {
+    pub fn run_test(
+        &mut self,
+        output: &Arc<dyn TestRunnerOutput>,
+        nocapture: bool,
+        test: &RegisteredTest,
+    ) -> (TestResult, Vec<(RegisteredTest, TestResult)>) {
+        self.used = true;
         let mut capture_enabled = self.capture_enabled.lock().unwrap();
         *capture_enabled = test.props.capture_control.requires_capturing(!nocapture);
         drop(capture_enabled);
@@ -1071,45 +1748,45 @@ impl Worker {
         let dump_on_ipc_failure = self.dump_on_failure();
 
         let msg = serialize_to_byte_vec(&cmd).expect("Failed to encode IPC command");
-        dump_on_ipc_failure.run(write_frame(&mut self.connection, &msg));
 
-        let response = loop {
-            let response_bytes = dump_on_ipc_failure.run(read_frame(&mut self.connection));
-            let response: IpcResponse = dump_on_ipc_failure.run(deserialize(&response_bytes));
-            match response {
-                IpcResponse::TestFinished { .. } => break response,
-                IpcResponse::CloneableAccepted { .. }
-                | IpcResponse::HostedDescriptorAccepted { .. } => {
-                    // Out-of-band ack from a previous Provide*; ignore.
-                    continue;
-                }
-                IpcResponse::HostedRpcCall {
-                    request_id,
-                    dep_id,
-                    method_idx,
-                    args_bytes,
-                } => {
-                    self.handle_hosted_rpc_call(
-                        &dump_on_ipc_failure,
-                        request_id,
-                        dep_id,
-                        method_idx,
-                        args_bytes,
-                    );
-                    continue;
+        let start = Instant::now();
+        let watchdog =
+            get_worker_timeout(test).map(|timeout| Watchdog::start(self.process.clone(), timeout));
+
+        // Any I/O error from here on means the worker process is gone: either
+        // the watchdog killed it, or it crashed while running the test.
+        let outcome = write_frame(&mut self.connection, &msg)
+            .and_then(|()| self.wait_for_answer(&dump_on_ipc_failure));
+        // The watchdog may have fired right after the result was sent, in
+        // which case the worker has to be replaced all the same.
+        let timed_out = watchdog.is_some_and(Watchdog::cancel);
+        let response = match outcome {
+            Ok(response) => {
+                if timed_out {
+                    self.exited = true;
                 }
+                response
+            }
+            Err(_) if timed_out => {
+                return (self.timed_out(nocapture, test, start.elapsed()), Vec::new())
             }
+            Err(_) => return (self.crashed(nocapture, test, start.elapsed()), Vec::new()),
         };
 
         let IpcResponse::TestFinished {
             result,
             finish_marker,
+            failed_hooks,
+            dependency_events,
         } = response
         else {
-            unreachable!("loop only breaks on TestFinished")
+            unreachable!("the worker answers RunTest with TestFinished")
         };
+        for event in &dependency_events {
+            output.dependency_event(event);
+        }
 
-        if test.props.capture_control.requires_capturing(!nocapture) {
+        let result = if test.props.capture_control.requires_capturing(!nocapture) {
             let out_lines: Vec<_> =
                 Self::drain_until(self.out_lines.clone(), finish_marker.clone());
             let err_lines: Vec<_> =
@@ -1117,7 +1794,55 @@ impl Worker {
             result.into_test_result(out_lines, err_lines)
         } else {
             result.into_test_result(Vec::new(), Vec::new())
+        };
+        (result, failed_hooks.into_iter().map(Into::into).collect())
+    }
+
+    /// Runs the `after_all` hooks of the suites the worker process ran tests
+    /// of and the teardowns of its dependencies, then stops the worker.
+    /// Returns the hooks that failed; failed teardowns are reported as
+    /// warnings. A worker not finishing within [`WORKER_FINISH_TIMEOUT`] is
+    /// killed by a [`Watchdog`].
+    fn finish(&mut self, output: &Arc<dyn TestRunnerOutput>) -> Vec<(RegisteredTest, TestResult)> {
+        let mut failed_hooks = Vec::new();
+        let mut finished = false;
+        if self.used && !self.exited {
+            let dump_on_ipc_failure = self.dump_on_failure();
+            let msg =
+                serialize_to_byte_vec(&IpcCommand::Finish).expect("Failed to encode IPC command");
+            let watchdog = Watchdog::start(self.process.clone(), WORKER_FINISH_TIMEOUT);
+            let outcome = write_frame(&mut self.connection, &msg)
+                .and_then(|()| self.wait_for_answer(&dump_on_ipc_failure));
+            let timed_out = watchdog.cancel();
+            match outcome {
+                Ok(IpcResponse::Finished {
+                    failed_hooks: failed,
+                    teardown_warnings,
+                    dependency_events,
+                }) => {
+                    failed_hooks = failed.into_iter().map(Into::into).collect();
+                    for warning in teardown_warnings {
+                        output.warning(&warning);
+                    }
+                    for event in &dependency_events {
+                        output.dependency_event(event);
+                    }
+                    finished = !timed_out;
+                }
+                Err(_) if timed_out => output.warning(&format!(
+                    "A worker process was killed because its after_all hooks and teardowns did not finish within {}s",
+                    WORKER_FINISH_TIMEOUT.as_secs()
+                )),
+                _ => {}
+            }
         }
+        // Only a worker that answered `Finish` exits on its own
+        self.retire(if finished {
+            WORKER_EXIT_TIMEOUT
+        } else {
+            Duration::ZERO
+        });
+        failed_hooks
     }
 
     /// Sends a Cloneable wire payload to this worker process and waits for
@@ -1146,7 +1871,7 @@ impl Worker {
                 IpcResponse::HostedDescriptorAccepted { .. } => {
                     // Out-of-band ack from a previous ProvideHostedDescriptor; ignore.
                 }
-                IpcResponse::TestFinished { .. } => {
+                IpcResponse::TestFinished { .. } | IpcResponse::Finished { .. } => {
                     // Should not happen before any RunTest.
                 }
                 IpcResponse::HostedRpcCall {
@@ -1159,18 +1884,27 @@ impl Worker {
                     // HostedRpcCall before its first RunTest, but if it does
                     // (e.g. stub built during a ProvideCloneable round-trip in
                     // a future extension) we still dispatch.
-                    self.handle_hosted_rpc_call(
-                        &dump_on_ipc_failure,
+                    dump_on_ipc_failure.run(self.handle_hosted_rpc_call(
                         request_id,
                         call_dep_id,
                         method_idx,
                         args_bytes,
-                    );
+                    ));
                 }
             }
         }
     }
 
+    /// Tells this worker process that the constructor of a Cloneable,
+    /// Hosted or HostedRpc dep failed in the parent. There is no response,
+    /// the worker handles the command before the next `RunTest`.
+    fn provide_failed_dependency(&mut self, dep_id: String, message: String) {
+        let dump_on_ipc_failure = self.dump_on_failure();
+        let cmd = IpcCommand::ProvideFailedDependency { dep_id, message };
+        let msg = serialize_to_byte_vec(&cmd).expect("Failed to encode IPC command");
+        dump_on_ipc_failure.run(write_frame(&mut self.connection, &msg));
+    }
+
     /// Sends a Hosted descriptor payload to this worker process and
     /// waits for the matching `HostedDescriptorAccepted` response.
     /// `dep_id` is the dep's fully-qualified id (`{crate}::{module}::{name}`).
@@ -1195,7 +1929,7 @@ impl Worker {
                 IpcResponse::CloneableAccepted { .. } => {
                     // Out-of-band ack from a previous ProvideCloneable; ignore.
                 }
-                IpcResponse::TestFinished { .. } => {
+                IpcResponse::TestFinished { .. } | IpcResponse::Finished { .. } => {
                     // Should not happen before any RunTest.
                 }
                 IpcResponse::HostedRpcCall {
@@ -1204,18 +1938,134 @@ impl Worker {
                     method_idx,
                     args_bytes,
                 } => {
-                    self.handle_hosted_rpc_call(
-                        &dump_on_ipc_failure,
+                    dump_on_ipc_failure.run(self.handle_hosted_rpc_call(
                         request_id,
                         call_dep_id,
                         method_idx,
                         args_bytes,
-                    );
+                    ));
                 }
             }
         }
     }
 
+    /// Reads IPC responses until the worker answers a `RunTest` or
+    /// `Finish` command, dispatching the `HostedRpcCall`s it makes
+    /// in the meantime.
+    fn wait_for_answer(
+        &mut self,
+        dump_on_ipc_failure: &DumpOnFailure,
+    ) -> std::io::Result<IpcResponse> {
+        loop {
+            let response_bytes = read_frame(&mut self.connection)?;
+            let response: IpcResponse = dump_on_ipc_failure.run(deserialize(&response_bytes));
+            match response {
+                IpcResponse::TestFinished { .. } | IpcResponse::Finished { .. } => {
+                    break Ok(response)
+                }
+                IpcResponse::CloneableAccepted { .. }
+                | IpcResponse::HostedDescriptorAccepted { .. } => {
+                    // Out-of-band ack from a previous Provide*; ignore.
+                    continue;
+                }
+                IpcResponse::HostedRpcCall {
+                    request_id,
+                    dep_id,
+                    method_idx,
+                    args_bytes,
+                } => {
+                    self.handle_hosted_rpc_call(request_id, dep_id, method_idx, args_bytes)?;
+                    continue;
+                }
+            }
+        }
+    }
+
+    /// Builds the result of a test whose worker was killed by the
+    /// [`Watchdog`]. Everything the worker printed before being killed is
+    /// reported as the test's captured output.
+    fn timed_out(
+        &mut self,
+        nocapture: bool,
+        test: &RegisteredTest,
+        elapsed: Duration,
+    ) -> TestResult {
+        let (_, captured) = self.reap(Duration::ZERO);
+        let mut result = TestResult::failed(
+            elapsed,
+            FailureCause::HarnessError("Test timed out".to_string()),
+        );
+        if test.props.capture_control.requires_capturing(!nocapture) {
+            result.set_captured_output(captured);
+        }
+        result
+    }
+
+    /// Builds the result of a test whose worker process exited before
+    /// reporting a result. The failure carries the exit status and the tail
+    /// of the worker's stderr, and the exceeded limit if the worker was
+    /// stopped because of one.
+    fn crashed(&mut self, nocapture: bool, test: &RegisteredTest, elapsed: Duration) -> TestResult {
+        let (status, captured) = self.reap(Duration::ZERO);
+        let stderr: Vec<String> = captured
+            .iter()
+            .filter(|line| matches!(line, CapturedOutput::Stderr { .. }))
+            .map(|line| line.line().to_string())
+            .collect();
+        let crash = WorkerCrash::new(status, &stderr);
+        let cause = match self.limits.exceeded_by(&crash) {
+            Some(limit) => FailureCause::ResourceLimitExceeded(limit, crash),
+            None => FailureCause::WorkerCrashed(crash),
+        };
+        let mut result = TestResult::failed(elapsed, cause);
+        if test.props.capture_control.requires_capturing(!nocapture) {
+            result.set_captured_output(captured);
+        }
+        result
+    }
+
+    /// Stops the worker process so it cannot run any more tests.
+    fn retire(&mut self, exit_timeout: Duration) {
+        let _ = self.reap(exit_timeout);
+    }
+
+    /// Marks the worker as exited, waits for its process to terminate and
+    /// collects everything it printed that was not attributed to a test yet.
+    /// The process is killed if it does not exit within `exit_timeout`, or
+    /// right away in case only the IPC connection broke.
+    fn reap(&mut self, exit_timeout: Duration) -> (Option<ExitStatus>, Vec<CapturedOutput>) {
+        self.exited = true;
+        let deadline = Instant::now() + exit_timeout;
+        let status = loop {
+            let mut process = self.process.lock().unwrap();
+            match process.try_wait() {
+                Ok(Some(status)) => break Some(status),
+                Ok(None) if Instant::now() < deadline => {
+                    drop(process);
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                _ => {
+                    let _ = process.kill();
+                    break process.wait().ok();
+                }
+            }
+        };
+
+        // The output readers stop at the exited process' EOF, unless a process
+        // started by one of its tests still holds its stdout or stderr
+        let deadline = Instant::now() + WORKER_OUTPUT_TIMEOUT;
+        while (!self.out_handle.is_finished() || !self.err_handle.is_finished())
+            && Instant::now() < deadline
+        {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let out_lines: Vec<_> = self.out_lines.lock().unwrap().drain(..).collect();
+        let err_lines: Vec<_> = self.err_lines.lock().unwrap().drain(..).collect();
+        let mut captured = [out_lines, err_lines].concat();
+        captured.sort();
+        (status, captured)
+    }
+
     fn dump_on_failure(&self) -> DumpOnFailure {
         DumpOnFailure {
             out_lines: self.out_lines.clone(),
@@ -1244,6 +2094,43 @@ impl Worker {
     }
 }
 
+/// Kills a worker process if it does not report a test's result within the
+/// test's timeout. Started by `Worker::run_test` right after sending the
+/// `RunTest` command; killing the process makes the parent's blocking IPC
+/// read fail, which `run_test` then turns into a timeout failure.
+/// `Worker::finish` guards the `Finish` command the same way.
+struct Watchdog {
+    cancel: mpsc::Sender<()>,
+    fired: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl Watchdog {
+    fn start(process: Arc<Mutex<Child>>, timeout: Duration) -> Self {
+        let (cancel, cancelled) = mpsc::channel::<()>();
+        let fired = Arc::new(AtomicBool::new(false));
+        let fired_clone = fired.clone();
+        let handle = spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = cancelled.recv_timeout(timeout) {
+                fired_clone.store(true, Ordering::SeqCst);
+                let _ = process.lock().unwrap().kill();
+            }
+        });
+        Self {
+            cancel,
+            fired,
+            handle,
+        }
+    }
+
+    /// Stops the watchdog and returns whether it had already killed the worker.
+    fn cancel(self) -> bool {
+        drop(self.cancel);
+        let _ = self.handle.join();
+        self.fired.load(Ordering::SeqCst)
+    }
+}
+
 struct DumpOnFailure {
     out_lines: Arc<Mutex<VecDeque<CapturedOutput>>>,
     err_lines: Arc<Mutex<VecDeque<CapturedOutput>>>,
@@ -1279,7 +2166,7 @@ impl DumpOnFailure {
     }
 }
 
-fn spawn_worker_if_needed(args: &Arguments) -> Option<Worker> {
+fn spawn_worker_if_needed(args: &Arguments, limits: WorkerLimits) -> Option<Worker> {
     if args.spawn_workers {
         let id = Uuid::new_v4();
         let name_str = format!("{id}.sock");
@@ -1300,14 +2187,16 @@ fn spawn_worker_if_needed(args: &Arguments) -> Option<Worker> {
         args.logfile = None;
         let args = args.to_args();
 
-        #[allow(clippy::zombie_processes)]
-        let mut process = Command::new(exe)
+        let mut command = Command::new(exe);
+        command
             .args(args)
             .stdin(Stdio::inherit())
             .stderr(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()
-            .expect("Failed to spawn worker process");
+            .stdout(Stdio::piped());
+        limits.apply(&mut command);
+
+        #[allow(clippy::zombie_processes)]
+        let mut process = command.spawn().expect("Failed to spawn worker process");
 
         let stdout = process.stdout.take().unwrap();
         let stderr = process.stderr.take().unwrap();
@@ -1385,7 +2274,7 @@ fn spawn_worker_if_needed(args: &Arguments) -> Option<Worker> {
 
         Some(Worker {
             listener,
-            process,
+            process: Arc::new(Mutex::new(process)),
             out_handle,
             err_handle,
             out_lines,
@@ -1393,6 +2282,9 @@ fn spawn_worker_if_needed(args: &Arguments) -> Option<Worker> {
             capture_enabled,
             connection,
             hosted_rpc_owner_cells: Arc::new(HashMap::new()),
+            exited: false,
+            used: false,
+            limits,
         })
     } else {
         None
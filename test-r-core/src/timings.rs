@@ -0,0 +1,113 @@
+//! Persists the execution time of each test between runs of a test binary, to support
+//! `--schedule longest-first`.
+//!
+//! Every line of the state file holds a test's fully qualified name and its last
+//! execution time in microseconds, separated by a tab. Like the list of failed tests,
+//! it is only updated by the top-level runner after the whole run has finished.
+
+use crate::internal::{RegisteredTest, TestResult};
+use crate::state::state_file;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Loads the execution times recorded by previous runs
+pub(crate) fn load() -> HashMap<String, Duration> {
+    state_file("timings")
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|contents| parse(&contents))
+        .unwrap_or_default()
+}
+
+/// Records the execution times of the tests that were run. Tests that were not run keep
+/// their previously recorded time, and suite hooks are not recorded.
+pub(crate) fn update(results: &[(RegisteredTest, TestResult)]) {
+    let Some(path) = state_file("timings") else {
+        return;
+    };
+    if !results
+        .iter()
+        .any(|(_, result)| result.exec_time().is_some())
+    {
+        return;
+    }
+    let timings = merge(load(), results);
+
+    let mut lines: Vec<String> = timings
+        .iter()
+        .map(|(name, time)| format!("{name}\t{}\n", time.as_micros()))
+        .collect();
+    lines.sort();
+    let write_result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(&path, lines.concat()));
+    if let Err(err) = write_result {
+        eprintln!(
+            "Failed to update the test execution times in {}: {err}",
+            path.display()
+        );
+    }
+}
+
+fn parse(contents: &str) -> HashMap<String, Duration> {
+    contents
+        .lines()
+        .filter_map(|line| line.rsplit_once('\t'))
+        .filter_map(|(name, micros)| {
+            let micros = micros.parse::<u64>().ok()?;
+            Some((name.to_string(), Duration::from_micros(micros)))
+        })
+        .collect()
+}
+
+fn merge(
+    mut timings: HashMap<String, Duration>,
+    results: &[(RegisteredTest, TestResult)],
+) -> HashMap<String, Duration> {
+    for (test, result) in results.iter().filter(|(test, _)| test.props.hook.is_none()) {
+        if let Some(exec_time) = result.exec_time() {
+            timings.insert(test.fully_qualified_name(), exec_time);
+        }
+    }
+    timings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::{make_test, FailureCause, HookKind};
+
+    #[test]
+    fn merge_updates_the_tests_that_were_run() {
+        let previous =
+            parse("mycrate::mod1::slow\t90000000\nmycrate::mod1::ignored\t1500\ninvalid line\n");
+        assert_eq!(previous.len(), 2);
+
+        let mut hook = make_test("setup [before_all]", "mod1");
+        hook.props.hook = Some(HookKind::BeforeAll);
+        let results = vec![
+            (
+                make_test("slow", "mod1"),
+                TestResult::failed(
+                    Duration::from_secs(80),
+                    FailureCause::HarnessError("x".into()),
+                ),
+            ),
+            (
+                make_test("new", "mod1"),
+                TestResult::passed(Duration::from_millis(3)),
+            ),
+            (make_test("ignored", "mod1"), TestResult::ignored()),
+            (hook, TestResult::passed(Duration::from_millis(5))),
+        ];
+
+        let merged = merge(previous, &results);
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged["mycrate::mod1::slow"], Duration::from_secs(80));
+        assert_eq!(merged["mycrate::mod1::new"], Duration::from_millis(3));
+        assert_eq!(
+            merged["mycrate::mod1::ignored"],
+            Duration::from_micros(1500)
+        );
+    }
+}
@@ -1,17 +1,27 @@
 use crate::args::{Arguments, TimeThreshold};
 use crate::bench::AsyncBencher;
-use crate::execution::{DepWireBytes, TestExecution, TestSuiteExecution};
+use crate::context::{attempt_passed, TestContext, WithTestContext};
+use crate::environment::with_test_environment_async;
+use crate::execution::{
+    DepWireBytes, DependencyEvents, PerTestDependencies, TestExecution, TestGuard,
+    TestSuiteExecution,
+};
 use crate::internal;
 use crate::internal::{
-    generate_tests, get_ensure_time, CapturedOutput, CloneableCodec, FailureCause,
-    FlakinessControl, HostedRpcChannel, HostedRpcError, HostedRpcOwnerCell, HostedRpcTransport,
-    InProcessHostedRpcTransport, RegisteredTest, RpcFactory, SuiteResult, TestFunction, TestResult,
-    WorkerReconstructor,
+    generate_tests, get_ensure_time, get_in_process_timeout, get_worker_timeout,
+    merge_rerun_results, tests_to_rerun, CapturedOutput, CloneableCodec, DependencyFailure,
+    FailureCause, FailureLimit, FlakinessControl, HookKind, HostedRpcChannel, HostedRpcError,
+    HostedRpcOwnerCell, HostedRpcTransport, InProcessHostedRpcTransport, RegisteredTest,
+    RerunAttempt, RpcFactory, SuiteResult, TestFunction, TestResult, WorkerCrash,
+    WorkerReconstructor, WORKER_EXIT_TIMEOUT, WORKER_FINISH_TIMEOUT, WORKER_OUTPUT_TIMEOUT,
 };
 use crate::ipc::{
-    ipc_name, read_frame_async, write_frame_async, HostedRpcReplyBody, IpcCommand, IpcResponse,
+    ipc_name, read_frame_async, write_frame_async, FailedHook, HostedRpcReplyBody, IpcCommand,
+    IpcResponse,
 };
-use crate::output::{test_runner_output, TestRunnerOutput};
+use crate::limits::WorkerLimits;
+use crate::output::{interactive_rerun, test_runner_output, TestRunnerOutput};
+use crate::sync::report_failed_hooks;
 use desert_rust::{deserialize, serialize_to_byte_vec};
 use futures::FutureExt;
 use interprocess::local_socket::tokio::prelude::*;
@@ -23,9 +33,10 @@ use std::collections::VecDeque;
 use std::future::Future;
 use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
-use std::process::{ExitCode, Stdio};
+use std::process::{ExitCode, ExitStatus, Stdio};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, Command};
 use tokio::spawn;
@@ -52,10 +63,12 @@ async fn async_test_runner() -> ExitCode {
     if let Some(idx) = args.worker_index {
         crate::worker::set_worker_index(idx);
     }
+    crate::seed::set_seeds(&args);
     // Host-side output capture is installed PER retry attempt below
     // (after `finalize_for_execution`), mirroring the sync runner.
     // See `crate::host_capture` for the pipeline.
     let output = test_runner_output(&args);
+    let dependency_events = DependencyEvents::new(&args, &output);
 
     let registered_tests = internal::REGISTERED_TESTS.lock().unwrap();
     let registered_dependency_constructors =
@@ -80,7 +93,23 @@ async fn async_test_runner() -> ExitCode {
         output.test_list(&tests_with_props);
         ExitCode::SUCCESS
     } else {
-        let mut remaining_retries = args.flaky_run.unwrap_or(1);
+        // Matrix suites multiply their tests, so the summary counts these instead of `all_tests`
+        let tests_with_props =
+            internal::apply_suite_props_to_tests(&all_tests, &registered_testsuite_props);
+        if args.partition.is_some() && args.is_top_level_parent() {
+            output.tests_in_other_partitions(internal::tests_in_other_partitions(
+                &args,
+                &tests_with_props,
+            ));
+        }
+
+        if args.is_top_level_parent() {
+            if let Some(warning) = crate::limits::unsupported_limits_warning(&args, &all_tests) {
+                output.warning(&warning);
+            }
+        }
+
+        let mut remaining_retries = args.flaky_run_attempts();
 
         let mut exit_code = ExitCode::from(101);
         while remaining_retries > 0 {
@@ -90,6 +119,16 @@ async fn async_test_runner() -> ExitCode {
                 &all_tests,
                 registered_testsuite_props.as_slice(),
             );
+            execution.set_dependency_events(&dependency_events);
+            if args.last_failed
+                && args.is_top_level_parent()
+                && !filtered_tests.is_empty()
+                && !args.has_last_failed(&filtered_tests)
+            {
+                output.warning(
+                    "None of the selected tests failed when last run, running all of them",
+                );
+            }
             args.finalize_for_execution(&execution, output.clone());
             // Install host capture for this attempt, after
             // `finalize_for_execution` has decided whether workers
@@ -119,6 +158,9 @@ async fn async_test_runner() -> ExitCode {
                     hosted_owners: Vec::new(),
                     hosted_rpc_owner_cells: Vec::new(),
                     parent_constructed_shared_values: Vec::new(),
+                    failed_dependencies: Vec::new(),
+                    teardowns: Vec::new(),
+                    constructed: Vec::new(),
                 }
             };
             let cloneable_wire_bytes = parent_shared.cloneable_wire_bytes;
@@ -128,6 +170,9 @@ async fn async_test_runner() -> ExitCode {
             let hosted_rpc_owner_cells: HashMap<String, Arc<HostedRpcOwnerCell>> =
                 parent_shared.hosted_rpc_owner_cells.into_iter().collect();
             let parent_constructed_shared_values = parent_shared.parent_constructed_shared_values;
+            let failed_dependencies = Arc::new(parent_shared.failed_dependencies);
+            let parent_teardowns = parent_shared.teardowns;
+            let parent_constructed = parent_shared.constructed;
             // Pre-built RpcFactory lookup keyed by qualified id, so worker
             // subprocesses can build stubs without re-locking the global
             // REGISTERED_DEPENDENCY_CONSTRUCTORS.
@@ -167,130 +212,105 @@ async fn async_test_runner() -> ExitCode {
                         }
                     })
                     .collect();
-            // Mode-consistent Cloneable semantics for the no-spawn-workers
-            // path (e.g. `--nocapture`): reuse the parent-constructed value
-            // directly instead of re-running the user constructor in
-            // `materialize_deps`. Without this, a Cloneable dep's
-            // constructor would run twice (once for parent-side
-            // `collect_parent_shared_dependencies_async`, once for the
-            // in-process test execution), which both violates the
-            // "constructor runs once" expectation and can deadlock when
-            // the constructor takes a runtime-wide lock.
-            if is_top_level_parent && !args.spawn_workers && !cloneable_local_values.is_empty() {
-                apply_cloneable_values_locally(&mut execution, &cloneable_local_values);
-            }
-            // Mode-consistent Hosted semantics: when this is the top-level
-            // parent AND we do NOT spawn workers (e.g. --nocapture), the
-            // test functions run in this same process, but they must still
-            // see the *worker-side handle* produced by
-            // `HostedDep::from_descriptor`. Reconstruct each handle locally
-            // via the descriptor round-trip and pre-populate the execution
-            // tree.
-            if is_top_level_parent && !args.spawn_workers && !hosted_descriptor_bytes.is_empty() {
-                apply_hosted_descriptors_locally(
-                    &mut execution,
-                    &cloneable_codecs,
-                    &hosted_descriptor_bytes,
-                )
-                .await;
-            }
-            // Mode-consistent HostedRpc semantics for the no-spawn-workers
-            // path: install in-process stubs that route straight to the
-            // parent-held owner cells, so tests see the same `Stub` value
-            // whether or not the runner spawns workers.
-            if is_top_level_parent && !args.spawn_workers && !hosted_rpc_owner_cells.is_empty() {
-                install_local_hosted_rpc_stubs(
-                    &mut execution,
-                    &rpc_factories,
-                    &hosted_rpc_owner_cells,
-                );
-            }
-            // Mirror of `sync::apply_parent_constructed_shared_values_locally`:
-            // in no-spawn-workers mode, install any `Shared`/`PerWorker` dep
-            // values the parent had to construct as transitive inputs to a
-            // Cloneable/Hosted/HostedRpc dep. The in-process test thread's
-            // `materialize_deps` then reuses them instead of re-running the
-            // constructor in the same process.
-            if is_top_level_parent
-                && !args.spawn_workers
-                && !parent_constructed_shared_values.is_empty()
-            {
-                apply_parent_constructed_shared_values_locally(
-                    &mut execution,
-                    &parent_constructed_shared_values,
-                );
-            }
-            if args.spawn_workers {
-                execution.skip_creating_dependencies();
-            }
+            let cloneable_wire_bytes = Arc::new(cloneable_wire_bytes);
+            let hosted_descriptor_bytes = Arc::new(hosted_descriptor_bytes);
+            let cloneable_codecs = Arc::new(cloneable_codecs);
+            let rpc_factories = Arc::new(rpc_factories);
+            let hosted_rpc_owner_cells = Arc::new(hosted_rpc_owner_cells);
+            prepare_execution(
+                &args,
+                &mut execution,
+                &cloneable_codecs,
+                &rpc_factories,
+                &cloneable_local_values,
+                &hosted_descriptor_bytes,
+                &hosted_rpc_owner_cells,
+                &parent_constructed_shared_values,
+                &failed_dependencies,
+            )
+            .await;
 
             // println!("Execution plan: {execution:?}");
             // println!("Final args: {args:?}");
             // println!("Has dependencies: {:?}", execution.has_dependencies());
 
-            let count = execution.remaining();
-            let results = Arc::new(Mutex::new(Vec::with_capacity(count)));
+            let mut results = Vec::with_capacity(execution.remaining());
             // Parent-side per-test execution windows aligned 1:1 with
             // `results`. The host-capture finaliser uses them after all
             // test_threads finish to attribute spilled host-log records
             // to the test(s) whose window contains each record.
-            let host_windows: Arc<Mutex<Vec<crate::host_capture::HostWindow>>> =
-                Arc::new(Mutex::new(Vec::with_capacity(count)));
+            let mut host_windows: Vec<crate::host_capture::HostWindow> = Vec::new();
 
             let start = Instant::now();
             output.start_suite(&filtered_tests);
 
-            let execution = Arc::new(Mutex::new(execution));
-            let cloneable_wire_bytes = Arc::new(cloneable_wire_bytes);
-            let hosted_descriptor_bytes = Arc::new(hosted_descriptor_bytes);
-            let cloneable_codecs = Arc::new(cloneable_codecs);
-            let rpc_factories = Arc::new(rpc_factories);
-            let hosted_rpc_owner_cells = Arc::new(hosted_rpc_owner_cells);
-            let mut join_set = JoinSet::new();
-            let threads = args.test_threads().get();
-
-            for worker_idx in 0..threads {
-                let execution_clone = execution.clone();
-                let output_clone = output.clone();
-                // Stamp each test-thread's args with the worker index it will
-                // hand to its spawned child via `--worker-index <N>`.
-                let mut args_clone = args.clone();
-                if args_clone.spawn_workers {
-                    args_clone.worker_index = Some(worker_idx);
-                }
-                let results_clone = results.clone();
-                let host_windows_clone = host_windows.clone();
-                let wire_bytes_clone = cloneable_wire_bytes.clone();
-                let hosted_bytes_clone = hosted_descriptor_bytes.clone();
-                let codecs_clone = cloneable_codecs.clone();
-                let rpc_factories_clone = rpc_factories.clone();
-                let hosted_rpc_owner_cells_clone = hosted_rpc_owner_cells.clone();
-                let handle = tokio::runtime::Handle::current();
-                join_set.spawn_blocking(move || {
-                    handle.block_on(test_thread(
-                        args_clone,
-                        execution_clone,
-                        output_clone,
-                        count,
-                        results_clone,
-                        host_windows_clone,
-                        wire_bytes_clone,
-                        hosted_bytes_clone,
-                        codecs_clone,
-                        rpc_factories_clone,
-                        hosted_rpc_owner_cells_clone,
-                        host_capture_epoch,
-                    ))
-                });
-            }
-
-            while let Some(res) = join_set.join_next().await {
-                res.expect("Failed to join task");
+            // With `--rerun-failed`, the tests failed in a pass are run again
+            // in a new pass until they pass or the attempts run out. The
+            // parent-side dependencies (including the Hosted owners) are
+            // created once and shared by all the passes, including the
+            // passes of the tests selected to run again with `--tui`.
+            let max_attempts = args.max_rerun_attempts();
+            let mut pass_args = args.clone();
+            let mut rerun_attempt: Option<RerunAttempt> = None;
+            loop {
+                let pass = run_pass(
+                    &pass_args,
+                    execution,
+                    &output,
+                    rerun_attempt,
+                    &cloneable_wire_bytes,
+                    &hosted_descriptor_bytes,
+                    &failed_dependencies,
+                    &cloneable_codecs,
+                    &rpc_factories,
+                    &hosted_rpc_owner_cells,
+                    host_capture_epoch,
+                )
+                .await;
+                let failed = tests_to_rerun(&pass.results);
+                results.extend(pass.results);
+                host_windows.extend(pass.host_windows);
+
+                let attempt = rerun_attempt.map_or(1, |rerun| rerun.attempt) + 1;
+                let rerun = if failed.is_empty() || pass.stopped_early || attempt > max_attempts {
+                    match interactive_rerun(&output, &filtered_tests) {
+                        Some(selected) => {
+                            rerun_attempt = None;
+                            selected
+                        }
+                        None => break,
+                    }
+                } else {
+                    rerun_attempt = Some(RerunAttempt {
+                        attempt,
+                        max_attempts,
+                    });
+                    failed
+                };
+                pass_args = args.for_rerun(&rerun);
+                (execution, _) = TestSuiteExecution::construct(
+                    &pass_args,
+                    registered_dependency_constructors.as_slice(),
+                    &all_tests,
+                    registered_testsuite_props.as_slice(),
+                );
+                execution.set_dependency_events(&dependency_events);
+                execution.record_results(&results);
+                pass_args.finalize_for_execution(&execution, output.clone());
+                prepare_execution(
+                    &pass_args,
+                    &mut execution,
+                    &cloneable_codecs,
+                    &rpc_factories,
+                    &cloneable_local_values,
+                    &hosted_descriptor_bytes,
+                    &hosted_rpc_owner_cells,
+                    &parent_constructed_shared_values,
+                    &failed_dependencies,
+                )
+                .await;
             }
 
-            drop(execution);
-
-            let mut results = results.lock().await;
             // Drop parent-owned hosted / hosted-rpc owners BEFORE
             // finalising host capture so any `Drop` impls that
             // shutdown background threads / subprocesses get a chance
@@ -298,8 +318,16 @@ async fn async_test_runner() -> ExitCode {
             // capture pipe. If we restored fd 1/2 first, those late
             // lines would either land on the about-to-render
             // structured output or be swallowed entirely.
+            for teardown in parent_teardowns.into_iter().rev() {
+                if let Some(warning) = teardown.run().await {
+                    output.warning(&warning);
+                }
+            }
             drop(hosted_rpc_owner_cells);
             drop(_hosted_owners);
+            for constructed in parent_constructed.into_iter().rev() {
+                dependency_events.dropped(constructed);
+            }
 
             // Finalise host capture (if any) BEFORE rendering the
             // suite so the attributed host-log records make it into
@@ -307,9 +335,8 @@ async fn async_test_runner() -> ExitCode {
             if let Some(hc) = host_capture.take() {
                 let epoch_wall = host_capture_epoch_wall.unwrap_or_else(|| hc.epoch_wall());
                 let records = hc.finalize();
-                let windows = host_windows.lock().await;
                 let windows_indexed: Vec<(usize, crate::host_capture::HostWindow)> =
-                    windows.iter().copied().enumerate().collect();
+                    host_windows.iter().copied().enumerate().collect();
                 crate::host_capture::attribute_records_to_tests(
                     epoch_wall,
                     &records,
@@ -317,16 +344,223 @@ async fn async_test_runner() -> ExitCode {
                     &mut results,
                 );
             }
-            output.finished_suite(&all_tests, &results, start.elapsed());
+            let results = merge_rerun_results(results);
+            output.finished_suite(&tests_with_props, &results, start.elapsed());
             exit_code = SuiteResult::exit_code(&results);
-
-            if exit_code == ExitCode::SUCCESS {
+            remaining_retries -= 1;
+
+            if exit_code == ExitCode::SUCCESS || remaining_retries == 0 || output.is_aborted() {
+                // Recorded only after the final attempt, so the worker processes
+                // of every attempt see the same list of failed tests as the parent
+                if args.is_top_level_parent() {
+                    crate::last_failed::update(&results);
+                    crate::timings::update(&results);
+                }
                 break;
-            } else {
-                remaining_retries -= 1;
             }
         }
-        exit_code
+        if output.is_aborted() {
+            ExitCode::from(130)
+        } else {
+            exit_code
+        }
+    }
+}
+
+/// Prepares the execution plan of a pass for running in this process or for driving
+/// worker processes, reusing the dependencies the parent has already created.
+#[allow(clippy::too_many_arguments)]
+async fn prepare_execution(
+    args: &Arguments,
+    execution: &mut TestSuiteExecution,
+    cloneable_codecs: &HashMap<String, (CloneableCodec, WorkerReconstructor)>,
+    rpc_factories: &HashMap<String, RpcFactory>,
+    cloneable_local_values: &[(String, Arc<dyn Any + Send + Sync>)],
+    hosted_descriptor_bytes: &[DepWireBytes],
+    hosted_rpc_owner_cells: &HashMap<String, Arc<HostedRpcOwnerCell>>,
+    parent_constructed_shared_values: &[(String, Arc<dyn Any + Send + Sync>)],
+    failed_dependencies: &[(String, DependencyFailure)],
+) {
+    let is_top_level_parent = args.is_top_level_parent();
+    // Mode-consistent Cloneable semantics for the no-spawn-workers
+    // path (e.g. `--nocapture`): reuse the parent-constructed value
+    // directly instead of re-running the user constructor in
+    // `materialize_deps`. Without this, a Cloneable dep's
+    // constructor would run twice (once for parent-side
+    // `collect_parent_shared_dependencies_async`, once for the
+    // in-process test execution), which both violates the
+    // "constructor runs once" expectation and can deadlock when
+    // the constructor takes a runtime-wide lock.
+    if is_top_level_parent && !args.spawn_workers && !cloneable_local_values.is_empty() {
+        apply_cloneable_values_locally(execution, cloneable_local_values);
+    }
+    // Mode-consistent Hosted semantics: when this is the top-level
+    // parent AND we do NOT spawn workers (e.g. --nocapture), the
+    // test functions run in this same process, but they must still
+    // see the *worker-side handle* produced by
+    // `HostedDep::from_descriptor`. Reconstruct each handle locally
+    // via the descriptor round-trip and pre-populate the execution
+    // tree.
+    if is_top_level_parent && !args.spawn_workers && !hosted_descriptor_bytes.is_empty() {
+        apply_hosted_descriptors_locally(execution, cloneable_codecs, hosted_descriptor_bytes)
+            .await;
+    }
+    // Mode-consistent HostedRpc semantics for the no-spawn-workers
+    // path: install in-process stubs that route straight to the
+    // parent-held owner cells, so tests see the same `Stub` value
+    // whether or not the runner spawns workers.
+    if is_top_level_parent && !args.spawn_workers && !hosted_rpc_owner_cells.is_empty() {
+        install_local_hosted_rpc_stubs(execution, rpc_factories, hosted_rpc_owner_cells);
+    }
+    // Mirror of `sync::apply_parent_constructed_shared_values_locally`:
+    // in no-spawn-workers mode, install any `Shared`/`PerWorker` dep
+    // values the parent had to construct as transitive inputs to a
+    // Cloneable/Hosted/HostedRpc dep. The in-process test thread's
+    // `materialize_deps` then reuses them instead of re-running the
+    // constructor in the same process.
+    if is_top_level_parent && !args.spawn_workers && !parent_constructed_shared_values.is_empty() {
+        apply_parent_constructed_shared_values_locally(execution, parent_constructed_shared_values);
+    }
+    // Mirror of `sync::apply_failed_dependencies_locally`: worker processes
+    // get the failures over IPC instead.
+    if is_top_level_parent && !args.spawn_workers {
+        apply_failed_dependencies_locally(execution, failed_dependencies);
+    }
+    if args.spawn_workers {
+        execution.skip_creating_dependencies();
+    } else {
+        // The worker processes run a single test at a time, but in this process the tests
+        // changing the process-global environment must not overlap with any other test
+        execution.run_environment_changes_exclusively();
+    }
+}
+
+/// The results of a single pass over the selected tests.
+struct PassOutcome {
+    results: Vec<(RegisteredTest, TestResult)>,
+    /// 1:1 with `results`
+    host_windows: Vec<crate::host_capture::HostWindow>,
+    stopped_early: bool,
+}
+
+/// Runs all the tests of a prepared execution plan on `--test-threads` test threads.
+#[allow(clippy::too_many_arguments)]
+async fn run_pass(
+    args: &Arguments,
+    execution: TestSuiteExecution,
+    output: &Arc<dyn TestRunnerOutput>,
+    rerun_attempt: Option<RerunAttempt>,
+    cloneable_wire_bytes: &Arc<Vec<DepWireBytes>>,
+    hosted_descriptor_bytes: &Arc<Vec<DepWireBytes>>,
+    failed_dependencies: &Arc<Vec<(String, DependencyFailure)>>,
+    cloneable_codecs: &Arc<HashMap<String, (CloneableCodec, WorkerReconstructor)>>,
+    rpc_factories: &Arc<HashMap<String, RpcFactory>>,
+    hosted_rpc_owner_cells: &Arc<HashMap<String, Arc<HostedRpcOwnerCell>>>,
+    host_capture_epoch: Option<std::time::Instant>,
+) -> PassOutcome {
+    let count = execution.remaining();
+    let results = Arc::new(Mutex::new(Vec::with_capacity(count)));
+    let host_windows = Arc::new(Mutex::new(Vec::with_capacity(count)));
+
+    let execution = Arc::new(Mutex::new(execution));
+    let failure_limit = Arc::new(FailureLimit::new(args));
+    let mut join_set = JoinSet::new();
+    let threads = args.test_threads().get();
+
+    for worker_idx in 0..threads {
+        let execution_clone = execution.clone();
+        let output_clone = output.clone();
+        // Stamp each test-thread's args with the worker index it will
+        // hand to its spawned child via `--worker-index <N>`.
+        let mut args_clone = args.clone();
+        if args_clone.spawn_workers {
+            args_clone.worker_index = Some(worker_idx);
+        }
+        let results_clone = results.clone();
+        let host_windows_clone = host_windows.clone();
+        let wire_bytes_clone = cloneable_wire_bytes.clone();
+        let hosted_bytes_clone = hosted_descriptor_bytes.clone();
+        let failed_dependencies_clone = failed_dependencies.clone();
+        let codecs_clone = cloneable_codecs.clone();
+        let rpc_factories_clone = rpc_factories.clone();
+        let hosted_rpc_owner_cells_clone = hosted_rpc_owner_cells.clone();
+        let failure_limit_clone = failure_limit.clone();
+        let handle = tokio::runtime::Handle::current();
+        join_set.spawn_blocking(move || {
+            handle.block_on(test_thread(
+                args_clone,
+                execution_clone,
+                output_clone,
+                failure_limit_clone,
+                rerun_attempt,
+                count,
+                results_clone,
+                host_windows_clone,
+                wire_bytes_clone,
+                hosted_bytes_clone,
+                failed_dependencies_clone,
+                codecs_clone,
+                rpc_factories_clone,
+                hosted_rpc_owner_cells_clone,
+                host_capture_epoch,
+            ))
+        });
+    }
+
+    while let Some(res) = join_set.join_next().await {
+        res.expect("Failed to join task");
+    }
+
+    // Worker processes run the `after_all` hooks when the test threads
+    // retire them
+    if !args.spawn_workers {
+        let failed_hooks = run_after_all_hooks(output, &execution, count).await;
+        report_failed_hooks(output, &failed_hooks, count.saturating_sub(1), count);
+        host_windows
+            .lock()
+            .await
+            .extend(
+                failed_hooks
+                    .iter()
+                    .map(|_| crate::host_capture::HostWindow {
+                        start: Duration::ZERO,
+                        end: Duration::ZERO,
+                    }),
+            );
+        results.lock().await.extend(failed_hooks);
+        for warning in run_teardowns(&execution).await {
+            output.warning(&warning);
+        }
+    }
+
+    // Tests left in the plan were not scheduled because the failure
+    // limit was reached. They are added to the results of this pass, so
+    // running them in a later pass (with `--tui`) replaces the not run
+    // result, and they get empty execution windows.
+    let not_run_tests = execution.lock().await.take_remaining_tests();
+    host_windows
+        .lock()
+        .await
+        .extend(
+            not_run_tests
+                .iter()
+                .map(|_| crate::host_capture::HostWindow {
+                    start: Duration::ZERO,
+                    end: Duration::ZERO,
+                }),
+        );
+    results.lock().await.extend(
+        not_run_tests
+            .into_iter()
+            .map(|test| (test, TestResult::not_run())),
+    );
+
+    let results = std::mem::take(&mut *results.lock().await);
+    let host_windows = std::mem::take(&mut *host_windows.lock().await);
+    PassOutcome {
+        results,
+        host_windows,
+        stopped_early: failure_limit.is_reached() || output.is_aborted(),
     }
 }
 
@@ -335,24 +569,29 @@ async fn test_thread(
     args: Arguments,
     execution: Arc<Mutex<TestSuiteExecution>>,
     output: Arc<dyn TestRunnerOutput>,
+    failure_limit: Arc<FailureLimit>,
+    rerun_attempt: Option<RerunAttempt>,
     count: usize,
     results: Arc<Mutex<Vec<(RegisteredTest, TestResult)>>>,
     host_windows: Arc<Mutex<Vec<crate::host_capture::HostWindow>>>,
     cloneable_wire_bytes: Arc<Vec<DepWireBytes>>,
     hosted_descriptor_bytes: Arc<Vec<DepWireBytes>>,
+    failed_dependencies: Arc<Vec<(String, DependencyFailure)>>,
     cloneable_codecs: Arc<HashMap<String, (CloneableCodec, WorkerReconstructor)>>,
     rpc_factories: Arc<HashMap<String, RpcFactory>>,
     hosted_rpc_owner_cells: Arc<HashMap<String, Arc<HostedRpcOwnerCell>>>,
     host_capture_epoch: Option<std::time::Instant>,
 ) {
-    let mut worker = spawn_worker_if_needed(&args).await;
-    // Parent dispatches incoming `HostedRpcCall` frames against the owner
-    // cells materialised in the top-level parent. Workers don't need the owner
-    // cells (they own stubs instead), so they receive an empty map and the
-    // dispatch code path is never reached in subprocesses.
-    if let Some(worker) = worker.as_mut() {
-        worker.set_hosted_rpc_owner_cells(hosted_rpc_owner_cells.clone());
-    }
+    let worker_factory = WorkerFactory {
+        args: args.clone(),
+        cloneable_wire_bytes,
+        hosted_descriptor_bytes,
+        failed_dependencies,
+        hosted_rpc_owner_cells,
+    };
+    let mut worker = worker_factory
+        .spawn_if_needed(WorkerLimits::new(&args, None))
+        .await;
     let connection_arc = if let Some(ref name) = args.ipc {
         let name = ipc_name(name.clone());
         let stream = Stream::connect(name)
@@ -363,20 +602,6 @@ async fn test_thread(
         None
     };
 
-    if let Some(worker) = worker.as_mut() {
-        for (dep_id, wire_bytes) in cloneable_wire_bytes.iter() {
-            worker
-                .provide_cloneable(dep_id.clone(), wire_bytes.clone())
-                .await;
-        }
-        // Ship every Hosted dep's descriptor bytes too.
-        for (dep_id, descriptor_bytes) in hosted_descriptor_bytes.iter() {
-            worker
-                .provide_hosted_descriptor(dep_id.clone(), descriptor_bytes.clone())
-                .await;
-        }
-    }
-
     // Worker subprocess side: build a stub for every HostedRpc dep registered
     // in this binary using the IPC-backed transport sharing the same socket as
     // the main IPC loop. Install the stubs in the execution tree so dependency
@@ -393,8 +618,14 @@ async fn test_thread(
     }
 
     let mut expected_test = None;
-
-    while !is_done(&execution).await {
+    let availability_changes = execution.lock().await.availability_changes();
+
+    // Worker processes keep serving commands until the parent retires them,
+    // as it may still ask them to run the `after_all` hooks
+    'serving: while (connection_arc.is_some() || !is_done(&execution).await)
+        && !failure_limit.is_reached()
+        && !output.is_aborted()
+    {
         if let Some(connection) = connection_arc.as_ref() {
             while expected_test.is_none() {
                 let mut conn = connection.lock().await;
@@ -451,6 +682,20 @@ async fn test_thread(
                             .await
                             .expect("Failed to write IPC response frame");
                     }
+                    IpcCommand::ProvideFailedDependency { dep_id, message } => {
+                        let failure = DependencyFailure {
+                            dependency: dep_id.clone(),
+                            message,
+                        };
+                        let applied = execution
+                            .lock()
+                            .await
+                            .provide_failed_dependency(&dep_id, failure);
+                        assert!(
+                            applied,
+                            "ProvideFailedDependency for dep '{dep_id}' did not match any registered dep in this worker"
+                        );
+                    }
                     IpcCommand::HostedRpcReply { .. } => {
                         // HR1.2: replies for worker-initiated HostedRpc calls
                         // are consumed inline by the IPC transport during
@@ -465,94 +710,400 @@ async fn test_thread(
                              draining it inline"
                         );
                     }
+                    IpcCommand::Finish => {
+                        let failed_hooks = run_after_all_hooks(&output, &execution, count).await;
+                        let teardown_warnings = run_teardowns(&execution).await;
+                        let response = IpcResponse::Finished {
+                            failed_hooks: failed_hooks.iter().map(FailedHook::from).collect(),
+                            teardown_warnings,
+                            dependency_events: execution.lock().await.take_dependency_events(),
+                        };
+                        let msg = serialize_to_byte_vec(&response)
+                            .expect("Failed to encode IPC response");
+                        let mut conn = connection.lock().await;
+                        write_frame_async(&mut *conn, &msg)
+                            .await
+                            .expect("Failed to write IPC response frame");
+                        // The parent waits for the worker process to exit
+                        break 'serving;
+                    }
                 }
             }
         }
 
-        if let Some(next) = pick_next(&execution).await {
-            let skip = if let Some((name, crate_name, module_path)) = &expected_test {
-                next.test.name != *name
-                    || next.test.crate_name != *crate_name
-                    || next.test.module_path != *module_path
-            } else {
-                false
+        let seen_changes = availability_changes.count();
+        let next = match &expected_test {
+            Some(expected) => match pick_expected(&execution, expected).await {
+                Some(next) => {
+                    expected_test = None;
+                    Some(next)
+                }
+                None => {
+                    availability_changes.wait(seen_changes).await;
+                    continue;
+                }
+            },
+            None => pick_next(&execution).await,
+        };
+        if let Some(mut next) = next {
+            let ensure_time = get_ensure_time(&args, &next.test);
+            let sync_timeout = get_in_process_timeout(&args, &next.test);
+
+            // Snapshot the parent's monotonic-clock view of the
+            // test start. The matching end-instant is captured
+            // after `finished_running_test`, and the pair becomes
+            // a `HostWindow` for record attribution. Uses
+            // `std::time::Instant` because the host-capture epoch
+            // is `std::time::Instant` (tokio's `Instant` is a
+            // different type without `From` interop).
+            let window_start = std::time::Instant::now();
+
+            start_running_test(&output, rerun_attempt, &next.test, next.index, count);
+            let (result, failed_hooks) = match &next.skip_reason {
+                Some(reason) => (TestResult::skipped(reason.clone()), Vec::new()),
+                None => {
+                    run_test(
+                        output.clone(),
+                        &mut next,
+                        count,
+                        args.include_ignored,
+                        ensure_time,
+                        sync_timeout,
+                        &worker_factory,
+                        &mut worker,
+                    )
+                    .await
+                }
             };
+            next.release_per_test_dependencies();
+            report_failed_hooks(&output, &failed_hooks, next.index, count);
+            output.finished_running_test(&next.test, next.index, count, &result);
+            let window_end = std::time::Instant::now();
+
+            if let Some(connection) = connection_arc.as_ref() {
+                let finish_marker = Uuid::new_v4().to_string();
+                let finish_marker_line = format!("{finish_marker}\n");
+                tokio::io::stdout()
+                    .write_all(finish_marker_line.as_bytes())
+                    .await
+                    .unwrap();
+                tokio::io::stderr()
+                    .write_all(finish_marker_line.as_bytes())
+                    .await
+                    .unwrap();
+                tokio::io::stdout().flush().await.unwrap();
+                tokio::io::stderr().flush().await.unwrap();
+
+                let response = IpcResponse::TestFinished {
+                    result: (&result).into(),
+                    finish_marker,
+                    failed_hooks: failed_hooks.iter().map(FailedHook::from).collect(),
+                    dependency_events: execution.lock().await.take_dependency_events(),
+                };
+                let msg = serialize_to_byte_vec(&response).expect("Failed to encode IPC response");
+                let mut conn = connection.lock().await;
+                write_frame_async(&mut *conn, &msg)
+                    .await
+                    .expect("Failed to write IPC response frame");
+            }
 
-            if !skip {
-                expected_test = None;
+            // Push the result and its window under the same
+            // critical section so the two vecs stay aligned in the
+            // face of concurrent pushes from sibling test_threads.
+            let window = crate::host_capture::HostWindow::from_instants(
+                host_capture_epoch,
+                window_start,
+                window_end,
+            )
+            .unwrap_or(crate::host_capture::HostWindow {
+                start: std::time::Duration::ZERO,
+                end: std::time::Duration::ZERO,
+            });
+            failure_limit.record(&result);
+            next.record_result(&result);
+            let mut results_guard = results.lock().await;
+            let mut windows_guard = host_windows.lock().await;
+            for (entry, hook_result) in failed_hooks {
+                failure_limit.record(&hook_result);
+                results_guard.push((entry, hook_result));
+                windows_guard.push(window);
+            }
+            results_guard.push((next.test.clone(), result));
+            windows_guard.push(window);
+        } else {
+            // The remaining tests wait for their prerequisites or resources, which are released
+            // by the other test tasks. If the last test was picked by another task since the loop
+            // condition was checked, there is nothing left to wait for.
+            if !is_done(&execution).await {
+                availability_changes.wait(seen_changes).await;
+            }
+        }
+    }
 
-                let ensure_time = get_ensure_time(&args, &next.test);
+    if let Some(mut worker) = worker.take() {
+        let failed_hooks = worker.finish(&output).await;
+        report_failed_hooks(&output, &failed_hooks, count.saturating_sub(1), count);
+        host_windows
+            .lock()
+            .await
+            .extend(
+                failed_hooks
+                    .iter()
+                    .map(|_| crate::host_capture::HostWindow {
+                        start: Duration::ZERO,
+                        end: Duration::ZERO,
+                    }),
+            );
+        results.lock().await.extend(failed_hooks);
+    }
+}
 
-                // Snapshot the parent's monotonic-clock view of the
-                // test start. The matching end-instant is captured
-                // after `finished_running_test`, and the pair becomes
-                // a `HostWindow` for record attribution. Uses
-                // `std::time::Instant` because the host-capture epoch
-                // is `std::time::Instant` (tokio's `Instant` is a
-                // different type without `From` interop).
-                let window_start = std::time::Instant::now();
+/// Runs a test in this process surrounded by the hooks of its suites. Mirrors
+/// `sync::run_with_hooks`.
+async fn run_with_hooks(
+    output: &Arc<dyn TestRunnerOutput>,
+    next: &TestExecution,
+    count: usize,
+    run_test: impl Future<Output = TestResult>,
+) -> (TestResult, Vec<(RegisteredTest, TestResult)>) {
+    let mut failed_hooks = Vec::new();
+
+    for suite in &next.suites {
+        let failed = suite
+            .before_all(&next.deps, async {
+                for hook in suite.hooks(HookKind::BeforeAll) {
+                    let entry = suite.entry(hook, None);
+                    if !run_hook(output, next, count, entry, &mut failed_hooks).await {
+                        return Some(hook.name.clone());
+                    }
+                }
+                None
+            })
+            .await;
+        if let Some(hook) = failed {
+            let result = TestResult::skipped(format!("before_all hook {hook} failed"));
+            return (result, failed_hooks);
+        }
+    }
+
+    let mut skip_reason = None;
+    'suites: for suite in &next.suites {
+        for hook in suite.hooks(HookKind::BeforeEach) {
+            let entry = suite.entry(hook, Some(&next.test));
+            if !run_hook(output, next, count, entry, &mut failed_hooks).await {
+                skip_reason = Some(format!("before_each hook {} failed", hook.name));
+                break 'suites;
+            }
+        }
+    }
+    let result = match skip_reason {
+        Some(reason) => TestResult::skipped(reason),
+        None => run_test.await,
+    };
+    for suite in next.suites.iter().rev() {
+        for hook in suite.hooks(HookKind::AfterEach) {
+            let entry = suite.entry(hook, Some(&next.test));
+            run_hook(output, next, count, entry, &mut failed_hooks).await;
+        }
+    }
+    (result, failed_hooks)
+}
+
+/// Runs a `before_*` or `after_each` hook of the test `next`, recording it in `failed_hooks` if
+/// it fails. Returns whether it passed.
+async fn run_hook(
+    output: &Arc<dyn TestRunnerOutput>,
+    next: &TestExecution,
+    count: usize,
+    entry: RegisteredTest,
+    failed_hooks: &mut Vec<(RegisteredTest, TestResult)>,
+) -> bool {
+    let result = run_test_in_process(
+        output.clone(),
+        next.index,
+        count,
+        None,
+        None,
+        next.deps.clone(),
+        None,
+        None,
+        &entry,
+    )
+    .await;
+    let passed = !result.is_failed();
+    if !passed {
+        failed_hooks.push((entry, result));
+    }
+    passed
+}
 
-                output.start_running_test(&next.test, next.index, count);
-                let result = run_test(
+/// Runs the `after_all` hooks of the suites that had tests running in this process, inner
+/// suites first, and returns the ones that failed. Mirrors `sync::run_after_all_hooks`.
+async fn run_after_all_hooks(
+    output: &Arc<dyn TestRunnerOutput>,
+    execution: &Arc<Mutex<TestSuiteExecution>>,
+    count: usize,
+) -> Vec<(RegisteredTest, TestResult)> {
+    let suites = execution.lock().await.suite_hooks();
+    let mut failed_hooks = Vec::new();
+    for suite in suites.iter().rev() {
+        if let Some(deps) = suite.start_after_all() {
+            for hook in suite.hooks(HookKind::AfterAll) {
+                let entry = suite.entry(hook, None);
+                let result = run_test_in_process(
                     output.clone(),
-                    next.index,
+                    count.saturating_sub(1),
                     count,
-                    args.nocapture,
-                    args.include_ignored,
-                    ensure_time,
-                    next.deps.clone(),
-                    &next.test,
-                    &mut worker,
+                    None,
+                    None,
+                    deps.clone(),
+                    None,
+                    None,
+                    &entry,
                 )
                 .await;
-                output.finished_running_test(&next.test, next.index, count, &result);
-                let window_end = std::time::Instant::now();
-
-                if let Some(connection) = connection_arc.as_ref() {
-                    let finish_marker = Uuid::new_v4().to_string();
-                    let finish_marker_line = format!("{finish_marker}\n");
-                    tokio::io::stdout()
-                        .write_all(finish_marker_line.as_bytes())
-                        .await
-                        .unwrap();
-                    tokio::io::stderr()
-                        .write_all(finish_marker_line.as_bytes())
-                        .await
-                        .unwrap();
-                    tokio::io::stdout().flush().await.unwrap();
-                    tokio::io::stderr().flush().await.unwrap();
-
-                    let response = IpcResponse::TestFinished {
-                        result: (&result).into(),
-                        finish_marker,
-                    };
-                    let msg =
-                        serialize_to_byte_vec(&response).expect("Failed to encode IPC response");
-                    let mut conn = connection.lock().await;
-                    write_frame_async(&mut *conn, &msg)
-                        .await
-                        .expect("Failed to write IPC response frame");
+                if result.is_failed() {
+                    failed_hooks.push((entry, result));
                 }
-
-                // Push the result and its window under the same
-                // critical section so the two vecs stay aligned in the
-                // face of concurrent pushes from sibling test_threads.
-                let window = crate::host_capture::HostWindow::from_instants(
-                    host_capture_epoch,
-                    window_start,
-                    window_end,
-                )
-                .unwrap_or(crate::host_capture::HostWindow {
-                    start: std::time::Duration::ZERO,
-                    end: std::time::Duration::ZERO,
-                });
-                let mut results_guard = results.lock().await;
-                let mut windows_guard = host_windows.lock().await;
-                results_guard.push((next.test.clone(), result));
-                windows_guard.push(window);
             }
         }
     }
+    failed_hooks
+}
+
+/// Runs the teardowns of the dependencies constructed in this process, in reverse construction
+/// order, then drops the dependencies. Returns the warnings of the teardowns that failed.
+/// Mirrors `sync::run_teardowns`.
+async fn run_teardowns(execution: &Arc<Mutex<TestSuiteExecution>>) -> Vec<String> {
+    let teardowns = execution.lock().await.take_teardowns();
+    let mut warnings = Vec::new();
+    for teardown in teardowns {
+        warnings.extend(teardown.run().await);
+    }
+    execution.lock().await.release_dependencies();
+    warnings
+}
+
+/// Everything a test thread needs to spawn and prepare worker processes.
+/// Mirrors `sync::WorkerFactory`.
+struct WorkerFactory {
+    args: Arguments,
+    cloneable_wire_bytes: Arc<Vec<DepWireBytes>>,
+    hosted_descriptor_bytes: Arc<Vec<DepWireBytes>>,
+    failed_dependencies: Arc<Vec<(String, DependencyFailure)>>,
+    hosted_rpc_owner_cells: Arc<HashMap<String, Arc<HostedRpcOwnerCell>>>,
+}
+
+impl WorkerFactory {
+    /// Spawns a worker process (if `--spawn-workers` is on) with the given
+    /// limits and prepares it for running tests. Mirrors
+    /// `sync::WorkerFactory::spawn_if_needed`.
+    async fn spawn_if_needed(&self, limits: WorkerLimits) -> Option<Worker> {
+        let mut worker = spawn_worker_if_needed(&self.args, limits).await?;
+        // Parent dispatches incoming `HostedRpcCall` frames against the owner
+        // cells materialised in the top-level parent. Workers don't need the
+        // owner cells (they own stubs instead), so they receive an empty map
+        // and the dispatch code path is never reached in subprocesses.
+        worker.set_hosted_rpc_owner_cells(self.hosted_rpc_owner_cells.clone());
+        for (dep_id, wire_bytes) in self.cloneable_wire_bytes.iter() {
+            worker
+                .provide_cloneable(dep_id.clone(), wire_bytes.clone())
+                .await;
+        }
+        // Ship every Hosted dep's descriptor bytes too.
+        for (dep_id, descriptor_bytes) in self.hosted_descriptor_bytes.iter() {
+            worker
+                .provide_hosted_descriptor(dep_id.clone(), descriptor_bytes.clone())
+                .await;
+        }
+        // And the deps whose constructor failed.
+        for (dep_id, failure) in self.failed_dependencies.iter() {
+            worker
+                .provide_failed_dependency(dep_id.clone(), failure.message.clone())
+                .await;
+        }
+        Some(worker)
+    }
+}
+
+/// Runs a test in the test thread's worker process, replacing the worker if
+/// it was killed or crashed, retrying crashed tests and giving isolated tests
+/// and tests with their own memory limit a fresh worker. Mirrors
+/// `sync::run_test_in_worker`.
+async fn run_test_in_worker(
+    worker_factory: &WorkerFactory,
+    worker: &mut Option<Worker>,
+    output: &Arc<dyn TestRunnerOutput>,
+    test: &RegisteredTest,
+    idx: usize,
+    count: usize,
+) -> (TestResult, Vec<(RegisteredTest, TestResult)>) {
+    let isolated =
+        worker_factory.args.isolate || test.props.is_isolated || test.props.memory_limit.is_some();
+    let limits = WorkerLimits::new(&worker_factory.args, test.props.memory_limit);
+    let max_attempts = worker_factory.args.retry_crashed.unwrap_or(0) + 1;
+    let mut attempt = 1;
+    let mut all_failed_hooks = Vec::new();
+    loop {
+        if let Some(mut unsuitable) =
+            worker.take_if(|worker| (isolated && worker.has_run_tests()) || worker.limits != limits)
+        {
+            all_failed_hooks.extend(unsuitable.finish(output).await);
+        }
+        if worker.is_none() {
+            *worker = worker_factory.spawn_if_needed(limits).await;
+        }
+        let current = worker
+            .as_mut()
+            .expect("No worker process to run the test in");
+        let (result, failed_hooks) = current
+            .run_test(output, worker_factory.args.nocapture, test)
+            .await;
+        all_failed_hooks.extend(failed_hooks);
+        if isolated {
+            all_failed_hooks.extend(current.finish(output).await);
+        }
+        if current.has_exited() {
+            *worker = None;
+        }
+
+        let crashed = result
+            .failure_cause()
+            .is_some_and(FailureCause::is_worker_crash);
+        if crashed && attempt < max_attempts {
+            attempt += 1;
+            output.repeat_running_test(
+                test,
+                idx,
+                count,
+                attempt,
+                max_attempts,
+                "because its worker process crashed",
+            );
+        } else {
+            break (result, all_failed_hooks);
+        }
+    }
+}
+
+fn start_running_test(
+    output: &Arc<dyn TestRunnerOutput>,
+    rerun_attempt: Option<RerunAttempt>,
+    test: &RegisteredTest,
+    idx: usize,
+    count: usize,
+) {
+    match rerun_attempt {
+        Some(rerun) => output.repeat_running_test(
+            test,
+            idx,
+            count,
+            rerun.attempt,
+            rerun.max_attempts,
+            "because it failed in the previous pass",
+        ),
+        None => output.start_running_test(test, idx, count),
+    }
 }
 
 async fn is_done(execution: &Arc<Mutex<TestSuiteExecution>>) -> bool {
@@ -623,6 +1174,20 @@ fn apply_cloneable_values_locally(
     }
 }
 
+/// Tokio counterpart to `sync::apply_failed_dependencies_locally`.
+fn apply_failed_dependencies_locally(
+    execution: &mut TestSuiteExecution,
+    failed_dependencies: &[(String, DependencyFailure)],
+) {
+    for (dep_id, failure) in failed_dependencies {
+        let applied = execution.provide_failed_dependency(dep_id, failure.clone());
+        assert!(
+            applied,
+            "Failed dep '{dep_id}' could not be marked as failed locally"
+        );
+    }
+}
+
 /// Tokio counterpart to `sync::apply_parent_constructed_shared_values_locally`.
 /// In no-spawn-workers mode, installs `Shared`/`PerWorker` dep values that
 /// the parent had to construct as transitive inputs to a
@@ -682,6 +1247,22 @@ async fn pick_next(execution: &Arc<Mutex<TestSuiteExecution>>) -> Option<TestExe
     execution.pick_next().await
 }
 
+/// Async counterpart of `sync::pick_expected`
+async fn pick_expected(
+    execution: &Arc<Mutex<TestSuiteExecution>>,
+    (name, crate_name, module_path): &(String, String, String),
+) -> Option<TestExecution> {
+    let is_expected = |test: &RegisteredTest| {
+        &test.name == name && &test.crate_name == crate_name && &test.module_path == module_path
+    };
+    let mut execution = execution.lock().await;
+    let next = execution.pick_next_matching(&is_expected).await;
+    if next.is_none() && !execution.has_matching_test(&is_expected) {
+        panic!("Test {crate_name}::{module_path}::{name} requested by the test runner was not found in the worker")
+    }
+    next
+}
+
 async fn run_with_flakiness_control<F>(
     output: Arc<dyn TestRunnerOutput>,
     test_description: &RegisteredTest,
@@ -692,6 +1273,7 @@ async fn run_with_flakiness_control<F>(
 where
     F: Fn(
             Instant,
+            usize,
         )
             -> Pin<Box<dyn Future<Output = Result<Result<(), FailureCause>, Box<dyn Any + Send>>>>>
         + Send
@@ -700,7 +1282,7 @@ where
     match &test_description.props.flakiness_control {
         FlakinessControl::None => {
             let start = Instant::now();
-            test(start).await
+            test(start, 1).await
         }
         FlakinessControl::ProveNonFlaky(tries) => {
             for n in 0..*tries {
@@ -715,7 +1297,7 @@ where
                     );
                 }
                 let start = Instant::now();
-                match test(start).await {
+                match test(start, n + 1).await {
                     Ok(Ok(())) => {}
                     Ok(Err(e)) => return Ok(Err(e)),
                     Err(e) => return Err(e),
@@ -727,7 +1309,7 @@ where
             let mut tries = 1;
             loop {
                 let start = Instant::now();
-                let result = test(start).await;
+                let result = test(start, tries).await;
 
                 if result.is_err() && tries < *max_retries {
                     tries += 1;
@@ -750,174 +1332,224 @@ where
 #[allow(clippy::too_many_arguments)]
 async fn run_test(
     output: Arc<dyn TestRunnerOutput>,
-    idx: usize,
+    next: &mut TestExecution,
     count: usize,
-    nocapture: bool,
     include_ignored: bool,
     ensure_time: Option<TimeThreshold>,
+    sync_timeout: Option<Duration>,
+    worker_factory: &WorkerFactory,
+    worker: &mut Option<Worker>,
+) -> (TestResult, Vec<(RegisteredTest, TestResult)>) {
+    if next.test.props.is_ignored && !include_ignored {
+        (TestResult::ignored(), Vec::new())
+    } else if worker_factory.args.spawn_workers {
+        run_test_in_worker(
+            worker_factory,
+            worker,
+            &output,
+            &next.test,
+            next.index,
+            count,
+        )
+        .await
+    } else if let Some(result) = next.create_per_test_dependencies().await {
+        (result, Vec::new())
+    } else {
+        let run = with_test_environment_async(&next.test.props, next.guard(), |guard| {
+            run_test_in_process(
+                output.clone(),
+                next.index,
+                count,
+                ensure_time,
+                sync_timeout,
+                next.deps.clone(),
+                next.per_test_dependencies(),
+                Some(guard),
+                &next.test,
+            )
+        });
+        run_with_hooks(&output, next, count, run).await
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run_test_in_process(
+    output: Arc<dyn TestRunnerOutput>,
+    idx: usize,
+    count: usize,
+    ensure_time: Option<TimeThreshold>,
+    sync_timeout: Option<Duration>,
     dependency_view: Arc<dyn internal::DependencyView + Send + Sync>,
+    per_test_dependencies: Option<PerTestDependencies>,
+    guard: Option<TestGuard>,
     test: &RegisteredTest,
-    worker: &mut Option<Worker>,
 ) -> TestResult {
-    if test.props.is_ignored && !include_ignored {
-        TestResult::ignored()
-    } else if let Some(worker) = worker.as_mut() {
-        worker.run_test(nocapture, test).await
-    } else {
-        let start = Instant::now();
-        let test = test.clone();
-        match &test.run {
-            TestFunction::Sync(_) => {
-                let handle = spawn_blocking(move || {
-                    let test = test.clone();
-                    crate::sync::run_sync_test_function(
-                        output,
-                        &test,
-                        idx,
-                        count,
-                        ensure_time,
-                        dependency_view,
-                    )
-                });
-                handle.await.unwrap_or_else(|join_error| {
-                    TestResult::failed(
-                        start.elapsed(),
-                        FailureCause::HarnessError(format!(
-                            "Failed joining test task: {join_error}"
-                        )),
-                    )
-                })
-            }
-            TestFunction::Async(test_fn) => {
-                let timeout = test.props.timeout;
+    let start = Instant::now();
+    let test = test.clone();
+    match &test.run {
+        TestFunction::Sync(_) => {
+            let handle = spawn_blocking(move || {
+                let test = test.clone();
+                crate::sync::run_sync_test_function(
+                    output,
+                    &test,
+                    idx,
+                    count,
+                    ensure_time,
+                    sync_timeout,
+                    dependency_view,
+                    per_test_dependencies,
+                    guard,
+                )
+            });
+            handle.await.unwrap_or_else(|join_error| {
+                TestResult::failed(
+                    start.elapsed(),
+                    FailureCause::HarnessError(format!("Failed joining test task: {join_error}")),
+                )
+            })
+        }
+        TestFunction::Async(test_fn) => {
+            let timeout = test.props.timeout;
+            let test_fn = test_fn.clone();
+            let detached_panic_policy = test.props.detached_panic_policy.clone();
+            let result = run_with_flakiness_control(output, &test, idx, count, |start, attempt| {
+                let context = Arc::new(TestContext::new(&test, attempt));
+                let dependency_view =
+                    WithTestContext::wrap(context.clone(), dependency_view.clone());
                 let test_fn = test_fn.clone();
-                let detached_panic_policy = test.props.detached_panic_policy.clone();
-                let result = run_with_flakiness_control(output, &test, idx, count, |start| {
-                    let dependency_view = dependency_view.clone();
-                    let test_fn = test_fn.clone();
-                    Box::pin(async move {
-                        let test_id = crate::panic_hook::next_test_id();
-                        crate::panic_hook::set_current_test_id(test_id);
-                        crate::panic_hook::create_detached_collector(test_id);
-                        let result = AssertUnwindSafe(Box::pin(async move {
-                            match timeout {
-                                None => test_fn(dependency_view).await,
-                                Some(duration) => {
-                                    let result =
-                                        tokio::time::timeout(duration, test_fn(dependency_view))
-                                            .await;
-                                    match result {
-                                        Ok(result) => result,
-                                        Err(_) => {
-                                            return Err(FailureCause::HarnessError(
-                                                "Test timed out".to_string(),
-                                            ))
-                                        }
+                let should_panic = test.props.should_panic.clone();
+                let per_test_dependencies = per_test_dependencies.clone();
+                Box::pin(async move {
+                    if attempt > 1 {
+                        if let Some(per_test_dependencies) = per_test_dependencies {
+                            if let Err(cause) = per_test_dependencies.create().await {
+                                return Ok(Err(cause));
+                            }
+                        }
+                    }
+                    let test_id = crate::panic_hook::next_test_id();
+                    crate::panic_hook::set_current_test_id(test_id);
+                    crate::panic_hook::create_detached_collector(test_id);
+                    let result = AssertUnwindSafe(Box::pin(async move {
+                        match timeout {
+                            None => test_fn(dependency_view).await,
+                            Some(duration) => {
+                                let result =
+                                    tokio::time::timeout(duration, test_fn(dependency_view)).await;
+                                match result {
+                                    Ok(result) => result,
+                                    Err(_) => {
+                                        return Err(FailureCause::HarnessError(
+                                            "Test timed out".to_string(),
+                                        ))
                                     }
                                 }
                             }
-                            .into_result()?;
-                            if let Some(ensure_time) = ensure_time {
-                                let elapsed = start.elapsed();
-                                if ensure_time.is_critical(&elapsed) {
-                                    return Err(FailureCause::HarnessError(format!(
-                                        "Test run time exceeds critical threshold: {elapsed:?}"
-                                    )));
-                                }
+                        }
+                        .into_result()?;
+                        if let Some(ensure_time) = ensure_time {
+                            let elapsed = start.elapsed();
+                            if ensure_time.is_critical(&elapsed) {
+                                return Err(FailureCause::HarnessError(format!(
+                                    "Test run time exceeds critical threshold: {elapsed:?}"
+                                )));
                             }
-                            Ok(())
-                        }))
-                        .catch_unwind()
-                        .await;
-                        result
-                    })
-                })
-                .await;
-                let mut test_result =
-                    TestResult::from_result(&test.props.should_panic, start.elapsed(), result);
-                if let Some(test_id) = crate::panic_hook::current_test_id() {
-                    if let Some(collector) = crate::panic_hook::take_detached_collector(test_id) {
-                        let panics = match collector.lock() {
-                            Ok(p) => p,
-                            Err(poisoned) => poisoned.into_inner(),
-                        };
-                        if !panics.is_empty()
-                            && detached_panic_policy == internal::DetachedPanicPolicy::FailTest
-                            && test_result.is_passed()
-                        {
-                            let messages: Vec<String> = panics.iter().map(|p| p.render()).collect();
-                            test_result = TestResult::failed(
-                                start.elapsed(),
-                                FailureCause::Panic(internal::PanicCause {
-                                    message: Some(format!(
-                                        "Detached task(s) panicked:\n{}",
-                                        messages.join("\n---\n")
-                                    )),
-                                    location: panics.first().and_then(|p| p.location.clone()),
-                                    backtrace: panics.first().and_then(|p| p.backtrace.clone()),
-                                }),
-                            );
                         }
+                        Ok(())
+                    }))
+                    .catch_unwind()
+                    .await;
+                    context.finish(attempt_passed(&should_panic, &result));
+                    result
+                })
+            })
+            .await;
+            let mut test_result =
+                TestResult::from_result(&test.props.should_panic, start.elapsed(), result);
+            if let Some(test_id) = crate::panic_hook::current_test_id() {
+                if let Some(collector) = crate::panic_hook::take_detached_collector(test_id) {
+                    let panics = match collector.lock() {
+                        Ok(p) => p,
+                        Err(poisoned) => poisoned.into_inner(),
+                    };
+                    if !panics.is_empty()
+                        && detached_panic_policy == internal::DetachedPanicPolicy::FailTest
+                        && test_result.is_passed()
+                    {
+                        let messages: Vec<String> = panics.iter().map(|p| p.render()).collect();
+                        test_result = TestResult::failed(
+                            start.elapsed(),
+                            FailureCause::Panic(internal::PanicCause {
+                                message: Some(format!(
+                                    "Detached task(s) panicked:\n{}",
+                                    messages.join("\n---\n")
+                                )),
+                                location: panics.first().and_then(|p| p.location.clone()),
+                                backtrace: panics.first().and_then(|p| p.backtrace.clone()),
+                            }),
+                        );
                     }
                 }
-                crate::panic_hook::clear_current_test_id();
-                test_result
             }
-            TestFunction::SyncBench(_) => {
-                let handle = spawn_blocking(move || {
-                    let test = test.clone();
-                    crate::sync::run_sync_test_function(
-                        output,
-                        &test,
-                        idx,
-                        count,
-                        ensure_time,
-                        dependency_view,
-                    )
-                });
-                handle.await.unwrap_or_else(|join_error| {
-                    TestResult::failed(
-                        start.elapsed(),
-                        FailureCause::HarnessError(format!(
-                            "Failed joining test task: {join_error}"
-                        )),
-                    )
-                })
-            }
-            TestFunction::AsyncBench(bench_fn) => {
-                let mut bencher = AsyncBencher::new();
-                let test_id = crate::panic_hook::next_test_id();
-                crate::panic_hook::set_current_test_id(test_id);
-                let result = AssertUnwindSafe(async move {
-                    bench_fn(&mut bencher, dependency_view).await;
-                    (
-                        bencher
-                            .summary()
-                            .expect("iter() was not called in bench function"),
-                        bencher.bytes,
-                    )
-                })
-                .catch_unwind()
-                .await;
-                let bytes = result.as_ref().map(|(_, bytes)| *bytes).unwrap_or_default();
-                let test_result = TestResult::from_summary(
-                    &test.props.should_panic,
+            crate::panic_hook::clear_current_test_id();
+            test_result
+        }
+        TestFunction::SyncBench(_) => {
+            let handle = spawn_blocking(move || {
+                let test = test.clone();
+                crate::sync::run_sync_test_function(
+                    output,
+                    &test,
+                    idx,
+                    count,
+                    ensure_time,
+                    sync_timeout,
+                    dependency_view,
+                    per_test_dependencies,
+                    guard,
+                )
+            });
+            handle.await.unwrap_or_else(|join_error| {
+                TestResult::failed(
                     start.elapsed(),
-                    result.map(|(summary, _)| summary),
-                    bytes,
-                );
-                crate::panic_hook::clear_current_test_id();
-                test_result
-            }
+                    FailureCause::HarnessError(format!("Failed joining test task: {join_error}")),
+                )
+            })
+        }
+        TestFunction::AsyncBench(bench_fn) => {
+            let mut bencher = AsyncBencher::new();
+            let test_id = crate::panic_hook::next_test_id();
+            crate::panic_hook::set_current_test_id(test_id);
+            let result = AssertUnwindSafe(async move {
+                bench_fn(&mut bencher, dependency_view).await;
+                (
+                    bencher
+                        .summary()
+                        .expect("iter() was not called in bench function"),
+                    bencher.bytes,
+                )
+            })
+            .catch_unwind()
+            .await;
+            let bytes = result.as_ref().map(|(_, bytes)| *bytes).unwrap_or_default();
+            let test_result = TestResult::from_summary(
+                &test.props.should_panic,
+                start.elapsed(),
+                result.map(|(summary, _)| summary),
+                bytes,
+            );
+            crate::panic_hook::clear_current_test_id();
+            test_result
         }
     }
 }
 
 struct Worker {
     _listener: Listener,
-    _process: Child,
-    _out_handle: JoinHandle<()>,
-    _err_handle: JoinHandle<()>,
+    process: Child,
+    out_handle: JoinHandle<()>,
+    err_handle: JoinHandle<()>,
     out_lines: Arc<Mutex<VecDeque<CapturedOutput>>>,
     err_lines: Arc<Mutex<VecDeque<CapturedOutput>>>,
     capture_enabled: Arc<Mutex<bool>>,
@@ -926,9 +1558,24 @@ struct Worker {
     /// to dispatch incoming `IpcResponse::HostedRpcCall` frames from the worker
     /// subprocess back to the right owner.
     hosted_rpc_owner_cells: Arc<HashMap<String, Arc<HostedRpcOwnerCell>>>,
+    /// Set once the worker process has been killed. The test thread replaces
+    /// an exited worker before running its next test.
+    exited: bool,
+    /// Set once the worker has been asked to run a test
+    used: bool,
+    /// Resource limits the worker process was spawned with
+    limits: WorkerLimits,
 }
 
 impl Worker {
+    fn has_exited(&self) -> bool {
+        self.exited
+    }
+
+    fn has_run_tests(&self) -> bool {
+        self.used
+    }
+
     /// Installs the parent-side map of HostedRpc owner cells so this worker can
     /// route incoming `IpcResponse::HostedRpcCall` frames to the right
     /// `HostedRpcOwnerCell` while waiting for a worker subprocess response.
@@ -943,12 +1590,11 @@ impl Worker {
     /// `sync::Worker::handle_hosted_rpc_call`.
     async fn handle_hosted_rpc_call(
         &mut self,
-        dump_on_ipc_failure: &DumpOnFailure,
         request_id: u64,
         dep_id: String,
         method_idx: u32,
         args_bytes: Vec<u8>,
-    ) {
+    ) -> std::io::Result<()> {
         let body = match self.hosted_rpc_owner_cells.get(&dep_id) {
             // Use the async dispatch entry point so an owner that implements
             // `AsyncHostedRpcDep` directly can `.await` inside its dispatcher
@@ -967,12 +1613,16 @@ impl Worker {
         };
         let reply = IpcCommand::HostedRpcReply { request_id, body };
         let msg = serialize_to_byte_vec(&reply).expect("Failed to encode HostedRpcReply");
-        dump_on_ipc_failure
-            .run(write_frame_async(&mut self.connection, &msg).await)
-            .await;
+        write_frame_async(&mut self.connection, &msg).await
     }
 
-    pub async fn run_test(&mut self, nocapture: bool, test: &RegisteredTest) -> TestResult {
+    pub async fn run_test(
+        &mut self,
+        output: &Arc<dyn TestRunnerOutput>,
+        nocapture: bool,
+        test: &RegisteredTest,
+    ) -> (TestResult, Vec<(RegisteredTest, TestResult)>) {
+        self.used = true;
         let mut capture_enabled = self.capture_enabled.lock().await;
         *capture_enabled = test.props.capture_control.requires_capturing(!nocapture);
         drop(capture_enabled);
@@ -987,47 +1637,48 @@ impl Worker {
         let dump_on_ipc_failure = self.dump_on_failure();
 
         let msg = serialize_to_byte_vec(&cmd).expect("Failed to encode IPC command");
-        dump_on_ipc_failure
-            .run(write_frame_async(&mut self.connection, &msg).await)
-            .await;
 
-        let response = loop {
-            let response_bytes = dump_on_ipc_failure
-                .run(read_frame_async(&mut self.connection).await)
-                .await;
-            let response: IpcResponse = dump_on_ipc_failure.run(deserialize(&response_bytes)).await;
-            match response {
-                IpcResponse::TestFinished { .. } => break response,
-                IpcResponse::CloneableAccepted { .. }
-                | IpcResponse::HostedDescriptorAccepted { .. } => continue,
-                IpcResponse::HostedRpcCall {
-                    request_id,
-                    dep_id,
-                    method_idx,
-                    args_bytes,
-                } => {
-                    self.handle_hosted_rpc_call(
-                        &dump_on_ipc_failure,
-                        request_id,
-                        dep_id,
-                        method_idx,
-                        args_bytes,
-                    )
-                    .await;
-                    continue;
+        let start = Instant::now();
+        let outcome = match get_worker_timeout(test) {
+            None => self.send_and_wait(&msg, &dump_on_ipc_failure).await,
+            Some(timeout) => {
+                // Unlike the sync runner's watchdog thread, the parent can
+                // simply stop waiting here; the worker is then killed and
+                // replaced because its test may still be running.
+                match tokio::time::timeout(timeout, self.send_and_wait(&msg, &dump_on_ipc_failure))
+                    .await
+                {
+                    Ok(outcome) => outcome,
+                    Err(_) => {
+                        let result = self.timed_out(nocapture, test, start.elapsed()).await;
+                        return (result, Vec::new());
+                    }
                 }
             }
         };
+        // An I/O error means the worker process is gone without reporting
+        // the test's result.
+        let Ok(response) = outcome else {
+            return (
+                self.crashed(nocapture, test, start.elapsed()).await,
+                Vec::new(),
+            );
+        };
 
         let IpcResponse::TestFinished {
             result,
             finish_marker,
+            failed_hooks,
+            dependency_events,
         } = response
         else {
-            unreachable!("loop only breaks on TestFinished")
+            unreachable!("the worker answers RunTest with TestFinished")
         };
+        for event in &dependency_events {
+            output.dependency_event(event);
+        }
 
-        if test.props.capture_control.requires_capturing(!nocapture) {
+        let result = if test.props.capture_control.requires_capturing(!nocapture) {
             let out_lines: Vec<_> =
                 Self::drain_until(self.out_lines.clone(), finish_marker.clone()).await;
             let err_lines: Vec<_> =
@@ -1035,7 +1686,169 @@ impl Worker {
             result.into_test_result(out_lines, err_lines)
         } else {
             result.into_test_result(Vec::new(), Vec::new())
+        };
+        (result, failed_hooks.into_iter().map(Into::into).collect())
+    }
+
+    /// Runs the `after_all` hooks of the suites the worker process ran tests
+    /// of and the teardowns of its dependencies, then stops the worker.
+    /// Returns the hooks that failed. Mirrors `sync::Worker::finish`.
+    async fn finish(
+        &mut self,
+        output: &Arc<dyn TestRunnerOutput>,
+    ) -> Vec<(RegisteredTest, TestResult)> {
+        let mut failed_hooks = Vec::new();
+        let mut finished = false;
+        if self.used && !self.exited {
+            let dump_on_ipc_failure = self.dump_on_failure();
+            let msg =
+                serialize_to_byte_vec(&IpcCommand::Finish).expect("Failed to encode IPC command");
+            match tokio::time::timeout(
+                WORKER_FINISH_TIMEOUT,
+                self.send_and_wait(&msg, &dump_on_ipc_failure),
+            )
+            .await
+            {
+                Ok(Ok(IpcResponse::Finished {
+                    failed_hooks: failed,
+                    teardown_warnings,
+                    dependency_events,
+                })) => {
+                    failed_hooks = failed.into_iter().map(Into::into).collect();
+                    for warning in teardown_warnings {
+                        output.warning(&warning);
+                    }
+                    for event in &dependency_events {
+                        output.dependency_event(event);
+                    }
+                    finished = true;
+                }
+                Err(_) => output.warning(&format!(
+                    "A worker process was killed because its after_all hooks and teardowns did not finish within {}s",
+                    WORKER_FINISH_TIMEOUT.as_secs()
+                )),
+                Ok(_) => {}
+            }
         }
+        // Only a worker that answered `Finish` exits on its own
+        self.retire(if finished {
+            WORKER_EXIT_TIMEOUT
+        } else {
+            Duration::ZERO
+        })
+        .await;
+        failed_hooks
+    }
+
+    /// Sends a `RunTest` or `Finish` command and waits for the
+    /// worker's answer, dispatching any `HostedRpcCall` it makes in the
+    /// meantime. Mirrors `sync::Worker::wait_for_answer`.
+    async fn send_and_wait(
+        &mut self,
+        msg: &[u8],
+        dump_on_ipc_failure: &DumpOnFailure,
+    ) -> std::io::Result<IpcResponse> {
+        write_frame_async(&mut self.connection, msg).await?;
+        loop {
+            let response_bytes = read_frame_async(&mut self.connection).await?;
+            let response: IpcResponse = dump_on_ipc_failure.run(deserialize(&response_bytes)).await;
+            match response {
+                IpcResponse::TestFinished { .. } | IpcResponse::Finished { .. } => {
+                    break Ok(response)
+                }
+                IpcResponse::CloneableAccepted { .. }
+                | IpcResponse::HostedDescriptorAccepted { .. } => continue,
+                IpcResponse::HostedRpcCall {
+                    request_id,
+                    dep_id,
+                    method_idx,
+                    args_bytes,
+                } => {
+                    self.handle_hosted_rpc_call(request_id, dep_id, method_idx, args_bytes)
+                        .await?;
+                    continue;
+                }
+            }
+        }
+    }
+
+    /// Builds the failed result of a test that exceeded its timeout. Mirrors
+    /// `sync::Worker::timed_out`.
+    async fn timed_out(
+        &mut self,
+        nocapture: bool,
+        test: &RegisteredTest,
+        elapsed: Duration,
+    ) -> TestResult {
+        let (_, captured) = self.reap(Duration::ZERO).await;
+        let mut result = TestResult::failed(
+            elapsed,
+            FailureCause::HarnessError("Test timed out".to_string()),
+        );
+        if test.props.capture_control.requires_capturing(!nocapture) {
+            result.set_captured_output(captured);
+        }
+        result
+    }
+
+    /// Builds the failed result of a test whose worker process exited before
+    /// reporting a result. Mirrors `sync::Worker::crashed`.
+    async fn crashed(
+        &mut self,
+        nocapture: bool,
+        test: &RegisteredTest,
+        elapsed: Duration,
+    ) -> TestResult {
+        let (status, captured) = self.reap(Duration::ZERO).await;
+        let stderr: Vec<String> = captured
+            .iter()
+            .filter(|line| matches!(line, CapturedOutput::Stderr { .. }))
+            .map(|line| line.line().to_string())
+            .collect();
+        let crash = WorkerCrash::new(status, &stderr);
+        let cause = match self.limits.exceeded_by(&crash) {
+            Some(limit) => FailureCause::ResourceLimitExceeded(limit, crash),
+            None => FailureCause::WorkerCrashed(crash),
+        };
+        let mut result = TestResult::failed(elapsed, cause);
+        if test.props.capture_control.requires_capturing(!nocapture) {
+            result.set_captured_output(captured);
+        }
+        result
+    }
+
+    /// Stops the worker process so it cannot run any more tests. Mirrors
+    /// `sync::Worker::retire`.
+    async fn retire(&mut self, exit_timeout: Duration) {
+        let _ = self.reap(exit_timeout).await;
+    }
+
+    /// Marks the worker as exited, waits for its process, killing it if it
+    /// does not exit within `exit_timeout`, and collects everything it printed
+    /// that was not attributed to a test yet. Mirrors `sync::Worker::reap`.
+    async fn reap(&mut self, exit_timeout: Duration) -> (Option<ExitStatus>, Vec<CapturedOutput>) {
+        self.exited = true;
+        let status = match tokio::time::timeout(exit_timeout, self.process.wait()).await {
+            Ok(status) => status.ok(),
+            Err(_) => {
+                let _ = self.process.start_kill();
+                self.process.wait().await.ok()
+            }
+        };
+
+        // The output readers stop at the exited process' EOF, unless a process
+        // started by one of its tests still holds its stdout or stderr
+        let deadline = Instant::now() + WORKER_OUTPUT_TIMEOUT;
+        while (!self.out_handle.is_finished() || !self.err_handle.is_finished())
+            && Instant::now() < deadline
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let out_lines: Vec<_> = self.out_lines.lock().await.drain(..).collect();
+        let err_lines: Vec<_> = self.err_lines.lock().await.drain(..).collect();
+        let mut captured = [out_lines, err_lines].concat();
+        captured.sort();
+        (status, captured)
     }
 
     /// Async counterpart to `sync::Worker::provide_cloneable`. `dep_id` is the
@@ -1065,7 +1878,7 @@ impl Worker {
                 IpcResponse::HostedDescriptorAccepted { .. } => {
                     // Out-of-band ack from a previous ProvideHostedDescriptor; ignore.
                 }
-                IpcResponse::TestFinished { .. } => {
+                IpcResponse::TestFinished { .. } | IpcResponse::Finished { .. } => {
                     // Should not happen before any RunTest.
                 }
                 IpcResponse::HostedRpcCall {
@@ -1078,19 +1891,26 @@ impl Worker {
                     // inside an in-progress test, even while the parent is
                     // mid-`ProvideCloneable` for a different dep. Dispatch it
                     // so the protocol doesn't desync.
-                    self.handle_hosted_rpc_call(
-                        &dump_on_ipc_failure,
-                        request_id,
-                        rpc_dep_id,
-                        method_idx,
-                        args_bytes,
-                    )
-                    .await;
+                    let result = self
+                        .handle_hosted_rpc_call(request_id, rpc_dep_id, method_idx, args_bytes)
+                        .await;
+                    dump_on_ipc_failure.run(result).await;
                 }
             }
         }
     }
 
+    /// Async counterpart to `sync::Worker::provide_failed_dependency`: not
+    /// acknowledged by the worker.
+    async fn provide_failed_dependency(&mut self, dep_id: String, message: String) {
+        let dump_on_ipc_failure = self.dump_on_failure();
+        let cmd = IpcCommand::ProvideFailedDependency { dep_id, message };
+        let msg = serialize_to_byte_vec(&cmd).expect("Failed to encode IPC command");
+        dump_on_ipc_failure
+            .run(write_frame_async(&mut self.connection, &msg).await)
+            .await;
+    }
+
     /// Async counterpart to `sync::Worker::provide_hosted_descriptor`.
     async fn provide_hosted_descriptor(&mut self, dep_id: String, wire_bytes: Vec<u8>) {
         let dump_on_ipc_failure = self.dump_on_failure();
@@ -1117,7 +1937,7 @@ impl Worker {
                 IpcResponse::CloneableAccepted { .. } => {
                     // Out-of-band ack from a previous ProvideCloneable; ignore.
                 }
-                IpcResponse::TestFinished { .. } => {
+                IpcResponse::TestFinished { .. } | IpcResponse::Finished { .. } => {
                     // Should not happen before any RunTest.
                 }
                 IpcResponse::HostedRpcCall {
@@ -1128,14 +1948,10 @@ impl Worker {
                 } => {
                     // See provide_cloneable arm. Dispatch the call inline so
                     // the IPC stream stays in sync.
-                    self.handle_hosted_rpc_call(
-                        &dump_on_ipc_failure,
-                        request_id,
-                        rpc_dep_id,
-                        method_idx,
-                        args_bytes,
-                    )
-                    .await;
+                    let result = self
+                        .handle_hosted_rpc_call(request_id, rpc_dep_id, method_idx, args_bytes)
+                        .await;
+                    dump_on_ipc_failure.run(result).await;
                 }
             }
         }
@@ -1204,7 +2020,7 @@ impl DumpOnFailure {
     }
 }
 
-async fn spawn_worker_if_needed(args: &Arguments) -> Option<Worker> {
+async fn spawn_worker_if_needed(args: &Arguments, limits: WorkerLimits) -> Option<Worker> {
     if args.spawn_workers {
         let id = Uuid::new_v4();
         let name_str = format!("{id}.sock");
@@ -1225,11 +2041,15 @@ async fn spawn_worker_if_needed(args: &Arguments) -> Option<Worker> {
         args.logfile = None;
         let args = args.to_args();
 
-        let mut process = Command::new(exe)
+        let mut command = std::process::Command::new(exe);
+        command
             .args(args)
             .stdin(Stdio::inherit())
             .stderr(Stdio::piped())
-            .stdout(Stdio::piped())
+            .stdout(Stdio::piped());
+        limits.apply(&mut command);
+
+        let mut process = Command::from(command)
             .spawn()
             .expect("Failed to spawn worker process");
 
@@ -1303,14 +2123,17 @@ async fn spawn_worker_if_needed(args: &Arguments) -> Option<Worker> {
 
         Some(Worker {
             _listener: listener,
-            _process: process,
-            _out_handle: out_handle,
-            _err_handle: err_handle,
+            process,
+            out_handle,
+            err_handle,
             out_lines,
             err_lines,
             connection,
             capture_enabled,
             hosted_rpc_owner_cells: Arc::new(HashMap::new()),
+            exited: false,
+            used: false,
+            limits,
         })
     } else {
         None
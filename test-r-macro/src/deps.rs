@@ -33,6 +33,9 @@ pub(crate) enum Scope {
     /// attribute that names the worker-visible handle type tests
     /// parameterise on.
     HostedRpc,
+    /// Constructed for every test using it; the only scope that can be
+    /// injected as `&mut T` or by value.
+    PerTest,
 }
 
 impl Scope {
@@ -43,6 +46,7 @@ impl Scope {
             Scope::Cloneable => quote!(test_r::core::DepScope::Cloneable),
             Scope::Hosted => quote!(test_r::core::DepScope::Hosted),
             Scope::HostedRpc => quote!(test_r::core::DepScope::HostedRpc),
+            Scope::PerTest => quote!(test_r::core::DepScope::PerTest),
         }
     }
 
@@ -53,6 +57,7 @@ impl Scope {
             "Cloneable" => Some(Scope::Cloneable),
             "Hosted" => Some(Scope::Hosted),
             "HostedRpc" => Some(Scope::HostedRpc),
+            "PerTest" => Some(Scope::PerTest),
             _ => None,
         }
     }
@@ -282,7 +287,7 @@ impl darling::FromMeta for Scope {
                     // `scope = PerWorker` form (unquoted ident).
                     let ident = p.path.get_ident().ok_or_else(|| {
                         darling::Error::unsupported_format(
-                            "scope must be one of Shared, PerWorker, Cloneable, Hosted, HostedRpc",
+                            "scope must be one of Shared, PerWorker, Cloneable, Hosted, HostedRpc, PerTest",
                         )
                         .with_span(p)
                     })?;
@@ -291,7 +296,7 @@ impl darling::FromMeta for Scope {
                         .ok_or_else(|| darling::Error::unknown_value(&name).with_span(ident))
                 } else {
                     Err(darling::Error::unsupported_format(
-                        "scope must be one of Shared, PerWorker, Cloneable, Hosted, HostedRpc",
+                        "scope must be one of Shared, PerWorker, Cloneable, Hosted, HostedRpc, PerTest",
                     )
                     .with_span(&nv.value))
                 }
@@ -326,7 +331,7 @@ pub fn test_dep(attr: TokenStream, item: TokenStream) -> TokenStream {
     let mut ast: ItemFn = syn::parse(item).expect("test ast");
     let ctor_name = ast.sig.ident.clone();
 
-    let dep_type = match &ast.sig.output {
+    let return_type = match &ast.sig.output {
         ReturnType::Default => {
             panic!("Dependency constructor must have a return type")
         }
@@ -337,6 +342,10 @@ pub fn test_dep(attr: TokenStream, item: TokenStream) -> TokenStream {
             }
         },
     };
+    // Constructors returning `Result<T, E>` register a dependency of type `T`
+    let fallible_dep_type = result_ok_type(&return_type);
+    let is_fallible = fallible_dep_type.is_some();
+    let dep_type = fallible_dep_type.unwrap_or(return_type);
 
     // `worker = both(Trait)` is lowered into two `RegisteredDependency`
     // entries that share one parent owner via `HostedBothShared`. The shape is
@@ -355,6 +364,14 @@ pub fn test_dep(attr: TokenStream, item: TokenStream) -> TokenStream {
                  (saw `scope = {declared_scope:?}`)."
             );
         }
+        if args.teardown.is_some() {
+            panic!("`teardown = ...` is not supported together with `worker = both(...)`.");
+        }
+        if is_fallible {
+            panic!(
+                "Constructors returning `Result` are not supported together with `worker = both(...)`."
+            );
+        }
         return expand_hosted_both_dep(&args, ast, trait_path);
     }
 
@@ -458,6 +475,19 @@ pub fn test_dep(attr: TokenStream, item: TokenStream) -> TokenStream {
         (_, None, _) => None,
     };
 
+    if args.teardown.is_some() && matches!(scope, Scope::HostedRpc) {
+        panic!(
+            "`teardown = ...` is not supported on HostedRpc deps (`scope = HostedRpc` or \
+             `worker = rpc(...)`)."
+        );
+    }
+    if args.teardown.is_some() && matches!(scope, Scope::PerTest) {
+        panic!(
+            "`teardown = ...` is not supported on PerTest deps, as tests can take them by value. \
+             Implement `Drop` for the dependency instead."
+        );
+    }
+
     let dep_name_str = match &stub_type_path {
         Some(stub_path) => type_path_to_string(stub_path, args.tagged_as.into()),
         None => type_path_to_string(&dep_type, args.tagged_as.into()),
@@ -639,10 +669,41 @@ pub fn test_dep(attr: TokenStream, item: TokenStream) -> TokenStream {
     // sync cell (legacy `HostedRpcDep`) and an async cell (`AsyncHostedRpcDep`
     // under the tokio feature) lives in one feature-gated helper instead of
     // being duplicated here.
+    //
+    // A constructor returning `Result` returns a `DependencyFailure` in place
+    // of the value on `Err`, failing the tests that use the dependency.
+    let (ctor_value_sync, ctor_value_async) = if is_fallible {
+        let on_error = quote! {
+            return test_r::core::DependencyFailure::from_error(
+                format!("{}::{}", module_path!(), #dep_name_str),
+                __error,
+            )
+        };
+        (
+            quote! {
+                match #ctor_name(#(#dep_getters),*) {
+                    Ok(__value) => __value,
+                    Err(__error) => #on_error,
+                }
+            },
+            quote! {
+                match #ctor_name(#(#dep_getters),*).await {
+                    Ok(__value) => __value,
+                    Err(__error) => #on_error,
+                }
+            },
+        )
+    } else {
+        (
+            quote! { #ctor_name(#(#dep_getters),*) },
+            quote! { #ctor_name(#(#dep_getters),*).await },
+        )
+    };
+
     let ctor_call_sync = if matches!(scope, Scope::HostedRpc) {
         quote! {
             {
-                let __owner = #ctor_name(#(#dep_getters),*);
+                let __owner = #ctor_value_sync;
                 let __cell = test_r::core::__test_r_make_hosted_rpc_cell(__owner);
                 let __arc: std::sync::Arc<dyn std::any::Any + Send + Sync> =
                     std::sync::Arc::new(__cell);
@@ -650,13 +711,19 @@ pub fn test_dep(attr: TokenStream, item: TokenStream) -> TokenStream {
             }
         }
     } else {
-        quote! { std::sync::Arc::new(#ctor_name(#(#dep_getters),*)) }
+        quote! {
+            {
+                let result: std::sync::Arc<dyn std::any::Any + Send + Sync> =
+                    std::sync::Arc::new(#ctor_value_sync);
+                result
+            }
+        }
     };
 
     let ctor_call_async = if matches!(scope, Scope::HostedRpc) {
         quote! {
             {
-                let __owner = #ctor_name(#(#dep_getters),*).await;
+                let __owner = #ctor_value_async;
                 let __cell = test_r::core::__test_r_make_hosted_rpc_cell(__owner);
                 let __arc: std::sync::Arc<dyn std::any::Any + Send + Sync> =
                     std::sync::Arc::new(__cell);
@@ -667,7 +734,7 @@ pub fn test_dep(attr: TokenStream, item: TokenStream) -> TokenStream {
         quote! {
             {
                 let result: std::sync::Arc<dyn std::any::Any + Send + Sync> =
-                    std::sync::Arc::new(#ctor_name(#(#dep_getters),*).await);
+                    std::sync::Arc::new(#ctor_value_async);
                 result
             }
         }
@@ -705,6 +772,37 @@ pub fn test_dep(attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     };
 
+    // The teardown function gets a reference to the constructed value. Its
+    // output is turned into a future with autoref specialization, so both
+    // sync and `async` teardown functions are accepted. Without the tokio
+    // runtime the teardowns run outside of any async runtime, so only sync
+    // teardown functions compile there.
+    let teardown_call = match &args.teardown {
+        Some(teardown) => quote! {
+            test_r::core::register_dependency_teardown(
+                #dep_name_str,
+                module_path!(),
+                test_r::core::DependencyTeardown(std::sync::Arc::new(|__test_r_dep_value| Box::pin(async move {
+                    let __dep = __test_r_dep_value
+                        .downcast_ref::<#dep_ty>()
+                        .expect("Dependency type mismatch in teardown");
+                    test_r::__test_r_select_runtime! {
+                        sync {
+                            test_r::core::sync_teardown_result(#teardown(__dep)).await
+                        }
+                        tokio {
+                            #[allow(unused_imports)]
+                            use test_r::core::{TeardownViaFuture as _, TeardownViaValue as _};
+                            let __output = test_r::core::TeardownOutput::new(#teardown(__dep));
+                            (&__output).teardown_result().await
+                        }
+                    }
+                }))),
+            );
+        },
+        None => quote! {},
+    };
+
     let getter_ident = Ident::new(&format!("test_r_get_dep_{dep_name_str}"), Span::call_site());
 
     // The getter must downcast to the *injected* type, which is the stub
@@ -749,16 +847,45 @@ pub fn test_dep(attr: TokenStream, item: TokenStream) -> TokenStream {
                 }
             }
         }
+    } else if matches!(scope, Scope::PerTest) {
+        quote! {
+            test_r::core::DependencyFailure::downcast_dependency::<#injected_ty>(
+                test_r::core::PerTestValue::get(dependency_view, #dep_name_str),
+            )
+        }
     } else {
         quote! {
-            dependency_view
-                .get(#dep_name_str)
-                .expect("Dependency not found")
-                .downcast::<#injected_ty>()
-                .expect("Dependency type mismatch")
+            test_r::core::DependencyFailure::downcast_dependency::<#injected_ty>(
+                dependency_view
+                    .get(#dep_name_str)
+                    .expect("Dependency not found"),
+            )
         }
     };
 
+    // Parameters taking the dependency as `&mut T` or by value call this
+    // instead of the getter. Only the values of PerTest deps are not shared.
+    let take_fn = if matches!(scope, Scope::PerTest) {
+        take_dep_fn(
+            &dep_name_str,
+            &injected_ty,
+            quote! { test_r::core::PerTestScope },
+            quote! {
+                let __value = test_r::core::PerTestValue::take(dependency_view, #dep_name_str)
+                    .downcast::<#injected_ty>()
+                    .expect("Dependency type mismatch");
+                std::sync::Arc::try_unwrap(__value).unwrap_or_else(|_| {
+                    panic!(
+                        "PerTest dependency {} is also injected by reference, so it cannot be taken as `&mut` or by value",
+                        #dep_name_str
+                    )
+                })
+            },
+        )
+    } else {
+        shared_take_dep_fn(&dep_name_str, &injected_ty)
+    };
+
     let result = quote! {
         #[cfg(test)]
         #[test_r::ctor::ctor(crate_path=::test_r::ctor)]
@@ -770,6 +897,7 @@ pub fn test_dep(attr: TokenStream, item: TokenStream) -> TokenStream {
              // shared re-export.
              #async_worker_deprecation_tokens
              #register_call
+             #teardown_call
         }
 
         #[cfg(test)]
@@ -777,6 +905,8 @@ pub fn test_dep(attr: TokenStream, item: TokenStream) -> TokenStream {
             #getter_body
         }
 
+        #take_fn
+
         #ast
     };
 
@@ -955,6 +1085,9 @@ fn expand_hosted_both_dep(args: &TestDepArgs, mut ast: ItemFn, trait_path: &Path
 
     let dep_ty = &dep_type;
     let stub_ty = &stub_type_path;
+    // Both views are shared, so neither can be taken as `&mut` or by value
+    let owner_take_fn = shared_take_dep_fn(&owner_dep_name, &quote! { #dep_ty });
+    let stub_take_fn = shared_take_dep_fn(&stub_dep_name, &quote! { #stub_ty });
 
     // Acquire helper. Two flavors:
     //
@@ -1287,6 +1420,9 @@ fn expand_hosted_both_dep(args: &TestDepArgs, mut ast: ItemFn, trait_path: &Path
             }
         }
 
+        #owner_take_fn
+        #stub_take_fn
+
         // Keep the user's constructor as a real function so any
         // helpers in the same module can still call it directly (it
         // is also what `#acquire_ident` invokes).
@@ -1296,6 +1432,55 @@ fn expand_hosted_both_dep(args: &TestDepArgs, mut ast: ItemFn, trait_path: &Path
     result.into()
 }
 
+/// The function taking the value of a dependency for the parameters injecting it as `&mut T` or
+/// by value, with the alias of its scope marker. Calls only compile if the scope marker
+/// implements `InjectableByValue`, which is checked where the dependency is taken, as the scope
+/// of a dependency is not known by the `#[test]` macro.
+fn take_dep_fn(
+    dep_name_str: &str,
+    injected_ty: &proc_macro2::TokenStream,
+    scope_marker: proc_macro2::TokenStream,
+    take_body: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let take_ident = Ident::new(
+        &format!("test_r_take_dep_{dep_name_str}"),
+        Span::call_site(),
+    );
+    let scope_ident = Ident::new(
+        &format!("test_r_scope_dep_{dep_name_str}"),
+        Span::call_site(),
+    );
+    quote! {
+        #[cfg(test)]
+        #[allow(dead_code, non_camel_case_types)]
+        type #scope_ident = #scope_marker;
+
+        #[cfg(test)]
+        #[allow(dead_code)]
+        fn #take_ident<V: test_r::core::DependencyView>(dependency_view: &V) -> #injected_ty
+        where
+            #scope_ident: test_r::core::InjectableByValue<V>,
+        {
+            #take_body
+        }
+    }
+}
+
+/// [`take_dep_fn`] of a dependency shared between tests, which cannot be taken
+fn shared_take_dep_fn(
+    dep_name_str: &str,
+    injected_ty: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    take_dep_fn(
+        dep_name_str,
+        injected_ty,
+        quote! { test_r::core::SharedValueScope },
+        quote! {
+            unreachable!("Dependency {} can only be injected by reference", #dep_name_str)
+        },
+    )
+}
+
 pub fn inherit_test_dep(item: TokenStream) -> TokenStream {
     let def: InheritTestDep = parse_macro_input!(item as InheritTestDep);
     let dep_type = match &def.typ {
@@ -1313,11 +1498,31 @@ pub fn inherit_test_dep(item: TokenStream) -> TokenStream {
 
     let dep_name_str = type_path_to_string(&dep_type, tag);
     let getter_ident = Ident::new(&format!("test_r_get_dep_{dep_name_str}"), Span::call_site());
+    let take_ident = Ident::new(
+        &format!("test_r_take_dep_{dep_name_str}"),
+        Span::call_site(),
+    );
+
+    let scope_ident = Ident::new(
+        &format!("test_r_scope_dep_{dep_name_str}"),
+        Span::call_site(),
+    );
 
     let result = quote! {
         fn #getter_ident<'a>(dependency_view: &'a impl test_r::core::DependencyView) -> std::sync::Arc<#dep_type> {
             super::#getter_ident(dependency_view)
         }
+
+        #[allow(dead_code, non_camel_case_types)]
+        type #scope_ident = super::#scope_ident;
+
+        #[allow(dead_code)]
+        fn #take_ident<V: test_r::core::DependencyView>(dependency_view: &V) -> #dep_type
+        where
+            #scope_ident: test_r::core::InjectableByValue<V>,
+        {
+            super::#take_ident(dependency_view)
+        }
     };
 
     result.into()
@@ -1387,7 +1592,7 @@ pub fn get_dependency_params(
     for (idx, param) in ast.sig.inputs.iter().enumerate() {
         if !is_bench || idx > 0 {
             // TODO: verify that the first bench arg is a Bencher/AsyncBencher
-            let (dep_type, tag) = match param {
+            let (dep_type, tag, injection) = match param {
                 FnArg::Receiver(_) => {
                     panic!("Test functions cannot have a self parameter")
                 }
@@ -1404,30 +1609,67 @@ pub fn get_dependency_params(
                         (None, None) => DependencyTag::None,
                     };
 
+                    let (typ, injection) = get_dependency_param_from_pat_type(typ);
                     if let DependencyTag::Matrix(dim) = &dep_tag {
+                        if injection != Injection::Ref {
+                            panic!("Test parameters with a dimension must be immutable references");
+                        }
                         dep_dimensions.push((idx, dim.clone()));
                     }
-
-                    let typ = get_dependency_param_from_pat_type(typ);
-                    (typ, dep_tag)
+                    (typ, dep_tag, injection)
                 }
             };
 
-            let dep_name_str = type_path_to_string(&dep_type, tag);
-            let getter_ident =
-                Ident::new(&format!("test_r_get_dep_{dep_name_str}"), Span::call_site());
-
-            dep_getters.push(quote! {
-                &#getter_ident(&__test_r_deps_arg)
-            });
-            dep_names.push(quote! {
-                #dep_name_str.to_string()
-            });
+            if let Some(getter) = test_context_getter(&dep_type, &tag, injection) {
+                if is_bench {
+                    panic!(
+                        "Benchmarks cannot take test_r::TestContext or test_r::TestRng parameters"
+                    );
+                }
+                dep_getters.push(getter);
+                dep_names.push(test_context_name());
+            } else {
+                let dep_name_str = type_path_to_string(&dep_type, tag);
+                dep_getters.push(injection.getter(&dep_name_str));
+                dep_names.push(quote! {
+                    #dep_name_str.to_string()
+                });
+            }
         }
     }
     (dep_getters, dep_names, dep_dimensions)
 }
 
+/// Returns the types of the dependency parameters of a test function as written in its
+/// signature, without the references, reported when listing the tests
+pub fn get_dependency_types(ast: &ItemFn, is_bench: bool) -> Vec<String> {
+    ast.sig
+        .inputs
+        .iter()
+        .skip(if is_bench { 1 } else { 0 })
+        .filter_map(|param| match param {
+            FnArg::Typed(typ) => {
+                let (typ, _) = get_dependency_param_from_pat_type(typ);
+                Some(type_display_name(&typ))
+            }
+            FnArg::Receiver(_) => None,
+        })
+        .collect()
+}
+
+/// Formats a type path like it is usually written in the source, `a::B<C>` instead of the
+/// `a :: B < C >` produced by the token stream's `Display` implementation
+fn type_display_name(typ: &TypePath) -> String {
+    typ.to_token_stream()
+        .to_string()
+        .replace(" :: ", "::")
+        .replace(":: ", "::")
+        .replace(" <", "<")
+        .replace("< ", "<")
+        .replace(" >", ">")
+        .replace(" ,", ",")
+}
+
 pub fn get_dependency_params_for_closure<'a>(
     ast: impl Iterator<Item = &'a Pat>,
 ) -> (
@@ -1439,13 +1681,14 @@ pub fn get_dependency_params_for_closure<'a>(
     let mut dep_names = Vec::new();
     let mut bindings = Vec::new();
     for pat in ast {
-        let (dep_type, tag) = match pat {
+        let (dep_type, tag, injection) = match pat {
             Pat::Type(typ) => {
                 let optional_tag = match get_lit_str_attr(&typ.attrs, "tagged_as") {
                     Some(tag) => DependencyTag::Tagged(tag),
                     None => DependencyTag::None,
                 };
-                (get_dependency_param_from_pat_type(typ), optional_tag)
+                let (dep_type, injection) = get_dependency_param_from_pat_type(typ);
+                (dep_type, optional_tag, injection)
             }
             _ => {
                 panic!(
@@ -1455,6 +1698,7 @@ pub fn get_dependency_params_for_closure<'a>(
                 // TODO: nicer error report
             }
         };
+        let context_getter = test_context_getter(&dep_type, &tag, injection);
         let dep_name_str = type_path_to_string(&dep_type, tag);
         let binding = match pat {
             Pat::Type(typ) => match &*typ.pat {
@@ -1476,36 +1720,125 @@ pub fn get_dependency_params_for_closure<'a>(
             }
         };
 
-        let getter_ident = Ident::new(&format!("test_r_get_dep_{dep_name_str}"), Span::call_site());
-
-        dep_getters.push(quote! {
-            &#getter_ident(&__test_r_deps_arg)
-        });
-        dep_names.push(quote! {
-            #dep_name_str.to_string()
-        });
+        if let Some(getter) = context_getter {
+            dep_getters.push(getter);
+            dep_names.push(test_context_name());
+        } else {
+            dep_getters.push(injection.getter(&dep_name_str));
+            dep_names.push(quote! {
+                #dep_name_str.to_string()
+            });
+        }
         bindings.push(binding);
     }
     (dep_getters, dep_names, bindings)
 }
 
-fn get_dependency_param_from_pat_type(typ: &PatType) -> TypePath {
+/// Returns the getter of a `&test_r::TestContext` or `&mut test_r::TestRng` parameter, which are
+/// not dependencies but are resolved from the test context passed to the test by the runner.
+/// Only the full paths are recognized, so user types with the same names are still dependencies.
+fn test_context_getter(
+    typ: &TypePath,
+    tag: &DependencyTag,
+    injection: Injection,
+) -> Option<proc_macro2::TokenStream> {
+    if typ.qself.is_some()
+        || typ.path.segments.len() != 2
+        || typ.path.segments[0].ident != "test_r"
+        || typ
+            .path
+            .segments
+            .iter()
+            .any(|segment| !segment.arguments.is_empty())
+    {
+        return None;
+    }
+    let name = typ.path.segments[1].ident.to_string();
+    let context = quote! { &test_r::core::TestContext::get(&__test_r_deps_arg) };
+    match (name.as_str(), injection) {
+        ("TestContext", Injection::Ref) if matches!(tag, DependencyTag::None) => Some(context),
+        ("TestRng", Injection::Mut) if matches!(tag, DependencyTag::None) => {
+            Some(quote! { &mut test_r::core::TestRng::from_context(#context) })
+        }
+        ("TestContext", _) => {
+            panic!("test_r::TestContext parameters must be untagged immutable references")
+        }
+        ("TestRng", _) => panic!("test_r::TestRng parameters must be untagged mutable references"),
+        _ => None,
+    }
+}
+
+fn test_context_name() -> proc_macro2::TokenStream {
+    quote! { test_r::core::TestContext::DEPENDENCY_NAME.to_string() }
+}
+
+/// Returns `T` if `typ` is `Result<T, E>`. Only the name of the type is
+/// checked, so aliases like `anyhow::Result<T>` are recognized too.
+fn result_ok_type(typ: &TypePath) -> Option<TypePath> {
+    let last = typ.path.segments.last()?;
+    if last.ident != "Result" {
+        return None;
+    }
+    match &last.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(Type::Path(ok_type)) => Some(ok_type.clone()),
+            _ => panic!("Dependency constructor must return a single concrete type"),
+        },
+        _ => None,
+    }
+}
+
+/// How a dependency is passed to a test function parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Injection {
+    /// `&T`
+    Ref,
+    /// `&mut T`, only supported for `scope = PerTest` deps
+    Mut,
+    /// `T`, only supported for `scope = PerTest` deps
+    Owned,
+}
+
+impl Injection {
+    fn getter(self, dep_name_str: &str) -> proc_macro2::TokenStream {
+        let getter_ident = Ident::new(&format!("test_r_get_dep_{dep_name_str}"), Span::call_site());
+        let take_ident = Ident::new(
+            &format!("test_r_take_dep_{dep_name_str}"),
+            Span::call_site(),
+        );
+        match self {
+            Injection::Ref => quote! { &#getter_ident(&__test_r_deps_arg) },
+            Injection::Mut => quote! { &mut #take_ident(&__test_r_deps_arg) },
+            Injection::Owned => quote! { #take_ident(&__test_r_deps_arg) },
+        }
+    }
+}
+
+fn get_dependency_param_from_pat_type(typ: &PatType) -> (TypePath, Injection) {
     match &*typ.ty {
         Type::Reference(reference) => {
             match &*reference.elem {
-                Type::Path(path) => path.clone(),
+                Type::Path(path) => {
+                    let injection = if reference.mutability.is_some() {
+                        Injection::Mut
+                    } else {
+                        Injection::Ref
+                    };
+                    (path.clone(), injection)
+                }
                 _ => {
                     panic!(
-                        "Test functions can only have parameters which are immutable references to concrete types, but got {:?}",
+                        "Test functions can only have parameters which are references to or values of concrete types, but got {:?}",
                         reference.elem.to_token_stream()
                     )
                     // TODO: nicer error report
                 }
             }
         }
+        Type::Path(path) => (path.clone(), Injection::Owned),
         _ => {
             panic!(
-                "Test functions can only have parameters which are immutable references to concrete types, but got {:?}",
+                "Test functions can only have parameters which are references to or values of concrete types, but got {:?}",
                 typ.ty.to_token_stream()
             )
             // TODO: nicer error report
@@ -1572,6 +1905,12 @@ struct TestDepArgs {
     /// emitted as `WorkerReconstructor::Async`.
     #[darling(default)]
     async_worker: bool,
+    /// Function run on the constructed value before it is dropped. Takes a
+    /// reference to the value, may be `async`, and may return a `Result`.
+    /// Rejected for `HostedRpc` deps, whose owner is only reachable through
+    /// its cell.
+    #[darling(default)]
+    teardown: Option<Path>,
 }
 
 struct DefineMatrixDimension {
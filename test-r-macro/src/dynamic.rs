@@ -1,5 +1,5 @@
 use crate::deps::get_dependency_params_for_closure;
-use crate::helpers::is_testr_attribute;
+use crate::helpers::{get_ignore, is_testr_attribute, source_location};
 use proc_macro::TokenStream;
 use proc_macro2::Ident;
 use quote::{ToTokens, quote};
@@ -11,10 +11,7 @@ pub fn test_gen(item: TokenStream) -> TokenStream {
     let generator_name = ast.sig.ident.clone();
     let generator_name_str = generator_name.to_string();
 
-    let is_ignored = ast
-        .attrs
-        .iter()
-        .any(|attr| is_testr_attribute(attr, "ignore"));
+    let (is_ignored, ignore_reason) = get_ignore(&ast);
 
     let register_ident = Ident::new(
         &format!("test_r_register_generator_{generator_name_str}"),
@@ -22,6 +19,7 @@ pub fn test_gen(item: TokenStream) -> TokenStream {
     );
 
     let is_async = ast.sig.asyncness.is_some();
+    let location = source_location(generator_name.span());
 
     let register_call = if is_async {
         quote! {
@@ -29,7 +27,9 @@ pub fn test_gen(item: TokenStream) -> TokenStream {
                   #generator_name_str,
                   module_path!(),
                   #is_ignored,
-                  test_r::core::TestGeneratorFunction::Async(std::sync::Arc::new(|| Box::pin(async move { #generator_name().await })))
+                  #ignore_reason,
+                  test_r::core::TestGeneratorFunction::Async(std::sync::Arc::new(|| Box::pin(async move { #generator_name().await }))),
+                  #location,
               );
         }
     } else {
@@ -38,7 +38,9 @@ pub fn test_gen(item: TokenStream) -> TokenStream {
                 #generator_name_str,
                 module_path!(),
                 #is_ignored,
-                test_r::core::TestGeneratorFunction::Sync(std::sync::Arc::new(|| #generator_name()))
+                #ignore_reason,
+                test_r::core::TestGeneratorFunction::Sync(std::sync::Arc::new(|| #generator_name())),
+                #location,
             );
         }
     };
@@ -1,4 +1,6 @@
-use syn::{FnArg, ItemFn};
+use proc_macro2::Span;
+use quote::{quote, quote_spanned};
+use syn::{Expr, ExprLit, FnArg, ItemFn, Lit, Meta};
 
 pub fn is_testr_attribute(attr: &syn::Attribute, name: &str) -> bool {
     let path = attr.path();
@@ -23,6 +25,28 @@ pub fn is_testr_attribute(attr: &syn::Attribute, name: &str) -> bool {
     }
 }
 
+/// Whether the function is marked with `#[ignore]`, and an expression with the reason given
+/// with `#[ignore = "reason"]`
+pub fn get_ignore(ast: &ItemFn) -> (bool, proc_macro2::TokenStream) {
+    match ast
+        .attrs
+        .iter()
+        .find(|attr| is_testr_attribute(attr, "ignore"))
+    {
+        Some(attr) => match &attr.meta {
+            Meta::NameValue(name_value) => match &name_value.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(reason),
+                    ..
+                }) => (true, quote! { Some(#reason.to_string()) }),
+                _ => panic!("ignore attribute's reason must be a string"),
+            },
+            _ => (true, quote! { None }),
+        },
+        None => (false, quote! { None }),
+    }
+}
+
 pub fn filter_custom_parameter_attributes(ast: &mut ItemFn) {
     ast.sig.inputs.iter_mut().for_each(|param| {
         if let FnArg::Typed(typed) = param {
@@ -32,3 +56,11 @@ pub fn filter_custom_parameter_attributes(ast: &mut ItemFn) {
         }
     });
 }
+
+/// Expression constructing a `SourceLocation` pointing to `span`, which is usually the name of
+/// the annotated function
+pub fn source_location(span: Span) -> proc_macro2::TokenStream {
+    quote_spanned! { span=>
+        test_r::core::SourceLocation::new(file!(), line!(), column!())
+    }
+}
@@ -0,0 +1,67 @@
+use crate::deps::get_dependency_params;
+use crate::helpers::filter_custom_parameter_attributes;
+use proc_macro::TokenStream;
+use proc_macro2::Ident;
+use quote::quote;
+use syn::ItemFn;
+
+pub fn hook(item: TokenStream, kind: &str) -> TokenStream {
+    let mut ast: ItemFn =
+        syn::parse(item).unwrap_or_else(|_| panic!("#[{kind}] must be applied to a function"));
+    let hook_name = ast.sig.ident.clone();
+    let hook_name_str = hook_name.to_string();
+
+    let (dep_getters, dep_names, dep_dimensions) = get_dependency_params(&ast, false);
+    if !dep_dimensions.is_empty() {
+        panic!("#[{kind}] hooks cannot have dependency parameters with a #[dimension]");
+    }
+
+    let kind_variant = Ident::new(
+        &kind
+            .split('_')
+            .map(|part| part[..1].to_uppercase() + &part[1..])
+            .collect::<String>(),
+        hook_name.span(),
+    );
+
+    let run = if ast.sig.asyncness.is_some() {
+        quote! {
+            test_r::core::TestFunction::Async(std::sync::Arc::new(
+                move |__test_r_deps_arg| {
+                    Box::pin(async move {
+                        let result = #hook_name(#(#dep_getters),*).await;
+                        Box::new(result) as Box<dyn test_r::core::TestReturnValue>
+                    })
+                }
+            ))
+        }
+    } else {
+        quote! {
+            test_r::core::TestFunction::Sync(std::sync::Arc::new(|__test_r_deps_arg| Box::new(#hook_name(#(#dep_getters),*))))
+        }
+    };
+
+    let register_ident = Ident::new(
+        &format!("test_r_register_hook_{hook_name_str}"),
+        hook_name.span(),
+    );
+
+    filter_custom_parameter_attributes(&mut ast);
+    let result = quote! {
+        #[cfg(test)]
+        #[test_r::ctor::ctor(crate_path=::test_r::ctor)]
+        fn #register_ident() {
+            test_r::core::register_suite_hook(
+                #hook_name_str,
+                module_path!(),
+                test_r::core::HookKind::#kind_variant,
+                #run,
+                vec![#(#dep_names),*],
+            );
+        }
+
+        #ast
+    };
+
+    result.into()
+}
@@ -1,6 +1,7 @@
 mod deps;
 mod dynamic;
 mod helpers;
+mod hooks;
 mod hosted_rpc;
 mod suite;
 mod test;
@@ -64,6 +65,41 @@ pub fn timeout_suite(input: TokenStream) -> TokenStream {
     suite::timeout_suite(input)
 }
 
+#[proc_macro_attribute]
+pub fn env(attr: TokenStream, item: TokenStream) -> TokenStream {
+    suite::env(attr, item, suite::EnvChanges::parse_set)
+}
+
+#[proc_macro]
+pub fn env_suite(input: TokenStream) -> TokenStream {
+    suite::env_suite(input, suite::EnvChanges::parse_set)
+}
+
+#[proc_macro_attribute]
+pub fn env_remove(attr: TokenStream, item: TokenStream) -> TokenStream {
+    suite::env(attr, item, suite::EnvChanges::parse_remove)
+}
+
+#[proc_macro]
+pub fn env_remove_suite(input: TokenStream) -> TokenStream {
+    suite::env_suite(input, suite::EnvChanges::parse_remove)
+}
+
+#[proc_macro_attribute]
+pub fn cwd(attr: TokenStream, item: TokenStream) -> TokenStream {
+    suite::cwd(attr, item)
+}
+
+#[proc_macro]
+pub fn cwd_suite(input: TokenStream) -> TokenStream {
+    suite::cwd_suite(input)
+}
+
+#[proc_macro]
+pub fn ignore_suite(input: TokenStream) -> TokenStream {
+    suite::ignore_suite(input)
+}
+
 #[proc_macro_attribute]
 pub fn flaky(_attr: TokenStream, item: TokenStream) -> TokenStream {
     item
@@ -109,6 +145,46 @@ pub fn ignore_detached_panics(_attr: TokenStream, item: TokenStream) -> TokenStr
     item
 }
 
+#[proc_macro_attribute]
+pub fn isolated(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+pub fn depends_on(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+pub fn memory_limit(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+pub fn display_name(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    item
+}
+
+#[proc_macro_attribute]
+pub fn before_all(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    hooks::hook(item, "before_all")
+}
+
+#[proc_macro_attribute]
+pub fn after_all(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    hooks::hook(item, "after_all")
+}
+
+#[proc_macro_attribute]
+pub fn before_each(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    hooks::hook(item, "before_each")
+}
+
+#[proc_macro_attribute]
+pub fn after_each(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    hooks::hook(item, "after_each")
+}
+
 #[proc_macro_attribute]
 pub fn tag(attr: TokenStream, item: TokenStream) -> TokenStream {
     suite::tag(attr, item)
@@ -165,6 +241,16 @@ pub fn sequential_suite(input: TokenStream) -> TokenStream {
     suite::sequential_suite(input)
 }
 
+#[proc_macro_attribute]
+pub fn uses_resource(attr: TokenStream, item: TokenStream) -> TokenStream {
+    suite::uses_resource(attr, item)
+}
+
+#[proc_macro]
+pub fn uses_resource_suite(input: TokenStream) -> TokenStream {
+    suite::uses_resource_suite(input)
+}
+
 /// HR1.1: trait-driven boilerplate eliminator for `HostedRpcDep` /
 /// `AsyncHostedRpcDep`.
 ///
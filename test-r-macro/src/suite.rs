@@ -2,7 +2,7 @@ use crate::deps::{DependencyTag, type_path_to_string};
 use proc_macro::TokenStream;
 use proc_macro2::{Ident, Span};
 use quote::{ToTokens, quote};
-use syn::parse::{Parse, ParseStream};
+use syn::parse::{Parse, ParseStream, Parser};
 use syn::punctuated::Punctuated;
 use syn::{Expr, ItemMod, LitInt, LitStr, Token, Type, parse_macro_input};
 
@@ -147,6 +147,125 @@ pub fn sequential_suite(input: TokenStream) -> TokenStream {
     result.into()
 }
 
+/// Parameters of `#[uses_resource(name)]` and `#[uses_resource(name, limit = N)]`
+pub(crate) struct UsesResourceArgs {
+    name: Ident,
+    limit: usize,
+}
+
+impl Parse for UsesResourceArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: Ident = input.parse()?;
+        let limit = if input.is_empty() {
+            1
+        } else {
+            input.parse::<Token![,]>()?;
+            let key: Ident = input.parse()?;
+            if key != "limit" {
+                return Err(syn::Error::new(key.span(), "Expected `limit`"));
+            }
+            input.parse::<Token![=]>()?;
+            let lit: LitInt = input.parse()?;
+            let limit = lit.base10_parse::<usize>()?;
+            if limit == 0 {
+                return Err(syn::Error::new(
+                    lit.span(),
+                    "The limit of a resource must be at least 1",
+                ));
+            }
+            limit
+        };
+        Ok(UsesResourceArgs { name, limit })
+    }
+}
+
+impl ToTokens for UsesResourceArgs {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let name_str = self.name.to_string();
+        let limit = self.limit;
+        tokens.extend(quote! { test_r::core::ResourceUsage::new(#name_str, #limit) });
+    }
+}
+
+pub fn uses_resource(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if let Ok(ast) = syn::parse::<ItemMod>(item.clone()) {
+        let resource = parse_macro_input!(attr as UsesResourceArgs);
+        let random = rand::random::<u64>();
+        let register_ident = Ident::new(
+            &format!("test_r_register_mod_{}_resource_{}", ast.ident, random),
+            Span::call_site(),
+        );
+
+        let mod_name_str = ast.ident.to_string();
+        let register_call = quote! {
+              test_r::core::register_suite_resource(
+                  #mod_name_str,
+                  module_path!(),
+                  #resource
+              );
+        };
+
+        let result = quote! {
+            #[cfg(test)]
+            #[test_r::ctor::ctor(crate_path=::test_r::ctor)]
+            fn #register_ident() {
+                 #register_call
+            }
+
+            #ast
+        };
+
+        result.into()
+    } else {
+        // applied to a test function
+        item
+    }
+}
+
+struct UsesResourceSuiteInput {
+    module: Ident,
+    resource: UsesResourceArgs,
+}
+
+impl Parse for UsesResourceSuiteInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let module: Ident = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let resource: UsesResourceArgs = input.parse()?;
+        Ok(UsesResourceSuiteInput { module, resource })
+    }
+}
+
+pub fn uses_resource_suite(input: TokenStream) -> TokenStream {
+    let UsesResourceSuiteInput { module, resource } =
+        parse_macro_input!(input as UsesResourceSuiteInput);
+
+    let mod_name_str = module.to_string();
+    let random = rand::random::<u64>();
+    let register_ident = Ident::new(
+        &format!("test_r_register_mod_{mod_name_str}_resource_{random}"),
+        Span::call_site(),
+    );
+
+    let register_call = quote! {
+          test_r::core::register_suite_resource(
+              #mod_name_str,
+              module_path!(),
+              #resource
+          );
+    };
+
+    let result = quote! {
+        #[cfg(test)]
+        #[test_r::ctor::ctor(crate_path=::test_r::ctor)]
+        fn #register_ident() {
+             #register_call
+        }
+    };
+
+    result.into()
+}
+
 pub fn timeout(attr: TokenStream, item: TokenStream) -> TokenStream {
     if let Ok(ast) = syn::parse::<ItemMod>(item.clone()) {
         let random = rand::random::<u64>();
@@ -246,6 +365,58 @@ pub fn timeout_suite(input: TokenStream) -> TokenStream {
     result.into()
 }
 
+pub fn ignore_suite(input: TokenStream) -> TokenStream {
+    let params = parse_macro_input!(input with Punctuated::<Expr, Token![,]>::parse_terminated);
+
+    if params.is_empty() || params.len() > 2 {
+        panic!(
+            "ignore_suite! expects the name of the suite module, optionally followed by the reason of ignoring it"
+        );
+    }
+
+    let mod_name_str = if let Expr::Path(path) = &params[0] {
+        path.path
+            .get_ident()
+            .expect("first parameter must be an identifier")
+            .to_string()
+    } else {
+        panic!("first parameter must be an identifier (the name of the suite module)");
+    };
+
+    let reason = match params.get(1) {
+        Some(Expr::Lit(lit)) => match &lit.lit {
+            syn::Lit::Str(reason) => quote! { Some(#reason.to_string()) },
+            _ => panic!("the reason of ignoring the suite must be a string"),
+        },
+        Some(_) => panic!("the reason of ignoring the suite must be a string"),
+        None => quote! { None },
+    };
+
+    let random = rand::random::<u64>();
+    let register_ident = Ident::new(
+        &format!("test_r_register_mod_{mod_name_str}_ignore_{random}"),
+        Span::call_site(),
+    );
+
+    let register_call = quote! {
+          test_r::core::register_suite_ignore(
+              #mod_name_str,
+              module_path!(),
+              #reason,
+          );
+    };
+
+    let result = quote! {
+        #[cfg(test)]
+        #[test_r::ctor::ctor(crate_path=::test_r::ctor)]
+        fn #register_ident() {
+             #register_call
+        }
+    };
+
+    result.into()
+}
+
 fn parse_timeout_millis(attr: TokenStream) -> u64 {
     if let Ok(timeout) = syn::parse::<LitInt>(attr.clone()) {
         timeout
@@ -262,6 +433,191 @@ fn parse_timeout_millis(attr: TokenStream) -> u64 {
     }
 }
 
+/// An environment variable name, given either as an identifier or as a string literal. Names
+/// that `std::env::set_var` would panic on are rejected.
+fn parse_env_key(input: ParseStream) -> syn::Result<String> {
+    if input.peek(LitStr) {
+        let key = input.parse::<LitStr>()?;
+        let value = key.value();
+        if value.is_empty() || value.contains(['=', '\0']) {
+            return Err(syn::Error::new(
+                key.span(),
+                "environment variable names must not be empty or contain `=` or NUL characters",
+            ));
+        }
+        Ok(value)
+    } else {
+        Ok(input.parse::<Ident>()?.to_string())
+    }
+}
+
+/// The environment changes declared by `#[env(KEY = "value", ...)]` or
+/// `#[env_remove(KEY, ...)]`, as `test_r::core::EnvChange` expressions
+pub(crate) struct EnvChanges(Vec<proc_macro2::TokenStream>);
+
+impl EnvChanges {
+    pub fn parse_set(input: ParseStream) -> syn::Result<Self> {
+        let mut changes = Vec::new();
+        loop {
+            let key = parse_env_key(input)?;
+            input.parse::<Token![=]>()?;
+            let value: LitStr = input.parse()?;
+            changes.push(quote! { test_r::core::EnvChange::set(#key, #value) });
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+        }
+        Ok(EnvChanges(changes))
+    }
+
+    pub fn parse_remove(input: ParseStream) -> syn::Result<Self> {
+        let mut changes = Vec::new();
+        loop {
+            let key = parse_env_key(input)?;
+            changes.push(quote! { test_r::core::EnvChange::remove(#key) });
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+        }
+        Ok(EnvChanges(changes))
+    }
+
+    pub fn into_changes(self) -> Vec<proc_macro2::TokenStream> {
+        self.0
+    }
+}
+
+/// Registers the environment changes declared by `#[env]` or `#[env_remove]` on a module, or
+/// leaves the item untouched if applied to a test function
+pub fn env(
+    attr: TokenStream,
+    item: TokenStream,
+    parser: fn(ParseStream) -> syn::Result<EnvChanges>,
+) -> TokenStream {
+    if let Ok(ast) = syn::parse::<ItemMod>(item.clone()) {
+        let changes = parse_macro_input!(attr with parser);
+        let registration = register_suite_env(&ast.ident, changes);
+
+        let result = quote! {
+            #registration
+
+            #ast
+        };
+
+        result.into()
+    } else {
+        // applied to a test function
+        item
+    }
+}
+
+pub fn env_suite(
+    input: TokenStream,
+    parser: fn(ParseStream) -> syn::Result<EnvChanges>,
+) -> TokenStream {
+    let suite_parser = |input: ParseStream| {
+        let module: Ident = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let changes = parser(input)?;
+        Ok((module, changes))
+    };
+    let (module, changes) = match suite_parser.parse(input) {
+        Ok(parsed) => parsed,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    register_suite_env(&module, changes).into()
+}
+
+fn register_suite_env(module: &Ident, changes: EnvChanges) -> proc_macro2::TokenStream {
+    let mod_name_str = module.to_string();
+    let random = rand::random::<u64>();
+    let register_ident = Ident::new(
+        &format!("test_r_register_mod_{mod_name_str}_env_{random}"),
+        Span::call_site(),
+    );
+
+    let register_calls = changes.into_changes().into_iter().map(|change| {
+        quote! {
+            test_r::core::register_suite_env(
+                #mod_name_str,
+                module_path!(),
+                #change
+            );
+        }
+    });
+
+    quote! {
+        #[cfg(test)]
+        #[test_r::ctor::ctor(crate_path=::test_r::ctor)]
+        fn #register_ident() {
+             #(#register_calls)*
+        }
+    }
+}
+
+pub fn cwd(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if let Ok(ast) = syn::parse::<ItemMod>(item.clone()) {
+        let path = parse_macro_input!(attr as LitStr);
+        let registration = register_suite_cwd(&ast.ident, &path);
+
+        let result = quote! {
+            #registration
+
+            #ast
+        };
+
+        result.into()
+    } else {
+        // applied to a test function
+        item
+    }
+}
+
+pub fn cwd_suite(input: TokenStream) -> TokenStream {
+    let suite_parser = |input: ParseStream| {
+        let module: Ident = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let path: LitStr = input.parse()?;
+        Ok((module, path))
+    };
+    let (module, path) = match suite_parser.parse(input) {
+        Ok(parsed) => parsed,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    register_suite_cwd(&module, &path).into()
+}
+
+fn register_suite_cwd(module: &Ident, path: &LitStr) -> proc_macro2::TokenStream {
+    let mod_name_str = module.to_string();
+    let random = rand::random::<u64>();
+    let register_ident = Ident::new(
+        &format!("test_r_register_mod_{mod_name_str}_cwd_{random}"),
+        Span::call_site(),
+    );
+
+    quote! {
+        #[cfg(test)]
+        #[test_r::ctor::ctor(crate_path=::test_r::ctor)]
+        fn #register_ident() {
+             test_r::core::register_suite_cwd(
+                 #mod_name_str,
+                 module_path!(),
+                 std::path::PathBuf::from(#path),
+             );
+        }
+    }
+}
+
 /// Parsed input of `matrix_suite!(<module>, <dim>, <DepType>)`.
 struct MatrixSuiteInput {
     module: Ident,
@@ -335,6 +691,7 @@ pub fn matrix_suite(input: TokenStream) -> TokenStream {
                 .into_iter()
                 .map(|(__case_label, __dep_name, _getter, __auto_tag)| {
                     test_r::core::MatrixCase {
+                        dimension: #dim_str.to_string(),
                         case_label: __case_label,
                         dep_name: __dep_name,
                         auto_tag: __auto_tag,
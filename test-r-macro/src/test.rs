@@ -1,10 +1,14 @@
-use crate::deps::get_dependency_params;
-use crate::helpers::{filter_custom_parameter_attributes, is_testr_attribute};
+use crate::deps::{get_dependency_params, get_dependency_types};
+use crate::helpers::{
+    filter_custom_parameter_attributes, get_ignore, is_testr_attribute, source_location,
+};
+use crate::suite::{EnvChanges, UsesResourceArgs};
 use proc_macro::TokenStream;
 use proc_macro2::{Ident, Span};
 use quote::quote;
 use syn::parse::{Parse, ParseStream};
 use syn::{Attribute, ItemFn, LitStr, Token};
+use test_r_core::args::MemorySize;
 use test_r_core::internal::ShouldPanic;
 
 pub fn test_impl(_attr: TokenStream, item: TokenStream, is_bench: bool) -> TokenStream {
@@ -12,10 +16,7 @@ pub fn test_impl(_attr: TokenStream, item: TokenStream, is_bench: bool) -> Token
     let test_name = ast.sig.ident.clone();
     let test_name_str = test_name.to_string();
 
-    let is_ignored = ast
-        .attrs
-        .iter()
-        .any(|attr| is_testr_attribute(attr, "ignore"));
+    let (is_ignored, ignore_reason) = get_ignore(&ast);
     let should_panic = ast
         .attrs
         .iter()
@@ -106,6 +107,11 @@ pub fn test_impl(_attr: TokenStream, item: TokenStream, is_bench: bool) -> Token
         quote! { test_r::core::DetachedPanicPolicy::FailTest }
     };
 
+    let is_isolated = ast
+        .attrs
+        .iter()
+        .any(|attr| is_testr_attribute(attr, "isolated"));
+
     let tag_attrs = ast
         .attrs
         .iter()
@@ -119,8 +125,104 @@ pub fn test_impl(_attr: TokenStream, item: TokenStream, is_bench: bool) -> Token
         });
     let tags = quote! { vec![#(#tag_attrs),*] };
 
+    let resource_attrs = ast
+        .attrs
+        .iter()
+        .filter(|attr| is_testr_attribute(attr, "uses_resource"))
+        .map(|attr| {
+            attr.parse_args::<UsesResourceArgs>()
+                .expect("uses_resource attribute's parameters must be a resource name, optionally followed by `limit = <number>`")
+        });
+    let resources = quote! { vec![#(#resource_attrs),*] };
+
+    let depends_on_attrs = ast
+        .attrs
+        .iter()
+        .filter(|attr| is_testr_attribute(attr, "depends_on"))
+        .map(|attr| {
+            let path = attr
+                .parse_args::<syn::Path>()
+                .expect("depends_on attribute's parameter must be the path of a test function");
+            let path_str = path
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .collect::<Vec<_>>()
+                .join("::");
+            quote! { test_r::core::resolve_test_path(module_path!(), #path_str) }
+        });
+    let depends_on = quote! { vec![#(#depends_on_attrs),*] };
+
+    let memory_limit = match ast
+        .attrs
+        .iter()
+        .find(|attr| is_testr_attribute(attr, "memory_limit"))
+    {
+        Some(attr) => {
+            let size = attr.parse_args::<LitStr>().expect(
+                "memory_limit attribute's parameter must be a memory size string, like \"512MiB\"",
+            );
+            let bytes = size
+                .value()
+                .parse::<MemorySize>()
+                .unwrap_or_else(|err| panic!("Invalid memory_limit attribute: {err}"))
+                .bytes;
+            quote! { Some(test_r::core::args::MemorySize { bytes: #bytes }) }
+        }
+        None => quote! { None },
+    };
+
+    let env_changes =
+        ast.attrs
+            .iter()
+            .filter_map(|attr| {
+                if is_testr_attribute(attr, "env") {
+                    Some(
+                        attr.parse_args_with(EnvChanges::parse_set)
+                            .expect("env attribute's parameters must be `KEY = \"value\"` pairs"),
+                    )
+                } else if is_testr_attribute(attr, "env_remove") {
+                    Some(attr.parse_args_with(EnvChanges::parse_remove).expect(
+                        "env_remove attribute's parameters must be environment variable names",
+                    ))
+                } else {
+                    None
+                }
+            })
+            .flat_map(EnvChanges::into_changes);
+    let env = quote! { vec![#(#env_changes),*] };
+
+    let cwd = match ast
+        .attrs
+        .iter()
+        .find(|attr| is_testr_attribute(attr, "cwd"))
+    {
+        Some(attr) => {
+            let path = attr
+                .parse_args::<LitStr>()
+                .expect("cwd attribute's parameter must be a path string");
+            quote! { Some(std::path::PathBuf::from(#path)) }
+        }
+        None => quote! { None },
+    };
+
+    let display_name = match ast
+        .attrs
+        .iter()
+        .find(|attr| is_testr_attribute(attr, "display_name"))
+    {
+        Some(attr) => {
+            let display_name = attr
+                .parse_args::<LitStr>()
+                .expect("display_name attribute's parameter must be a string");
+            quote! { Some(#display_name.to_string()) }
+        }
+        None => quote! { None },
+    };
+
     let is_async = ast.sig.asyncness.is_some();
     let (dep_getters, dep_names, dep_dimensions) = get_dependency_params(&ast, is_bench);
+    let dep_types = get_dependency_types(&ast, is_bench);
 
     let details = TestDetails {
         test_name,
@@ -128,6 +230,7 @@ pub fn test_impl(_attr: TokenStream, item: TokenStream, is_bench: bool) -> Token
         is_bench,
         is_async,
         is_ignored,
+        ignore_reason,
         should_panic,
         timeout,
         has_timeout,
@@ -137,8 +240,16 @@ pub fn test_impl(_attr: TokenStream, item: TokenStream, is_bench: bool) -> Token
         ensure_time_control,
         tags,
         detached_panic_policy,
+        is_isolated,
+        resources,
+        depends_on,
+        memory_limit,
+        env,
+        cwd,
+        display_name,
         dep_getters,
         dep_names,
+        dep_types,
     };
 
     if dep_dimensions.is_empty() {
@@ -173,6 +284,7 @@ struct TestDetails {
     is_bench: bool,
     is_async: bool,
     is_ignored: bool,
+    ignore_reason: proc_macro2::TokenStream,
     should_panic: proc_macro2::TokenStream,
     timeout: proc_macro2::TokenStream,
     has_timeout: bool,
@@ -182,8 +294,16 @@ struct TestDetails {
     ensure_time_control: proc_macro2::TokenStream,
     tags: proc_macro2::TokenStream,
     detached_panic_policy: proc_macro2::TokenStream,
+    is_isolated: bool,
+    resources: proc_macro2::TokenStream,
+    depends_on: proc_macro2::TokenStream,
+    memory_limit: proc_macro2::TokenStream,
+    env: proc_macro2::TokenStream,
+    cwd: proc_macro2::TokenStream,
+    display_name: proc_macro2::TokenStream,
     dep_getters: Vec<proc_macro2::TokenStream>,
     dep_names: Vec<proc_macro2::TokenStream>,
+    dep_types: Vec<String>,
 }
 
 fn single_test_impl(ast: &mut ItemFn, details: TestDetails) -> TokenStream {
@@ -193,6 +313,7 @@ fn single_test_impl(ast: &mut ItemFn, details: TestDetails) -> TokenStream {
         is_bench,
         is_async,
         is_ignored,
+        ignore_reason,
         should_panic,
         timeout,
         has_timeout,
@@ -202,14 +323,23 @@ fn single_test_impl(ast: &mut ItemFn, details: TestDetails) -> TokenStream {
         ensure_time_control,
         tags,
         detached_panic_policy,
+        is_isolated,
+        resources,
+        depends_on,
+        memory_limit,
+        env,
+        cwd,
+        display_name,
         dep_getters,
         dep_names,
+        dep_types,
     } = details;
 
     let register_ident = Ident::new(
         &format!("test_r_register_{test_name_str}"),
         test_name.span(),
     );
+    let location = source_location(test_name.span());
 
     let register_call = if is_bench {
         if has_timeout {
@@ -222,6 +352,7 @@ fn single_test_impl(ast: &mut ItemFn, details: TestDetails) -> TokenStream {
                       #test_name_str,
                       module_path!(),
                       #is_ignored,
+                      #ignore_reason,
                       #should_panic,
                       test_r::core::TestType::from_path(file!()),
                       None,
@@ -231,8 +362,17 @@ fn single_test_impl(ast: &mut ItemFn, details: TestDetails) -> TokenStream {
                       #report_time_control,
                       #ensure_time_control,
                       #detached_panic_policy,
+                      #is_isolated,
+                      #resources,
+                      #depends_on,
+                      #memory_limit,
+                      #env,
+                      #cwd,
+                      #display_name,
                       test_r::core::TestFunction::AsyncBench(std::sync::Arc::new(|__test_r_bencher_arg, __test_r_deps_arg| Box::pin(async move { #test_name(__test_r_bencher_arg, #(#dep_getters),*).await }))),
                       Some(vec![#(#dep_names),*]),
+                      vec![#(#dep_types.to_string()),*],
+                      #location,
                   );
             }
         } else {
@@ -241,6 +381,7 @@ fn single_test_impl(ast: &mut ItemFn, details: TestDetails) -> TokenStream {
                     #test_name_str,
                     module_path!(),
                     #is_ignored,
+                    #ignore_reason,
                     #should_panic,
                     test_r::core::TestType::from_path(file!()),
                     None,
@@ -250,8 +391,17 @@ fn single_test_impl(ast: &mut ItemFn, details: TestDetails) -> TokenStream {
                     #report_time_control,
                     #ensure_time_control,
                     #detached_panic_policy,
+                    #is_isolated,
+                    #resources,
+                    #depends_on,
+                    #memory_limit,
+                    #env,
+                    #cwd,
+                    #display_name,
                     test_r::core::TestFunction::SyncBench(std::sync::Arc::new(|__test_r_bencher_arg, __test_r_deps_arg| #test_name(__test_r_bencher_arg, #(#dep_getters),*))),
                     Some(vec![#(#dep_names),*]),
+                    vec![#(#dep_types.to_string()),*],
+                    #location,
                 );
             }
         }
@@ -261,6 +411,7 @@ fn single_test_impl(ast: &mut ItemFn, details: TestDetails) -> TokenStream {
                   #test_name_str,
                   module_path!(),
                   #is_ignored,
+                  #ignore_reason,
                   #should_panic,
                   test_r::core::TestType::from_path(file!()),
                   #timeout,
@@ -270,6 +421,13 @@ fn single_test_impl(ast: &mut ItemFn, details: TestDetails) -> TokenStream {
                   #report_time_control,
                   #ensure_time_control,
                   #detached_panic_policy,
+                  #is_isolated,
+                  #resources,
+                  #depends_on,
+                  #memory_limit,
+                  #env,
+                  #cwd,
+                  #display_name,
                   test_r::core::TestFunction::Async(std::sync::Arc::new(
                     move |__test_r_deps_arg| {
                         Box::pin(async move {
@@ -279,29 +437,37 @@ fn single_test_impl(ast: &mut ItemFn, details: TestDetails) -> TokenStream {
                     }
                 )),
                   Some(vec![#(#dep_names),*]),
+                  vec![#(#dep_types.to_string()),*],
+                  #location,
               );
         }
     } else {
-        if has_timeout {
-            panic!("The #[timeout()] attribute is only supported for async tests");
-        }
-
         quote! {
             test_r::core::register_test(
                 #test_name_str,
                 module_path!(),
                 #is_ignored,
+                #ignore_reason,
                 #should_panic,
                 test_r::core::TestType::from_path(file!()),
-                None,
+                #timeout,
                 #flakiness_control,
                 #capture_control,
                 #tags,
                 #report_time_control,
                 #ensure_time_control,
                 #detached_panic_policy,
+                #is_isolated,
+                #resources,
+                #depends_on,
+                #memory_limit,
+                #env,
+                #cwd,
+                #display_name,
                 test_r::core::TestFunction::Sync(std::sync::Arc::new(|__test_r_deps_arg| Box::new(#test_name(#(#dep_getters),*)))),
                 Some(vec![#(#dep_names),*]),
+                vec![#(#dep_types.to_string()),*],
+                #location,
             );
         }
     };
@@ -333,6 +499,7 @@ fn matrix_test_impl(
         is_bench,
         is_async,
         is_ignored,
+        ignore_reason,
         should_panic,
         timeout,
         has_timeout,
@@ -342,8 +509,16 @@ fn matrix_test_impl(
         ensure_time_control,
         tags,
         detached_panic_policy,
+        is_isolated,
+        resources,
+        depends_on,
+        memory_limit,
+        env,
+        cwd,
+        display_name,
         dep_getters,
         dep_names,
+        dep_types,
     } = details;
 
     if is_bench {
@@ -401,11 +576,28 @@ fn matrix_test_impl(
             __test_r_matrix_tags
         } });
         props.push(quote! { is_ignored: #is_ignored });
+        props.push(quote! { ignore_reason: #ignore_reason });
         props.push(quote! { detached_panic_policy: #detached_panic_policy });
+        props.push(quote! { is_isolated: #is_isolated });
+        props.push(quote! { resources: #resources });
+        props.push(quote! { depends_on: #depends_on });
+        props.push(quote! { memory_limit: #memory_limit });
+        props.push(quote! { env: #env });
+        props.push(quote! { cwd: #cwd });
+        props.push(quote! { hook: None });
+        props.push(quote! { dependency_types: vec![#(#dep_types.to_string()),*] });
+        props.push(quote! { matrix_case: matrix_case_stack.clone() });
+        // Each case gets the display name of the test, followed by the cases it was generated for
+        props.push(
+            quote! { display_name: #display_name.map(|display_name: String| {
+                format!("{display_name} ({})", name_stack.join(", "))
+            }) },
+        );
 
         props
     };
 
+    let location = source_location(test_name.span());
     let mut loops = if is_async {
         quote! {
             let mut tags_as_string = String::new();
@@ -430,6 +622,7 @@ fn matrix_test_impl(
                     })
                 },
             );
+            r.set_location(#location);
         }
     } else {
         quote! {
@@ -452,6 +645,7 @@ fn matrix_test_impl(
                     #test_name_impl(#(#overridden_dep_getters),*)
                 },
             );
+            r.set_location(#location);
         }
     };
 
@@ -461,8 +655,10 @@ fn matrix_test_impl(
         let dep_var = Ident::new(&format!("dep_{idx}"), Span::call_site());
         let dep_case_tag_var = Ident::new(&format!("case_tag_{idx}"), Span::call_site());
         let get_dep_tags_fn = Ident::new(&format!("test_r_get_dep_tags_{dim}"), Span::call_site());
+        let dim_str = dim.to_string();
         loops = quote! {
             for (#dep_name_var, #dep_actual_name_var, #dep_var, #dep_case_tag_var) in #get_dep_tags_fn() {
+                matrix_case_stack.push((#dim_str.to_string(), #dep_name_var.clone()));
                 name_stack.push(#dep_name_var);
                 matrix_tag_stack.push(#dep_case_tag_var);
                 dep_name_stack.push(#dep_actual_name_var);
@@ -470,6 +666,7 @@ fn matrix_test_impl(
                 dep_name_stack.pop();
                 matrix_tag_stack.pop();
                 name_stack.pop();
+                matrix_case_stack.pop();
             }
         };
     }
@@ -481,6 +678,7 @@ fn matrix_test_impl(
             let mut name_stack = Vec::new();
             let mut dep_name_stack: Vec<String> = Vec::new();
             let mut matrix_tag_stack: Vec<String> = Vec::new();
+            let mut matrix_case_stack: Vec<(String, String)> = Vec::new();
             #loops
         }
 
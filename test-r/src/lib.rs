@@ -1,14 +1,29 @@
 pub use test_r_macro::add_test;
+pub use test_r_macro::after_all;
+pub use test_r_macro::after_each;
 pub use test_r_macro::always_capture;
 pub use test_r_macro::always_ensure_time;
 pub use test_r_macro::always_report_time;
+pub use test_r_macro::before_all;
+pub use test_r_macro::before_each;
 pub use test_r_macro::bench;
+pub use test_r_macro::cwd;
+pub use test_r_macro::cwd_suite;
 pub use test_r_macro::define_matrix_dimension;
+pub use test_r_macro::depends_on;
+pub use test_r_macro::display_name;
+pub use test_r_macro::env;
+pub use test_r_macro::env_remove;
+pub use test_r_macro::env_remove_suite;
+pub use test_r_macro::env_suite;
 pub use test_r_macro::flaky;
 pub use test_r_macro::hosted_rpc;
 pub use test_r_macro::ignore_detached_panics;
+pub use test_r_macro::ignore_suite;
 pub use test_r_macro::inherit_test_dep;
+pub use test_r_macro::isolated;
 pub use test_r_macro::matrix_suite;
+pub use test_r_macro::memory_limit;
 pub use test_r_macro::never_capture;
 pub use test_r_macro::never_ensure_time;
 pub use test_r_macro::never_report_time;
@@ -22,6 +37,8 @@ pub use test_r_macro::test_dep;
 pub use test_r_macro::test_gen;
 pub use test_r_macro::timeout;
 pub use test_r_macro::timeout_suite;
+pub use test_r_macro::uses_resource;
+pub use test_r_macro::uses_resource_suite;
 pub use test_r_macro::uses_test_r as enable;
 
 #[cfg(feature = "tokio")]
@@ -35,18 +52,22 @@ pub use test_r_core::internal::{
     AsyncHostedDep, AsyncHostedRpcDep, CloneableDep, HostedDep, HostedRpcDep,
 };
 pub use test_r_core::worker_index;
+pub use test_r_core::{TestContext, TestRng};
 
 pub mod core {
+    use std::path::PathBuf;
     use std::time::Duration;
     pub use test_r_core::internal::{
         AsyncHostedDep, AsyncHostedRpcDep, AsyncHostedRpcDispatcher, CaptureControl,
-        CloneableCodec, CloneableDep, DepScope, DependencyConstructor, DependencyView,
-        DetachedPanicPolicy, DynamicTestRegistration, FailureCause, FlakinessControl,
-        GeneratedTest, HostedBothShared, HostedDep, HostedRpcChannel, HostedRpcDep,
-        HostedRpcDispatcher, HostedRpcError, HostedRpcOwnerCell, HostedRpcTransport,
-        InProcessHostedRpcTransport, MatrixCase, ReportTimeControl, RpcFactory, ShouldPanic,
-        TestFunction, TestGeneratorFunction, TestProperties, TestReturnValue, TestType,
-        WorkerReconstructor,
+        CloneableCodec, CloneableDep, DepScope, DependencyConstructor, DependencyFailure,
+        DependencyTeardown, DependencyView, DetachedPanicPolicy, DynamicTestRegistration,
+        EnvChange, FailureCause, FlakinessControl, GeneratedTest, HookKind, HostedBothShared,
+        HostedDep, HostedRpcChannel, HostedRpcDep, HostedRpcDispatcher, HostedRpcError,
+        HostedRpcOwnerCell, HostedRpcTransport, InProcessHostedRpcTransport, InjectableByValue,
+        MatrixCase, PerTestScope, PerTestValue, ReportTimeControl, ResourceUsage, RpcFactory,
+        SharedValueScope, ShouldPanic, SourceLocation, SyncTeardownValue, TeardownOutput,
+        TeardownViaFuture, TeardownViaValue, TestFunction, TestGeneratorFunction, TestProperties,
+        TestReturnValue, TestType, WorkerReconstructor, resolve_test_path, sync_teardown_result,
     };
     pub use test_r_core::*;
 
@@ -55,6 +76,7 @@ pub mod core {
         name: &str,
         module_path: &str,
         is_ignored: bool,
+        ignore_reason: Option<String>,
         should_panic: ShouldPanic,
         test_type: TestType,
         timeout: Option<Duration>,
@@ -64,8 +86,17 @@ pub mod core {
         report_time_control: ReportTimeControl,
         ensure_time_control: ReportTimeControl,
         detached_panic_policy: DetachedPanicPolicy,
+        is_isolated: bool,
+        resources: Vec<ResourceUsage>,
+        depends_on: Vec<String>,
+        memory_limit: Option<args::MemorySize>,
+        env: Vec<EnvChange>,
+        cwd: Option<PathBuf>,
+        display_name: Option<String>,
         run: TestFunction,
         dependencies: Option<Vec<String>>,
+        dependency_types: Vec<String>,
+        location: SourceLocation,
     ) {
         let (crate_name, module_path) = split_module_path(module_path);
 
@@ -87,9 +118,21 @@ pub mod core {
                     ensure_time_control,
                     tags,
                     is_ignored,
+                    ignore_reason,
                     detached_panic_policy,
+                    is_isolated,
+                    resources,
+                    depends_on,
+                    memory_limit,
+                    env,
+                    cwd,
+                    hook: None,
+                    dependency_types,
+                    matrix_case: Vec::new(),
+                    display_name,
                 },
                 dependencies,
+                location: Some(location),
             });
     }
 
@@ -173,9 +216,30 @@ pub mod core {
                 hosted_codec,
                 rpc_factory,
                 companions,
+                teardown: None,
             });
     }
 
+    /// Sets the teardown function of the dependency `name` registered right before in the same
+    /// module
+    pub fn register_dependency_teardown(
+        name: &str,
+        module_path: &str,
+        teardown: DependencyTeardown,
+    ) {
+        let (crate_name, module_path) = split_module_path(module_path);
+
+        let mut dependencies = internal::REGISTERED_DEPENDENCY_CONSTRUCTORS.lock().unwrap();
+        let dependency = dependencies
+            .iter_mut()
+            .rev()
+            .find(|dep| {
+                dep.name == name && dep.crate_name == crate_name && dep.module_path == module_path
+            })
+            .unwrap_or_else(|| panic!("Teardown registered for unknown dependency {name}"));
+        dependency.teardown = Some(teardown);
+    }
+
     pub fn register_suite_sequential(name: &str, module_path: &str) {
         let (crate_name, module_path) = split_module_path(module_path);
 
@@ -188,6 +252,36 @@ pub mod core {
         );
     }
 
+    /// Registers a hook of the suite it is defined in, `module_path` being the hook's own
+    /// `module_path!()`
+    pub fn register_suite_hook(
+        name: &str,
+        module_path: &str,
+        kind: HookKind,
+        run: TestFunction,
+        dependencies: Vec<String>,
+    ) {
+        let (crate_name, suite_path) = split_module_path(module_path);
+        let (module_path, suite_name) = suite_path
+            .rsplit_once("::")
+            .map(|(module_path, suite_name)| (module_path.to_string(), suite_name.to_string()))
+            .unwrap_or((String::new(), suite_path));
+
+        internal::REGISTERED_TESTSUITE_PROPS.lock().unwrap().push(
+            internal::RegisteredTestSuiteProperty::Hook {
+                name: suite_name,
+                crate_name,
+                module_path,
+                hook: internal::SuiteHook {
+                    name: name.to_string(),
+                    kind,
+                    run,
+                    dependencies,
+                },
+            },
+        );
+    }
+
     pub fn register_suite_timeout(name: &str, module_path: &str, timeout: Duration) {
         let (crate_name, module_path) = split_module_path(module_path);
 
@@ -201,6 +295,58 @@ pub mod core {
         );
     }
 
+    pub fn register_suite_ignore(name: &str, module_path: &str, reason: Option<String>) {
+        let (crate_name, module_path) = split_module_path(module_path);
+
+        internal::REGISTERED_TESTSUITE_PROPS.lock().unwrap().push(
+            internal::RegisteredTestSuiteProperty::Ignore {
+                name: name.to_string(),
+                crate_name,
+                module_path,
+                reason,
+            },
+        );
+    }
+
+    pub fn register_suite_resource(name: &str, module_path: &str, resource: ResourceUsage) {
+        let (crate_name, module_path) = split_module_path(module_path);
+
+        internal::REGISTERED_TESTSUITE_PROPS.lock().unwrap().push(
+            internal::RegisteredTestSuiteProperty::Resource {
+                name: name.to_string(),
+                crate_name,
+                module_path,
+                resource,
+            },
+        );
+    }
+
+    pub fn register_suite_env(name: &str, module_path: &str, change: EnvChange) {
+        let (crate_name, module_path) = split_module_path(module_path);
+
+        internal::REGISTERED_TESTSUITE_PROPS.lock().unwrap().push(
+            internal::RegisteredTestSuiteProperty::Env {
+                name: name.to_string(),
+                crate_name,
+                module_path,
+                change,
+            },
+        );
+    }
+
+    pub fn register_suite_cwd(name: &str, module_path: &str, path: PathBuf) {
+        let (crate_name, module_path) = split_module_path(module_path);
+
+        internal::REGISTERED_TESTSUITE_PROPS.lock().unwrap().push(
+            internal::RegisteredTestSuiteProperty::Cwd {
+                name: name.to_string(),
+                crate_name,
+                module_path,
+                path,
+            },
+        );
+    }
+
     pub fn register_suite_tag(name: &str, module_path: &str, tag: String) {
         let (crate_name, module_path) = split_module_path(module_path);
 
@@ -245,7 +391,9 @@ pub mod core {
         name: &str,
         module_path: &str,
         is_ignored: bool,
+        ignore_reason: Option<String>,
         run: TestGeneratorFunction,
+        location: SourceLocation,
     ) {
         let (crate_name, module_path) = split_module_path(module_path);
 
@@ -256,6 +404,8 @@ pub mod core {
                 module_path,
                 run,
                 is_ignored,
+                ignore_reason,
+                location: Some(location),
             },
         );
     }
@@ -1,4 +1,5 @@
 use serial_test::serial;
+use std::collections::{HashMap, HashSet};
 use std::time::{Duration, Instant};
 
 mod cargo_tests {
@@ -17,6 +18,24 @@ mod cargo_tests {
             .arg("other::tests::result_based_test_err")
             .arg("--skip")
             .arg("tests::it_does_work")
+            .arg("--skip")
+            .arg("sync_timeout_tests::sync_timeout_exceeds")
+            .arg("--skip")
+            .arg("worker_crash_tests::worker_crash_aborts")
+            .arg("--skip")
+            .arg("fail_fast_tests::")
+            .arg("--skip")
+            .arg("depends_on_failure_tests::")
+            .arg("--skip")
+            .arg("memory_limit_tests::")
+            .arg("--skip")
+            .arg("failing_hooks::")
+            .arg("--skip")
+            .arg("fallible_dep_tests::")
+            .arg("--skip")
+            .arg("failing_context_tests::")
+            .arg("--skip")
+            .arg("failing_seed_tests::")
             .current_dir(root)
             .status()
             .unwrap();
@@ -47,6 +66,24 @@ mod cargo_tests {
             .arg("suite_timeout_tests::suite_timeout_exceeds")
             .arg("--skip")
             .arg("suite_timeout_macro_tests::suite_timeout_macro_exceeds")
+            .arg("--skip")
+            .arg("sync_timeout_tests::sync_timeout_exceeds")
+            .arg("--skip")
+            .arg("worker_crash_tests::worker_crash_aborts")
+            .arg("--skip")
+            .arg("fail_fast_tests::")
+            .arg("--skip")
+            .arg("depends_on_failure_tests::")
+            .arg("--skip")
+            .arg("memory_limit_tests::")
+            .arg("--skip")
+            .arg("failing_hooks::")
+            .arg("--skip")
+            .arg("fallible_dep_tests::")
+            .arg("--skip")
+            .arg("failing_context_tests::")
+            .arg("--skip")
+            .arg("failing_seed_tests::")
             .current_dir(root)
             .status()
             .unwrap();
@@ -70,6 +107,1372 @@ mod cargo_tests {
         assert_eq!(process.code(), Some(101));
     }
 
+    fn run_worker_crash_tests(
+        example: &str,
+        extra_args: &[&str],
+        crash_once_marker: Option<&std::path::Path>,
+    ) -> (Option<i32>, String) {
+        let cwd = std::env::current_dir().unwrap();
+        let root = cwd.parent().unwrap().join(example);
+
+        let mut command = std::process::Command::new("cargo");
+        command
+            .arg("test")
+            .arg("--lib")
+            .arg("worker_crash_tests::")
+            .arg("--")
+            .arg("--test-threads")
+            .arg("1")
+            .args(extra_args)
+            .current_dir(&root);
+        if let Some(marker) = crash_once_marker {
+            command.env("TEST_R_CRASH_ONCE_MARKER", marker);
+        }
+        let process = command.output().unwrap();
+        (
+            process.status.code(),
+            String::from_utf8(process.stdout).unwrap(),
+        )
+    }
+
+    #[test]
+    #[serial]
+    fn worker_crash_is_reported_as_failure() {
+        // The crashed worker gets replaced, so the remaining tests still run
+        let (code, stdout) = run_worker_crash_tests("example", &[], None);
+        assert_eq!(code, Some(101));
+        assert!(stdout.contains("2 passed; 1 failed"));
+        assert!(stdout.contains("Worker process crashed"));
+        assert!(stdout.contains("About to crash in worker_crash_aborts"));
+
+        let (code, stdout) = run_worker_crash_tests("example-tokio", &[], None);
+        assert_eq!(code, Some(101));
+        assert!(stdout.contains("2 passed; 1 failed"));
+        assert!(stdout.contains("Worker process crashed"));
+        assert!(stdout.contains("About to crash in worker_crash_aborts"));
+    }
+
+    #[test]
+    #[serial]
+    fn crashed_tests_can_be_retried() {
+        for example in ["example", "example-tokio"] {
+            let marker = std::env::temp_dir().join(format!(
+                "test-r-crash-once-{example}-{}",
+                std::process::id()
+            ));
+            let _ = std::fs::remove_file(&marker);
+
+            let (code, stdout) = run_worker_crash_tests(
+                example,
+                &["--skip", "worker_crash_aborts", "--retry-crashed", "1"],
+                Some(&marker),
+            );
+            let _ = std::fs::remove_file(&marker);
+
+            assert_eq!(code, Some(0));
+            assert!(stdout.contains("2 passed; 0 failed"));
+        }
+    }
+
+    /// Runs the `isolation_tests` module and returns the process id each test
+    /// reported, keyed by test name.
+    fn run_isolation_tests(example: &str, extra_args: &[&str]) -> HashMap<String, String> {
+        let cwd = std::env::current_dir().unwrap();
+        let root = cwd.parent().unwrap().join(example);
+
+        let process = std::process::Command::new("cargo")
+            .arg("test")
+            .arg("--lib")
+            .arg("isolation_tests::")
+            .arg("--")
+            .arg("--test-threads")
+            .arg("1")
+            .arg("--nocapture")
+            .args(extra_args)
+            .current_dir(&root)
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8(process.stdout).unwrap();
+        assert_eq!(process.status.code(), Some(0), "{stdout}");
+        assert!(stdout.contains("4 passed; 0 failed"));
+
+        stdout
+            .lines()
+            .filter_map(|line| line.strip_prefix("Process of "))
+            .filter_map(|line| line.split_once(": "))
+            .map(|(name, pid)| (name.to_string(), pid.to_string()))
+            .collect()
+    }
+
+    #[test]
+    #[serial]
+    fn isolated_tests_run_in_their_own_process() {
+        for example in ["example", "example-tokio"] {
+            let pids = run_isolation_tests(example, &[]);
+            assert_eq!(pids.len(), 4);
+            for isolated in ["isolated_test_1", "isolated_test_2"] {
+                let others = pids.iter().filter(|(name, _)| *name != isolated);
+                for (other, pid) in others {
+                    assert_ne!(
+                        &pids[isolated], pid,
+                        "{isolated} shared its process with {other} in {example}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn isolate_flag_runs_every_test_in_its_own_process() {
+        for example in ["example", "example-tokio"] {
+            let pids = run_isolation_tests(example, &["--isolate"]);
+            let distinct: HashSet<_> = pids.values().collect();
+            assert_eq!(pids.len(), 4);
+            assert_eq!(distinct.len(), 4, "{pids:?}");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn processes_left_behind_by_tests_do_not_block_the_run() {
+        for example in ["example", "example-tokio"] {
+            let cwd = std::env::current_dir().unwrap();
+            let root = cwd.parent().unwrap().join(example);
+
+            let process = std::process::Command::new("cargo")
+                .arg("test")
+                .arg("--lib")
+                .arg("leftover_process_tests::")
+                .arg("--")
+                .arg("--test-threads")
+                .arg("1")
+                .env("TEST_R_LEFTOVER_PROCESS_SECONDS", "60")
+                .current_dir(&root)
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8(process.stdout).unwrap();
+            assert_eq!(process.status.code(), Some(0), "{stdout}");
+
+            let seconds: f64 = stdout
+                .split_once("finished in ")
+                .and_then(|(_, rest)| rest.split_once('s'))
+                .and_then(|(seconds, _)| seconds.parse().ok())
+                .unwrap_or_else(|| panic!("no suite duration in {stdout}"));
+            assert!(
+                seconds < 30.0,
+                "{example} waited for the leftover process: {stdout}"
+            );
+        }
+    }
+
+    fn run_fail_fast_tests(example: &str, extra_args: &[&str]) -> (Option<i32>, String) {
+        let cwd = std::env::current_dir().unwrap();
+        let root = cwd.parent().unwrap().join(example);
+
+        let process = std::process::Command::new("cargo")
+            .arg("test")
+            .arg("--lib")
+            .arg("fail_fast_tests::")
+            .arg("--")
+            .arg("--test-threads")
+            .arg("1")
+            .args(extra_args)
+            .current_dir(&root)
+            .output()
+            .unwrap();
+        (
+            process.status.code(),
+            String::from_utf8(process.stdout).unwrap(),
+        )
+    }
+
+    #[test]
+    #[serial]
+    fn fail_fast_stops_after_first_failure() {
+        for example in ["example", "example-tokio"] {
+            let (code, stdout) = run_fail_fast_tests(example, &["--fail-fast"]);
+            assert_eq!(code, Some(101));
+            assert!(stdout.contains("0 passed; 1 failed; 0 ignored; 0 measured; 2 not run;"));
+            assert!(stdout.contains("Stopped after 1 failed test(s), 2 test(s) were not run"));
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn max_fail_stops_after_given_number_of_failures() {
+        for example in ["example", "example-tokio"] {
+            let (code, stdout) = run_fail_fast_tests(example, &["--max-fail", "2"]);
+            assert_eq!(code, Some(101));
+            assert!(stdout.contains("0 passed; 2 failed; 0 ignored; 0 measured; 1 not run;"));
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn partitions_split_the_selected_tests() {
+        for example in ["example", "example-tokio"] {
+            let (_, first) = run_fail_fast_tests(example, &["--partition", "count:1/2"]);
+            assert!(
+                first.contains("0 passed; 2 failed; 0 ignored; 0 measured; 1 in other partitions;")
+            );
+
+            let (_, second) = run_fail_fast_tests(example, &["--partition", "count:2/2"]);
+            assert!(
+                second
+                    .contains("0 passed; 1 failed; 0 ignored; 0 measured; 2 in other partitions;")
+            );
+
+            for test in ["fail_fast_1", "fail_fast_2", "fail_fast_3"] {
+                let in_first = first.contains(&format!("fail_fast_tests::{test} "));
+                let in_second = second.contains(&format!("fail_fast_tests::{test} "));
+                assert_ne!(
+                    in_first, in_second,
+                    "{test} must be in exactly one partition"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn longest_first_schedule_uses_recorded_timings() {
+        for (example, crate_name) in [
+            ("example", "test_r_example"),
+            ("example-tokio", "test_r_example_tokio"),
+        ] {
+            // Records the timings of the tests
+            run_fail_fast_tests(example, &[]);
+
+            let cwd = std::env::current_dir().unwrap();
+            let state_dir = cwd.parent().unwrap().join("target/debug/test-r");
+            let timings_file = std::fs::read_dir(&state_dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .find(|path| {
+                    let file_name = path.file_name().unwrap().to_string_lossy();
+                    file_name.starts_with(&format!("{crate_name}-"))
+                        && file_name.ends_with(".timings")
+                })
+                .expect("timings should be recorded");
+            let timings: String = std::fs::read_to_string(&timings_file)
+                .unwrap()
+                .lines()
+                .map(|line| {
+                    let (name, _) = line.rsplit_once('\t').unwrap();
+                    match name.rsplit_once("::").unwrap().1 {
+                        "fail_fast_2" => format!("{name}\t90000000\n"),
+                        "fail_fast_3" => format!("{name}\t30000000\n"),
+                        _ => format!("{line}\n"),
+                    }
+                })
+                .collect();
+            std::fs::write(&timings_file, timings).unwrap();
+
+            let (_, stdout) = run_fail_fast_tests(example, &["--schedule", "longest-first"]);
+            let order: Vec<&str> = stdout
+                .lines()
+                .filter_map(|line| line.split_once("Running test: "))
+                .map(|(_, name)| name.trim())
+                .collect();
+            assert_eq!(
+                order,
+                vec![
+                    format!("{crate_name}::fail_fast_tests::fail_fast_2"),
+                    format!("{crate_name}::fail_fast_tests::fail_fast_3"),
+                    format!("{crate_name}::fail_fast_tests::fail_fast_1"),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn tests_sharing_a_resource_respect_its_limit() {
+        for example in ["example", "example-tokio"] {
+            let cwd = std::env::current_dir().unwrap();
+            let root = cwd.parent().unwrap().join(example);
+
+            for extra_args in [&[][..], &["--nocapture"][..]] {
+                let process = std::process::Command::new("cargo")
+                    .arg("test")
+                    .arg("--lib")
+                    .arg("resource_tests::")
+                    .arg("--")
+                    .arg("--test-threads")
+                    .arg("8")
+                    .args(extra_args)
+                    .current_dir(&root)
+                    .output()
+                    .unwrap();
+                let stdout = String::from_utf8(process.stdout).unwrap();
+                assert_eq!(process.status.code(), Some(0), "{stdout}");
+                assert!(stdout.contains("6 passed"), "{stdout}");
+            }
+        }
+    }
+
+    fn run_depends_on_tests(example: &str, extra_args: &[&str]) -> (Option<i32>, String) {
+        let cwd = std::env::current_dir().unwrap();
+        let root = cwd.parent().unwrap().join(example);
+
+        let process = std::process::Command::new("cargo")
+            .arg("test")
+            .arg("--lib")
+            .arg("depends_on")
+            .arg("--")
+            .arg("--test-threads")
+            .arg("4")
+            .arg("--shuffle")
+            .args(extra_args)
+            .current_dir(&root)
+            .output()
+            .unwrap();
+        (
+            process.status.code(),
+            String::from_utf8(process.stdout).unwrap(),
+        )
+    }
+
+    #[test]
+    #[serial]
+    fn tests_run_after_their_prerequisites() {
+        for example in ["example", "example-tokio"] {
+            for extra_args in [&[][..], &["--nocapture"][..]] {
+                let (code, stdout) = run_depends_on_tests(example, extra_args);
+                assert_eq!(code, Some(101), "{stdout}");
+                assert!(
+                    stdout.contains("4 passed; 1 failed; 0 ignored; 0 measured; 1 skipped;"),
+                    "{stdout}"
+                );
+                let skipped_line = stdout
+                    .lines()
+                    .find(|line| line.contains("Finished test") && line.contains("[SKIPPED]"))
+                    .unwrap_or_else(|| panic!("no skipped test in {stdout}"));
+                assert!(skipped_line.contains("depends_on_failure_tests::after_broken_step"));
+                assert!(skipped_line.contains("depends_on_failure_tests::broken_step failed"));
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn skipped_tests_are_reported_in_json_output() {
+        let (code, stdout) =
+            run_depends_on_tests("example", &["-Z", "unstable-options", "--format", "json"]);
+        assert_eq!(code, Some(101));
+        assert_eq!(stdout.matches(r#""event": "skipped""#).count(), 1);
+        assert!(stdout.contains(r#""message": "prerequisite test_r_example::depends_on_failure_tests::broken_step failed""#));
+        assert!(stdout.contains(r#""skipped": 1"#));
+    }
+
+    fn run_hook_tests(example: &str, extra_args: &[&str]) -> (Option<i32>, String) {
+        let cwd = std::env::current_dir().unwrap();
+        let root = cwd.parent().unwrap().join(example);
+
+        let process = std::process::Command::new("cargo")
+            .arg("test")
+            .arg("--lib")
+            .arg("hook")
+            .arg("--")
+            .args(extra_args)
+            .current_dir(&root)
+            .output()
+            .unwrap();
+        (
+            process.status.code(),
+            String::from_utf8(process.stdout).unwrap(),
+        )
+    }
+
+    #[test]
+    #[serial]
+    fn failing_suite_hooks_are_reported_as_their_own_entries() {
+        for example in ["example", "example-tokio"] {
+            for extra_args in [&[][..], &["--nocapture"][..], &["--isolate"][..]] {
+                let (code, stdout) = run_hook_tests(example, extra_args);
+                assert_eq!(code, Some(101), "{stdout}");
+                assert!(
+                    stdout.contains("4 passed; 4 failed; 0 ignored; 0 measured; 2 skipped;"),
+                    "{stdout}"
+                );
+                for failed in [
+                    "failing_before_all::broken_start [before_all] (broken_start failed",
+                    "failing_before_each::broken_set_up [before_each of skipped_by_before_each] (broken_set_up failed",
+                    "failing_before_each::broken_tear_down [after_each of skipped_by_before_each] (broken_tear_down failed",
+                    "failing_after_all::broken_finish [after_all] (broken_finish failed",
+                ] {
+                    assert!(stdout.contains(failed), "{failed} in {stdout}");
+                }
+                assert!(
+                    stdout.contains("skipped_by_before_all (before_all hook broken_start failed)")
+                );
+                assert!(
+                    stdout
+                        .contains("skipped_by_before_each (before_each hook broken_set_up failed)")
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn failing_suite_hooks_are_counted_in_junit_output() {
+        for example in ["example", "example-tokio"] {
+            let (code, stdout) = run_hook_tests(example, &["--format", "junit"]);
+            assert_eq!(code, Some(101), "{stdout}");
+            assert_eq!(stdout.matches("<testcase ").count(), 10, "{stdout}");
+            assert!(
+                stdout.contains(r#"failures="4" tests="10" skipped="2""#),
+                "{stdout}"
+            );
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn failing_suite_hooks_are_reported_in_json_output() {
+        let (code, stdout) =
+            run_hook_tests("example", &["-Z", "unstable-options", "--format", "json"]);
+        assert_eq!(code, Some(101));
+        assert_eq!(
+            stdout
+                .matches(r#""type": "test", "event": "failed""#)
+                .count(),
+            4
+        );
+        assert!(stdout.contains(
+            r#""name": "test_r_example::failing_hooks::failing_after_all::broken_finish [after_all]""#
+        ));
+    }
+
+    #[test]
+    #[serial]
+    fn tests_skipped_by_a_failed_before_all_hook_are_run_by_last_failed() {
+        // The examples are built into the same target directory as this binary
+        let exe = std::env::current_exe().unwrap();
+        let state_dir = exe.parent().unwrap().parent().unwrap().join("test-r");
+        for entry in std::fs::read_dir(&state_dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            if entry
+                .path()
+                .extension()
+                .is_some_and(|ext| ext == "last-failed")
+            {
+                std::fs::remove_file(entry.path()).unwrap();
+            }
+        }
+
+        for example in ["example", "example-tokio"] {
+            let (code, stdout) = run_hook_tests(example, &[]);
+            assert_eq!(code, Some(101), "{stdout}");
+
+            let (code, stdout) = run_hook_tests(example, &["--last-failed"]);
+            assert_eq!(code, Some(101), "{stdout}");
+            assert!(
+                stdout.contains("0 passed; 1 failed; 0 ignored; 0 measured; 1 skipped;"),
+                "{stdout}"
+            );
+            assert!(stdout.contains("skipped_by_before_all (before_all hook broken_start failed)"));
+            assert!(
+                !stdout.contains("hooks_of_outer_suites_run_too"),
+                "{stdout}"
+            );
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn dependency_teardowns_run_in_reverse_order_and_report_failures() {
+        for (example, crate_name) in [
+            ("example", "test_r_example"),
+            ("example-tokio", "test_r_example_tokio"),
+        ] {
+            let cwd = std::env::current_dir().unwrap();
+            let root = cwd.parent().unwrap().join(example);
+            for extra_args in [&[][..], &["--nocapture"][..], &["--isolate"][..]] {
+                let process = std::process::Command::new("cargo")
+                    .arg("test")
+                    .arg("--lib")
+                    .arg("teardown_tests")
+                    .arg("--")
+                    .args(extra_args)
+                    .current_dir(&root)
+                    .output()
+                    .unwrap();
+                let stdout = String::from_utf8(process.stdout).unwrap();
+                let stderr = String::from_utf8(process.stderr).unwrap();
+                assert_eq!(process.status.code(), Some(0), "{stdout}\n{stderr}");
+                assert!(
+                    stderr.contains(&format!(
+                        "Teardown of dependency {crate_name}::teardown_tests::lease failed: the lease was already released"
+                    )),
+                    "{stderr}"
+                );
+                // The server is stopped before the database it depends on is closed
+                assert!(
+                    !stderr.contains(&format!(
+                        "Teardown of dependency {crate_name}::teardown_tests::server"
+                    )),
+                    "{stderr}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn per_test_dependencies_are_constructed_for_every_test() {
+        for example in ["example", "example-tokio"] {
+            let cwd = std::env::current_dir().unwrap();
+            let root = cwd.parent().unwrap().join(example);
+            for extra_args in [
+                &[][..],
+                &["--nocapture"][..],
+                &["--isolate"][..],
+                &["--test-threads=1"][..],
+            ] {
+                let process = std::process::Command::new("cargo")
+                    .arg("test")
+                    .arg("--lib")
+                    .arg("per_test_tests")
+                    .arg("--")
+                    .args(extra_args)
+                    .current_dir(&root)
+                    .output()
+                    .unwrap();
+                let stdout = String::from_utf8(process.stdout).unwrap();
+                assert_eq!(process.status.code(), Some(0), "{stdout}");
+                assert!(stdout.contains("6 passed; 0 failed"), "{stdout}");
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn env_and_cwd_are_applied_and_restored() {
+        for example in ["example", "example-tokio"] {
+            let cwd = std::env::current_dir().unwrap();
+            let root = cwd.parent().unwrap().join(example);
+            for extra_args in [
+                &[][..],
+                &["--nocapture"][..],
+                &["--isolate"][..],
+                &["--test-threads=1"][..],
+            ] {
+                let process = std::process::Command::new("cargo")
+                    .arg("test")
+                    .arg("--lib")
+                    .arg("env_tests")
+                    .arg("--")
+                    .args(extra_args)
+                    .current_dir(&root)
+                    .output()
+                    .unwrap();
+                let stdout = String::from_utf8(process.stdout).unwrap();
+                assert_eq!(process.status.code(), Some(0), "{stdout}");
+                assert!(stdout.contains("7 passed; 0 failed"), "{stdout}");
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_context_temp_dir_is_kept_only_on_failure() {
+        for example in ["example", "example-tokio"] {
+            let cwd = std::env::current_dir().unwrap();
+            let root = cwd.parent().unwrap().join(example);
+            for extra_args in [
+                &[][..],
+                &["--nocapture"][..],
+                &["--isolate"][..],
+                &["--test-threads=1"][..],
+            ] {
+                let process = std::process::Command::new("cargo")
+                    .arg("test")
+                    .arg("--lib")
+                    .arg("context_tests")
+                    .arg("--")
+                    .args(extra_args)
+                    .current_dir(&root)
+                    .output()
+                    .unwrap();
+                let stdout = String::from_utf8(process.stdout).unwrap();
+                let stderr = String::from_utf8(process.stderr).unwrap();
+                let output = format!("{stdout}{stderr}");
+                assert_eq!(process.status.code(), Some(101), "{output}");
+                assert!(stdout.contains("3 passed; 1 failed"), "{output}");
+
+                let kept = output
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Temporary directory of the test kept at "))
+                    .unwrap_or_else(|| panic!("no kept temporary directory in {output}"));
+                let kept = std::path::Path::new(kept.trim());
+                assert!(kept.join("data.txt").exists(), "{output}");
+                std::fs::remove_dir_all(kept).unwrap();
+
+                if extra_args == ["--nocapture"] {
+                    let deleted = output
+                        .lines()
+                        .find_map(|line| line.strip_prefix("Temporary directory: "))
+                        .unwrap_or_else(|| panic!("no temporary directory in {output}"));
+                    assert!(!std::path::Path::new(deleted.trim()).exists(), "{output}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn failing_test_seeds_are_reported_and_reproducible() {
+        for (example, crate_name) in [
+            ("example", "test_r_example"),
+            ("example-tokio", "test_r_example_tokio"),
+        ] {
+            let cwd = std::env::current_dir().unwrap();
+            let root = cwd.parent().unwrap().join(example);
+            let name = format!("{crate_name}::failing_seed_tests::random_failure");
+            let run = |args: &[&str]| {
+                let process = std::process::Command::new("cargo")
+                    .arg("test")
+                    .arg("--lib")
+                    .arg("failing_seed_tests")
+                    .arg("--")
+                    .args(args)
+                    .current_dir(&root)
+                    .output()
+                    .unwrap();
+                let stdout = String::from_utf8(process.stdout).unwrap();
+                assert_eq!(process.status.code(), Some(101), "{stdout}");
+                stdout
+            };
+            let printed_value = |stdout: &str| {
+                stdout
+                    .lines()
+                    .find(|line| line.starts_with("Seed: "))
+                    .unwrap_or_else(|| panic!("no seed printed in {stdout}"))
+                    .to_string()
+            };
+            let reported_seed = |stdout: &str| {
+                let prefix = format!(" - {name} [seed: ");
+                let line = stdout
+                    .lines()
+                    .find_map(|line| line.strip_prefix(&prefix))
+                    .unwrap_or_else(|| panic!("no seed reported in {stdout}"));
+                line.split(']').next().unwrap().to_string()
+            };
+
+            for extra_args in [&[][..], &["--nocapture"][..], &["--isolate"][..]] {
+                let first = run(&[&["--seed", "42"], extra_args].concat());
+                let second = run(&[&["--seed", "42"], extra_args].concat());
+                assert_eq!(printed_value(&first), printed_value(&second));
+                assert_eq!(reported_seed(&first), reported_seed(&second));
+
+                let seed = reported_seed(&run(extra_args));
+                let rerun = run(&[&["--seed-for", &format!("{name}={seed}")], extra_args].concat());
+                assert_eq!(reported_seed(&rerun), seed, "{rerun}");
+                assert!(
+                    printed_value(&rerun).starts_with(&format!("Seed: {seed}, ")),
+                    "{rerun}"
+                );
+            }
+
+            let json = run(&["--seed", "42", "-Z", "unstable-options", "--format", "json"]);
+            let seed = reported_seed(&run(&["--seed", "42"]));
+            assert!(json.contains(&format!(r#""seed": {seed}"#)), "{json}");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn listing_tests_as_json_and_ctrf_includes_their_metadata() {
+        for (example, crate_name) in [
+            ("example", "test_r_example"),
+            ("example-tokio", "test_r_example_tokio"),
+        ] {
+            let cwd = std::env::current_dir().unwrap();
+            let root = cwd.parent().unwrap().join(example);
+            let list = |format: &str| {
+                let process = std::process::Command::new("cargo")
+                    .arg("test")
+                    .arg("--lib")
+                    .arg("--")
+                    .arg("--list")
+                    .arg("--format")
+                    .arg(format)
+                    .current_dir(&root)
+                    .output()
+                    .unwrap();
+                let stdout = String::from_utf8(process.stdout).unwrap();
+                assert_eq!(process.status.code(), Some(0), "{stdout}");
+                stdout
+            };
+
+            // The test generators of the examples print to the standard output as well
+            let events: Vec<serde_json::Value> = list("json")
+                .lines()
+                .filter(|line| line.starts_with('{'))
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect();
+            assert_eq!(events[0]["event"], "discovery");
+            let completed = events.last().unwrap();
+            assert_eq!(completed["event"], "completed");
+            assert_eq!(completed["total"], events.len() - 2);
+            let discovered = |name: &str| {
+                let name = format!("{crate_name}::{name}");
+                events
+                    .iter()
+                    .find(|event| event["name"] == name.as_str())
+                    .unwrap_or_else(|| panic!("{name} was not listed"))
+                    .clone()
+            };
+
+            let test = discovered("context_tests::attempts_are_counted");
+            assert_eq!(test["type"], "test");
+            assert_eq!(test["event"], "discovered");
+            assert_eq!(test["crate"], crate_name);
+            assert_eq!(test["module_path"], "context_tests");
+            assert_eq!(test["kind"], "test");
+            assert_eq!(test["test_type"], "unit");
+            assert_eq!(test["ignored"], false);
+            assert_eq!(test["should_panic"], false);
+            assert_eq!(
+                test["flakiness_control"],
+                serde_json::json!({ "kind": "retry_known_flaky", "count": 3 })
+            );
+            assert_eq!(test["capture_control"], "default");
+            assert_eq!(
+                test["dependencies"],
+                serde_json::json!(["test_r::TestContext"])
+            );
+            assert_eq!(test["matrix_case"], serde_json::json!({}));
+            let test = discovered("context_tests::context_describes_the_test");
+            assert_eq!(test["tags"], serde_json::json!(["context"]));
+            assert_eq!(test["flakiness_control"], serde_json::Value::Null);
+
+            if example == "example" {
+                let test =
+                    discovered("matrix_features_e2e::matrix_suite_example::thing_one_postgres");
+                assert_eq!(test["matrix_case"], serde_json::json!({ "db": "postgres" }));
+                assert_eq!(test["dependencies"], serde_json::json!(["DbDep"]));
+            }
+
+            let ctrf = list("ctrf");
+            let report: serde_json::Value =
+                serde_json::from_str(ctrf.lines().find(|line| line.starts_with('{')).unwrap())
+                    .unwrap();
+            let tests = report["results"]["tests"].as_array().unwrap();
+            assert_eq!(report["results"]["summary"]["other"], tests.len());
+            let name = format!("{crate_name}::context_tests::context_describes_the_test");
+            let test = tests
+                .iter()
+                .find(|test| test["name"] == name.as_str())
+                .unwrap();
+            assert_eq!(test["status"], "other");
+            assert_eq!(test["tags"], serde_json::json!(["context"]));
+            assert_eq!(
+                test["extra"]["dependencies"],
+                serde_json::json!(["test_r::TestContext"])
+            );
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_locations_are_reported_in_the_outputs() {
+        for (example, crate_name) in [
+            ("example", "test_r_example"),
+            ("example-tokio", "test_r_example_tokio"),
+        ] {
+            let cwd = std::env::current_dir().unwrap();
+            let root = cwd.parent().unwrap().join(example);
+            let source = std::fs::read_to_string(root.join("src/lib.rs")).unwrap();
+            let (idx, line) = source
+                .lines()
+                .enumerate()
+                .find(|(_, line)| line.contains("fn context_describes_the_test("))
+                .unwrap();
+            let file = format!("{example}/src/lib.rs");
+            let line_number = idx + 1;
+            let column = line.find("context_describes_the_test").unwrap() + 1;
+            let name = format!("{crate_name}::context_tests::context_describes_the_test");
+
+            let run = |args: &[&str]| {
+                let process = std::process::Command::new("cargo")
+                    .arg("test")
+                    .arg("--lib")
+                    .arg("--")
+                    .args(args)
+                    .current_dir(&root)
+                    .output()
+                    .unwrap();
+                let stdout = String::from_utf8(process.stdout).unwrap();
+                assert_eq!(process.status.code(), Some(0), "{stdout}");
+                stdout
+            };
+            let events = |stdout: String| {
+                stdout
+                    .lines()
+                    .filter(|line| line.starts_with('{'))
+                    .map(|line| serde_json::from_str(line).unwrap())
+                    .filter(|event: &serde_json::Value| event["name"] == name.as_str())
+                    .collect::<Vec<_>>()
+            };
+
+            let listed = events(run(&["--list", "--format", "json"]));
+            let ran = events(run(&[
+                "--exact",
+                "context_tests::context_describes_the_test",
+                "--format",
+                "json",
+            ]));
+            assert_eq!(ran.len(), 2, "{ran:?}");
+            for event in listed.iter().chain(&ran) {
+                assert_eq!(event["file"], file.as_str(), "{event}");
+                assert_eq!(event["line"], line_number, "{event}");
+                assert_eq!(event["column"], column, "{event}");
+            }
+
+            let junit = run(&["--list", "--format", "junit"]);
+            assert!(
+                junit.contains(&format!(
+                    r#"<testcase name="context_describes_the_test" classname="{crate_name}::context_tests" file="{file}" line="{line_number}"/>"#
+                )),
+                "{junit}"
+            );
+
+            let ctrf = run(&["--list", "--format", "ctrf"]);
+            let report: serde_json::Value =
+                serde_json::from_str(ctrf.lines().find(|line| line.starts_with('{')).unwrap())
+                    .unwrap();
+            let test = report["results"]["tests"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|test| test["name"] == name.as_str())
+                .unwrap();
+            assert_eq!(test["filePath"], file.as_str());
+            assert_eq!(test["line"], line_number);
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn display_names_are_shown_in_the_reports() {
+        for (example, crate_name) in [
+            ("example", "test_r_example"),
+            ("example-tokio", "test_r_example_tokio"),
+        ] {
+            let cwd = std::env::current_dir().unwrap();
+            let root = cwd.parent().unwrap().join(example);
+            let run = |args: &[&str]| {
+                let process = std::process::Command::new("cargo")
+                    .arg("test")
+                    .arg("--lib")
+                    .arg("display_name_tests")
+                    .arg("--")
+                    .args(args)
+                    .current_dir(&root)
+                    .output()
+                    .unwrap();
+                let stdout = String::from_utf8(process.stdout).unwrap();
+                assert_eq!(process.status.code(), Some(0), "{stdout}");
+                stdout
+            };
+            let name = format!("{crate_name}::display_name_tests::creates_user_with_unicode_email");
+            let display_name = "creates user with unicode email";
+
+            let pretty = run(&[]);
+            assert!(
+                pretty.contains(&format!("Finished test: {display_name}")),
+                "{pretty}"
+            );
+
+            let junit = run(&["--format", "junit"]);
+            assert!(
+                junit.contains(&format!(
+                    r#"<testcase name="{display_name}" classname="{crate_name}::display_name_tests""#
+                )),
+                "{junit}"
+            );
+
+            let ctrf = run(&["--format", "ctrf"]);
+            let report: serde_json::Value =
+                serde_json::from_str(ctrf.lines().find(|line| line.starts_with('{')).unwrap())
+                    .unwrap();
+            assert_eq!(report["results"]["tests"][0]["name"], display_name);
+
+            let listed = run(&["--list", "--format", "json"]);
+            let test: serde_json::Value = listed
+                .lines()
+                .filter(|line| line.starts_with('{'))
+                .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+                .find(|event| event["name"] == name.as_str())
+                .unwrap();
+            assert_eq!(test["display_name"], display_name);
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn ignore_reasons_are_shown_in_the_reports() {
+        for (example, crate_name) in [
+            ("example", "test_r_example"),
+            ("example-tokio", "test_r_example_tokio"),
+        ] {
+            let cwd = std::env::current_dir().unwrap();
+            let root = cwd.parent().unwrap().join(example);
+            let run = |args: &[&str]| {
+                let process = std::process::Command::new("cargo")
+                    .arg("test")
+                    .arg("--lib")
+                    .arg("ignore_reason_tests")
+                    .arg("--")
+                    .args(args)
+                    .current_dir(&root)
+                    .output()
+                    .unwrap();
+                let stdout = String::from_utf8(process.stdout).unwrap();
+                assert_eq!(process.status.code(), Some(0), "{stdout}");
+                stdout
+            };
+            let name = format!("{crate_name}::ignore_reason_tests::ignored_with_reason");
+
+            let pretty = run(&[]);
+            let finished = |test: &str| {
+                pretty
+                    .lines()
+                    .find(|line| line.contains(&format!("Finished test: {test} ")))
+                    .unwrap_or_else(|| panic!("{test} not finished:\n{pretty}"))
+                    .to_string()
+            };
+            assert!(
+                finished(&name).ends_with(" ignored, waiting for upstream fix"),
+                "{pretty}"
+            );
+            assert!(
+                finished(&format!(
+                    "{crate_name}::ignore_reason_tests::unfinished::ignored_by_the_suite"
+                ))
+                .ends_with(" ignored, the suite is not finished yet"),
+                "{pretty}"
+            );
+            assert!(
+                pretty.contains("0 passed; 0 failed; 2 ignored;"),
+                "{pretty}"
+            );
+
+            let json = run(&["--format", "json"]);
+            assert!(
+                json.contains(&format!(
+                    r#"{{ "type": "test", "event": "ignored", "name": "{name}""#
+                )),
+                "{json}"
+            );
+            assert!(
+                json.contains(r#""message": "waiting for upstream fix""#),
+                "{json}"
+            );
+            assert!(
+                json.contains(r#""message": "the suite is not finished yet""#),
+                "{json}"
+            );
+
+            let junit = run(&["--format", "junit"]);
+            assert!(
+                junit.contains(r#"<skipped message="waiting for upstream fix"/>"#),
+                "{junit}"
+            );
+
+            let ctrf = run(&["--format", "ctrf"]);
+            let report: serde_json::Value =
+                serde_json::from_str(ctrf.lines().find(|line| line.starts_with('{')).unwrap())
+                    .unwrap();
+            let test = report["results"]["tests"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|test| test["name"] == name.as_str())
+                .unwrap();
+            assert_eq!(test["status"], "skipped");
+            assert_eq!(test["message"], "Ignored because waiting for upstream fix");
+
+            let listed = run(&["--list", "--format", "json"]);
+            let test: serde_json::Value = listed
+                .lines()
+                .filter(|line| line.starts_with('{'))
+                .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+                .find(|event| event["name"] == name.as_str())
+                .unwrap();
+            assert_eq!(test["ignored"], true);
+            assert_eq!(test["ignore_reason"], "waiting for upstream fix");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn tui_falls_back_to_pretty_output_without_a_terminal() {
+        for example in ["example", "example-tokio"] {
+            let cwd = std::env::current_dir().unwrap();
+            let root = cwd.parent().unwrap().join(example);
+            for extra_args in [&[][..], &["--isolate"][..]] {
+                let process = std::process::Command::new("cargo")
+                    .arg("test")
+                    .arg("--lib")
+                    .arg("context_tests")
+                    .arg("--")
+                    .arg("--skip")
+                    .arg("failing_")
+                    .arg("--tui")
+                    .args(extra_args)
+                    .current_dir(&root)
+                    .stdin(std::process::Stdio::null())
+                    .output()
+                    .unwrap();
+                let stdout = String::from_utf8(process.stdout).unwrap();
+                let stderr = String::from_utf8(process.stderr).unwrap();
+                assert_eq!(process.status.code(), Some(0), "{stdout}");
+                assert!(
+                    stderr.contains("--tui requires an interactive terminal"),
+                    "{stderr}"
+                );
+                assert!(stdout.contains("test result: ok; 3 passed"), "{stdout}");
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn dependency_lifecycle_is_reported_with_show_deps() {
+        for (example, crate_name) in [
+            ("example", "test_r_example"),
+            ("example-tokio", "test_r_example_tokio"),
+        ] {
+            let cwd = std::env::current_dir().unwrap();
+            let root = cwd.parent().unwrap().join(example);
+            for extra_args in [&[][..], &["--nocapture"][..], &["--isolate"][..]] {
+                let process = std::process::Command::new("cargo")
+                    .arg("test")
+                    .arg("--lib")
+                    .arg("per_test_tests")
+                    .arg("--")
+                    .arg("--show-deps")
+                    .args(extra_args)
+                    .current_dir(&root)
+                    .output()
+                    .unwrap();
+                let stdout = String::from_utf8(process.stdout).unwrap();
+                assert_eq!(process.status.code(), Some(0), "{stdout}");
+                let scratch = format!("{crate_name}::per_test_tests::scratch");
+                // Every test attempt gets its own PerTest instance, dropped when the attempt
+                // finishes: one for each of the 4 single-attempt tests, 3 for the non-flaky
+                // test and 2 for the flaky test passing on its second attempt
+                assert_eq!(
+                    stdout
+                        .matches(&format!("Constructing dependency: {scratch} (PerTest"))
+                        .count(),
+                    9,
+                    "{stdout}"
+                );
+                assert_eq!(
+                    stdout
+                        .matches(&format!("Dropped dependency: {scratch} after"))
+                        .count(),
+                    9,
+                    "{stdout}"
+                );
+                assert!(
+                    stdout.contains("Dependency construction times:"),
+                    "{stdout}"
+                );
+            }
+
+            let process = std::process::Command::new("cargo")
+                .arg("test")
+                .arg("--lib")
+                .arg("per_test_tests")
+                .arg("--")
+                .arg("-Z")
+                .arg("unstable-options")
+                .arg("--format")
+                .arg("json")
+                .current_dir(&root)
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8(process.stdout).unwrap();
+            assert_eq!(process.status.code(), Some(0), "{stdout}");
+            assert!(
+                stdout.contains(&format!(
+                    r#"{{ "type": "dependency", "event": "constructed", "name": "{crate_name}::per_test_tests::constructions", "scope": "Shared", "process": "worker 0""#
+                )),
+                "{stdout}"
+            );
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn failing_dependency_constructors_fail_dependent_tests() {
+        for (example, crate_name) in [
+            ("example", "test_r_example"),
+            ("example-tokio", "test_r_example_tokio"),
+        ] {
+            let cwd = std::env::current_dir().unwrap();
+            let root = cwd.parent().unwrap().join(example);
+            for extra_args in [&[][..], &["--nocapture"][..], &["--isolate"][..]] {
+                let process = std::process::Command::new("cargo")
+                    .arg("test")
+                    .arg("--lib")
+                    .arg("fallible_dep_tests")
+                    .arg("--")
+                    .args(extra_args)
+                    .current_dir(&root)
+                    .output()
+                    .unwrap();
+                let stdout = String::from_utf8(process.stdout).unwrap();
+                assert_eq!(process.status.code(), Some(101), "{stdout}");
+                assert!(stdout.contains("2 passed; 4 failed"), "{stdout}");
+                for (test, dep, message) in [
+                    (
+                        "fallible_database",
+                        "database",
+                        "could not connect to the database",
+                    ),
+                    (
+                        "fallible_repository",
+                        "database",
+                        "could not connect to the database",
+                    ),
+                    (
+                        "fallible_cache",
+                        "cache",
+                        "the cache directory is not writable",
+                    ),
+                    ("fallible_schema", "schema", "the schema is invalid"),
+                ] {
+                    let expected = format!(
+                        "{crate_name}::fallible_dep_tests::{test} (Dependency {crate_name}::fallible_dep_tests::{dep} failed: {message})"
+                    );
+                    assert!(stdout.contains(&expected), "{expected}\n{stdout}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    #[cfg(target_os = "linux")]
+    fn exceeding_worker_limits_fails_the_test() {
+        for example in ["example", "example-tokio"] {
+            for extra_args in [&[][..], &["--nocapture"][..]] {
+                let cwd = std::env::current_dir().unwrap();
+                let root = cwd.parent().unwrap().join(example);
+
+                let process = std::process::Command::new("cargo")
+                    .arg("test")
+                    .arg("--lib")
+                    .arg("memory_limit_tests::")
+                    .arg("--")
+                    .arg("--worker-cpu-time-limit")
+                    .arg("1s")
+                    .args(extra_args)
+                    .current_dir(&root)
+                    .output()
+                    .unwrap();
+                let stdout = String::from_utf8(process.stdout).unwrap();
+                assert_eq!(process.status.code(), Some(101), "{stdout}");
+                assert!(stdout.contains("1 passed; 2 failed"), "{stdout}");
+                // Without the captured allocation failure the abort is reported as it is
+                let memory_failure = if extra_args.is_empty() {
+                    "Resource limit exceeded: the worker process ran out of its memory limit of 256MiB"
+                } else {
+                    "Worker process crashed (signal 6)"
+                };
+                assert!(stdout.contains(memory_failure), "{stdout}");
+                assert!(
+                    stdout.contains("Resource limit exceeded: the worker process ran out of its CPU time limit of 1s"),
+                    "{stdout}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn not_run_tests_are_reported_in_json_output() {
+        let (code, stdout) = run_fail_fast_tests(
+            "example",
+            &["--fail-fast", "-Z", "unstable-options", "--format", "json"],
+        );
+        assert_eq!(code, Some(101));
+        assert_eq!(stdout.matches(r#""event": "not_run""#).count(), 2);
+        assert!(stdout.contains(r#""not_run": 2"#));
+    }
+
+    #[test]
+    #[serial]
+    fn tests_passing_on_rerun_are_reported_as_flaky() {
+        for example in ["example", "example-tokio"] {
+            let marker = std::env::temp_dir().join(format!("test-r-fail-once-{example}"));
+            let _ = std::fs::remove_file(&marker);
+
+            let cwd = std::env::current_dir().unwrap();
+            let root = cwd.parent().unwrap().join(example);
+            let process = std::process::Command::new("cargo")
+                .arg("test")
+                .arg("--lib")
+                .arg("rerun_tests::")
+                .arg("--")
+                .arg("--rerun-failed")
+                .arg("2")
+                .env("TEST_R_FAIL_ONCE_MARKER", &marker)
+                .current_dir(&root)
+                .output()
+                .unwrap();
+            let _ = std::fs::remove_file(&marker);
+            let stdout = String::from_utf8(process.stdout).unwrap();
+
+            assert_eq!(process.status.code(), Some(0));
+            assert_eq!(stdout.matches("Retrying test").count(), 1);
+            assert!(stdout.contains("because it failed in the previous pass"));
+            assert!(stdout.contains("2 passed; 0 failed; 0 ignored; 0 measured; 1 flaky;"));
+            assert!(stdout.contains("Flaky tests (passed when rerun):"));
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn failed_tests_are_rerun_until_attempts_run_out() {
+        for example in ["example", "example-tokio"] {
+            let (code, stdout) = run_fail_fast_tests(example, &["--rerun-failed", "2"]);
+            assert_eq!(code, Some(101));
+            assert_eq!(stdout.matches("Retrying test").count(), 6);
+            assert!(stdout.contains("0 passed; 3 failed; 0 ignored; 0 measured;"));
+            assert!(!stdout.contains("flaky;"));
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn failed_tests_of_a_partition_are_all_rerun() {
+        for example in ["example", "example-tokio"] {
+            let (code, stdout) = run_fail_fast_tests(
+                example,
+                &["--partition", "count:1/2", "--rerun-failed", "1"],
+            );
+            assert_eq!(code, Some(101));
+            assert_eq!(stdout.matches("Retrying test").count(), 2, "{stdout}");
+            assert!(
+                stdout
+                    .contains("0 passed; 2 failed; 0 ignored; 0 measured; 1 in other partitions;"),
+                "{stdout}"
+            );
+
+            let (code, stdout) = run_fail_fast_tests(
+                example,
+                &["--partition", "count:2/2", "--rerun-failed", "1"],
+            );
+            assert_eq!(code, Some(101));
+            assert_eq!(stdout.matches("Retrying test").count(), 1, "{stdout}");
+        }
+    }
+
+    fn run_rerun_tests(
+        example: &str,
+        extra_args: &[&str],
+        fail_once_marker: &std::path::Path,
+    ) -> (Option<i32>, String, String) {
+        let cwd = std::env::current_dir().unwrap();
+        let root = cwd.parent().unwrap().join(example);
+
+        let process = std::process::Command::new("cargo")
+            .arg("test")
+            .arg("--lib")
+            .arg("rerun_tests::")
+            .arg("--")
+            .arg("--test-threads")
+            .arg("1")
+            .args(extra_args)
+            .env("TEST_R_FAIL_ONCE_MARKER", fail_once_marker)
+            .current_dir(&root)
+            .output()
+            .unwrap();
+        (
+            process.status.code(),
+            String::from_utf8(process.stdout).unwrap(),
+            String::from_utf8(process.stderr).unwrap(),
+        )
+    }
+
+    #[test]
+    #[serial]
+    fn last_failed_tests_can_be_run_first_or_alone() {
+        for (example, crate_name) in [
+            ("example", "test_r_example"),
+            ("example-tokio", "test_r_example_tokio"),
+        ] {
+            let marker = std::env::temp_dir().join(format!("test-r-last-failed-{example}"));
+            let _ = std::fs::remove_file(&marker);
+            let (code, stdout, _) = run_rerun_tests(example, &[], &marker);
+            assert_eq!(code, Some(101));
+            assert!(stdout.contains("1 passed; 1 failed"));
+
+            let (code, stdout, _) = run_rerun_tests(example, &["--failed-first"], &marker);
+            assert_eq!(code, Some(0));
+            let first_test = stdout.find("Running test: ").unwrap();
+            assert!(stdout[first_test..].starts_with(&format!(
+                "Running test: {crate_name}::rerun_tests::rerun_fails_once"
+            )));
+
+            let _ = std::fs::remove_file(&marker);
+            let (code, _, _) = run_rerun_tests(example, &[], &marker);
+            assert_eq!(code, Some(101));
+
+            let (code, stdout, _) = run_rerun_tests(example, &["--last-failed"], &marker);
+            assert_eq!(code, Some(0));
+            assert!(stdout.contains("1 passed; 0 failed"));
+            assert!(stdout.contains("rerun_fails_once"));
+            assert!(!stdout.contains("rerun_passes"));
+
+            // The failure is fixed, so everything runs again
+            let (code, stdout, stderr) = run_rerun_tests(example, &["--last-failed"], &marker);
+            assert_eq!(code, Some(0));
+            assert!(stdout.contains("2 passed; 0 failed"));
+            assert!(stderr.contains("None of the selected tests failed when last run"));
+
+            // `cargo test-r watch --failed-first` sets the environment variable instead. The
+            // shuffle seed runs `rerun_passes` first otherwise.
+            let _ = std::fs::remove_file(&marker);
+            let (code, _, _) = run_rerun_tests(example, &[], &marker);
+            assert_eq!(code, Some(101));
+            let cwd = std::env::current_dir().unwrap();
+            let process = std::process::Command::new("cargo")
+                .arg("test")
+                .arg("--lib")
+                .arg("rerun_tests::")
+                .arg("--")
+                .arg("--test-threads")
+                .arg("1")
+                .arg("--shuffle-seed")
+                .arg("1")
+                .env("TEST_R_FAIL_ONCE_MARKER", &marker)
+                .env("TEST_R_FAILED_FIRST", "1")
+                .current_dir(cwd.parent().unwrap().join(example))
+                .output()
+                .unwrap();
+            let stdout = String::from_utf8(process.stdout).unwrap();
+            assert_eq!(process.status.code(), Some(0), "{stdout}");
+            let first_test = stdout.find("Running test: ").unwrap();
+            assert!(stdout[first_test..].starts_with(&format!(
+                "Running test: {crate_name}::rerun_tests::rerun_fails_once"
+            )));
+            let _ = std::fs::remove_file(&marker);
+        }
+    }
+
     #[test]
     #[serial]
     fn async_output_capturing_works() {
@@ -305,6 +1708,71 @@ mod timing_tests {
         assert!(elapsed < Duration::from_secs(15));
     }
 
+    fn run_sync_timeout_tests(example: &str, extra_args: &[&str]) -> (Option<i32>, String) {
+        let cwd = std::env::current_dir().unwrap();
+        let root = cwd.parent().unwrap().join(example);
+
+        let process = std::process::Command::new("cargo")
+            .arg("test")
+            .arg("sync_timeout_tests::")
+            .arg("--")
+            .arg("--test-threads")
+            .arg("1")
+            .args(extra_args)
+            .current_dir(&root)
+            .output()
+            .unwrap();
+        (
+            process.status.code(),
+            String::from_utf8(process.stdout).unwrap(),
+        )
+    }
+
+    #[test]
+    #[serial]
+    fn sync_timeout_works_in_process() {
+        let start = Instant::now();
+        let (code, stdout) = run_sync_timeout_tests("example", &["--nocapture"]);
+        let elapsed = start.elapsed();
+
+        assert_eq!(code, Some(101));
+        assert!(stdout.contains("1 passed; 1 failed"));
+        assert!(stdout.contains("Test timed out"));
+        assert!(elapsed < Duration::from_secs(25));
+    }
+
+    #[test]
+    #[serial]
+    fn sync_timeout_works_with_spawned_workers() {
+        let start = Instant::now();
+        let (code, stdout) = run_sync_timeout_tests("example", &[]);
+        let elapsed = start.elapsed();
+
+        // The worker running the stuck test gets killed and replaced, so the
+        // other test still runs. The output printed before the kill is kept.
+        assert_eq!(code, Some(101));
+        assert!(stdout.contains("1 passed; 1 failed"));
+        assert!(stdout.contains("Test timed out"));
+        assert!(stdout.contains("Start sleeping in sync_timeout_exceeds"));
+        assert!(elapsed < Duration::from_secs(25));
+    }
+
+    #[test]
+    #[serial]
+    fn sync_timeout_works_with_tokio_runner() {
+        let start = Instant::now();
+        let (code, stdout) = run_sync_timeout_tests("example-tokio", &[]);
+        assert_eq!(code, Some(101));
+        assert!(stdout.contains("1 passed; 1 failed"));
+        assert!(stdout.contains("Start sleeping in sync_timeout_exceeds"));
+
+        let (code, stdout) = run_sync_timeout_tests("example-tokio", &["--nocapture"]);
+        let elapsed = start.elapsed();
+        assert_eq!(code, Some(101));
+        assert!(stdout.contains("1 passed; 1 failed"));
+        assert!(elapsed < Duration::from_secs(40));
+    }
+
     #[test]
     #[serial]
     fn suite_timeout_attribute_works() {